@@ -16,7 +16,7 @@ pub fn sys_sysconf(name: c_int) -> c_long {
             // Total physical pages
             ctypes::_SC_PHYS_PAGES => Ok(axconfig::PHYS_MEMORY_SIZE / PAGE_SIZE_4K),
             // Number of processors in use
-            ctypes::_SC_NPROCESSORS_ONLN => Ok(axconfig::SMP),
+            ctypes::_SC_NPROCESSORS_ONLN => Ok(axhal::cpu::cpu_num()),
             // Avaliable physical pages
             #[cfg(feature = "alloc")]
             ctypes::_SC_AVPHYS_PAGES => Ok(axalloc::global_allocator().available_pages()),
@@ -20,6 +20,12 @@ pub fn init_current_dir() {
     axfs::api::set_current_dir("/").expect("reset current dir failed");
 }
 
+/// 创建由内核生成内容的 procfs 文件，在文件系统初始化之后调用一次
+pub fn init_procfs() {
+    axprocess::init_procfs();
+    crate::strace::init();
+}
+
 /// Flags for opening a file
 pub type FileFlags = OpenFlags;
 
@@ -123,7 +129,7 @@ pub fn run_testcase(testcase: &str, envs: Vec<String>) -> AxResult<()> {
     let now_process_id = user_process.get_process_id() as i32;
     let mut exit_code = 0;
     loop {
-        if unsafe { wait_pid(now_process_id, &mut exit_code as *mut i32) }.is_ok() {
+        if unsafe { wait_pid(now_process_id, &mut exit_code as *mut i32, false, false) }.is_ok() {
             break;
         }
         yield_now_task();
@@ -16,9 +16,9 @@ bitflags! {
     pub struct WaitFlags: u32 {
         /// 不挂起当前进程，直接返回
         const WNOHANG = 1 << 0;
-        /// 报告已执行结束的用户进程的状态
-        const WIMTRACED = 1 << 1;
-        /// 报告还未结束的用户进程的状态
+        /// 报告已停止的子进程的状态
+        const WUNTRACED = 1 << 1;
+        /// 报告因 SIGCONT 继续执行的子进程的状态
         const WCONTINUED = 1 << 3;
         /// Wait for any child
         const WALL = 1 << 30;
@@ -80,6 +80,41 @@ pub struct ITimerVal {
     pub it_value: TimeVal,
 }
 
+/// timer_settime / timerfd_settime 指定的类型，以纳秒为精度的定时器
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ITimerSpec {
+    /// The cycle of the timer
+    pub it_interval: TimeSecs,
+    /// The remaining time of the timer
+    pub it_value: TimeSecs,
+}
+
+/// timer_create 指定的类型，即 `struct sigevent`
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SigEvent {
+    /// The value sent with the signal
+    pub sigev_value: usize,
+    /// The signal to send
+    pub sigev_signo: i32,
+    /// How to notify, e.g. `SIGEV_SIGNAL`
+    pub sigev_notify: i32,
+    /// The thread to send the signal to for `SIGEV_THREAD_ID`
+    pub sigev_tid: i32,
+}
+
+/// 定时器到期时发送信号
+pub const SIGEV_SIGNAL: i32 = 0;
+/// 定时器到期时不通知
+pub const SIGEV_NONE: i32 = 1;
+/// 定时器到期时创建线程，由用户库实现
+pub const SIGEV_THREAD: i32 = 2;
+/// 定时器到期时向指定线程发送信号
+pub const SIGEV_THREAD_ID: i32 = 4;
+/// timer_settime 与 timerfd_settime 中，表示给定的是绝对时间
+pub const TIMER_ABSTIME: usize = 1;
+
 /// sys_nanosleep指定的结构体类型
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Default)]
@@ -107,6 +142,14 @@ impl TimeSecs {
         self.tv_sec * NSEC_PER_SEC + self.tv_nsec
     }
 
+    /// create a TimeSecs from nano seconds
+    pub fn from_nanos(nanos: usize) -> Self {
+        TimeSecs {
+            tv_sec: nanos / NSEC_PER_SEC,
+            tv_nsec: nanos % NSEC_PER_SEC,
+        }
+    }
+
     /// turn the TimeSecs to cpu ticks, which is related to cpu frequency
     pub fn get_ticks(&self) -> usize {
         self.tv_sec * axconfig::TIMER_FREQUENCY + (nanos_to_ticks(self.tv_nsec as u64) as usize)
@@ -173,6 +216,8 @@ bitflags! {
         const MAP_FIXED = 1 << 4;
         /// 不映射到实际文件
         const MAP_ANONYMOUS = 1 << 5;
+        /// 用作栈，在缺页时向下扩展
+        const MAP_GROWSDOWN = 1 << 8;
         /// 映射时不保留空间，即可能在实际使用mmp出来的内存时内存溢出
         const MAP_NORESERVE = 1 << 14;
         /// Allocation is for a stack.
@@ -272,6 +317,8 @@ pub const RLIMIT_STACK: i32 = 3;
 pub const RLIMIT_NOFILE: i32 = 7;
 /// 用户地址空间的最大大小
 pub const RLIMIT_AS: i32 = 9;
+/// 可以排队的未决信号数
+pub const RLIMIT_SIGPENDING: i32 = 11;
 
 /// robust list
 #[repr(C)]
@@ -342,11 +389,61 @@ numeric_enum_macro::numeric_enum! {
         F_GETFL = 3,
         /// 设置 flags 信息
         F_SETFL = 4,
+        /// 获取与给定记录锁冲突的锁
+        F_GETLK = 5,
+        /// 设置记录锁，冲突时立即返回
+        F_SETLK = 6,
+        /// 设置记录锁，冲突时等待
+        F_SETLKW = 7,
+        /// 获取与给定 OFD 锁冲突的锁
+        F_OFD_GETLK = 36,
+        /// 设置 OFD 锁，冲突时立即返回
+        F_OFD_SETLK = 37,
+        /// 设置 OFD 锁，冲突时等待
+        F_OFD_SETLKW = 38,
         /// 复制 fd，然后设置 cloexec 信息，即 exec 成功时删除该 fd
         F_DUPFD_CLOEXEC = 1030,
     }
 }
 
+/// sys_fcntl64 的记录锁命令使用的结构体
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Flock {
+    /// 锁的类型: F_RDLCK, F_WRLCK 或 F_UNLCK
+    pub l_type: i16,
+    /// l_start 的起点: SEEK_SET, SEEK_CUR 或 SEEK_END
+    pub l_whence: i16,
+    /// 锁的起始偏移
+    pub l_start: i64,
+    /// 锁的长度，为 0 时表示直到文件末尾
+    pub l_len: i64,
+    /// 持有冲突锁的进程，仅 F_GETLK 使用
+    pub l_pid: i32,
+}
+
+/// 共享锁
+pub const F_RDLCK: i16 = 0;
+/// 互斥锁
+pub const F_WRLCK: i16 = 1;
+/// 解锁
+pub const F_UNLCK: i16 = 2;
+
+bitflags::bitflags! {
+    /// sys_flock 的操作
+    #[derive(Debug, Clone, Copy)]
+    pub struct FlockOp: u32 {
+        /// 共享锁
+        const LOCK_SH = 1;
+        /// 互斥锁
+        const LOCK_EX = 2;
+        /// 不阻塞
+        const LOCK_NB = 4;
+        /// 解锁
+        const LOCK_UN = 8;
+    }
+}
+
 /// syscall_info 用到的 结构体
 #[repr(C)]
 #[derive(Debug)]
@@ -670,6 +767,9 @@ pub fn normal_file_mode(file_type: StMode) -> StMode {
 #[allow(unused)]
 pub const PR_NAME_SIZE: usize = 16;
 
+/// PR_SET_LOG_FILTER 接受的日志过滤规则的最大长度，不含末尾的 0
+pub const PR_LOG_FILTER_SIZE: usize = 1024;
+
 numeric_enum_macro::numeric_enum! {
     #[repr(usize)]
     #[allow(missing_docs)]
@@ -681,6 +781,10 @@ numeric_enum_macro::numeric_enum! {
         PR_SET_NAME = 15,
         /// get the name of the process
         PR_GET_NAME = 16,
+        /// Starry 扩展：设置内核的日志过滤规则，如 `warn,axfs=debug`
+        PR_SET_LOG_FILTER = 0x5354_0001,
+        /// Starry 扩展：获取内核的日志过滤规则
+        PR_GET_LOG_FILTER = 0x5354_0002,
     }
 }
 
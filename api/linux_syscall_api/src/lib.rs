@@ -3,6 +3,7 @@
 #![feature(stmt_expr_attributes)]
 mod ctypes;
 use ctypes::*;
+mod strace;
 mod syscall;
 mod syscall_fs;
 mod syscall_mem;
@@ -10,7 +11,7 @@ mod syscall_net;
 mod syscall_task;
 
 pub use axfs::api::{File, OpenFlags};
-pub use axprocess::link::{create_link, FilePath};
+pub use axprocess::link::FilePath;
 pub use syscall_fs::new_file;
 
 mod api;
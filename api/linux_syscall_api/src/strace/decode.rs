@@ -0,0 +1,440 @@
+//! 系统调用名称与参数的解码
+//!
+//! 系统调用号在不同架构上不同，但各个 `*SyscallId` 的变体名是相同的，
+//! 因此按照变体名查找系统调用的 Linux 名称与参数类型。
+//! 不在表中的系统调用以小写的变体名为名称，并以十六进制输出全部 6 个参数。
+extern crate alloc;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use axprocess::current_process;
+use axsignal::signal_no::SignalNo;
+
+use crate::syscall_fs::FsSyscallId;
+use crate::syscall_mem::MemSyscallId;
+use crate::syscall_net::NetSyscallId;
+use crate::syscall_task::TaskSyscallId;
+use crate::SyscallResult;
+
+/// 参数的类型，决定参数的输出格式
+#[derive(Clone, Copy)]
+pub(super) enum Arg {
+    /// 有符号整数
+    Int,
+    /// 无符号整数，如长度
+    UInt,
+    /// 以十六进制输出的整数，如标志位
+    Hex,
+    /// 用户态指针，为 0 时输出 NULL
+    Ptr,
+    /// 文件描述符，-100 输出为 AT_FDCWD
+    Fd,
+    /// 以 0 结尾的路径字符串
+    Path,
+    /// open 的标志位，如 O_RDONLY|O_CLOEXEC
+    OpenFlags,
+    /// 以八进制输出的文件权限
+    Mode,
+    /// 信号编号，如 SIGKILL
+    Signal,
+}
+
+/// 返回值的类型
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(super) enum Ret {
+    /// 整数，如文件描述符或读写的字节数
+    Int,
+    /// 地址，以十六进制输出
+    Addr,
+}
+
+use Arg::*;
+
+/// 系统调用的描述，即 (变体名, Linux 名称, 参数类型)
+type SyscallDesc = (&'static str, &'static str, &'static [Arg]);
+
+/// 已知参数类型的系统调用
+static SYSCALLS: &[SyscallDesc] = &[
+    // fs
+    ("GETCWD", "getcwd", &[Ptr, UInt]),
+    ("EVENTFD", "eventfd", &[UInt, Hex]),
+    ("EVENTFD2", "eventfd2", &[UInt, Hex]),
+    ("EPOLL_CREATE", "epoll_create", &[Hex]),
+    ("EPOLL_CREATE1", "epoll_create1", &[Hex]),
+    ("EPOLL_CTL", "epoll_ctl", &[Fd, Int, Fd, Ptr]),
+    ("EPOLL_WAIT", "epoll_wait", &[Fd, Ptr, Int, Int]),
+    (
+        "EPOLL_PWAIT",
+        "epoll_pwait",
+        &[Fd, Ptr, Int, Int, Ptr, UInt],
+    ),
+    ("DUP", "dup", &[Fd]),
+    ("DUP2", "dup2", &[Fd, Fd]),
+    ("DUP3", "dup3", &[Fd, Fd, OpenFlags]),
+    ("FCNTL64", "fcntl", &[Fd, Int, Hex]),
+    ("INOTIFY_INIT", "inotify_init", &[]),
+    ("INOTIFY_INIT1", "inotify_init1", &[OpenFlags]),
+    ("INOTIFY_ADD_WATCH", "inotify_add_watch", &[Fd, Path, Hex]),
+    ("INOTIFY_RM_WATCH", "inotify_rm_watch", &[Fd, Int]),
+    ("IOCTL", "ioctl", &[Fd, Hex, Hex]),
+    ("FLOCK", "flock", &[Fd, Int]),
+    ("MKDIRAT", "mkdirat", &[Fd, Path, Mode]),
+    ("MKDIR", "mkdir", &[Path, Mode]),
+    ("MKNOD", "mknodat", &[Fd, Path, Mode, Hex]),
+    ("RMDIR", "rmdir", &[Path]),
+    ("SYMLINKAT", "symlinkat", &[Path, Fd, Path]),
+    ("SYMLINK", "symlink", &[Path, Path]),
+    ("UNLINKAT", "unlinkat", &[Fd, Path, Hex]),
+    ("UNLINK", "unlink", &[Path]),
+    ("LINKAT", "linkat", &[Fd, Path, Fd, Path, Hex]),
+    ("LINK", "link", &[Path, Path]),
+    ("RENAMEAT", "renameat", &[Fd, Path, Fd, Path]),
+    ("RENAMEAT2", "renameat2", &[Fd, Path, Fd, Path, Hex]),
+    ("RENAME", "rename", &[Path, Path]),
+    ("UNMOUNT", "umount2", &[Path, Hex]),
+    ("MOUNT", "mount", &[Path, Path, Path, Hex, Ptr]),
+    ("STATFS", "statfs", &[Path, Ptr]),
+    ("FTRUNCATE64", "ftruncate", &[Fd, Int]),
+    ("FACCESSAT", "faccessat", &[Fd, Path, Mode]),
+    ("ACCESS", "access", &[Path, Mode]),
+    ("CHDIR", "chdir", &[Path]),
+    ("FCHMODAT", "fchmodat", &[Fd, Path, Mode]),
+    ("CHMOD", "chmod", &[Path, Mode]),
+    ("FCHOWN", "fchown", &[Fd, Int, Int]),
+    ("CHOWN", "chown", &[Path, Int, Int]),
+    ("OPENAT", "openat", &[Fd, Path, OpenFlags, Mode]),
+    ("OPEN", "open", &[Path, OpenFlags, Mode]),
+    ("CREAT", "creat", &[Path, Mode]),
+    ("CLOSE", "close", &[Fd]),
+    ("PIPE", "pipe", &[Ptr]),
+    ("PIPE2", "pipe2", &[Ptr, OpenFlags]),
+    ("GETDENTS64", "getdents64", &[Fd, Ptr, UInt]),
+    ("LSEEK", "lseek", &[Fd, Int, Int]),
+    ("READ", "read", &[Fd, Ptr, UInt]),
+    ("WRITE", "write", &[Fd, Ptr, UInt]),
+    ("READV", "readv", &[Fd, Ptr, Int]),
+    ("WRITEV", "writev", &[Fd, Ptr, Int]),
+    ("PREAD64", "pread64", &[Fd, Ptr, UInt, Int]),
+    ("PWRITE64", "pwrite64", &[Fd, Ptr, UInt, Int]),
+    ("SENDFILE64", "sendfile", &[Fd, Fd, Ptr, UInt]),
+    (
+        "COPYFILERANGE",
+        "copy_file_range",
+        &[Fd, Ptr, Fd, Ptr, UInt, Hex],
+    ),
+    ("POLL", "poll", &[Ptr, UInt, Int]),
+    ("PPOLL", "ppoll", &[Ptr, UInt, Ptr, Ptr, UInt]),
+    ("SELECT", "select", &[Int, Ptr, Ptr, Ptr, Ptr]),
+    ("PSELECT6", "pselect6", &[Int, Ptr, Ptr, Ptr, Ptr, Ptr]),
+    ("SIGNALFD", "signalfd", &[Fd, Ptr, UInt]),
+    ("SIGNALFD4", "signalfd4", &[Fd, Ptr, UInt, OpenFlags]),
+    ("FSTATAT", "newfstatat", &[Fd, Path, Ptr, Hex]),
+    ("FSTAT", "fstat", &[Fd, Ptr]),
+    ("STAT", "stat", &[Path, Ptr]),
+    ("LSTAT", "lstat", &[Path, Ptr]),
+    ("STATX", "statx", &[Fd, Path, Hex, Hex, Ptr]),
+    ("PREADLINKAT", "readlinkat", &[Fd, Path, Ptr, UInt]),
+    ("READLINK", "readlink", &[Path, Ptr, UInt]),
+    ("SYNC", "sync", &[]),
+    ("FSYNC", "fsync", &[Fd]),
+    ("TIMERFD_CREATE", "timerfd_create", &[Int, OpenFlags]),
+    ("TIMERFD_SETTIME", "timerfd_settime", &[Fd, Hex, Ptr, Ptr]),
+    ("TIMERFD_GETTIME", "timerfd_gettime", &[Fd, Ptr]),
+    ("UTIMENSAT", "utimensat", &[Fd, Path, Ptr, Hex]),
+    ("PIDFD_OPEN", "pidfd_open", &[Int, Hex]),
+    // mem
+    ("BRK", "brk", &[Ptr]),
+    ("MMAP", "mmap", &[Ptr, UInt, Hex, Hex, Fd, Hex]),
+    ("MUNMAP", "munmap", &[Ptr, UInt]),
+    ("MREMAP", "mremap", &[Ptr, UInt, UInt, Hex, Ptr]),
+    ("MPROTECT", "mprotect", &[Ptr, UInt, Hex]),
+    ("MSYNC", "msync", &[Ptr, UInt, Hex]),
+    ("MLOCK", "mlock", &[Ptr, UInt]),
+    ("MEMBARRIER", "membarrier", &[Int, Hex, Int]),
+    ("SHMGET", "shmget", &[Hex, UInt, Hex]),
+    ("SHMCTL", "shmctl", &[Int, Int, Ptr]),
+    ("SHMAT", "shmat", &[Int, Ptr, Hex]),
+    // net
+    ("SOCKET", "socket", &[Int, Hex, Int]),
+    ("SOCKETPAIR", "socketpair", &[Int, Hex, Int, Ptr]),
+    ("BIND", "bind", &[Fd, Ptr, UInt]),
+    ("CONNECT", "connect", &[Fd, Ptr, UInt]),
+    ("LISTEN", "listen", &[Fd, Int]),
+    ("ACCEPT", "accept", &[Fd, Ptr, Ptr]),
+    ("ACCEPT4", "accept4", &[Fd, Ptr, Ptr, OpenFlags]),
+    ("GETSOCKNAME", "getsockname", &[Fd, Ptr, Ptr]),
+    ("GETPEERNAME", "getpeername", &[Fd, Ptr, Ptr]),
+    ("SENDTO", "sendto", &[Fd, Ptr, UInt, Hex, Ptr, UInt]),
+    ("RECVFROM", "recvfrom", &[Fd, Ptr, UInt, Hex, Ptr, Ptr]),
+    ("SENDMSG", "sendmsg", &[Fd, Ptr, Hex]),
+    ("SENDMMSG", "sendmmsg", &[Fd, Ptr, UInt, Hex]),
+    ("SETSOCKOPT", "setsockopt", &[Fd, Int, Int, Ptr, UInt]),
+    ("GETSOCKOPT", "getsockopt", &[Fd, Int, Int, Ptr, Ptr]),
+    ("SHUTDOWN", "shutdown", &[Fd, Int]),
+    // task
+    ("EXIT", "exit", &[Int]),
+    ("EXIT_GROUP", "exit_group", &[Int]),
+    ("EXECVE", "execve", &[Path, Ptr, Ptr]),
+    ("CLONE", "clone", &[Hex, Ptr, Ptr, Ptr, Ptr]),
+    ("CLONE3", "clone3", &[Ptr, UInt]),
+    ("FORK", "fork", &[]),
+    ("VFORK", "vfork", &[]),
+    ("WAIT4", "wait4", &[Int, Ptr, Hex, Ptr]),
+    ("SET_TID_ADDRESS", "set_tid_address", &[Ptr]),
+    ("FUTEX", "futex", &[Ptr, Int, UInt, Ptr, Ptr, UInt]),
+    ("SET_ROBUST_LIST", "set_robust_list", &[Ptr, UInt]),
+    ("GET_ROBUST_LIST", "get_robust_list", &[Int, Ptr, Ptr]),
+    ("NANO_SLEEP", "nanosleep", &[Ptr, Ptr]),
+    ("CLOCK_NANOSLEEP", "clock_nanosleep", &[Int, Hex, Ptr, Ptr]),
+    ("CLOCK_GET_TIME", "clock_gettime", &[Int, Ptr]),
+    ("CLOCK_GETRES", "clock_getres", &[Int, Ptr]),
+    ("GETTIMEOFDAY", "gettimeofday", &[Ptr, Ptr]),
+    ("TIME", "time", &[Ptr]),
+    ("TIMES", "times", &[Ptr]),
+    ("ALARM", "alarm", &[UInt]),
+    ("GETTIMER", "getitimer", &[Int, Ptr]),
+    ("SETITIMER", "setitimer", &[Int, Ptr, Ptr]),
+    ("TIMER_CREATE", "timer_create", &[Int, Ptr, Ptr]),
+    ("TIMER_SETTIME", "timer_settime", &[Int, Hex, Ptr, Ptr]),
+    ("TIMER_GETTIME", "timer_gettime", &[Int, Ptr]),
+    ("TIMER_GETOVERRUN", "timer_getoverrun", &[Int]),
+    ("TIMER_DELETE", "timer_delete", &[Int]),
+    ("SYSLOG", "syslog", &[Int, Ptr, Int]),
+    ("SCHED_YIELD", "sched_yield", &[]),
+    ("SCHED_SETSCHEDULER", "sched_setscheduler", &[Int, Int, Ptr]),
+    ("SCHED_GETSCHEDULER", "sched_getscheduler", &[Int]),
+    ("SCHED_GET_PRORITY_MAX", "sched_get_priority_max", &[Int]),
+    ("SCHED_GET_PRORITY_MIN", "sched_get_priority_min", &[Int]),
+    ("SCHED_SETAFFINITY", "sched_setaffinity", &[Int, UInt, Ptr]),
+    ("SCHED_GETAFFINITY", "sched_getaffinity", &[Int, UInt, Ptr]),
+    (
+        "GET_MEMPOLICY",
+        "get_mempolicy",
+        &[Ptr, Ptr, UInt, Ptr, Hex],
+    ),
+    ("MADVICE", "madvise", &[Ptr, UInt, Int]),
+    ("PERSONALITY", "personality", &[Hex]),
+    ("PRCTL", "prctl", &[Int, Hex, Hex, Hex, Hex]),
+    ("ARCH_PRCTL", "arch_prctl", &[Hex, Hex]),
+    ("PRLIMIT64", "prlimit64", &[Int, Int, Ptr, Ptr]),
+    ("GETRUSAGE", "getrusage", &[Int, Ptr]),
+    ("GETRANDOM", "getrandom", &[Ptr, UInt, Hex]),
+    ("UMASK", "umask", &[Mode]),
+    ("UNAME", "uname", &[Ptr]),
+    ("SYSINFO", "sysinfo", &[Ptr]),
+    ("RSEQ", "rseq", &[Ptr, UInt, Hex, Hex]),
+    ("GETPID", "getpid", &[]),
+    ("GETPPID", "getppid", &[]),
+    ("GETTID", "gettid", &[]),
+    ("GETUID", "getuid", &[]),
+    ("GETEUID", "geteuid", &[]),
+    ("GETGID", "getgid", &[]),
+    ("GETEGID", "getegid", &[]),
+    ("SETGID", "setgid", &[Int]),
+    ("SETPGID", "setpgid", &[Int, Int]),
+    ("GETPGID", "getpgid", &[Int]),
+    ("SETSID", "setsid", &[]),
+    ("KILL", "kill", &[Int, Signal]),
+    ("TKILL", "tkill", &[Int, Signal]),
+    ("TGKILL", "tgkill", &[Int, Int, Signal]),
+    ("SIGACTION", "rt_sigaction", &[Signal, Ptr, Ptr, UInt]),
+    ("SIGPROCMASK", "rt_sigprocmask", &[Int, Ptr, Ptr, UInt]),
+    ("SIGSUSPEND", "rt_sigsuspend", &[Ptr, UInt]),
+    ("SIGTIMEDWAIT", "rt_sigtimedwait", &[Ptr, Ptr, Ptr, UInt]),
+    ("SIGRETURN", "rt_sigreturn", &[]),
+    ("SIGALTSTACK", "sigaltstack", &[Ptr, Ptr]),
+    ("RT_SIGQUEUEINFO", "rt_sigqueueinfo", &[Int, Signal, Ptr]),
+    (
+        "RT_TGSIGQUEUEINFO",
+        "rt_tgsigqueueinfo",
+        &[Int, Int, Signal, Ptr],
+    ),
+    (
+        "PIDFD_SEND_SIGNAL",
+        "pidfd_send_signal",
+        &[Fd, Signal, Ptr, Hex],
+    ),
+];
+
+/// 返回地址的系统调用
+const ADDR_RETURNS: &[&str] = &["brk", "mmap", "mremap", "shmat"];
+
+/// 未知参数类型时输出全部参数
+const UNKNOWN_ARGS: &[Arg] = &[Hex; 6];
+
+/// 输出的字符串的最大长度，更长的部分以 `...` 代替
+const MAX_STR_LEN: usize = 256;
+
+const AT_FDCWD: i32 = -100;
+
+/// Linux 的 open 标志位，不含访问模式
+const OPEN_FLAGS: &[(usize, &str)] = &[
+    (0o100, "O_CREAT"),
+    (0o200, "O_EXCL"),
+    (0o400, "O_NOCTTY"),
+    (0o1000, "O_TRUNC"),
+    (0o2000, "O_APPEND"),
+    (0o4000, "O_NONBLOCK"),
+    (0o4010000, "O_SYNC"),
+    (0o10000, "O_DSYNC"),
+    (0o20000, "O_ASYNC"),
+    (0o40000, "O_DIRECT"),
+    (0o100000, "O_LARGEFILE"),
+    (0o200000, "O_DIRECTORY"),
+    (0o400000, "O_NOFOLLOW"),
+    (0o1000000, "O_NOATIME"),
+    (0o2000000, "O_CLOEXEC"),
+    (0o10000000, "O_PATH"),
+];
+
+/// 返回系统调用号对应的变体名，不存在时返回 None
+///
+/// 与分发时相同，依次查找各个模块的系统调用号，后找到的优先
+fn variant_name(syscall_id: usize) -> Option<String> {
+    let mut name = None;
+    if let Ok(id) = NetSyscallId::try_from(syscall_id) {
+        name = Some(alloc::format!("{:?}", id));
+    }
+    if let Ok(id) = MemSyscallId::try_from(syscall_id) {
+        name = Some(alloc::format!("{:?}", id));
+    }
+    if let Ok(id) = FsSyscallId::try_from(syscall_id) {
+        name = Some(alloc::format!("{:?}", id));
+    }
+    if let Ok(id) = TaskSyscallId::try_from(syscall_id) {
+        name = Some(alloc::format!("{:?}", id));
+    }
+    name
+}
+
+/// 返回系统调用的 Linux 名称与参数类型，不存在的系统调用返回 None
+pub(super) fn describe(syscall_id: usize) -> Option<(String, &'static [Arg], Ret)> {
+    let variant = variant_name(syscall_id)?;
+    let (name, args) = match SYSCALLS.iter().find(|(id, _, _)| *id == variant) {
+        Some((_, name, args)) => (name.to_string(), *args),
+        None => (variant.to_lowercase(), UNKNOWN_ARGS),
+    };
+    let ret = if ADDR_RETURNS.contains(&name.as_str()) {
+        Ret::Addr
+    } else {
+        Ret::Int
+    };
+    Some((name, args, ret))
+}
+
+/// 读取用户态以 0 结尾的字符串，最多读取 `max_len` 字节
+///
+/// 返回读取到的字节与字符串是否更长，地址无效时返回 None
+fn read_user_str(ptr: usize, max_len: usize) -> Option<(Vec<u8>, bool)> {
+    let process = current_process();
+    let mut bytes = Vec::new();
+    for addr in ptr..ptr + max_len {
+        // 每进入一个新的页面都需要确保其已被分配
+        if (addr == ptr || addr % axhal::mem::PAGE_SIZE_4K == 0)
+            && process.manual_alloc_for_lazy(addr.into()).is_err()
+        {
+            return None;
+        }
+        match unsafe { *(addr as *const u8) } {
+            0 => return Some((bytes, false)),
+            c => bytes.push(c),
+        }
+    }
+    Some((bytes, true))
+}
+
+/// 以 C 字符串字面量的格式输出，不可打印的字符被转义
+fn write_escaped(out: &mut String, bytes: &[u8]) {
+    out.push('"');
+    for &c in bytes {
+        match c {
+            b'"' => out.push_str("\\\""),
+            b'\\' => out.push_str("\\\\"),
+            b'\n' => out.push_str("\\n"),
+            b'\t' => out.push_str("\\t"),
+            b' '..=b'~' => out.push(c as char),
+            _ => {
+                let _ = write!(out, "\\x{:02x}", c);
+            }
+        }
+    }
+    out.push('"');
+}
+
+fn write_open_flags(out: &mut String, flags: usize) {
+    out.push_str(match flags & 0o3 {
+        0 => "O_RDONLY",
+        1 => "O_WRONLY",
+        2 => "O_RDWR",
+        _ => "O_ACCMODE",
+    });
+    let mut rest = flags & !0o3;
+    for &(flag, name) in OPEN_FLAGS {
+        if rest & flag == flag {
+            let _ = write!(out, "|{}", name);
+            rest &= !flag;
+        }
+    }
+    if rest != 0 {
+        let _ = write!(out, "|{:#x}", rest);
+    }
+}
+
+fn write_signal(out: &mut String, sig: usize) {
+    let _ = match sig {
+        1..=31 => write!(out, "{:?}", SignalNo::from(sig)),
+        32 => write!(out, "SIGRTMIN"),
+        33..=64 => write!(out, "SIGRTMIN+{}", sig - 32),
+        _ => write!(out, "{}", sig as i32),
+    };
+}
+
+/// 按照参数类型输出一个参数
+pub(super) fn write_arg(out: &mut String, arg: Arg, value: usize) {
+    match arg {
+        Int => {
+            let _ = write!(out, "{}", value as isize);
+        }
+        UInt => {
+            let _ = write!(out, "{}", value);
+        }
+        Hex => {
+            let _ = write!(out, "{:#x}", value);
+        }
+        Ptr if value == 0 => out.push_str("NULL"),
+        Ptr => {
+            let _ = write!(out, "{:#x}", value);
+        }
+        Fd if value as i32 == AT_FDCWD => out.push_str("AT_FDCWD"),
+        Fd => {
+            let _ = write!(out, "{}", value as i32);
+        }
+        Path if value == 0 => out.push_str("NULL"),
+        Path => match read_user_str(value, MAX_STR_LEN) {
+            Some((bytes, truncated)) => {
+                write_escaped(out, &bytes);
+                if truncated {
+                    out.push_str("...");
+                }
+            }
+            None => {
+                let _ = write!(out, "{:#x}", value);
+            }
+        },
+        OpenFlags => write_open_flags(out, value),
+        Mode => {
+            let _ = write!(out, "0{:o}", value);
+        }
+        Signal => write_signal(out, value),
+    }
+}
+
+/// 输出系统调用的返回值，错误时输出错误码的名称与描述
+pub(super) fn write_result(out: &mut String, ret: Ret, result: &SyscallResult) {
+    let _ = match result {
+        Ok(value) if ret == Ret::Addr => write!(out, "{:#x}", value),
+        Ok(value) => write!(out, "{}", value),
+        Err(err) => write!(out, "-1 {:?} ({})", err, err.as_str()),
+    };
+}
@@ -0,0 +1,311 @@
+//! 系统调用跟踪，类似于 strace
+//!
+//! 开启后，每个符合过滤条件的系统调用在返回时生成一条记录，包含开始时间、pid:tid、
+//! 解码后的名称与参数、返回值或错误码以及耗时，例如
+//!
+//! ```text
+//! [    1.234567] 3:3 openat(AT_FDCWD, "/etc/passwd", O_RDONLY|O_CLOEXEC) = -1 ENOENT (No such file or directory) <0.000042>
+//! ```
+//!
+//! 不会返回的 exit 与 exit_group 在进入时生成记录，返回值记为 `?`。
+//!
+//! 记录保存在大小固定的环形缓冲区中，可以从 `/proc/strace` 读取，也可以同时输出到控制台。
+//! 通过写入 `/proc/sys/kernel/strace` 或在编译时设置 `AX_STRACE` 控制跟踪，
+//! 格式为以空白分隔的若干项，未给出的项保持不变：
+//!
+//! - `on` / `off`：开启或关闭跟踪
+//! - `pid=1,2` / `tid=3`：只跟踪给定的进程或线程，`all` 表示不限制
+//! - `syscall=openat,read`：只跟踪给定的系统调用，可以使用名称或编号，`all` 表示不限制
+//! - `console=on` / `console=off`：是否同时输出到控制台
+//! - `clear`：清空缓冲区
+mod decode;
+
+extern crate alloc;
+use alloc::collections::{BTreeSet, VecDeque};
+use alloc::format;
+use alloc::string::String;
+use core::fmt::Write;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
+
+use axerrno::{AxError, AxResult};
+use axhal::time::current_time_nanos;
+use axlog::{ax_print, warn};
+use axprocess::{current_process, current_task};
+use axsync::Mutex;
+
+use crate::SyscallResult;
+use decode::Ret;
+
+/// 记录缓冲区的大小，超出时丢弃最早的记录
+pub const TRACE_BUF_LEN: usize = 64 * 1024;
+
+/// 按名称查找系统调用时搜索的系统调用号范围
+const MAX_SYSCALL_ID: usize = 512;
+
+/// 是否开启跟踪，与 CONFIG 中的值相同，用于在关闭时快速返回
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+static CONFIG: Mutex<TraceConfig> = Mutex::new(TraceConfig {
+    enabled: false,
+    console: false,
+    pids: None,
+    tids: None,
+    syscalls: None,
+});
+
+static BUFFER: Mutex<TraceBuffer> = Mutex::new(TraceBuffer {
+    records: VecDeque::new(),
+    size: 0,
+});
+
+/// 跟踪的配置，过滤条件为 None 时表示不限制
+#[derive(Clone)]
+struct TraceConfig {
+    enabled: bool,
+    console: bool,
+    pids: Option<BTreeSet<u64>>,
+    tids: Option<BTreeSet<u64>>,
+    syscalls: Option<BTreeSet<usize>>,
+}
+
+impl TraceConfig {
+    fn matches(&self, pid: u64, tid: u64, syscall_id: usize) -> bool {
+        self.pids.as_ref().map_or(true, |pids| pids.contains(&pid))
+            && self.tids.as_ref().map_or(true, |tids| tids.contains(&tid))
+            && self
+                .syscalls
+                .as_ref()
+                .map_or(true, |syscalls| syscalls.contains(&syscall_id))
+    }
+}
+
+/// 保存记录的环形缓冲区
+struct TraceBuffer {
+    records: VecDeque<String>,
+    /// 所有记录的总长度
+    size: usize,
+}
+
+impl TraceBuffer {
+    fn push(&mut self, record: String) {
+        self.size += record.len();
+        self.records.push_back(record);
+        while self.size > TRACE_BUF_LEN {
+            let dropped = self.records.pop_front().unwrap();
+            self.size -= dropped.len();
+        }
+    }
+
+    fn clear(&mut self) {
+        self.records.clear();
+        self.size = 0;
+    }
+}
+
+/// 一次正在进行的系统调用，在返回时调用 [`SyscallTrace::exit`] 生成记录
+pub(crate) struct SyscallTrace {
+    /// 开始的时间，单位为纳秒
+    start: u64,
+    /// 已解码的 `pid:tid name(args)`
+    call: String,
+    ret: Ret,
+    console: bool,
+}
+
+impl SyscallTrace {
+    /// 记录系统调用的返回值
+    pub(crate) fn exit(self, result: &SyscallResult) {
+        let mut call = self.call;
+        call.push_str(" = ");
+        decode::write_result(&mut call, self.ret, result);
+        let elapsed = Duration::from_nanos(current_time_nanos() - self.start);
+        let _ = write!(
+            call,
+            " <{}.{:06}>",
+            elapsed.as_secs(),
+            elapsed.subsec_micros()
+        );
+        emit(self.start, &call, self.console);
+    }
+}
+
+/// 生成一条记录，写入缓冲区并按需输出到控制台
+fn emit(start: u64, call: &str, console: bool) {
+    let start = Duration::from_nanos(start);
+    let record = format!(
+        "[{:>5}.{:06}] {}\n",
+        start.as_secs(),
+        start.subsec_micros(),
+        call
+    );
+    if console {
+        ax_print!("{}", record);
+    }
+    BUFFER.lock().push(record);
+}
+
+/// 在进入系统调用时调用，当前系统调用需要被跟踪时返回 Some
+pub(crate) fn enter(syscall_id: usize, args: [usize; 6]) -> Option<SyscallTrace> {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return None;
+    }
+    let pid = current_process().pid();
+    let tid = current_task().id().as_u64();
+    let console = {
+        let config = CONFIG.lock();
+        if !config.matches(pid, tid, syscall_id) {
+            return None;
+        }
+        config.console
+    };
+    let (name, arg_types, ret) = decode::describe(syscall_id)?;
+    let mut call = format!("{}:{} {}(", pid, tid, name);
+    for (i, (&arg, &value)) in arg_types.iter().zip(args.iter()).enumerate() {
+        if i != 0 {
+            call.push_str(", ");
+        }
+        decode::write_arg(&mut call, arg, value);
+    }
+    call.push(')');
+    let start = current_time_nanos();
+    if name == "exit" || name == "exit_group" {
+        call.push_str(" = ?");
+        emit(start, &call, console);
+        return None;
+    }
+    Some(SyscallTrace {
+        start,
+        call,
+        ret,
+        console,
+    })
+}
+
+/// 解析 `pid=` 与 `tid=` 的值
+fn parse_ids(value: &str) -> AxResult<Option<BTreeSet<u64>>> {
+    if value == "all" {
+        return Ok(None);
+    }
+    value
+        .split(',')
+        .map(|id| id.parse().map_err(|_| AxError::InvalidInput))
+        .collect::<AxResult<_>>()
+        .map(Some)
+}
+
+/// 解析 `syscall=` 的值
+fn parse_syscalls(value: &str) -> AxResult<Option<BTreeSet<usize>>> {
+    if value == "all" {
+        return Ok(None);
+    }
+    let mut syscalls = BTreeSet::new();
+    for syscall in value.split(',') {
+        let syscall_id = match syscall.parse() {
+            Ok(syscall_id) => syscall_id,
+            Err(_) => (0..MAX_SYSCALL_ID)
+                .find(|&id| decode::describe(id).is_some_and(|(name, _, _)| name == syscall))
+                .ok_or(AxError::InvalidInput)?,
+        };
+        syscalls.insert(syscall_id);
+    }
+    Ok(Some(syscalls))
+}
+
+fn parse_switch(value: &str) -> AxResult<bool> {
+    match value {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        _ => Err(AxError::InvalidInput),
+    }
+}
+
+/// 修改跟踪的配置，格式见[模块文档](self)，格式错误时不做任何修改
+pub fn set_strace(spec: &str) -> AxResult {
+    let mut config = CONFIG.lock();
+    let mut new = config.clone();
+    let mut clear = false;
+    for item in spec.split_whitespace() {
+        match item.split_once('=') {
+            None if item == "clear" => clear = true,
+            None => new.enabled = parse_switch(item)?,
+            Some(("pid", value)) => new.pids = parse_ids(value)?,
+            Some(("tid", value)) => new.tids = parse_ids(value)?,
+            Some(("syscall", value)) => new.syscalls = parse_syscalls(value)?,
+            Some(("console", value)) => new.console = parse_switch(value)?,
+            Some(_) => return Err(AxError::InvalidInput),
+        }
+    }
+    if clear {
+        BUFFER.lock().clear();
+    }
+    ENABLED.store(new.enabled, Ordering::Relaxed);
+    *config = new;
+    Ok(())
+}
+
+/// `/proc/sys/kernel/strace` 的内容，即当前的配置
+fn show_config() -> String {
+    let config = CONFIG.lock();
+    let switch = |on| if on { "on" } else { "off" };
+    let mut spec = String::from(switch(config.enabled));
+    let _ = write!(spec, " console={}", switch(config.console));
+    for (key, ids) in [("pid", &config.pids), ("tid", &config.tids)] {
+        let _ = write!(spec, " {}=", key);
+        match ids {
+            None => spec.push_str("all"),
+            Some(ids) => write_list(&mut spec, ids.iter().map(|id| format!("{}", id))),
+        }
+    }
+    spec.push_str(" syscall=");
+    match &config.syscalls {
+        None => spec.push_str("all"),
+        Some(syscalls) => write_list(
+            &mut spec,
+            syscalls.iter().map(|&id| match decode::describe(id) {
+                Some((name, _, _)) => name,
+                None => format!("{}", id),
+            }),
+        ),
+    }
+    spec.push('\n');
+    spec
+}
+
+fn write_list(out: &mut String, items: impl Iterator<Item = String>) {
+    for (i, item) in items.enumerate() {
+        if i != 0 {
+            out.push(',');
+        }
+        out.push_str(&item);
+    }
+}
+
+fn store_config(spec: &str) -> AxResult {
+    set_strace(spec).map_err(|err| {
+        warn!("invalid strace config {:?}", spec);
+        err
+    })
+}
+
+/// `/proc/strace` 的内容，即缓冲区中的所有记录
+fn show_records() -> String {
+    BUFFER.lock().records.iter().map(String::as_str).collect()
+}
+
+/// 创建 procfs 中的跟踪文件，并应用编译时的 `AX_STRACE` 配置
+///
+/// 由 [`crate::init_procfs`] 在文件系统初始化之后调用一次
+pub(crate) fn init() {
+    if let Some(spec) = option_env!("AX_STRACE") {
+        let _ = store_config(spec);
+    }
+    if let Err(err) = axfs::procfs::register_text("strace", show_records, None) {
+        warn!("failed to create /proc/strace: {:?}", err);
+    }
+    if let Err(err) =
+        axfs::procfs::register_text("sys/kernel/strace", show_config, Some(store_config))
+    {
+        warn!("failed to create /proc/sys/kernel/strace: {:?}", err);
+    }
+}
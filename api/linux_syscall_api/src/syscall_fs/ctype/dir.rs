@@ -19,6 +19,13 @@ impl DirDesc {
     }
 }
 
+impl Drop for DirDesc {
+    /// 最后一个引用被释放时，释放该打开的目录持有的 flock 锁
+    fn drop(&mut self) {
+        axfs::lock::release_file_locks(self as *const Self as usize);
+    }
+}
+
 /// 为DirDesc实现FileIO trait
 impl FileIO for DirDesc {
     fn read(&self, _: &mut [u8]) -> AxResult<usize> {
@@ -50,11 +57,12 @@ impl FileIO for DirDesc {
     }
 
     fn get_stat(&self) -> AxResult<Kstat> {
+        let (ino, nlink) = axfs::api::inode_info(self.dir_path.as_str(), true).unwrap_or((0, 1));
         let kstat = Kstat {
             st_dev: 1,
-            st_ino: 0,
+            st_ino: ino,
             st_mode: normal_file_mode(StMode::S_IFDIR).bits(),
-            st_nlink: 1,
+            st_nlink: nlink as _,
             st_uid: 0,
             st_gid: 0,
             st_rdev: 0,
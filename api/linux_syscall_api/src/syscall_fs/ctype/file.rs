@@ -11,7 +11,6 @@ use axfs::api::{File, FileIO, FileIOType, Kstat, OpenFlags, Read, Seek, SeekFrom
 use axlog::debug;
 
 use crate::{new_file, normal_file_mode, StMode, TimeSecs};
-use axprocess::link::get_link_count;
 use axsync::Mutex;
 
 pub static INODE_NAME_MAP: Mutex<BTreeMap<String, u64>> = Mutex::new(BTreeMap::new());
@@ -94,8 +93,12 @@ impl FileIO for FileDesc {
         let file = self.file.lock();
         let attr = file.get_attr()?;
         let stat = self.stat.lock();
+        // 优先使用文件系统自身的 inode 编号和硬链接数，不支持时退回到 INODE_NAME_MAP
+        let inode_info = axfs::api::inode_info(self.path.as_str(), true).ok();
         let inode_map = INODE_NAME_MAP.lock();
-        let inode_number = if let Some(inode_number) = inode_map.get(&self.path) {
+        let inode_number = if let Some((ino, _)) = inode_info {
+            ino
+        } else if let Some(inode_number) = inode_map.get(&self.path) {
             *inode_number
         } else {
             // return Err(axerrno::AxError::NotFound);
@@ -112,7 +115,7 @@ impl FileIO for FileDesc {
             st_dev: 1,
             st_ino: inode_number,
             st_mode: normal_file_mode(StMode::S_IFREG).bits() | 0o777,
-            st_nlink: get_link_count(&(self.path.as_str().to_string())) as _,
+            st_nlink: inode_info.map_or(1, |(_, nlink)| nlink as _),
             st_uid: 0,
             st_gid: 0,
             st_rdev: 0,
@@ -195,6 +198,13 @@ impl FileDesc {
     }
 }
 
+impl Drop for FileDesc {
+    /// 最后一个引用被释放时，释放该打开的文件描述持有的 flock 锁和 OFD 锁
+    fn drop(&mut self) {
+        axfs::lock::release_file_locks(self as *const Self as usize);
+    }
+}
+
 /// 新建一个文件描述符
 pub fn new_fd(path: String, flags: OpenFlags) -> AxResult<FileDesc> {
     debug!("Into function new_fd, path: {}", path);
@@ -0,0 +1,408 @@
+//! inotify 实例，监视文件系统的变化
+//!
+//! 事件由 axfs 的 [`axfs::notify`] 模块产生，这里负责按监视项过滤并转换为 `struct inotify_event`
+extern crate alloc;
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use axerrno::{AxError, AxResult};
+use axfs::api::{FileIO, FileIOType, OpenFlags};
+use axfs::notify::{FsEvent, FsEventMask, FsWatcher};
+use axsync::Mutex;
+use axtask::WaitQueue;
+use bitflags::bitflags;
+
+bitflags! {
+    /// inotify 的事件及选项
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct InotifyMask: u32 {
+        /// 文件被读取
+        const IN_ACCESS = 0x1;
+        /// 文件被修改
+        const IN_MODIFY = 0x2;
+        /// 文件属性被修改
+        const IN_ATTRIB = 0x4;
+        /// 以写方式打开的文件被关闭
+        const IN_CLOSE_WRITE = 0x8;
+        /// 以非写方式打开的文件被关闭
+        const IN_CLOSE_NOWRITE = 0x10;
+        /// 文件被打开
+        const IN_OPEN = 0x20;
+        /// 文件被移出监视的目录
+        const IN_MOVED_FROM = 0x40;
+        /// 文件被移入监视的目录
+        const IN_MOVED_TO = 0x80;
+        /// 在监视的目录中创建了文件
+        const IN_CREATE = 0x100;
+        /// 在监视的目录中删除了文件
+        const IN_DELETE = 0x200;
+        /// 监视的文件本身被删除
+        const IN_DELETE_SELF = 0x400;
+        /// 监视的文件本身被移动
+        const IN_MOVE_SELF = 0x800;
+        /// 文件系统被卸载
+        const IN_UNMOUNT = 0x2000;
+        /// 事件队列溢出
+        const IN_Q_OVERFLOW = 0x4000;
+        /// 监视项被移除
+        const IN_IGNORED = 0x8000;
+        /// 只监视目录
+        const IN_ONLYDIR = 0x100_0000;
+        /// 不跟随符号链接
+        const IN_DONT_FOLLOW = 0x200_0000;
+        /// 不报告已删除的子项的事件
+        const IN_EXCL_UNLINK = 0x400_0000;
+        /// 监视项已存在时返回 EEXIST
+        const IN_MASK_CREATE = 0x1000_0000;
+        /// 与已有的监视项的掩码合并
+        const IN_MASK_ADD = 0x2000_0000;
+        /// 事件的主体是目录
+        const IN_ISDIR = 0x4000_0000;
+        /// 只报告一次事件
+        const IN_ONESHOT = 0x8000_0000;
+    }
+}
+
+impl InotifyMask {
+    /// 所有可以被监视的事件
+    pub const IN_ALL_EVENTS: Self = Self::from_bits_retain(0xfff);
+}
+
+/// 事件队列的最大长度，与 Linux 的 max_queued_events 默认值相同
+const MAX_QUEUED_EVENTS: usize = 16384;
+
+/// `struct inotify_event` 中定长部分的大小
+const EVENT_HEADER_SIZE: usize = 16;
+
+/// ioctl 获取可读字节数
+const FIONREAD: usize = 0x541B;
+
+struct Watch {
+    /// 监视的文件的绝对路径
+    path: String,
+    mask: InotifyMask,
+}
+
+#[derive(PartialEq, Eq)]
+struct InotifyEvent {
+    wd: i32,
+    mask: InotifyMask,
+    cookie: u32,
+    name: String,
+}
+
+impl InotifyEvent {
+    /// name 字段的长度，包括结尾的 '\0' 和对齐用的填充
+    fn name_len(&self) -> usize {
+        if self.name.is_empty() {
+            0
+        } else {
+            (self.name.len() + 1).next_multiple_of(EVENT_HEADER_SIZE)
+        }
+    }
+
+    fn size(&self) -> usize {
+        EVENT_HEADER_SIZE + self.name_len()
+    }
+
+    fn write_to(&self, buf: &mut [u8]) {
+        let name_len = self.name_len();
+        buf[0..4].copy_from_slice(&self.wd.to_ne_bytes());
+        buf[4..8].copy_from_slice(&self.mask.bits().to_ne_bytes());
+        buf[8..12].copy_from_slice(&self.cookie.to_ne_bytes());
+        buf[12..16].copy_from_slice(&(name_len as u32).to_ne_bytes());
+        let name = &mut buf[EVENT_HEADER_SIZE..EVENT_HEADER_SIZE + name_len];
+        name.fill(0);
+        name[..self.name.len()].copy_from_slice(self.name.as_bytes());
+    }
+}
+
+struct InotifyInner {
+    watches: BTreeMap<i32, Watch>,
+    next_wd: i32,
+    events: VecDeque<InotifyEvent>,
+    /// rename 事件的 cookie 到被移动的监视项，用于在 MOVED_TO 时更新其路径
+    moving: BTreeMap<u32, i32>,
+}
+
+impl InotifyInner {
+    fn push_event(&mut self, wd: i32, mask: InotifyMask, cookie: u32, name: &str) {
+        let event = InotifyEvent {
+            wd,
+            mask,
+            cookie,
+            name: name.to_string(),
+        };
+        // 与上一个未读的事件相同时合并
+        if self.events.back() == Some(&event) {
+            return;
+        }
+        if self.events.len() >= MAX_QUEUED_EVENTS {
+            if self
+                .events
+                .back()
+                .map_or(true, |last| last.mask != InotifyMask::IN_Q_OVERFLOW)
+            {
+                self.events.push_back(InotifyEvent {
+                    wd: -1,
+                    mask: InotifyMask::IN_Q_OVERFLOW,
+                    cookie: 0,
+                    name: String::new(),
+                });
+            }
+            return;
+        }
+        self.events.push_back(event);
+    }
+
+    fn remove_watch(&mut self, wd: i32) -> bool {
+        if self.watches.remove(&wd).is_some() {
+            self.push_event(wd, InotifyMask::IN_IGNORED, 0, "");
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// inotify 文件描述符
+pub struct Inotify {
+    inner: Mutex<InotifyInner>,
+    flags: Mutex<OpenFlags>,
+    /// 等待事件的读者，有新事件加入队列时被唤醒
+    readers: WaitQueue,
+}
+
+impl Inotify {
+    /// 创建一个新的 inotify 实例
+    pub fn new(flags: OpenFlags) -> Self {
+        Self {
+            inner: Mutex::new(InotifyInner {
+                watches: BTreeMap::new(),
+                next_wd: 1,
+                events: VecDeque::new(),
+                moving: BTreeMap::new(),
+            }),
+            flags: Mutex::new(flags),
+            readers: WaitQueue::new(),
+        }
+    }
+
+    /// 添加或修改对 `path` 的监视项，返回监视项的编号
+    pub fn add_watch(&self, path: &str, mask: InotifyMask) -> AxResult<i32> {
+        let path = normalize(path);
+        let mut inner = self.inner.lock();
+        let existing = inner
+            .watches
+            .iter()
+            .find(|(_, watch)| watch.path == path)
+            .map(|(wd, _)| *wd);
+        if let Some(wd) = existing {
+            if mask.contains(InotifyMask::IN_MASK_CREATE) {
+                return Err(AxError::AlreadyExists);
+            }
+            let watch = inner.watches.get_mut(&wd).unwrap();
+            if mask.contains(InotifyMask::IN_MASK_ADD) {
+                watch.mask |= mask;
+            } else {
+                watch.mask = mask;
+            }
+            return Ok(wd);
+        }
+        let wd = inner.next_wd;
+        inner.next_wd += 1;
+        inner.watches.insert(wd, Watch { path, mask });
+        Ok(wd)
+    }
+
+    /// 移除监视项
+    pub fn rm_watch(&self, wd: i32) -> AxResult {
+        if self.inner.lock().remove_watch(wd) {
+            Ok(())
+        } else {
+            Err(AxError::InvalidInput)
+        }
+    }
+
+    fn is_non_block(&self) -> bool {
+        self.flags.lock().contains(OpenFlags::NON_BLOCK)
+    }
+}
+
+/// 去掉路径末尾的 '/'
+fn normalize(path: &str) -> String {
+    match path.trim_end_matches('/') {
+        "" => String::from("/"),
+        path => path.to_string(),
+    }
+}
+
+/// 由 axfs 的事件得到 inotify 对应的事件
+fn to_inotify_mask(mask: FsEventMask) -> InotifyMask {
+    InotifyMask::from_bits_truncate(mask.bits())
+}
+
+impl FsWatcher for Inotify {
+    fn on_event(&self, event: &FsEvent) {
+        let (parent, name) = match event.path.rsplit_once('/') {
+            Some(("", name)) => ("/", name),
+            Some((parent, name)) => (parent, name),
+            None => return,
+        };
+        let mask = to_inotify_mask(event.mask);
+        let isdir = mask & InotifyMask::IN_ISDIR;
+        let kind = mask - InotifyMask::IN_ISDIR;
+        let mut inner = self.inner.lock();
+        let mut oneshot = Vec::new();
+        let mut ignored = Vec::new();
+        let mut moved = None;
+        let watches: Vec<(i32, String, InotifyMask)> = inner
+            .watches
+            .iter()
+            .map(|(wd, watch)| (*wd, watch.path.clone(), watch.mask))
+            .collect();
+        for (wd, path, watch_mask) in watches {
+            // 监视的目录中的子项发生的事件
+            if path == parent && watch_mask.intersects(kind) {
+                inner.push_event(wd, kind | isdir, event.cookie, name);
+                if watch_mask.contains(InotifyMask::IN_ONESHOT) {
+                    oneshot.push(wd);
+                }
+            }
+            if path != event.path {
+                continue;
+            }
+            // 监视的文件本身发生的事件
+            let self_event = if kind == InotifyMask::IN_DELETE {
+                ignored.push(wd);
+                InotifyMask::IN_DELETE_SELF
+            } else if kind == InotifyMask::IN_MOVED_FROM {
+                inner.moving.insert(event.cookie, wd);
+                InotifyMask::IN_MOVE_SELF
+            } else if kind.intersects(InotifyMask::IN_MOVED_TO | InotifyMask::IN_CREATE) {
+                InotifyMask::empty()
+            } else {
+                kind
+            };
+            if watch_mask.intersects(self_event) {
+                inner.push_event(wd, self_event | isdir, 0, "");
+                if watch_mask.contains(InotifyMask::IN_ONESHOT) {
+                    oneshot.push(wd);
+                }
+            }
+        }
+        if kind == InotifyMask::IN_MOVED_TO {
+            moved = inner.moving.remove(&event.cookie);
+        }
+        // 被移动的监视项跟随文件到新的路径
+        if let Some(wd) = moved {
+            if let Some(watch) = inner.watches.get_mut(&wd) {
+                watch.path = event.path.to_string();
+            }
+        }
+        for wd in ignored.into_iter().chain(oneshot) {
+            inner.remove_watch(wd);
+        }
+        let has_events = !inner.events.is_empty();
+        drop(inner);
+        if has_events {
+            self.readers.notify_all();
+        }
+    }
+}
+
+impl FileIO for Inotify {
+    fn read(&self, buf: &mut [u8]) -> AxResult<usize> {
+        let process = axprocess::current_process();
+        loop {
+            let mut inner = self.inner.lock();
+            if let Some(first) = inner.events.front() {
+                if first.size() > buf.len() {
+                    return Err(AxError::InvalidInput);
+                }
+                let mut read = 0;
+                while let Some(event) = inner.events.front() {
+                    let size = event.size();
+                    if read + size > buf.len() {
+                        break;
+                    }
+                    event.write_to(&mut buf[read..read + size]);
+                    read += size;
+                    inner.events.pop_front();
+                }
+                return Ok(read);
+            }
+            drop(inner);
+            if self.is_non_block() {
+                return Err(AxError::WouldBlock);
+            }
+            if process.have_signals().is_some() {
+                return Err(AxError::Interrupted);
+            }
+            self.readers
+                .wait_until(|| self.ready_to_read() || process.have_signals().is_some());
+        }
+    }
+
+    fn readable(&self) -> bool {
+        true
+    }
+
+    fn writable(&self) -> bool {
+        false
+    }
+
+    fn executable(&self) -> bool {
+        false
+    }
+
+    fn get_type(&self) -> FileIOType {
+        FileIOType::Other
+    }
+
+    fn ready_to_read(&self) -> bool {
+        !self.inner.lock().events.is_empty()
+    }
+
+    fn ready_to_write(&self) -> bool {
+        false
+    }
+
+    fn set_status(&self, flags: OpenFlags) -> bool {
+        *self.flags.lock() = flags;
+        true
+    }
+
+    fn get_status(&self) -> OpenFlags {
+        *self.flags.lock()
+    }
+
+    fn set_close_on_exec(&self, is_set: bool) -> bool {
+        if is_set {
+            *self.flags.lock() |= OpenFlags::CLOEXEC;
+        } else {
+            *self.flags.lock() &= !OpenFlags::CLOEXEC;
+        }
+        true
+    }
+
+    fn ioctl(&self, request: usize, arg1: usize) -> AxResult<isize> {
+        // 其余请求 (如 FIONBIO) 由 ioctl 的通用处理完成
+        if request != FIONREAD {
+            return Err(AxError::Unsupported);
+        }
+        let len: usize = self
+            .inner
+            .lock()
+            .events
+            .iter()
+            .map(InotifyEvent::size)
+            .sum();
+        let count = arg1 as *mut i32;
+        axprocess::current_process()
+            .manual_alloc_type_for_lazy(count as *const i32)
+            .map_err(|_| AxError::BadAddress)?;
+        unsafe { *count = len as i32 };
+        Ok(0)
+    }
+}
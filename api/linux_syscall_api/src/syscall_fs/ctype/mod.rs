@@ -12,4 +12,10 @@ pub mod epoll;
 
 pub mod eventfd;
 
+pub mod inotify;
+
 pub mod pidfd;
+
+pub mod signalfd;
+
+pub mod timerfd;
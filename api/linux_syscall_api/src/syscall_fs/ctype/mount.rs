@@ -143,14 +143,15 @@ pub fn get_stat_in_fs(path: &FilePath) -> Result<Kstat, SyscallError> {
                     .as_any()
                     .downcast_ref::<axfs::axfs_devfs::NullDev>()
                     .is_some()
-                || node
-                    .as_any()
-                    .downcast_ref::<axfs::axfs_devfs::RandomDev>()
-                    .is_some()
             {
                 stat.st_mode = normal_file_mode(StMode::S_IFCHR).bits();
                 return Ok(stat);
             }
+            if let Some(dev) = node.as_any().downcast_ref::<axfs::chrdev::CharDevNode>() {
+                stat.st_mode = normal_file_mode(StMode::S_IFCHR).bits();
+                stat.st_rdev = dev.rdev();
+                return Ok(stat);
+            }
             if node
                 .as_any()
                 .downcast_ref::<axfs::axfs_ramfs::FileNode>()
@@ -0,0 +1,198 @@
+//! signalfd，以文件描述符的形式读取发给当前线程或进程的信号
+//!
+//! 读取时从读取者的未决信号集中取出属于 signalfd 掩码的信号，与 sigtimedwait 相同，
+//! 被取出的信号不会再递送给信号处理函数。
+use axerrno::{AxError, AxResult};
+use axfs::api::{FileIO, FileIOType, OpenFlags};
+use axprocess::signal::{current_pending_in, take_current_signal_in, wait_for_signal};
+use axsignal::info::{SigInfo, SI_TIMER};
+use axsync::Mutex;
+
+/// 读取 signalfd 得到的记录，即 `struct signalfd_siginfo`，大小为 128 字节
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct SignalfdSiginfo {
+    /// 信号编号
+    pub ssi_signo: u32,
+    /// errno，通常为 0
+    pub ssi_errno: i32,
+    /// 信号来源
+    pub ssi_code: i32,
+    /// 发送者的 pid
+    pub ssi_pid: u32,
+    /// 发送者的 uid
+    pub ssi_uid: u32,
+    /// SIGIO 对应的文件描述符
+    pub ssi_fd: i32,
+    /// POSIX 定时器的 ID
+    pub ssi_tid: u32,
+    /// SIGIO 的事件
+    pub ssi_band: u32,
+    /// POSIX 定时器的溢出次数
+    pub ssi_overrun: u32,
+    /// 产生信号的异常编号
+    pub ssi_trapno: u32,
+    /// SIGCHLD 的退出状态
+    pub ssi_status: i32,
+    /// sigqueue 发送的整数值
+    pub ssi_int: i32,
+    /// sigqueue 发送的指针值
+    pub ssi_ptr: u64,
+    /// SIGCHLD 的用户态时间
+    pub ssi_utime: u64,
+    /// SIGCHLD 的内核态时间
+    pub ssi_stime: u64,
+    /// 产生信号的地址
+    pub ssi_addr: u64,
+    /// 产生信号的地址的最低有效位
+    pub ssi_addr_lsb: u16,
+    _pad2: u16,
+    /// seccomp 的系统调用号
+    pub ssi_syscall: i32,
+    /// seccomp 的调用地址
+    pub ssi_call_addr: u64,
+    /// seccomp 的架构
+    pub ssi_arch: u32,
+    _pad: [u8; 28],
+}
+
+impl From<&SigInfo> for SignalfdSiginfo {
+    fn from(info: &SigInfo) -> Self {
+        // SI_TIMER 的 pid 与 uid 字段存放的是定时器 ID 与溢出次数
+        let (pid, uid, tid, overrun) = if info.si_code == SI_TIMER {
+            (0, 0, info.pid as u32, info.uid)
+        } else {
+            (info.pid as u32, info.uid, 0, 0)
+        };
+        Self {
+            ssi_signo: info.si_signo as u32,
+            ssi_errno: info.si_errno,
+            ssi_code: info.si_code,
+            ssi_pid: pid,
+            ssi_uid: uid,
+            ssi_fd: 0,
+            ssi_tid: tid,
+            ssi_band: 0,
+            ssi_overrun: overrun,
+            ssi_trapno: 0,
+            ssi_status: 0,
+            ssi_int: info.si_value as i32,
+            ssi_ptr: info.si_value as u64,
+            ssi_utime: 0,
+            ssi_stime: 0,
+            ssi_addr: 0,
+            ssi_addr_lsb: 0,
+            _pad2: 0,
+            ssi_syscall: 0,
+            ssi_call_addr: 0,
+            ssi_arch: 0,
+            _pad: [0; 28],
+        }
+    }
+}
+
+/// signalfd 文件描述符
+pub struct SignalFd {
+    /// 读取的信号集
+    mask: Mutex<usize>,
+    flags: Mutex<OpenFlags>,
+}
+
+impl SignalFd {
+    /// 创建一个读取 `mask` 中信号的 signalfd
+    pub fn new(mask: usize, flags: OpenFlags) -> Self {
+        Self {
+            mask: Mutex::new(mask),
+            flags: Mutex::new(flags),
+        }
+    }
+
+    /// 修改读取的信号集
+    pub fn set_mask(&self, mask: usize) {
+        *self.mask.lock() = mask;
+    }
+
+    fn is_non_block(&self) -> bool {
+        self.flags.lock().contains(OpenFlags::NON_BLOCK)
+    }
+}
+
+impl FileIO for SignalFd {
+    fn read(&self, buf: &mut [u8]) -> AxResult<usize> {
+        let len = core::mem::size_of::<SignalfdSiginfo>();
+        if buf.len() < len {
+            return Err(AxError::InvalidInput);
+        }
+        loop {
+            let mask = *self.mask.lock();
+            // 缓冲区能放下多少条记录就读取多少条，但至少读取一条时才返回
+            let mut read_len = 0;
+            while read_len + len <= buf.len() {
+                let Some((_, info)) = take_current_signal_in(mask) else {
+                    break;
+                };
+                let record = SignalfdSiginfo::from(&info);
+                let bytes =
+                    unsafe { core::slice::from_raw_parts(&record as *const _ as *const u8, len) };
+                buf[read_len..read_len + len].copy_from_slice(bytes);
+                read_len += len;
+            }
+            if read_len != 0 {
+                return Ok(read_len);
+            }
+            if self.is_non_block() {
+                return Err(AxError::WouldBlock);
+            }
+            let process = axprocess::current_process();
+            if process.have_signals().is_some() {
+                return Err(AxError::Interrupted);
+            }
+            wait_for_signal(
+                || current_pending_in(mask).is_some() || process.have_signals().is_some(),
+                None,
+            );
+        }
+    }
+
+    fn readable(&self) -> bool {
+        true
+    }
+
+    fn writable(&self) -> bool {
+        false
+    }
+
+    fn executable(&self) -> bool {
+        false
+    }
+
+    fn get_type(&self) -> FileIOType {
+        FileIOType::Other
+    }
+
+    fn ready_to_read(&self) -> bool {
+        current_pending_in(*self.mask.lock()).is_some()
+    }
+
+    fn ready_to_write(&self) -> bool {
+        false
+    }
+
+    fn set_status(&self, flags: OpenFlags) -> bool {
+        *self.flags.lock() = flags;
+        true
+    }
+
+    fn get_status(&self) -> OpenFlags {
+        *self.flags.lock()
+    }
+
+    fn set_close_on_exec(&self, is_set: bool) -> bool {
+        if is_set {
+            *self.flags.lock() |= OpenFlags::CLOEXEC;
+        } else {
+            *self.flags.lock() &= !OpenFlags::CLOEXEC;
+        }
+        true
+    }
+}
@@ -0,0 +1,174 @@
+//! timerfd，以文件描述符的形式读取定时器的到期次数
+//!
+//! 到期次数在读取或查询时根据当前时间计算。阻塞的读取在 timerfd 的等待队列上睡眠到下一次到期，
+//! 定时器被重新设置或收到信号时提前醒来。
+use axerrno::{AxError, AxResult};
+use axfs::api::{FileIO, FileIOType, OpenFlags};
+use axhal::time::current_time_nanos;
+use axsync::Mutex;
+use axtask::WaitQueue;
+use core::time::Duration;
+
+#[derive(Default)]
+struct TimerFdInner {
+    /// 下一次到期的时刻，单位为纳秒；为 0 时定时器未启用
+    deadline: u64,
+    /// 到期后重新设置的间隔，为 0 时只到期一次
+    interval: u64,
+    /// 上一次读取之后的到期次数
+    expirations: u64,
+}
+
+impl TimerFdInner {
+    /// 计入 `now` 之前的到期
+    fn update(&mut self, now: u64) {
+        if self.deadline == 0 || now < self.deadline {
+            return;
+        }
+        if self.interval == 0 {
+            self.expirations += 1;
+            self.deadline = 0;
+        } else {
+            let count = (now - self.deadline) / self.interval + 1;
+            self.expirations += count;
+            self.deadline += count * self.interval;
+        }
+    }
+}
+
+/// timerfd 文件描述符
+pub struct TimerFd {
+    inner: Mutex<TimerFdInner>,
+    flags: Mutex<OpenFlags>,
+    /// 阻塞读取的线程，定时器被重新设置时被唤醒
+    wait: WaitQueue,
+}
+
+impl TimerFd {
+    /// 创建一个未启用的 timerfd
+    pub fn new(flags: OpenFlags) -> Self {
+        Self {
+            inner: Mutex::new(TimerFdInner::default()),
+            flags: Mutex::new(flags),
+            wait: WaitQueue::new(),
+        }
+    }
+
+    /// 设置定时器，`value` 为距离现在的时间，为 0 时停止定时器；返回原来的间隔与剩余时间
+    pub fn set_time(&self, value: u64, interval: u64) -> (u64, u64) {
+        let now = current_time_nanos();
+        let old = self.get_time();
+        let mut inner = self.inner.lock();
+        inner.expirations = 0;
+        inner.interval = interval;
+        inner.deadline = match value {
+            0 => 0,
+            value => now.saturating_add(value),
+        };
+        drop(inner);
+        self.wait.notify_all();
+        old
+    }
+
+    /// 返回定时器的间隔与距离下一次到期的时间，单位为纳秒
+    pub fn get_time(&self) -> (u64, u64) {
+        let now = current_time_nanos();
+        let mut inner = self.inner.lock();
+        inner.update(now);
+        let remaining = match inner.deadline {
+            0 => 0,
+            deadline => deadline - now,
+        };
+        (inner.interval, remaining)
+    }
+
+    fn is_non_block(&self) -> bool {
+        self.flags.lock().contains(OpenFlags::NON_BLOCK)
+    }
+}
+
+impl FileIO for TimerFd {
+    fn read(&self, buf: &mut [u8]) -> AxResult<usize> {
+        let len = core::mem::size_of::<u64>();
+        if buf.len() < len {
+            return Err(AxError::InvalidInput);
+        }
+        loop {
+            let mut inner = self.inner.lock();
+            inner.update(current_time_nanos());
+            if inner.expirations != 0 {
+                buf[..len].copy_from_slice(&inner.expirations.to_ne_bytes());
+                inner.expirations = 0;
+                return Ok(len);
+            }
+            let deadline = inner.deadline;
+            drop(inner);
+            if self.is_non_block() {
+                return Err(AxError::WouldBlock);
+            }
+            let process = axprocess::current_process();
+            if process.have_signals().is_some() {
+                return Err(AxError::Interrupted);
+            }
+            // 到期、定时器被重新设置或收到信号时醒来
+            let wake = || {
+                let mut inner = self.inner.lock();
+                inner.update(current_time_nanos());
+                let changed = inner.expirations != 0 || inner.deadline != deadline;
+                drop(inner);
+                changed || process.have_signals().is_some()
+            };
+            if deadline == 0 {
+                self.wait.wait_until(wake);
+            } else {
+                let timeout = deadline.saturating_sub(current_time_nanos());
+                self.wait
+                    .wait_timeout_until(Duration::from_nanos(timeout), wake);
+            }
+        }
+    }
+
+    fn readable(&self) -> bool {
+        true
+    }
+
+    fn writable(&self) -> bool {
+        false
+    }
+
+    fn executable(&self) -> bool {
+        false
+    }
+
+    fn get_type(&self) -> FileIOType {
+        FileIOType::Other
+    }
+
+    fn ready_to_read(&self) -> bool {
+        let mut inner = self.inner.lock();
+        inner.update(current_time_nanos());
+        inner.expirations != 0
+    }
+
+    fn ready_to_write(&self) -> bool {
+        false
+    }
+
+    fn set_status(&self, flags: OpenFlags) -> bool {
+        *self.flags.lock() = flags;
+        true
+    }
+
+    fn get_status(&self) -> OpenFlags {
+        *self.flags.lock()
+    }
+
+    fn set_close_on_exec(&self, is_set: bool) -> bool {
+        if is_set {
+            *self.flags.lock() |= OpenFlags::CLOEXEC;
+        } else {
+            *self.flags.lock() &= !OpenFlags::CLOEXEC;
+        }
+        true
+    }
+}
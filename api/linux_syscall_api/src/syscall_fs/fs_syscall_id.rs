@@ -22,7 +22,11 @@ pub enum FsSyscallId {
     DUP = 23,
     DUP3 = 24,
     FCNTL64 = 25,
+    INOTIFY_INIT1 = 26,
+    INOTIFY_ADD_WATCH = 27,
+    INOTIFY_RM_WATCH = 28,
     IOCTL = 29,
+    FLOCK = 32,
     MKDIRAT = 34,
     SYMLINKAT = 36,
     UNLINKAT = 35,
@@ -46,6 +50,7 @@ pub enum FsSyscallId {
     READV = 65,
     WRITEV = 66,
     PPOLL = 73,
+    SIGNALFD4 = 74,
     FSTATAT = 79,
     PREAD64 = 67,
     PWRITE64 = 68,
@@ -55,6 +60,9 @@ pub enum FsSyscallId {
     FSTAT = 80,
     SYNC = 81,
     FSYNC = 82,
+    TIMERFD_CREATE = 85,
+    TIMERFD_SETTIME = 86,
+    TIMERFD_GETTIME = 87,
     UTIMENSAT = 88,
     RENAMEAT2 = 276,
     COPYFILERANGE = 285,
@@ -77,6 +85,8 @@ numeric_enum_macro::numeric_enum! {
         EVENTFD2 = 290,
         GETCWD = 79,
         UNLINK = 87,
+        LINK = 86,
+        SYMLINK = 88,
         EPOLL_CREATE = 213,
         EPOLL_CTL = 233,
         EPOLL_WAIT = 232,
@@ -84,6 +94,16 @@ numeric_enum_macro::numeric_enum! {
         DUP2 = 33,
         DUP3 = 292,
         FCNTL64 = 72,
+        FLOCK = 73,
+        INOTIFY_INIT = 253,
+        INOTIFY_ADD_WATCH = 254,
+        INOTIFY_RM_WATCH = 255,
+        INOTIFY_INIT1 = 294,
+        TIMERFD_CREATE = 283,
+        TIMERFD_SETTIME = 286,
+        TIMERFD_GETTIME = 287,
+        SIGNALFD = 282,
+        SIGNALFD4 = 289,
         IOCTL = 16,
         MKDIRAT = 258,
         SYMLINKAT = 266,
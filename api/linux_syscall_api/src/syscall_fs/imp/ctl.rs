@@ -1,8 +1,10 @@
 //! 对文件系统的管理,包括目录项的创建、文件权限设置等内容
+use axerrno::AxError;
 use axfs::api::{
-    remove_dir, remove_file, rename, ConsoleWinSize, OpenFlags, Permissions, FIOCLEX, FIONBIO,
-    TCGETS, TIOCGPGRP, TIOCGWINSZ, TIOCSPGRP,
+    remove_dir, remove_file, rename, ConsoleWinSize, FileIO, OpenFlags, Permissions, SeekFrom,
+    FIOCLEX, FIONBIO, TCGETS, TIOCGPGRP, TIOCGWINSZ, TIOCSPGRP,
 };
+use axfs::lock::{LockOwner, LockType, RecordLock};
 use axlog::{debug, error, info};
 use core::ptr::{self, copy_nonoverlapping};
 
@@ -11,7 +13,8 @@ use crate::{
         ctype::{file::new_fd, pidfd::new_pidfd, FileDesc},
         solve_path,
     },
-    DirEnt, DirEntType, Fcntl64Cmd, RenameFlags, SyscallError, SyscallResult, TimeSecs,
+    DirEnt, DirEntType, Fcntl64Cmd, Flock, FlockOp, RenameFlags, SyscallError, SyscallResult,
+    TimeSecs, F_RDLCK, F_UNLCK, F_WRLCK,
 };
 use axhal::mem::VirtAddr;
 use axprocess::{
@@ -21,6 +24,7 @@ use axprocess::{
 
 extern crate alloc;
 use alloc::string::ToString;
+use alloc::sync::Arc;
 
 /// 功能:获取当前工作目录；
 /// # Arguments
@@ -203,6 +207,8 @@ pub fn syscall_getdents64(args: [usize; 6]) -> SyscallResult {
             dirent.set_fixed_part(1, offset, entry_size, DirEntType::Dir);
         } else if file_type.is_file() {
             dirent.set_fixed_part(1, offset, entry_size, DirEntType::Reg);
+        } else if file_type.is_symlink() {
+            dirent.set_fixed_part(1, offset, entry_size, DirEntType::Lnk);
         } else {
             dirent.set_fixed_part(1, offset, entry_size, DirEntType::Unknown);
         }
@@ -417,6 +423,18 @@ pub fn syscall_fcntl64(args: [usize; 6]) -> SyscallResult {
                 Err(SyscallError::EINVAL)
             }
         }
+        Ok(
+            cmd @ (Fcntl64Cmd::F_GETLK
+            | Fcntl64Cmd::F_SETLK
+            | Fcntl64Cmd::F_SETLKW
+            | Fcntl64Cmd::F_OFD_GETLK
+            | Fcntl64Cmd::F_OFD_SETLK
+            | Fcntl64Cmd::F_OFD_SETLKW),
+        ) => {
+            // 等待锁时不能持有 fd_table
+            drop(fd_table);
+            fcntl_lock(file, cmd, arg as *mut Flock)
+        }
         _ => {
             error!("error fd: {}, cmd: {}", fd, cmd);
             Err(SyscallError::EINVAL)
@@ -424,6 +442,162 @@ pub fn syscall_fcntl64(args: [usize; 6]) -> SyscallResult {
     }
 }
 
+/// 处理 fcntl 的记录锁命令
+///
+/// POSIX 锁属于进程，OFD 锁属于打开的文件描述
+fn fcntl_lock(file: Arc<dyn FileIO>, cmd: Fcntl64Cmd, lock_ptr: *mut Flock) -> SyscallResult {
+    let process = current_process();
+    if process
+        .manual_alloc_for_lazy((lock_ptr as usize).into())
+        .is_err()
+    {
+        return Err(SyscallError::EFAULT);
+    }
+    let flock = unsafe { *lock_ptr };
+    let ofd = matches!(
+        cmd,
+        Fcntl64Cmd::F_OFD_GETLK | Fcntl64Cmd::F_OFD_SETLK | Fcntl64Cmd::F_OFD_SETLKW
+    );
+    if ofd && flock.l_pid != 0 {
+        return Err(SyscallError::EINVAL);
+    }
+    let ty = match flock.l_type {
+        F_RDLCK => LockType::Read,
+        F_WRLCK => LockType::Write,
+        F_UNLCK => LockType::Unlock,
+        _ => return Err(SyscallError::EINVAL),
+    };
+    let base = match flock.l_whence {
+        0 => 0,
+        1 => file
+            .seek(SeekFrom::Current(0))
+            .map_err(|_| SyscallError::EINVAL)? as i64,
+        2 => file.get_stat().map_err(|_| SyscallError::EINVAL)?.st_size as i64,
+        _ => return Err(SyscallError::EINVAL),
+    };
+    let start = base
+        .checked_add(flock.l_start)
+        .ok_or(SyscallError::EOVERFLOW)?;
+    // l_len 为负数时锁住 [start + l_len, start)
+    let (start, end) = match flock.l_len {
+        0 => (start, u64::MAX),
+        len if len > 0 => (
+            start,
+            start.checked_add(len).ok_or(SyscallError::EOVERFLOW)? as u64,
+        ),
+        len => (
+            start.checked_add(len).ok_or(SyscallError::EINVAL)?,
+            start as u64,
+        ),
+    };
+    if start < 0 {
+        return Err(SyscallError::EINVAL);
+    }
+    let owner = if ofd {
+        LockOwner::File(Arc::as_ptr(&file) as *const u8 as usize)
+    } else {
+        LockOwner::Process(process.pid())
+    };
+    let lock = RecordLock {
+        owner,
+        pid: process.pid(),
+        ty,
+        start: start as u64,
+        end,
+    };
+    let path = file.get_path();
+    match cmd {
+        Fcntl64Cmd::F_GETLK | Fcntl64Cmd::F_OFD_GETLK => {
+            if ty == LockType::Unlock {
+                return Err(SyscallError::EINVAL);
+            }
+            let result = match axfs::lock::get_record_lock(&path, &lock) {
+                Some(conflict) => Flock {
+                    l_type: if conflict.ty == LockType::Write {
+                        F_WRLCK
+                    } else {
+                        F_RDLCK
+                    },
+                    l_whence: 0,
+                    l_start: conflict.start as i64,
+                    l_len: if conflict.end == u64::MAX {
+                        0
+                    } else {
+                        (conflict.end - conflict.start) as i64
+                    },
+                    l_pid: match conflict.owner {
+                        LockOwner::Process(_) => conflict.pid as i32,
+                        LockOwner::File(_) => -1,
+                    },
+                },
+                None => Flock {
+                    l_type: F_UNLCK,
+                    ..flock
+                },
+            };
+            unsafe { *lock_ptr = result };
+            Ok(0)
+        }
+        _ => {
+            if (ty == LockType::Read && !file.readable())
+                || (ty == LockType::Write && !file.writable())
+            {
+                return Err(SyscallError::EBADF);
+            }
+            let wait = matches!(cmd, Fcntl64Cmd::F_SETLKW | Fcntl64Cmd::F_OFD_SETLKW);
+            axfs::lock::set_record_lock(&path, lock, wait, || process.have_signals().is_some())
+                .map(|_| 0)
+                .map_err(|err| match err {
+                    AxError::WouldBlock => SyscallError::EAGAIN,
+                    AxError::Interrupted => SyscallError::EINTR,
+                    AxError::ResourceBusy => SyscallError::EDEADLK,
+                    _ => SyscallError::EINVAL,
+                })
+        }
+    }
+}
+
+/// 功能:对打开的文件加建议锁或解锁；
+/// # Arguments
+/// * `fd`: usize, 文件描述符
+/// * `operation`: usize, LOCK_SH, LOCK_EX 或 LOCK_UN, 可以与 LOCK_NB 组合
+/// # Return
+/// 成功执行,返回0。失败,返回-1。
+///
+/// flock 锁属于打开的文件描述, 通过 dup 或 fork 得到的文件描述符共享同一个锁
+pub fn syscall_flock(args: [usize; 6]) -> SyscallResult {
+    let fd = args[0];
+    let operation = FlockOp::from_bits(args[1] as u32).ok_or(SyscallError::EINVAL)?;
+    let process = current_process();
+    let file = {
+        let fd_table = process.fd_manager.fd_table.lock();
+        match fd_table.get(fd) {
+            Some(Some(file)) => file.clone(),
+            _ => return Err(SyscallError::EBADF),
+        }
+    };
+    let ty = match operation.difference(FlockOp::LOCK_NB) {
+        FlockOp::LOCK_SH => LockType::Read,
+        FlockOp::LOCK_EX => LockType::Write,
+        FlockOp::LOCK_UN => LockType::Unlock,
+        _ => return Err(SyscallError::EINVAL),
+    };
+    let owner = Arc::as_ptr(&file) as *const u8 as usize;
+    axfs::lock::flock(
+        &file.get_path(),
+        owner,
+        ty,
+        operation.contains(FlockOp::LOCK_NB),
+        || process.have_signals().is_some(),
+    )
+    .map(|_| 0)
+    .map_err(|err| match err {
+        AxError::WouldBlock => SyscallError::EAGAIN,
+        AxError::Interrupted => SyscallError::EINTR,
+        _ => SyscallError::EINVAL,
+    })
+}
+
 /// 29
 /// 执行各种设备相关的控制功能
 /// todo: 未实现
@@ -449,8 +623,27 @@ pub fn syscall_ioctl(args: [usize; 6]) -> SyscallResult {
     if process.manual_alloc_for_lazy(argp.into()).is_err() {
         return Err(SyscallError::EFAULT); // 地址不合法
     }
+    // 按 _IOC 编码带有读写方向的请求，参数是 size 字节的缓冲区，需要整段检查
+    let size = (request >> 16) & 0x3fff;
+    if request >> 30 != 0 && size != 0 {
+        let end = argp.checked_add(size - 1).ok_or(SyscallError::EFAULT)?;
+        if process
+            .manual_alloc_range_for_lazy(argp.into(), end.into())
+            .is_err()
+        {
+            return Err(SyscallError::EFAULT);
+        }
+    }
 
     let file = fd_table[fd].clone().unwrap();
+    // 先交给文件自身处理, 如设备文件
+    match file.ioctl(request, argp) {
+        Err(AxError::Unsupported) => {}
+        Err(AxError::InvalidInput) => return Err(SyscallError::EINVAL),
+        Err(AxError::BadAddress) => return Err(SyscallError::EFAULT),
+        Err(_) => return Err(SyscallError::ENOTTY),
+        Ok(ret) => return Ok(ret),
+    }
     match request {
         TIOCGWINSZ => {
             let winsize = argp as *mut ConsoleWinSize;
@@ -0,0 +1,126 @@
+//! inotify 相关系统调用
+extern crate alloc;
+use alloc::string::String;
+use alloc::sync::Arc;
+use axfs::api::{FileIO, OpenFlags};
+use axfs::notify::FsWatcher;
+use axprocess::current_process;
+
+use super::{path_error, solve_path};
+use crate::syscall_fs::ctype::inotify::{Inotify, InotifyMask};
+use crate::{SyscallError, SyscallResult};
+
+/// inotify_init1 的 flags：非阻塞
+pub const IN_NONBLOCK: usize = 0x800;
+
+/// inotify_init1 的 flags：执行 exec 时关闭
+pub const IN_CLOEXEC: usize = 0x80000;
+
+/// 功能：创建一个 inotify 实例
+/// # Arguments
+/// * `flags`: usize, IN_NONBLOCK 和 IN_CLOEXEC 的组合
+/// # Return
+/// 成功时返回新的文件描述符
+pub fn syscall_inotify_init1(args: [usize; 6]) -> SyscallResult {
+    let flags = args[0];
+    if flags & !(IN_NONBLOCK | IN_CLOEXEC) != 0 {
+        return Err(SyscallError::EINVAL);
+    }
+    let mut open_flags = OpenFlags::RDONLY;
+    if flags & IN_NONBLOCK != 0 {
+        open_flags |= OpenFlags::NON_BLOCK;
+    }
+    if flags & IN_CLOEXEC != 0 {
+        open_flags |= OpenFlags::CLOEXEC;
+    }
+
+    let process = current_process();
+    let mut fd_table = process.fd_manager.fd_table.lock();
+    let Ok(fd) = process.alloc_fd(&mut fd_table) else {
+        return Err(SyscallError::EMFILE);
+    };
+    let inotify = Arc::new(Inotify::new(open_flags));
+    axfs::notify::add_watcher(&(inotify.clone() as Arc<dyn FsWatcher>));
+    fd_table[fd] = Some(inotify);
+    Ok(fd as isize)
+}
+
+/// 功能：创建一个 inotify 实例，等价于 flags 为 0 的 inotify_init1
+#[cfg(target_arch = "x86_64")]
+pub fn syscall_inotify_init(_args: [usize; 6]) -> SyscallResult {
+    syscall_inotify_init1([0; 6])
+}
+
+fn get_inotify(fd: usize) -> Result<Arc<dyn FileIO>, SyscallError> {
+    let file = match current_process().fd_manager.fd_table.lock().get(fd) {
+        Some(Some(file)) => file.clone(),
+        _ => return Err(SyscallError::EBADF),
+    };
+    if file.as_any().downcast_ref::<Inotify>().is_none() {
+        return Err(SyscallError::EINVAL);
+    }
+    Ok(file)
+}
+
+/// 功能：添加或修改 inotify 实例对某个文件的监视项
+/// # Arguments
+/// * `fd`: usize, inotify 实例的文件描述符
+/// * `path`: *const u8, 被监视的文件的路径
+/// * `mask`: u32, 监视的事件及选项
+/// # Return
+/// 成功时返回监视项的编号
+pub fn syscall_inotify_add_watch(args: [usize; 6]) -> SyscallResult {
+    let fd = args[0];
+    let path = args[1] as *const u8;
+    let mask = InotifyMask::from_bits_truncate(args[2] as u32);
+    let file = get_inotify(fd)?;
+    if !mask.intersects(InotifyMask::IN_ALL_EVENTS)
+        || mask.contains(InotifyMask::IN_MASK_ADD | InotifyMask::IN_MASK_CREATE)
+    {
+        return Err(SyscallError::EINVAL);
+    }
+
+    let path = solve_path(axprocess::link::AT_FDCWD, Some(path), false)?;
+    let path = String::from(path.path().trim_end_matches('/'));
+    let path = if path.is_empty() {
+        String::from("/")
+    } else if mask.contains(InotifyMask::IN_DONT_FOLLOW) {
+        if axfs::api::read_link(&path).is_err() {
+            axfs::api::lookup(&path).map_err(path_error)?;
+        }
+        // 只解析父目录中的符号链接
+        let (parent, name) = path.rsplit_once('/').unwrap_or(("", &path));
+        let parent = axfs::api::real_path(&(String::from(parent) + "/")).map_err(path_error)?;
+        String::from(parent.trim_end_matches('/')) + "/" + name
+    } else {
+        axfs::api::real_path(&path).map_err(path_error)?
+    };
+    if mask.contains(InotifyMask::IN_ONLYDIR)
+        && !axfs::api::metadata(&path).is_ok_and(|meta| meta.is_dir())
+    {
+        return Err(SyscallError::ENOTDIR);
+    }
+
+    let inotify = file.as_any().downcast_ref::<Inotify>().unwrap();
+    match inotify.add_watch(&path, mask - InotifyMask::IN_DONT_FOLLOW) {
+        Ok(wd) => Ok(wd as isize),
+        Err(err) => Err(path_error(err)),
+    }
+}
+
+/// 功能：移除 inotify 实例的监视项
+/// # Arguments
+/// * `fd`: usize, inotify 实例的文件描述符
+/// * `wd`: i32, 监视项的编号
+/// # Return
+/// 成功时返回 0
+pub fn syscall_inotify_rm_watch(args: [usize; 6]) -> SyscallResult {
+    let fd = args[0];
+    let wd = args[1] as i32;
+    let file = get_inotify(fd)?;
+    let inotify = file.as_any().downcast_ref::<Inotify>().unwrap();
+    inotify
+        .rm_watch(wd)
+        .map(|_| 0)
+        .map_err(|_| SyscallError::EINVAL)
+}
@@ -1,17 +1,16 @@
 //! 负责与 IO 相关的系统调用
 extern crate alloc;
-use crate::syscall_fs::solve_path;
+use crate::syscall_fs::{path_error, solve_path};
 use crate::syscall_net::Socket;
 use crate::{IoVec, SyscallError, SyscallResult};
 use alloc::string::ToString;
 use alloc::sync::Arc;
 use alloc::vec;
 use axerrno::AxError;
-use axfs::api::{FileIOType, OpenFlags, SeekFrom};
+use axfs::api::{FileIO, FileIOType, OpenFlags, SeekFrom};
 
-use axlog::{debug, info};
+use axlog::{debug, info, warn};
 use axprocess::current_process;
-use axprocess::link::{create_link, real_path};
 
 use crate::syscall_fs::ctype::{
     dir::new_dir,
@@ -262,6 +261,12 @@ pub fn syscall_pipe(mut args: [usize; 6]) -> SyscallResult {
     syscall_pipe2(args)
 }
 
+/// 关闭文件的任意一个描述符都会释放进程在该文件上的 POSIX 锁，
+/// 包括 close 以及 dup2/dup3 隐式关闭新的文件描述符原先指向的文件
+fn release_locks_on_close(pid: u64, file: &Arc<dyn FileIO>) {
+    axfs::lock::release_process_locks(pid, Some(&file.get_path()));
+}
+
 /// 功能:复制文件描述符；
 /// # Arguments
 /// * `fd`: usize, 被复制的文件描述符。
@@ -323,8 +328,14 @@ pub fn syscall_dup2(args: [usize; 6]) -> SyscallResult {
     //     return ErrorNo::EINVAL as isize;
     // }
     info!("dup2 fd {} to new fd {}", fd, new_fd);
+    if fd == new_fd {
+        return Ok(new_fd as isize);
+    }
     // 就算new_fd已经被打开了,也可以被重新替代掉
-    fd_table[new_fd] = fd_table[fd].clone();
+    let file = fd_table[fd].clone();
+    if let Some(old) = core::mem::replace(&mut fd_table[new_fd], file) {
+        release_locks_on_close(process.pid(), &old);
+    }
     Ok(new_fd as isize)
 }
 
@@ -362,7 +373,10 @@ pub fn syscall_dup3(args: [usize; 6]) -> SyscallResult {
         }
     }
     info!("dup3 fd {} to new fd {} with flags {}", fd, new_fd, flags);
-    fd_table[new_fd] = fd_table[fd].clone();
+    let file = fd_table[fd].clone();
+    if let Some(old) = core::mem::replace(&mut fd_table[new_fd], file) {
+        release_locks_on_close(process.pid(), &old);
+    }
     if flags as u32 & crate::ctypes::O_CLOEXEC != 0 {
         fd_table[new_fd].as_mut().unwrap().set_close_on_exec(true);
     }
@@ -386,6 +400,16 @@ pub fn syscall_openat(args: [usize; 6]) -> SyscallResult {
     let _mode = args[3] as u8;
     let force_dir = OpenFlags::from(flags).is_dir();
     let path = solve_path(fd, Some(path), force_dir)?;
+    // O_NOFOLLOW 时若最后一个分量是符号链接, 则返回 ELOOP
+    if OpenFlags::from(flags).contains(OpenFlags::NOFOLLOW)
+        && axfs::api::read_link(path.path()).is_ok()
+    {
+        return Err(SyscallError::ELOOP);
+    }
+    // 路径中的符号链接过多
+    if let Err(AxError::InvalidData) = axfs::api::real_path(path.path()) {
+        return Err(SyscallError::ELOOP);
+    }
     let process = current_process();
     let mut fd_table = process.fd_manager.fd_table.lock();
     let fd_num: usize = if let Ok(fd) = process.alloc_fd(&mut fd_table) {
@@ -409,13 +433,35 @@ pub fn syscall_openat(args: [usize; 6]) -> SyscallResult {
             Err(SyscallError::ENOENT)
         }
     }
-    // 如果是FILE,注意若创建了新文件,需要添加链接
+    // 如果是设备文件, 由设备驱动创建文件对象
+    else if let Some(dev) = axfs::chrdev::open(path.path(), flags.into()) {
+        debug!("open char device");
+        match dev {
+            Ok(dev) => {
+                fd_table[fd_num] = Some(dev);
+                Ok(fd_num as isize)
+            }
+            Err(AxError::WouldBlock) => Err(SyscallError::EAGAIN),
+            Err(_) => Err(SyscallError::ENODEV),
+        }
+    }
+    // 如果是内核生成内容的 procfs 文件, 由注册的函数创建文件对象
+    else if let Some(file) = axfs::procfs::open(path.path(), flags.into()) {
+        debug!("open procfs file");
+        match file {
+            Ok(file) => {
+                fd_table[fd_num] = Some(file);
+                Ok(fd_num as isize)
+            }
+            Err(_) => Err(SyscallError::EACCES),
+        }
+    }
+    // 如果是FILE
     else {
         debug!("open file");
         if let Ok(file) = new_fd(path.path().to_string(), flags.into()) {
             debug!("new file_desc successfully allocated");
             fd_table[fd_num] = Some(Arc::new(file));
-            let _ = create_link(&path, &path); // 不需要检查是否成功,因为如果成功,说明是新建的文件,如果失败,说明已经存在了
             Ok(fd_num as isize)
         } else {
             debug!("open file failed");
@@ -489,7 +535,9 @@ pub fn syscall_close(args: [usize; 6]) -> SyscallResult {
         }
     }
 
-    fd_table[fd] = None;
+    if let Some(file) = fd_table[fd].take() {
+        release_locks_on_close(process.pid(), &file);
+    }
     // for i in 0..process_inner.fd_table.len() {
     //     if let Some(file) = process_inner.fd_table[i].as_ref() {
     //         debug!("fd: {} has file", i);
@@ -643,15 +691,18 @@ pub fn syscall_readlinkat(args: [usize; 6]) -> SyscallResult {
         return Ok(file_real_path.len() as isize);
     }
 
-    if *path.path() != real_path(&(path.path().to_string())) {
-        // 说明链接存在
-        let path = path.path();
-        let len = bufsiz.min(path.len());
-        let slice = unsafe { core::slice::from_raw_parts_mut(buf, len) };
-        slice.copy_from_slice(&path.as_bytes()[..len]);
-        return Ok(path.len() as isize);
+    match axfs::api::read_link(path.path()) {
+        Ok(target) => {
+            // 内容超出 bufsiz 时直接截断, 返回写入的字节数
+            let len = bufsiz.min(target.len());
+            let slice = unsafe { core::slice::from_raw_parts_mut(buf, len) };
+            slice.copy_from_slice(&target.as_bytes()[..len]);
+            Ok(len as isize)
+        }
+        // 不是符号链接
+        Err(AxError::InvalidInput) => Err(SyscallError::EINVAL),
+        Err(e) => Err(path_error(e)),
     }
-    Err(SyscallError::EINVAL)
 }
 
 /// readlinkat
@@ -718,7 +769,6 @@ pub fn syscall_lseek(args: [usize; 6]) -> SyscallResult {
 
 /// 82
 /// 写回硬盘
-#[allow(unused)]
 /// # Arguments
 /// * `fd`: usize
 pub fn syscall_fsync(args: [usize; 6]) -> SyscallResult {
@@ -728,9 +778,12 @@ pub fn syscall_fsync(args: [usize; 6]) -> SyscallResult {
         debug!("fd {} is out of range", fd);
         return Err(SyscallError::EBADF);
     }
-    let fd_table = process.fd_manager.fd_table.lock();
-    if let Some(file) = fd_table[fd].clone() {
-        // if file.flush().is_err() {}
+    let file = process.fd_manager.fd_table.lock()[fd].clone();
+    if let Some(file) = file {
+        // 文件的数据先写入块设备缓存，再写回硬盘
+        if file.flush().is_err() || axfs::api::sync().is_err() {
+            return Err(SyscallError::EIO);
+        }
         Ok(0)
     } else {
         debug!("fd {} is none", fd);
@@ -738,6 +791,15 @@ pub fn syscall_fsync(args: [usize; 6]) -> SyscallResult {
     }
 }
 
+/// 81
+/// 将所有块设备缓存中的数据写回硬盘
+pub fn syscall_sync() -> SyscallResult {
+    if let Err(e) = axfs::api::sync() {
+        warn!("sync failed: {:?}", e);
+    }
+    Ok(0)
+}
+
 /**
 该系统调用应复制文件描述符 fd_in 中的至多 len 个字节到文件描述符 fd_out 中。
 若 off_in 为 NULL,则复制时应从文件描述符 fd_in 本身的文件偏移处开始读取,并将其文件偏移增加成功复制的字节数；否则,从 *off_in 指定的文件偏移处开始读取,不改变 fd_in 的文件偏移,而是将 *off_in 增加成功复制的字节数。
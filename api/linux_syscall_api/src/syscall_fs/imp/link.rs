@@ -3,15 +3,21 @@
 // const STDERR: usize = 2;
 extern crate alloc;
 
-use crate::{SyscallError, SyscallResult};
+use alloc::string::ToString;
+use axerrno::AxError;
 use axlog::debug;
-use axprocess::link::{create_link, remove_link, FilePath};
+use axprocess::current_process;
+use axprocess::link::{raw_ptr_to_ref_str, FilePath, AT_FDCWD};
 
-use super::solve_path;
+use super::{path_error, solve_path};
+use crate::{SyscallError, SyscallResult};
 
 /// Special value used to indicate openat should use the current working directory.
 pub const AT_REMOVEDIR: usize = 0x200; // Remove directory instead of unlinking file.
 
+/// Follow the symbolic link of `old_path` in linkat.
+pub const AT_SYMLINK_FOLLOW: usize = 0x400;
+
 /// 功能:创建文件的链接；
 /// # Arguments
 /// * `old_dir_fd`: usize, 原来的文件所在目录的文件描述符。
@@ -21,23 +27,88 @@ pub const AT_REMOVEDIR: usize = 0x200; // Remove directory instead of unlinking
 /// * `flags`: usize, 在2.6.18内核之前,应置为0。其它的值详见`man 2 linkat`。
 /// # Return
 /// 成功执行,返回0。失败,返回-1。
-#[allow(dead_code)]
 pub fn sys_linkat(args: [usize; 6]) -> SyscallResult {
     let old_dir_fd = args[0];
     let old_path = args[1] as *const u8;
     let new_dir_fd = args[2];
     let new_path = args[3] as *const u8;
-    let _flags = args[4];
+    let flags = args[4];
 
     let old_path = solve_path(old_dir_fd, Some(old_path), false)?;
     let new_path = solve_path(new_dir_fd, Some(new_path), false)?;
-    if create_link(&old_path, &new_path) {
-        Ok(0)
+    let old_path = if flags & AT_SYMLINK_FOLLOW != 0 {
+        axfs::api::real_path(old_path.path()).map_err(path_error)?
     } else {
-        Err(SyscallError::EINVAL)
+        old_path.path().to_string()
+    };
+    match axfs::api::hard_link(old_path.as_str(), new_path.path()) {
+        Ok(_) => Ok(0),
+        // 两个路径不在同一个文件系统中
+        Err(AxError::InvalidInput) => Err(SyscallError::EXDEV),
+        Err(e) => {
+            debug!("link error: {:?}", e);
+            Err(path_error(e))
+        }
     }
 }
 
+/// 功能:创建文件的链接
+/// # Arguments
+/// * `old_path`: *const u8, 文件原来的名字。
+/// * `new_path`: *const u8, 文件的新名字。
+/// # Return
+/// 成功执行,返回0。失败,返回-1。
+#[cfg(target_arch = "x86_64")]
+pub fn syscall_link(args: [usize; 6]) -> SyscallResult {
+    let temp_args = [AT_FDCWD, args[0], AT_FDCWD, args[1], 0, 0];
+    sys_linkat(temp_args)
+}
+
+/// 功能:创建符号链接
+/// # Arguments
+/// * `target`: *const u8, 符号链接的内容, 原样保存, 不要求其指向的文件存在。
+/// * `new_dir_fd`: usize, 符号链接所在的目录。
+/// * `link_path`: *const u8, 符号链接的名字。如果link_path是相对路径,则它是相对于new_dir_fd目录而言的。
+/// # Return
+/// 成功执行,返回0。失败,返回-1。
+pub fn syscall_symlinkat(args: [usize; 6]) -> SyscallResult {
+    let target = args[0] as *const u8;
+    let new_dir_fd = args[1];
+    let link_path = args[2] as *const u8;
+
+    if target.is_null() {
+        return Err(SyscallError::EFAULT);
+    }
+    if current_process()
+        .manual_alloc_for_lazy((target as usize).into())
+        .is_err()
+    {
+        return Err(SyscallError::EFAULT);
+    }
+    let target = unsafe { raw_ptr_to_ref_str(target) };
+    if target.is_empty() {
+        return Err(SyscallError::ENOENT);
+    }
+    let link_path = solve_path(new_dir_fd, Some(link_path), false)?;
+    axfs::api::symlink(target, link_path.path()).map_err(|e| {
+        debug!("symlink error: {:?}", e);
+        path_error(e)
+    })?;
+    Ok(0)
+}
+
+/// 功能:创建符号链接
+/// # Arguments
+/// * `target`: *const u8, 符号链接的内容。
+/// * `link_path`: *const u8, 符号链接的名字。
+/// # Return
+/// 成功执行,返回0。失败,返回-1。
+#[cfg(target_arch = "x86_64")]
+pub fn syscall_symlink(args: [usize; 6]) -> SyscallResult {
+    let temp_args = [args[0], AT_FDCWD, args[1], 0, 0, 0];
+    syscall_symlinkat(temp_args)
+}
+
 /// 功能:移除指定文件的链接
 /// # Arguments
 /// * `path`: *const u8, 要删除的链接的名字。
@@ -46,7 +117,7 @@ pub fn sys_linkat(args: [usize; 6]) -> SyscallResult {
 #[cfg(target_arch = "x86_64")]
 pub fn syscall_unlink(args: [usize; 6]) -> SyscallResult {
     let path = args[0] as *const u8;
-    let temp_args = [axprocess::link::AT_FDCWD, path as usize, 0, 0, 0, 0];
+    let temp_args = [AT_FDCWD, path as usize, 0, 0, 0, 0];
     syscall_unlinkat(temp_args)
 }
 
@@ -75,13 +146,16 @@ pub fn syscall_unlinkat(args: [usize; 6]) -> SyscallResult {
         }
         return Ok(0);
     }
-    let metadata = axfs::api::metadata(path.path()).unwrap();
-    if metadata.is_dir() {
-        return Err(SyscallError::EISDIR);
+    // 符号链接本身会被删除, 而不是其指向的文件
+    if axfs::api::read_link(path.path()).is_err() {
+        let metadata = axfs::api::metadata(path.path()).map_err(path_error)?;
+        if metadata.is_dir() {
+            return Err(SyscallError::EISDIR);
+        }
     }
-    if remove_link(&path).is_none() {
-        debug!("unlink file error");
-        return Err(SyscallError::EINVAL);
+    if let Err(e) = axfs::api::remove_file(path.path()) {
+        debug!("unlink file error: {:?}", e);
+        return Err(path_error(e));
     }
     Ok(0)
 }
@@ -4,21 +4,27 @@ extern crate alloc;
 mod ctl;
 mod epoll;
 mod eventfd;
+mod inotify;
 mod io;
 mod link;
 mod mount;
 mod poll;
+mod signalfd;
 mod stat;
+mod timerfd;
 use axerrno::AxError;
 use axprocess::link::{deal_with_path, FilePath};
 pub use ctl::*;
 pub use epoll::*;
 pub use eventfd::*;
+pub use inotify::*;
 pub use io::*;
 pub use link::*;
 pub use mount::*;
 pub use poll::*;
+pub use signalfd::*;
 pub use stat::*;
+pub use timerfd::*;
 
 use crate::SyscallError;
 
@@ -38,3 +44,21 @@ pub fn solve_path(
         Err(_) => Err(SyscallError::EPERM),
     }
 }
+
+/// To convert the error of a filesystem operation on a path to the errno returned to the user.
+///
+/// `InvalidData` is reported by axfs when too many symbolic links are encountered.
+pub fn path_error(err: AxError) -> SyscallError {
+    match err {
+        AxError::NotFound => SyscallError::ENOENT,
+        AxError::AlreadyExists => SyscallError::EEXIST,
+        AxError::NotADirectory => SyscallError::ENOTDIR,
+        AxError::IsADirectory => SyscallError::EISDIR,
+        AxError::DirectoryNotEmpty => SyscallError::ENOTEMPTY,
+        AxError::PermissionDenied => SyscallError::EPERM,
+        AxError::InvalidData => SyscallError::ELOOP,
+        AxError::StorageFull => SyscallError::ENOSPC,
+        AxError::BadAddress => SyscallError::EFAULT,
+        _ => SyscallError::EINVAL,
+    }
+}
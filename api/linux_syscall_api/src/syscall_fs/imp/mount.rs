@@ -49,6 +49,12 @@ pub fn syscall_mount(args: [usize; 6]) -> SyscallResult {
         // data可以为NULL, 必须判断, 否则会panic, 发生LoadPageFault
         _data_str = unsafe { raw_ptr_to_ref_str(_data) }.to_string();
     }
+    // 9p 的 special 是共享目录的 mount tag，而不是设备文件
+    #[cfg(feature = "virtio-9p")]
+    if fs_type == "9p" {
+        let tag = unsafe { raw_ptr_to_ref_str(special) };
+        return mount_9p(tag, mount_path.path(), &_data_str);
+    }
     if device_path.is_dir() {
         debug!("device_path should not be a dir");
         return Err(SyscallError::EPERM);
@@ -111,6 +117,10 @@ pub fn syscall_umount(args: [usize; 6]) -> SyscallResult {
         debug!("mount path not exist");
         return Err(SyscallError::EPERM);
     }
+    // 先尝试卸载真正挂载的文件系统，如 9p
+    if axfs::api::umount(mount_path.path()).is_ok() {
+        return Ok(0);
+    }
     // 从挂载点中删除
     if !umount_fat_fs(&mount_path) {
         debug!("umount error");
@@ -119,3 +129,20 @@ pub fn syscall_umount(args: [usize; 6]) -> SyscallResult {
 
     Ok(0)
 }
+
+/// 挂载 VirtIO 9P 设备共享的主机目录
+#[cfg(feature = "virtio-9p")]
+fn mount_9p(tag: &str, mount_path: &str, data: &str) -> SyscallResult {
+    use axerrno::AxError;
+    let opts = axfs::P9MountOptions::parse(data).map_err(|_| SyscallError::EINVAL)?;
+    match axfs::mount_9p(tag, mount_path, &opts) {
+        Ok(()) => Ok(0),
+        Err(AxError::NotFound) => Err(SyscallError::ENOENT),
+        Err(AxError::ResourceBusy) => Err(SyscallError::EBUSY),
+        Err(AxError::InvalidInput) => Err(SyscallError::EINVAL),
+        Err(e) => {
+            debug!("mount 9p error: {:?}", e);
+            Err(SyscallError::EIO)
+        }
+    }
+}
@@ -0,0 +1,71 @@
+//! signalfd 相关系统调用
+extern crate alloc;
+use alloc::sync::Arc;
+use axfs::api::OpenFlags;
+use axprocess::current_process;
+use axsignal::signal_no::SignalNo;
+
+use crate::syscall_fs::ctype::signalfd::SignalFd;
+use crate::{SyscallError, SyscallResult, SIGSET_SIZE_IN_BYTE};
+
+/// signalfd4 的 flags：非阻塞
+pub const SFD_NONBLOCK: usize = 0x800;
+
+/// signalfd4 的 flags：执行 exec 时关闭
+pub const SFD_CLOEXEC: usize = 0x80000;
+
+/// 功能：创建一个 signalfd，或修改已有的 signalfd 读取的信号集
+/// # Arguments
+/// * `fd`: isize, 为 -1 时创建新的 signalfd，否则为要修改的 signalfd
+/// * `mask`: *const usize, 读取的信号集，其中的 SIGKILL 与 SIGSTOP 被忽略
+/// * `sizemask`: usize, 信号集的大小
+/// * `flags`: usize, SFD_NONBLOCK 和 SFD_CLOEXEC 的组合
+/// # Return
+/// 成功时返回 signalfd 的文件描述符
+pub fn syscall_signalfd4(args: [usize; 6]) -> SyscallResult {
+    let fd = args[0] as isize;
+    let mask = args[1] as *const usize;
+    let sizemask = args[2];
+    let flags = args[3];
+    if sizemask != SIGSET_SIZE_IN_BYTE || flags & !(SFD_NONBLOCK | SFD_CLOEXEC) != 0 {
+        return Err(SyscallError::EINVAL);
+    }
+    let process = current_process();
+    if process.manual_alloc_type_for_lazy(mask).is_err() {
+        return Err(SyscallError::EFAULT);
+    }
+    let mask = unsafe { *mask }
+        & !((1 << (SignalNo::SIGKILL as usize - 1)) | (1 << (SignalNo::SIGSTOP as usize - 1)));
+
+    let mut fd_table = process.fd_manager.fd_table.lock();
+    if fd != -1 {
+        let file = match fd_table.get(fd as usize) {
+            Some(Some(file)) => file.clone(),
+            _ => return Err(SyscallError::EBADF),
+        };
+        let Some(signalfd) = file.as_any().downcast_ref::<SignalFd>() else {
+            return Err(SyscallError::EINVAL);
+        };
+        signalfd.set_mask(mask);
+        return Ok(fd);
+    }
+
+    let mut open_flags = OpenFlags::RDONLY;
+    if flags & SFD_NONBLOCK != 0 {
+        open_flags |= OpenFlags::NON_BLOCK;
+    }
+    if flags & SFD_CLOEXEC != 0 {
+        open_flags |= OpenFlags::CLOEXEC;
+    }
+    let Ok(fd) = process.alloc_fd(&mut fd_table) else {
+        return Err(SyscallError::EMFILE);
+    };
+    fd_table[fd] = Some(Arc::new(SignalFd::new(mask, open_flags)));
+    Ok(fd as isize)
+}
+
+/// 功能：创建或修改 signalfd，等价于 flags 为 0 的 signalfd4
+#[cfg(target_arch = "x86_64")]
+pub fn syscall_signalfd(args: [usize; 6]) -> SyscallResult {
+    syscall_signalfd4([args[0], args[1], args[2], 0, 0, 0])
+}
@@ -11,6 +11,12 @@ use axprocess::{
 
 use crate::syscall_fs::ctype::mount::get_stat_in_fs;
 
+/// Do not follow the symbolic link in the last component of the path.
+pub const AT_SYMLINK_NOFOLLOW: usize = 0x100;
+
+/// The file type bits of a symbolic link in `st_mode`.
+const S_IFLNK: u32 = 0o120000;
+
 /// 实现 stat 系列系统调用
 /// # Arguments
 /// * `fd` - usize
@@ -51,10 +57,12 @@ pub fn syscall_fstat(args: [usize; 6]) -> SyscallResult {
 /// * `dir_fd` - usize
 /// * `path` - *const u8
 /// * `kst` - *mut Kstat
+/// * `flags` - usize, 含 AT_SYMLINK_NOFOLLOW 时获取符号链接本身的信息
 pub fn syscall_fstatat(args: [usize; 6]) -> SyscallResult {
     let dir_fd = args[0];
     let path = args[1] as *const u8;
     let kst = args[2] as *mut Kstat;
+    let flags = args[3];
     let file_path = if let Ok(file_path) = solve_path(dir_fd, Some(path), false) {
         // error!("test {:?}", file_path);
         file_path
@@ -73,6 +81,25 @@ pub fn syscall_fstatat(args: [usize; 6]) -> SyscallResult {
         panic!("Wrong path at syscall_fstatat: {}(dir_fd={})", path, dir_fd);
     };
     info!("path : {}", file_path.path());
+    if flags & AT_SYMLINK_NOFOLLOW != 0 {
+        let metadata = axfs::api::symlink_metadata(file_path.path()).ok();
+        if let Some(metadata) = metadata.filter(|metadata| metadata.file_type().is_symlink()) {
+            let (ino, nlink) = axfs::api::inode_info(file_path.path(), false).unwrap_or((0, 1));
+            unsafe {
+                *kst = Kstat {
+                    st_dev: 1,
+                    st_ino: ino,
+                    st_mode: S_IFLNK | metadata.permissions().bits() as u32,
+                    st_nlink: nlink as _,
+                    st_size: metadata.len(),
+                    st_blksize: axfs::BLOCK_SIZE as u32,
+                    st_blocks: metadata.blocks(),
+                    ..Default::default()
+                };
+            }
+            return Ok(0);
+        }
+    }
     if !axfs::api::path_exists(file_path.path()) {
         return Err(SyscallError::ENOENT);
     }
@@ -97,7 +124,7 @@ pub fn syscall_fstatat(args: [usize; 6]) -> SyscallResult {
 pub fn syscall_lstat(args: [usize; 6]) -> SyscallResult {
     let path = args[0];
     let kst = args[1];
-    let temp_args = [AT_FDCWD, path, kst, 0, 0, 0];
+    let temp_args = [AT_FDCWD, path, kst, AT_SYMLINK_NOFOLLOW, 0, 0];
     syscall_fstatat(temp_args)
 }
 
@@ -0,0 +1,125 @@
+//! timerfd 相关系统调用
+extern crate alloc;
+use alloc::sync::Arc;
+use axfs::api::{FileIO, OpenFlags};
+use axprocess::{current_process, timer::TimerClock};
+
+use crate::syscall_fs::ctype::timerfd::TimerFd;
+use crate::syscall_task::{timer_clock, timer_spec_to_nanos};
+use crate::{ITimerSpec, SyscallError, SyscallResult, TimeSecs};
+
+/// timerfd_create 的 flags：非阻塞
+pub const TFD_NONBLOCK: usize = 0x800;
+
+/// timerfd_create 的 flags：执行 exec 时关闭
+pub const TFD_CLOEXEC: usize = 0x80000;
+
+/// timerfd_settime 的 flags：给定的是绝对时间
+pub const TFD_TIMER_ABSTIME: usize = 0x1;
+
+/// timerfd_settime 的 flags：系统时间被修改时取消读取，这里系统时间不会被修改
+pub const TFD_TIMER_CANCEL_ON_SET: usize = 0x2;
+
+/// 功能：创建一个 timerfd
+/// # Arguments
+/// * `clock_id`: usize, 所用的时钟，不支持 CPU 时钟
+/// * `flags`: usize, TFD_NONBLOCK 和 TFD_CLOEXEC 的组合
+/// # Return
+/// 成功时返回新的文件描述符
+pub fn syscall_timerfd_create(args: [usize; 6]) -> SyscallResult {
+    let clock_id = args[0];
+    let flags = args[1];
+    if timer_clock(clock_id) != Some(TimerClock::Real) || flags & !(TFD_NONBLOCK | TFD_CLOEXEC) != 0
+    {
+        return Err(SyscallError::EINVAL);
+    }
+    let mut open_flags = OpenFlags::RDONLY;
+    if flags & TFD_NONBLOCK != 0 {
+        open_flags |= OpenFlags::NON_BLOCK;
+    }
+    if flags & TFD_CLOEXEC != 0 {
+        open_flags |= OpenFlags::CLOEXEC;
+    }
+
+    let process = current_process();
+    let mut fd_table = process.fd_manager.fd_table.lock();
+    let Ok(fd) = process.alloc_fd(&mut fd_table) else {
+        return Err(SyscallError::EMFILE);
+    };
+    fd_table[fd] = Some(Arc::new(TimerFd::new(open_flags)));
+    Ok(fd as isize)
+}
+
+fn get_timerfd(fd: usize) -> Result<Arc<dyn FileIO>, SyscallError> {
+    let file = match current_process().fd_manager.fd_table.lock().get(fd) {
+        Some(Some(file)) => file.clone(),
+        _ => return Err(SyscallError::EBADF),
+    };
+    if file.as_any().downcast_ref::<TimerFd>().is_none() {
+        return Err(SyscallError::EINVAL);
+    }
+    Ok(file)
+}
+
+fn timer_spec(interval: u64, remaining: u64) -> ITimerSpec {
+    ITimerSpec {
+        it_interval: TimeSecs::from_nanos(interval as usize),
+        it_value: TimeSecs::from_nanos(remaining as usize),
+    }
+}
+
+/// 功能：启动或停止 timerfd 的定时器
+/// # Arguments
+/// * `fd`: usize, timerfd 的文件描述符
+/// * `flags`: usize, TFD_TIMER_ABSTIME 和 TFD_TIMER_CANCEL_ON_SET 的组合
+/// * `new_value`: *const ITimerSpec, 新的设置
+/// * `old_value`: *mut ITimerSpec, 不为空时存放原来的设置
+/// # Return
+/// 成功时返回 0
+pub fn syscall_timerfd_settime(args: [usize; 6]) -> SyscallResult {
+    let fd = args[0];
+    let flags = args[1];
+    let new_value = args[2] as *const ITimerSpec;
+    let old_value = args[3] as *mut ITimerSpec;
+    if flags & !(TFD_TIMER_ABSTIME | TFD_TIMER_CANCEL_ON_SET) != 0 {
+        return Err(SyscallError::EINVAL);
+    }
+    let file = get_timerfd(fd)?;
+    let process = current_process();
+    if process.manual_alloc_type_for_lazy(new_value).is_err()
+        || (!old_value.is_null() && process.manual_alloc_type_for_lazy(old_value).is_err())
+    {
+        return Err(SyscallError::EFAULT);
+    }
+    let new_value = unsafe { *new_value };
+    let now = axhal::time::current_time_nanos();
+    let (value, interval) = timer_spec_to_nanos(&new_value, flags, now)?;
+    let timerfd = file.as_any().downcast_ref::<TimerFd>().unwrap();
+    let (old_interval, old_remaining) = timerfd.set_time(value, interval);
+    if !old_value.is_null() {
+        unsafe { *old_value = timer_spec(old_interval, old_remaining) };
+    }
+    Ok(0)
+}
+
+/// 功能：获取 timerfd 的定时器的间隔与剩余时间
+/// # Arguments
+/// * `fd`: usize, timerfd 的文件描述符
+/// * `curr_value`: *mut ITimerSpec, 存放当前的设置
+/// # Return
+/// 成功时返回 0
+pub fn syscall_timerfd_gettime(args: [usize; 6]) -> SyscallResult {
+    let fd = args[0];
+    let curr_value = args[1] as *mut ITimerSpec;
+    let file = get_timerfd(fd)?;
+    if current_process()
+        .manual_alloc_type_for_lazy(curr_value)
+        .is_err()
+    {
+        return Err(SyscallError::EFAULT);
+    }
+    let timerfd = file.as_any().downcast_ref::<TimerFd>().unwrap();
+    let (interval, remaining) = timerfd.get_time();
+    unsafe { *curr_value = timer_spec(interval, remaining) };
+    Ok(0)
+}
@@ -43,6 +43,14 @@ pub fn fs_syscall(syscall_id: fs_syscall_id::FsSyscallId, args: [usize; 6]) -> S
         READV => syscall_readv(args),
         WRITEV => syscall_writev(args),
         FCNTL64 => syscall_fcntl64(args),
+        FLOCK => syscall_flock(args),
+        INOTIFY_INIT1 => syscall_inotify_init1(args),
+        INOTIFY_ADD_WATCH => syscall_inotify_add_watch(args),
+        INOTIFY_RM_WATCH => syscall_inotify_rm_watch(args),
+        TIMERFD_CREATE => syscall_timerfd_create(args),
+        TIMERFD_SETTIME => syscall_timerfd_settime(args),
+        TIMERFD_GETTIME => syscall_timerfd_gettime(args),
+        SIGNALFD4 => syscall_signalfd4(args),
         FSTATAT => syscall_fstatat(args),
         STATFS => syscall_statfs(args),
         FCHMODAT => syscall_fchmodat(args),
@@ -52,18 +60,17 @@ pub fn fs_syscall(syscall_id: fs_syscall_id::FsSyscallId, args: [usize; 6]) -> S
         PREADLINKAT => syscall_readlinkat(args),
         PWRITE64 => syscall_pwrite64(args),
         SENDFILE64 => syscall_sendfile64(args),
-        FSYNC => Ok(0),
+        FSYNC => syscall_fsync(args),
         FTRUNCATE64 => {
             syscall_ftruncate64(args)
             // 0
         }
         IOCTL => syscall_ioctl(args),
-        // 不做处理即可
-        SYNC => Ok(0),
+        SYNC => syscall_sync(),
         COPYFILERANGE => syscall_copyfilerange(args),
         LINKAT => sys_linkat(args),
         UNLINKAT => syscall_unlinkat(args),
-        SYMLINKAT => Ok(0),
+        SYMLINKAT => syscall_symlinkat(args),
         UTIMENSAT => syscall_utimensat(args),
         EPOLL_CREATE => syscall_epoll_create1(args),
         EPOLL_CTL => syscall_epoll_ctl(args),
@@ -81,6 +88,10 @@ pub fn fs_syscall(syscall_id: fs_syscall_id::FsSyscallId, args: [usize; 6]) -> S
         #[cfg(target_arch = "x86_64")]
         EVENTFD2 => syscall_eventfd(args),
         #[cfg(target_arch = "x86_64")]
+        INOTIFY_INIT => syscall_inotify_init(args),
+        #[cfg(target_arch = "x86_64")]
+        SIGNALFD => syscall_signalfd(args),
+        #[cfg(target_arch = "x86_64")]
         DUP2 => syscall_dup2(args),
         #[cfg(target_arch = "x86_64")]
         LSTAT => syscall_lstat(args),
@@ -95,6 +106,10 @@ pub fn fs_syscall(syscall_id: fs_syscall_id::FsSyscallId, args: [usize; 6]) -> S
         #[cfg(target_arch = "x86_64")]
         UNLINK => syscall_unlink(args),
         #[cfg(target_arch = "x86_64")]
+        LINK => syscall_link(args),
+        #[cfg(target_arch = "x86_64")]
+        SYMLINK => syscall_symlink(args),
+        #[cfg(target_arch = "x86_64")]
         ACCESS => syscall_access(args),
         #[cfg(target_arch = "x86_64")]
         MKDIR => syscall_mkdir(args),
@@ -63,17 +63,46 @@ pub fn syscall_mmap(args: [usize; 6]) -> SyscallResult {
         if offset != 0 {
             return Err(SyscallError::EINVAL);
         }
-        process
-            .memory_set
-            .lock()
-            .lock()
-            .mmap(start.into(), len, prot.into(), shared, fixed, None)
+        let memory_set_wrapper = process.memory_set.lock();
+        let mut memory_set = memory_set_wrapper.lock();
+        let result = memory_set.mmap(start.into(), len, prot.into(), shared, fixed, None);
+        if let Ok(addr) = result {
+            if flags.contains(MMAPFlags::MAP_GROWSDOWN) {
+                memory_set.set_grows_down(addr.into());
+            } else if flags.contains(MMAPFlags::MAP_STACK) {
+                memory_set.set_stack(addr.into());
+            }
+        }
+        result
     } else {
         // file backend
         axlog::debug!("[mmap] fd: {}, offset: 0x{:x}", fd, offset);
         if fd >= process.fd_manager.fd_table.lock().len() as i32 || fd < 0 {
             return Err(SyscallError::EINVAL);
         }
+        let file_io = process.fd_manager.fd_table.lock()[fd as usize].clone();
+        // 设备内存（如 framebuffer）直接映射设备的页面，不经过页面缓存
+        if let Some(file_io) = file_io {
+            match file_io.mmap_phys(offset, len) {
+                Ok(paddr) => {
+                    let result = process.memory_set.lock().lock().map_device_mem(
+                        start.into(),
+                        len,
+                        paddr.into(),
+                        prot.into(),
+                        fixed,
+                    );
+                    flush_tlb(None);
+                    return match result {
+                        Ok(addr) => Ok(addr as isize),
+                        Err(AxError::NoMemory) => Err(SyscallError::ENOMEM),
+                        Err(_) => Err(SyscallError::EINVAL),
+                    };
+                }
+                Err(AxError::Unsupported) => {}
+                Err(_) => return Err(SyscallError::EINVAL),
+            }
+        }
         let file = match &process.fd_manager.fd_table.lock()[fd as usize] {
             // 文件描述符表里面存的是文件描述符，这很合理罢
             Some(file) => alloc::boxed::Box::new(
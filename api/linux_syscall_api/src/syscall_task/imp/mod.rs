@@ -4,8 +4,12 @@ mod futex;
 
 mod schedule;
 
+mod syslog;
+
 mod task;
 
+mod timer;
+
 mod utils;
 
 pub use signal::*;
@@ -14,6 +18,10 @@ pub use futex::*;
 
 pub use schedule::*;
 
+pub use syslog::*;
+
 pub use task::*;
 
+pub use timer::*;
+
 pub use utils::*;
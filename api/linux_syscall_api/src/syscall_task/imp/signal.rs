@@ -1,13 +1,19 @@
 //! 支持信号相关的 syscall
 //! 与信号处理相关的系统调用
 
+use axerrno::AxError;
 use axhal::cpu::this_cpu_id;
+use axhal::time::current_time_nanos;
 use axlog::{debug, info};
-use axprocess::{current_process, current_task, yield_now_task};
-use axsignal::signal_no::SignalNo;
+use axprocess::signal::{current_pending_in, take_current_signal_in, wait_for_signal};
+use axprocess::{current_process, current_task, yield_now_task, PID2PC, TID2TASK};
+use axsignal::info::{SigInfo, SI_TKILL, SI_USER};
+use axsignal::signal_no::{SignalNo, MAX_SIG_NUM};
 use axsignal::{action::SigAction, ucontext::SignalStack};
 
-use crate::{SigMaskFlag, SyscallError, SyscallResult, SIGSET_SIZE_IN_BYTE};
+use crate::{
+    SigMaskFlag, SyscallError, SyscallResult, TimeSecs, NSEC_PER_SEC, SIGSET_SIZE_IN_BYTE,
+};
 
 /// # Arguments
 /// * `signum` - usize
@@ -83,11 +89,8 @@ pub fn syscall_sigsuspend(args: [usize; 6]) -> SyscallResult {
     let signal_module = signal_modules
         .get_mut(&current_task().id().as_u64())
         .unwrap();
-    // 设置新的掩码
-    if signal_module.last_trap_frame_for_signal.is_some() {
-        // 信号嵌套的情况下触发这个调用
-        return Err(SyscallError::EINTR);
-    }
+    // 设置新的掩码，原掩码在递送信号之后恢复
+    signal_module.saved_mask = Some(signal_module.signal_set.mask);
     signal_module.signal_set.mask = unsafe { *mask };
     drop(signal_modules);
     loop {
@@ -111,6 +114,68 @@ pub fn syscall_sigsuspend(args: [usize; 6]) -> SyscallResult {
     Err(SyscallError::EINTR)
 }
 
+/// 等待 `set` 中的信号到来，并将其从未决信号集中取出而不调用信号处理函数
+///
+/// 也用于实现 sigwaitinfo 与 sigwait，此时 `timeout` 为空。
+/// # Arguments
+/// * `set` - *const usize
+/// * `info` - *mut SigInfo, 不为空时存放信号的附加信息
+/// * `timeout` - *const TimeSecs, 为空时一直等待
+/// * `sigsetsize` - usize
+/// # Return
+/// 成功时返回取出的信号编号，超时返回 EAGAIN，被其他信号中断返回 EINTR
+pub fn syscall_rt_sigtimedwait(args: [usize; 6]) -> SyscallResult {
+    let set = args[0] as *const usize;
+    let info = args[1] as *mut SigInfo;
+    let timeout = args[2] as *const TimeSecs;
+    let sigsetsize = args[3];
+    if sigsetsize != SIGSET_SIZE_IN_BYTE {
+        return Err(SyscallError::EINVAL);
+    }
+    let process = current_process();
+    if process.manual_alloc_type_for_lazy(set).is_err()
+        || (!info.is_null() && process.manual_alloc_type_for_lazy(info).is_err())
+        || (!timeout.is_null() && process.manual_alloc_type_for_lazy(timeout).is_err())
+    {
+        return Err(SyscallError::EFAULT);
+    }
+    // SIGKILL 与 SIGSTOP 不能被等待
+    let set = unsafe { *set }
+        & !((1 << (SignalNo::SIGKILL as usize - 1)) | (1 << (SignalNo::SIGSTOP as usize - 1)));
+    let deadline = if timeout.is_null() {
+        None
+    } else {
+        let timeout = unsafe { *timeout };
+        if timeout.tv_nsec >= NSEC_PER_SEC {
+            return Err(SyscallError::EINVAL);
+        }
+        // 截止时间溢出时视为没有截止时间
+        timeout
+            .tv_sec
+            .checked_mul(NSEC_PER_SEC)
+            .and_then(|nanos| nanos.checked_add(timeout.tv_nsec))
+            .and_then(|nanos| current_time_nanos().checked_add(nanos as u64))
+    };
+    loop {
+        if let Some((sig_num, sig_info)) = take_current_signal_in(set) {
+            if !info.is_null() {
+                unsafe { *info = sig_info };
+            }
+            return Ok(sig_num as isize);
+        }
+        if process.have_signals().is_some() {
+            return Err(SyscallError::EINTR);
+        }
+        if deadline.is_some_and(|deadline| current_time_nanos() >= deadline) {
+            return Err(SyscallError::EAGAIN);
+        }
+        wait_for_signal(
+            || current_pending_in(set).is_some() || process.have_signals().is_some(),
+            deadline,
+        );
+    }
+}
+
 /// Note: It can only be called by the signal processing function during signal processing.
 pub fn syscall_sigreturn() -> SyscallResult {
     Ok(axprocess::signal::signal_return())
@@ -184,8 +249,14 @@ pub fn syscall_kill(args: [usize; 6]) -> SyscallResult {
     let pid = args[0] as isize;
     let signum = args[1] as isize;
     if pid > 0 && signum > 0 {
+        let info = SigInfo {
+            si_signo: signum as i32,
+            si_code: SI_USER,
+            pid: current_process().pid() as i32,
+            ..Default::default()
+        };
         // 不关心是否成功
-        let _ = axprocess::signal::send_signal_to_process(pid, signum, None);
+        let _ = axprocess::signal::send_signal_to_process(pid, signum, Some(info));
         Ok(0)
     } else if pid == 0 {
         Err(SyscallError::ESRCH)
@@ -208,8 +279,17 @@ pub fn syscall_tkill(args: [usize; 6]) -> SyscallResult {
         tid
     );
     if tid > 0 && signum > 0 {
-        let _ = axprocess::signal::send_signal_to_thread(tid, signum);
-        Ok(0)
+        let info = SigInfo {
+            si_signo: signum as i32,
+            si_code: SI_TKILL,
+            pid: current_process().pid() as i32,
+            ..Default::default()
+        };
+        match axprocess::signal::send_signal_to_thread(tid, signum, Some(info)) {
+            // 排队的实时信号数达到上限
+            Err(AxError::WouldBlock) => Err(SyscallError::EAGAIN),
+            _ => Ok(0),
+        }
     } else {
         Err(SyscallError::EINVAL)
     }
@@ -227,13 +307,100 @@ pub fn syscall_tgkill(args: [usize; 6]) -> SyscallResult {
         tid
     );
     if tgid > 0 && tid > 0 && signum > 0 {
-        let _ = axprocess::signal::send_signal_to_thread(tid, signum);
-        Ok(0)
+        let info = SigInfo {
+            si_signo: signum as i32,
+            si_code: SI_TKILL,
+            pid: current_process().pid() as i32,
+            ..Default::default()
+        };
+        match axprocess::signal::send_signal_to_thread(tid, signum, Some(info)) {
+            Err(AxError::WouldBlock) => Err(SyscallError::EAGAIN),
+            _ => Ok(0),
+        }
     } else {
         Err(SyscallError::EINVAL)
     }
 }
 
+/// 读取用户传入的信号附加信息
+///
+/// 除了发送给自己之外，不允许伪造由 `kill` 或内核发送的信号（`si_code` 非负或为 `SI_TKILL`）
+fn read_user_siginfo(
+    uinfo: *const SigInfo,
+    signum: usize,
+    target_pid: u64,
+) -> Result<SigInfo, SyscallError> {
+    let process = current_process();
+    if uinfo.is_null() || process.manual_alloc_type_for_lazy(uinfo).is_err() {
+        return Err(SyscallError::EFAULT);
+    }
+    let mut info = unsafe { *uinfo };
+    if (info.si_code >= 0 || info.si_code == SI_TKILL) && target_pid != process.pid() {
+        return Err(SyscallError::EPERM);
+    }
+    info.si_signo = signum as i32;
+    Ok(info)
+}
+
+fn send_error(err: AxError) -> SyscallError {
+    match err {
+        AxError::WouldBlock => SyscallError::EAGAIN,
+        _ => SyscallError::ESRCH,
+    }
+}
+
+/// 向pid指定的进程发送带附加信息的信号，用于实现 sigqueue
+/// # Arguments
+/// * `pid` - isize
+/// * `signum` - usize
+/// * `uinfo` - *const SigInfo
+pub fn syscall_rt_sigqueueinfo(args: [usize; 6]) -> SyscallResult {
+    let pid = args[0] as isize;
+    let signum = args[1];
+    let uinfo = args[2] as *const SigInfo;
+    if pid <= 0 || signum > MAX_SIG_NUM {
+        return Err(SyscallError::EINVAL);
+    }
+    let info = read_user_siginfo(uinfo, signum, pid as u64)?;
+    if !PID2PC.lock().contains_key(&(pid as u64)) {
+        return Err(SyscallError::ESRCH);
+    }
+    if signum == 0 {
+        // 仅检查进程是否存在
+        return Ok(0);
+    }
+    axprocess::signal::send_signal_to_process(pid, signum as isize, Some(info))
+        .map_err(send_error)?;
+    Ok(0)
+}
+
+/// 向tgid进程中tid指定的线程发送带附加信息的信号
+/// # Arguments
+/// * `tgid` - isize
+/// * `tid` - isize
+/// * `signum` - usize
+/// * `uinfo` - *const SigInfo
+pub fn syscall_rt_tgsigqueueinfo(args: [usize; 6]) -> SyscallResult {
+    let tgid = args[0] as isize;
+    let tid = args[1] as isize;
+    let signum = args[2];
+    let uinfo = args[3] as *const SigInfo;
+    if tgid <= 0 || tid <= 0 || signum > MAX_SIG_NUM {
+        return Err(SyscallError::EINVAL);
+    }
+    let info = read_user_siginfo(uinfo, signum, tgid as u64)?;
+    match TID2TASK.lock().get(&(tid as u64)) {
+        Some(task) if task.get_process_id() == tgid as u64 => {}
+        _ => return Err(SyscallError::ESRCH),
+    }
+    if signum == 0 {
+        return Ok(0);
+    }
+    axprocess::signal::send_signal_to_thread(tid, signum as isize, Some(info))
+        .map_err(send_error)?;
+    Ok(0)
+}
+
 /// Set and get the alternate signal stack
 pub fn syscall_sigaltstack(args: [usize; 6]) -> SyscallResult {
     let current_process = current_process();
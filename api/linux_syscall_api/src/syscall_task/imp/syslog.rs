@@ -0,0 +1,212 @@
+//! syslog 系统调用，读取与控制内核日志缓冲区
+extern crate alloc;
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use core::fmt::Write;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use axlog::kmsg::{self, KmsgCursor, KmsgRecord, LOG_BUF_LEN, LOG_LINE_MAX};
+use axprocess::current_process;
+use axsync::Mutex;
+
+use crate::{SyscallError, SyscallResult};
+
+/// 关闭日志，不做处理
+const SYSLOG_ACTION_CLOSE: usize = 0;
+/// 打开日志，不做处理
+const SYSLOG_ACTION_OPEN: usize = 1;
+/// 读取尚未被本操作读取过的日志，没有日志时阻塞
+const SYSLOG_ACTION_READ: usize = 2;
+/// 读取缓冲区中最近的日志
+const SYSLOG_ACTION_READ_ALL: usize = 3;
+/// 读取缓冲区中最近的日志并清空缓冲区
+const SYSLOG_ACTION_READ_CLEAR: usize = 4;
+/// 清空缓冲区
+const SYSLOG_ACTION_CLEAR: usize = 5;
+/// 不再向控制台输出日志，只保留最严重的日志
+const SYSLOG_ACTION_CONSOLE_OFF: usize = 6;
+/// 恢复 CONSOLE_OFF 之前的控制台日志级别
+const SYSLOG_ACTION_CONSOLE_ON: usize = 7;
+/// 设置控制台日志级别
+const SYSLOG_ACTION_CONSOLE_LEVEL: usize = 8;
+/// 返回 READ 尚未读取的字节数
+const SYSLOG_ACTION_SIZE_UNREAD: usize = 9;
+/// 返回缓冲区的大小
+const SYSLOG_ACTION_SIZE_BUFFER: usize = 10;
+
+/// CONSOLE_OFF 时的控制台日志级别，只输出 KERN_EMERG
+const MINIMUM_CONSOLE_LOGLEVEL: u8 = 1;
+
+/// SYSLOG_ACTION_READ 的读取位置，所有进程共享
+static SYSLOG_CURSOR: Mutex<KmsgCursor> = Mutex::new(KmsgCursor::oldest());
+
+/// CONSOLE_OFF 之前的控制台日志级别，为 0 时表示未关闭
+static SAVED_CONSOLE_LEVEL: AtomicU8 = AtomicU8::new(0);
+
+/// 按照 syslog 的格式输出一条日志，即 `<level>[seconds.micros][caller] text`
+fn format_record(record: &KmsgRecord) -> String {
+    let mut caller = String::new();
+    match (record.task_id, record.cpu_id) {
+        (Some(tid), _) => {
+            let _ = write!(caller, "T{}", tid);
+        }
+        (None, Some(cpu_id)) => {
+            let _ = write!(caller, "C{}", cpu_id);
+        }
+        (None, None) => {}
+    }
+    let mut line = String::new();
+    let _ = writeln!(
+        line,
+        "<{}>[{:>5}.{:06}][{:>6}] {}",
+        record.syslog_level(),
+        record.time.as_secs(),
+        record.time.subsec_micros(),
+        caller,
+        record.text
+    );
+    line
+}
+
+/// 读取并格式化 `cursor` 处的日志，同时移动 `cursor`
+fn next_line(cursor: &mut KmsgCursor) -> Option<String> {
+    let mut text = [0; LOG_LINE_MAX];
+    kmsg::read_record(cursor, &mut text).map(|record| format_record(&record))
+}
+
+/// 读取缓冲区中最近的、总长度不超过 `len` 的日志
+fn read_all(len: usize) -> VecDeque<String> {
+    let mut cursor = KmsgCursor::after_clear();
+    let mut lines = VecDeque::new();
+    let mut total = 0;
+    while let Some(line) = next_line(&mut cursor) {
+        total += line.len();
+        lines.push_back(line);
+        while total > len {
+            total -= lines.pop_front().unwrap().len();
+        }
+    }
+    lines
+}
+
+/// 将日志依次复制到用户缓冲区，返回复制的字节数
+fn copy_lines<'a>(buf: &mut [u8], lines: impl IntoIterator<Item = &'a String>) -> usize {
+    let mut pos = 0;
+    for line in lines {
+        buf[pos..pos + line.len()].copy_from_slice(line.as_bytes());
+        pos += line.len();
+    }
+    pos
+}
+
+/// 检查读取操作的用户缓冲区
+fn user_buffer<'a>(buf: *mut u8, len: i32) -> Result<&'a mut [u8], SyscallError> {
+    if buf.is_null() || len < 0 {
+        return Err(SyscallError::EINVAL);
+    }
+    let len = len as usize;
+    if len != 0
+        && current_process()
+            .manual_alloc_range_for_lazy((buf as usize).into(), (buf as usize + len - 1).into())
+            .is_err()
+    {
+        return Err(SyscallError::EFAULT);
+    }
+    Ok(unsafe { core::slice::from_raw_parts_mut(buf, len) })
+}
+
+/// 读取与控制内核日志缓冲区
+///
+/// # Arguments
+/// * `action` - usize, SYSLOG_ACTION_*
+/// * `buf` - *mut u8, 读取操作存放日志的缓冲区
+/// * `len` - usize, 读取操作的缓冲区长度，或 CONSOLE_LEVEL 设置的级别
+/// # Return
+/// 读取操作返回读取的字节数，SIZE_UNREAD 与 SIZE_BUFFER 返回对应的大小，其余操作返回 0
+pub fn syscall_syslog(args: [usize; 6]) -> SyscallResult {
+    let action = args[0];
+    let buf = args[1] as *mut u8;
+    let len = args[2] as i32;
+    match action {
+        SYSLOG_ACTION_CLOSE | SYSLOG_ACTION_OPEN => Ok(0),
+        SYSLOG_ACTION_READ => {
+            let buf = user_buffer(buf, len)?;
+            if buf.is_empty() {
+                return Ok(0);
+            }
+            let process = current_process();
+            loop {
+                let mut cursor = SYSLOG_CURSOR.lock();
+                let mut pos = 0;
+                // 与 Linux 相同，只读取完整的日志，但第一条日志放不下时截断它
+                let mut next = *cursor;
+                while let Some(line) = next_line(&mut next) {
+                    let len = if pos == 0 {
+                        line.len().min(buf.len())
+                    } else if pos + line.len() <= buf.len() {
+                        line.len()
+                    } else {
+                        break;
+                    };
+                    buf[pos..pos + len].copy_from_slice(&line.as_bytes()[..len]);
+                    pos += len;
+                    *cursor = next;
+                }
+                if pos != 0 {
+                    return Ok(pos as isize);
+                }
+                let waiting = *cursor;
+                drop(cursor);
+                if process.have_signals().is_some() {
+                    return Err(SyscallError::EINTR);
+                }
+                axfs::chrdev::wait_for_record(&waiting, || process.have_signals().is_some());
+            }
+        }
+        SYSLOG_ACTION_READ_ALL | SYSLOG_ACTION_READ_CLEAR => {
+            let buf = user_buffer(buf, len)?;
+            let lines = read_all(buf.len());
+            if action == SYSLOG_ACTION_READ_CLEAR {
+                kmsg::clear();
+            }
+            Ok(copy_lines(buf, &lines) as isize)
+        }
+        SYSLOG_ACTION_CLEAR => {
+            kmsg::clear();
+            Ok(0)
+        }
+        SYSLOG_ACTION_CONSOLE_OFF => {
+            if SAVED_CONSOLE_LEVEL.load(Ordering::Relaxed) == 0 {
+                SAVED_CONSOLE_LEVEL.store(kmsg::console_level(), Ordering::Relaxed);
+            }
+            kmsg::set_console_level(MINIMUM_CONSOLE_LOGLEVEL);
+            Ok(0)
+        }
+        SYSLOG_ACTION_CONSOLE_ON => {
+            let saved = SAVED_CONSOLE_LEVEL.swap(0, Ordering::Relaxed);
+            if saved != 0 {
+                kmsg::set_console_level(saved);
+            }
+            Ok(0)
+        }
+        SYSLOG_ACTION_CONSOLE_LEVEL => {
+            if !(1..=8).contains(&len) {
+                return Err(SyscallError::EINVAL);
+            }
+            kmsg::set_console_level(len as u8);
+            // 显式设置的级别使 CONSOLE_OFF 失效
+            SAVED_CONSOLE_LEVEL.store(0, Ordering::Relaxed);
+            Ok(0)
+        }
+        SYSLOG_ACTION_SIZE_UNREAD => {
+            let mut cursor = *SYSLOG_CURSOR.lock();
+            let mut size = 0;
+            while let Some(line) = next_line(&mut cursor) {
+                size += line.len();
+            }
+            Ok(size as isize)
+        }
+        SYSLOG_ACTION_SIZE_BUFFER => Ok(LOG_BUF_LEN as isize),
+        _ => Err(SyscallError::EINVAL),
+    }
+}
@@ -1,3 +1,4 @@
+use axerrno::AxError;
 use axfs::api::OpenFlags;
 use axhal::time::current_time;
 use axprocess::{
@@ -21,7 +22,7 @@ use crate::{
         imp::solve_path,
     },
     CloneArgs, RLimit, SyscallError, SyscallResult, TimeSecs, WaitFlags, RLIMIT_AS, RLIMIT_NOFILE,
-    RLIMIT_STACK,
+    RLIMIT_SIGPENDING, RLIMIT_STACK,
 };
 use axlog::info;
 use axtask::TaskId;
@@ -131,13 +132,16 @@ pub fn syscall_exec(args: [usize; 6]) -> SyscallResult {
     info!("envs: {:?}", envs_vec);
     let curr_process = current_process();
 
-    // 设置 file_path
-    curr_process.set_file_path(path.clone());
-
     let argc = args_vec.len();
-    if curr_process.exec(path, args_vec, &envs_vec).is_err() {
-        exit_current_task(0);
+    if let Err(e) = curr_process.exec(path.clone(), args_vec, &envs_vec) {
+        // 格式错误的程序对应 ENOEXEC
+        return Err(match e {
+            AxError::InvalidData => SyscallError::ENOEXEC,
+            e => e.into(),
+        });
     }
+    // 设置 file_path
+    curr_process.set_file_path(path);
     Ok(argc as isize)
 }
 
@@ -288,7 +292,7 @@ pub fn syscall_vfork() -> SyscallResult {
 }
 
 /// 等待子进程完成任务，若子进程没有完成，则自身yield
-/// 当前仅支持WNOHANG选项，即若未完成时则不予等待，直接返回0
+/// 支持WNOHANG、WUNTRACED与WCONTINUED选项
 /// # Arguments
 /// * `pid` - i32
 /// * `exit_code_ptr` - *mut i32
@@ -298,7 +302,14 @@ pub fn syscall_wait4(args: [usize; 6]) -> SyscallResult {
     let exit_code_ptr = args[1] as *mut i32;
     let option = WaitFlags::from_bits(args[2] as u32).unwrap();
     loop {
-        let answer = unsafe { wait_pid(pid, exit_code_ptr) };
+        let answer = unsafe {
+            wait_pid(
+                pid,
+                exit_code_ptr,
+                option.contains(WaitFlags::WUNTRACED),
+                option.contains(WaitFlags::WCONTINUED),
+            )
+        };
         match answer {
             Ok(pid) => {
                 return Ok(pid as isize);
@@ -407,10 +418,10 @@ pub fn syscall_prlimit64(args: [usize; 6]) -> SyscallResult {
     let curr_process = current_process();
     if pid == 0 || pid == curr_process.pid() as usize {
         match resource {
-            // TODO: 改变了新创建的任务栈大小，但未实现当前任务的栈扩展
+            // 用户栈在缺页时向下扩展，直到这一限制；同时改变新创建的任务的内核栈大小
             RLIMIT_STACK => {
-                let mut stack_limit: u64 = curr_process.get_stack_limit();
                 if old_limit as usize != 0 {
+                    let stack_limit = curr_process.memory_set.lock().lock().stack_limit() as u64;
                     unsafe {
                         *old_limit = RLimit {
                             rlim_cur: stack_limit,
@@ -420,9 +431,13 @@ pub fn syscall_prlimit64(args: [usize; 6]) -> SyscallResult {
                 }
                 if new_limit as usize != 0 {
                     let new_size = unsafe { (*new_limit).rlim_cur };
+                    curr_process
+                        .memory_set
+                        .lock()
+                        .lock()
+                        .set_stack_limit(new_size.min(usize::MAX as u64) as usize);
                     if new_size > axconfig::TASK_STACK_SIZE as u64 {
-                        stack_limit = new_size;
-                        curr_process.set_stack_limit(stack_limit);
+                        curr_process.set_stack_limit(new_size);
                     }
                 }
             }
@@ -442,6 +457,20 @@ pub fn syscall_prlimit64(args: [usize; 6]) -> SyscallResult {
                     curr_process.fd_manager.set_limit(new_limit);
                 }
             }
+            RLIMIT_SIGPENDING => {
+                if old_limit as usize != 0 {
+                    let limit = curr_process.get_sigpending_limit();
+                    unsafe {
+                        *old_limit = RLimit {
+                            rlim_cur: limit,
+                            rlim_max: limit,
+                        };
+                    }
+                }
+                if new_limit as usize != 0 {
+                    curr_process.set_sigpending_limit(unsafe { (*new_limit).rlim_cur });
+                }
+            }
             RLIMIT_AS => {
                 const USER_MEMORY_LIMIT: usize = 0xffff_ffff;
                 if old_limit as usize != 0 {
@@ -604,10 +633,33 @@ pub fn syscall_fork() -> SyscallResult {
     syscall_clone(args)
 }
 
+/// 读取用户态以 0 结尾的日志过滤规则，长度不超过 PR_LOG_FILTER_SIZE
+fn read_log_filter(spec: *const u8) -> Result<String, SyscallError> {
+    use crate::PR_LOG_FILTER_SIZE;
+
+    let process = current_process();
+    let mut bytes = Vec::new();
+    for i in 0..=PR_LOG_FILTER_SIZE {
+        let addr = spec as usize + i;
+        // 每进入一个新的页面都需要确保其已被分配
+        if (i == 0 || addr % axhal::mem::PAGE_SIZE_4K == 0)
+            && process.manual_alloc_for_lazy(addr.into()).is_err()
+        {
+            return Err(SyscallError::EFAULT);
+        }
+        match unsafe { *(addr as *const u8) } {
+            0 => return String::from_utf8(bytes).map_err(|_| SyscallError::EINVAL),
+            c => bytes.push(c),
+        }
+    }
+    Err(SyscallError::EINVAL)
+}
+
 /// prctl
 /// # Arguments
 /// * `option` - usize
 /// * `arg2` - *mut u8
+/// * `arg3` - usize, PR_GET_LOG_FILTER 的缓冲区长度
 pub fn syscall_prctl(args: [usize; 6]) -> SyscallResult {
     use crate::{PrctlOption, PR_NAME_SIZE};
 
@@ -656,10 +708,53 @@ pub fn syscall_prctl(args: [usize; 6]) -> SyscallResult {
                 Err(SyscallError::EINVAL)
             }
         }
+        Ok(PrctlOption::PR_SET_LOG_FILTER) => {
+            let spec = read_log_filter(arg2)?;
+            axlog::set_log_filter(spec.trim()).map_err(|_| SyscallError::EINVAL)?;
+            Ok(0)
+        }
+        Ok(PrctlOption::PR_GET_LOG_FILTER) => {
+            // 与 PR_GET_NAME 相同，返回以 0 结尾的字符串，缓冲区不足时返回 EINVAL
+            let len = args[2];
+            let mut spec = String::new();
+            let _ = axlog::write_log_filter(&mut spec);
+            spec.push('\0');
+            if spec.len() > len {
+                return Err(SyscallError::EINVAL);
+            }
+            if current_process()
+                .manual_alloc_range_for_lazy(
+                    (arg2 as usize).into(),
+                    (arg2 as usize + spec.len() - 1).into(),
+                )
+                .is_err()
+            {
+                return Err(SyscallError::EFAULT);
+            }
+            unsafe {
+                core::slice::from_raw_parts_mut(arg2, spec.len()).copy_from_slice(spec.as_bytes());
+            }
+            Ok(0)
+        }
         _ => Ok(0),
     }
 }
 
+/// 设置进程的执行域，返回原先的值
+///
+/// 目前只有 `ADDR_NO_RANDOMIZE` 会产生影响，它在下一次 execve 时关闭地址随机化
+/// # Arguments
+/// * `persona` - u32，为 0xffffffff 时只查询当前的值
+pub fn syscall_personality(args: [usize; 6]) -> SyscallResult {
+    let persona = args[0] as u32;
+    let process = current_process();
+    let old = process.get_personality();
+    if persona != 0xffff_ffff {
+        process.set_personality(persona);
+    }
+    Ok(old as isize)
+}
+
 /// Sendthe signal sig to the target process referred to by pidfd
 pub fn syscall_pidfd_send_signal(args: [usize; 6]) -> SyscallResult {
     let fd = args[0];
@@ -699,7 +794,7 @@ pub fn syscall_pidfd_send_signal(args: [usize; 6]) -> SyscallResult {
         unsafe { *sig_info_ptr }
     };
 
-    info!("Pid: {} Sig Info: {:?}", pidfd.pid(), sig_info.si_val_int);
+    info!("Pid: {} Sig Info: {:?}", pidfd.pid(), sig_info.si_value);
 
     send_signal_to_process(pidfd.pid() as isize, signum as isize, Some(sig_info))?;
 
@@ -0,0 +1,291 @@
+//! 间隔定时器与 POSIX 定时器相关的系统调用
+extern crate alloc;
+use alloc::sync::Arc;
+use axprocess::{
+    current_process,
+    timer::{IntervalTimer, TimerClock, TimerNotify},
+    Process, TID2TASK,
+};
+use axsignal::signal_no::{SignalNo, MAX_SIG_NUM};
+
+use crate::{
+    ITimerSpec, ITimerVal, SigEvent, SyscallError, SyscallResult, TimeSecs, TimeVal, SIGEV_NONE,
+    SIGEV_SIGNAL, SIGEV_THREAD_ID, TIMER_ABSTIME,
+};
+
+const NSEC_PER_USEC: u64 = 1000;
+
+/// 由时钟 ID 得到定时器所用的时钟，不支持线程的 CPU 时钟
+pub(crate) fn timer_clock(clock_id: usize) -> Option<TimerClock> {
+    match clock_id {
+        // CLOCK_PROCESS_CPUTIME_ID
+        2 => Some(TimerClock::Prof),
+        // CLOCK_REALTIME、CLOCK_MONOTONIC、CLOCK_MONOTONIC_RAW、CLOCK_REALTIME_COARSE、
+        // CLOCK_MONOTONIC_COARSE、CLOCK_BOOTTIME、CLOCK_REALTIME_ALARM、CLOCK_BOOTTIME_ALARM
+        0 | 1 | 4..=9 => Some(TimerClock::Real),
+        _ => None,
+    }
+}
+
+fn itimer_val(interval: u64, remaining: u64) -> ITimerVal {
+    // 剩余时间不足 1 微秒时向上取整，以免定时器看起来已经停止
+    ITimerVal {
+        it_interval: TimeVal::from_micro((interval / NSEC_PER_USEC) as usize),
+        it_value: TimeVal::from_micro(remaining.div_ceil(NSEC_PER_USEC) as usize),
+    }
+}
+
+/// # Arguments
+/// * `which` - usize
+/// * `new_value` - *const ITimerVal
+/// * `old_value` - *mut ITimerVal
+pub fn syscall_settimer(args: [usize; 6]) -> SyscallResult {
+    let which = args[0];
+    let new_value = args[1] as *const ITimerVal;
+    let old_value = args[2] as *mut ITimerVal;
+    let process = current_process();
+
+    let timer = process.timers.itimer(which).ok_or(SyscallError::EINVAL)?;
+    // 与 Linux 相同，new_value 为空时视为停止定时器
+    let (interval, value) = if new_value.is_null() {
+        (0, 0)
+    } else {
+        if process.manual_alloc_type_for_lazy(new_value).is_err() {
+            return Err(SyscallError::EFAULT);
+        }
+        let new_value = unsafe { &*new_value };
+        if new_value.it_value.usec >= 1_000_000 || new_value.it_interval.usec >= 1_000_000 {
+            return Err(SyscallError::EINVAL);
+        }
+        (
+            new_value.it_interval.turn_to_nanos() as u64,
+            new_value.it_value.turn_to_nanos() as u64,
+        )
+    };
+    if !old_value.is_null() && process.manual_alloc_type_for_lazy(old_value).is_err() {
+        return Err(SyscallError::EFAULT);
+    }
+    let now = process.timers.clock_now(&process, timer.clock());
+    let (old_interval, old_remaining) = timer.set(value, interval, now);
+    if !old_value.is_null() {
+        unsafe {
+            *old_value = itimer_val(old_interval, old_remaining);
+        }
+    }
+    Ok(0)
+}
+
+/// # Arguments
+/// * `which` - usize
+/// * `value` - *mut ITimerVal
+pub fn syscall_gettimer(args: [usize; 6]) -> SyscallResult {
+    let which = args[0];
+    let value = args[1] as *mut ITimerVal;
+    let process = current_process();
+    let timer = process.timers.itimer(which).ok_or(SyscallError::EINVAL)?;
+    if process
+        .manual_alloc_type_for_lazy(value as *const ITimerVal)
+        .is_err()
+    {
+        return Err(SyscallError::EFAULT);
+    }
+    let now = process.timers.clock_now(&process, timer.clock());
+    let (interval, remaining) = timer.get(now);
+    unsafe {
+        *value = itimer_val(interval, remaining);
+    }
+    Ok(0)
+}
+
+/// 在 `seconds` 秒之后发送 SIGALRM，为 0 时取消；返回上一次设置的剩余秒数
+///
+/// # Arguments
+/// * `seconds` - usize
+pub fn syscall_alarm(args: [usize; 6]) -> SyscallResult {
+    let seconds = args[0] as u32 as u64;
+    let process = current_process();
+    let timer = process
+        .timers
+        .itimer(axprocess::timer::ITIMER_REAL)
+        .unwrap();
+    let now = process.timers.clock_now(&process, TimerClock::Real);
+    let (_, remaining) = timer.set(seconds * 1_000_000_000, 0, now);
+    // 向上取整到秒，但不足 1 秒时为 1
+    Ok(remaining.div_ceil(1_000_000_000) as isize)
+}
+
+/// 由 `struct sigevent` 得到定时器的通知方式，`sevp` 为空时返回 `None`
+fn read_sigevent(
+    process: &Process,
+    sevp: *const SigEvent,
+) -> Result<Option<TimerNotify>, SyscallError> {
+    if sevp.is_null() {
+        return Ok(None);
+    }
+    if process.manual_alloc_type_for_lazy(sevp).is_err() {
+        return Err(SyscallError::EFAULT);
+    }
+    let event = unsafe { *sevp };
+    let signo = event.sigev_signo as usize;
+    let valid_signo = signo > 0 && signo <= MAX_SIG_NUM;
+    match event.sigev_notify {
+        SIGEV_NONE => Ok(Some(TimerNotify::None)),
+        SIGEV_SIGNAL if valid_signo => Ok(Some(TimerNotify::Process {
+            signo,
+            value: event.sigev_value,
+        })),
+        SIGEV_THREAD_ID if valid_signo => {
+            // 只能通知本进程中的线程
+            let tid = event.sigev_tid as u64;
+            match TID2TASK.lock().get(&tid) {
+                Some(task) if task.get_process_id() == process.pid() => {}
+                _ => return Err(SyscallError::EINVAL),
+            }
+            Ok(Some(TimerNotify::Thread {
+                tid,
+                signo,
+                value: event.sigev_value,
+            }))
+        }
+        // SIGEV_THREAD 由用户库借助 SIGEV_THREAD_ID 实现
+        _ => Err(SyscallError::EINVAL),
+    }
+}
+
+/// 创建一个 POSIX 定时器
+///
+/// # Arguments
+/// * `clock_id` - usize
+/// * `sevp` - *const SigEvent
+/// * `timer_id` - *mut i32
+pub fn syscall_timer_create(args: [usize; 6]) -> SyscallResult {
+    let clock_id = args[0];
+    let sevp = args[1] as *const SigEvent;
+    let timer_id = args[2] as *mut i32;
+    let process = current_process();
+    let clock = timer_clock(clock_id).ok_or(SyscallError::EINVAL)?;
+    let notify = read_sigevent(&process, sevp)?;
+    if process.manual_alloc_type_for_lazy(timer_id).is_err() {
+        return Err(SyscallError::EFAULT);
+    }
+    // sevp 为空时发送 SIGALRM，随信号发送的值为定时器 ID
+    let id = process
+        .timers
+        .create(clock, |id| {
+            notify.unwrap_or(TimerNotify::Process {
+                signo: SignalNo::SIGALRM as usize,
+                value: id as usize,
+            })
+        })
+        .map_err(|_| SyscallError::EAGAIN)?;
+    unsafe { *timer_id = id };
+    Ok(0)
+}
+
+fn find_timer(process: &Process, timer_id: usize) -> Result<Arc<IntervalTimer>, SyscallError> {
+    process
+        .timers
+        .get(timer_id as i32)
+        .ok_or(SyscallError::EINVAL)
+}
+
+/// 设置 POSIX 定时器
+///
+/// # Arguments
+/// * `timer_id` - usize
+/// * `flags` - usize
+/// * `new_value` - *const ITimerSpec
+/// * `old_value` - *mut ITimerSpec
+pub fn syscall_timer_settime(args: [usize; 6]) -> SyscallResult {
+    let flags = args[1];
+    let new_value = args[2] as *const ITimerSpec;
+    let old_value = args[3] as *mut ITimerSpec;
+    let process = current_process();
+    let timer = find_timer(&process, args[0])?;
+    if process.manual_alloc_type_for_lazy(new_value).is_err()
+        || (!old_value.is_null() && process.manual_alloc_type_for_lazy(old_value).is_err())
+    {
+        return Err(SyscallError::EFAULT);
+    }
+    let new_value = unsafe { *new_value };
+    let now = process.timers.clock_now(&process, timer.clock());
+    let (value, interval) = timer_spec_to_nanos(&new_value, flags, now)?;
+    let (old_interval, old_remaining) = timer.set(value, interval, now);
+    if !old_value.is_null() {
+        unsafe {
+            *old_value = ITimerSpec {
+                it_interval: TimeSecs::from_nanos(old_interval as usize),
+                it_value: TimeSecs::from_nanos(old_remaining as usize),
+            };
+        }
+    }
+    Ok(0)
+}
+
+/// 由 `struct itimerspec` 得到距离 `now` 的到期时间与间隔，单位为纳秒
+///
+/// 给定的绝对时间已经过去时，定时器立即到期
+pub(crate) fn timer_spec_to_nanos(
+    spec: &ITimerSpec,
+    flags: usize,
+    now: u64,
+) -> Result<(u64, u64), SyscallError> {
+    const NSEC_PER_SEC: usize = 1_000_000_000;
+    if spec.it_value.tv_nsec >= NSEC_PER_SEC || spec.it_interval.tv_nsec >= NSEC_PER_SEC {
+        return Err(SyscallError::EINVAL);
+    }
+    let value = spec.it_value.turn_to_nanos() as u64;
+    let interval = spec.it_interval.turn_to_nanos() as u64;
+    let value = if flags & TIMER_ABSTIME != 0 && value != 0 {
+        value.saturating_sub(now).max(1)
+    } else {
+        value
+    };
+    Ok((value, interval))
+}
+
+/// 获取 POSIX 定时器的间隔与剩余时间
+///
+/// # Arguments
+/// * `timer_id` - usize
+/// * `curr_value` - *mut ITimerSpec
+pub fn syscall_timer_gettime(args: [usize; 6]) -> SyscallResult {
+    let curr_value = args[1] as *mut ITimerSpec;
+    let process = current_process();
+    let timer = find_timer(&process, args[0])?;
+    if process.manual_alloc_type_for_lazy(curr_value).is_err() {
+        return Err(SyscallError::EFAULT);
+    }
+    let now = process.timers.clock_now(&process, timer.clock());
+    let (interval, remaining) = timer.get(now);
+    unsafe {
+        *curr_value = ITimerSpec {
+            it_interval: TimeSecs::from_nanos(interval as usize),
+            it_value: TimeSecs::from_nanos(remaining as usize),
+        };
+    }
+    Ok(0)
+}
+
+/// 获取 POSIX 定时器最近一次到期时错过的到期次数
+///
+/// # Arguments
+/// * `timer_id` - usize
+pub fn syscall_timer_getoverrun(args: [usize; 6]) -> SyscallResult {
+    let process = current_process();
+    let timer = find_timer(&process, args[0])?;
+    Ok(timer.overrun().min(i32::MAX as u64) as isize)
+}
+
+/// 删除 POSIX 定时器
+///
+/// # Arguments
+/// * `timer_id` - usize
+pub fn syscall_timer_delete(args: [usize; 6]) -> SyscallResult {
+    let process = current_process();
+    process
+        .timers
+        .delete(args[0] as i32)
+        .map_err(|_| SyscallError::EINVAL)?;
+    Ok(0)
+}
@@ -2,12 +2,11 @@ use core::{slice::from_raw_parts_mut, time::Duration};
 
 use axhal::time::{current_time, current_time_nanos, nanos_to_ticks, NANOS_PER_SEC};
 
-use axprocess::{current_process, current_task, time_stat_output};
-use rand::{rngs::SmallRng, Fill, SeedableRng};
+use axprocess::{current_process, time_stat_output};
 
 use crate::{
-    ClockId, ITimerVal, RusageFlags, SysInfo, SyscallError, SyscallResult, TimeSecs, TimeVal, Tms,
-    UtsName,
+    ClockId, RusageFlags, SysInfo, SyscallError, SyscallResult, TimeSecs, TimeVal, Tms, UtsName,
+    TIMER_ABSTIME,
 };
 
 /// 返回值为当前经过的时钟中断数
@@ -86,69 +85,6 @@ pub fn syscall_sysinfo(args: [usize; 6]) -> SyscallResult {
     Ok(0)
 }
 
-/// # Arguments
-/// * `which` - usize
-/// * `new_value` - *const ITimerVal
-/// * `old_value` - *mut ITimerVal
-pub fn syscall_settimer(args: [usize; 6]) -> SyscallResult {
-    let which = args[0];
-    let new_value = args[1] as *const ITimerVal;
-    let old_value = args[2] as *mut ITimerVal;
-    let process = current_process();
-
-    if new_value.is_null() {
-        return Err(SyscallError::EFAULT);
-    }
-
-    let new_value = match process.manual_alloc_type_for_lazy(new_value) {
-        Ok(_) => unsafe { &*new_value },
-        Err(_) => return Err(SyscallError::EFAULT),
-    };
-
-    if !old_value.is_null() {
-        if process.manual_alloc_type_for_lazy(old_value).is_err() {
-            return Err(SyscallError::EFAULT);
-        }
-
-        let (time_interval_us, time_remained_us) = current_task().timer_output();
-        unsafe {
-            (*old_value).it_interval = TimeVal::from_micro(time_interval_us);
-            (*old_value).it_value = TimeVal::from_micro(time_remained_us);
-        }
-    }
-    let (time_interval_ns, time_remained_ns) = (
-        new_value.it_interval.turn_to_nanos(),
-        new_value.it_value.turn_to_nanos(),
-    );
-    if current_task().set_timer(time_interval_ns, time_remained_ns, which) {
-        Ok(0)
-    } else {
-        // 说明which参数错误
-        Err(SyscallError::EFAULT)
-    }
-}
-
-/// # Arguments
-/// * `which` - usize
-/// * `value` - *mut ITimerVal
-pub fn syscall_gettimer(args: [usize; 6]) -> SyscallResult {
-    let _which = args[0];
-    let value = args[1] as *mut ITimerVal;
-    let process = current_process();
-    if process
-        .manual_alloc_type_for_lazy(value as *const ITimerVal)
-        .is_err()
-    {
-        return Err(SyscallError::EFAULT);
-    }
-    let (time_interval_us, time_remained_us) = current_task().timer_output();
-    unsafe {
-        (*value).it_interval = TimeVal::from_micro(time_interval_us);
-        (*value).it_value = TimeVal::from_micro(time_remained_us);
-    }
-    Ok(0)
-}
-
 /// # Arguments
 /// * `who` - i32
 /// * `utime` - *mut TimeVal
@@ -174,6 +110,16 @@ pub fn syscall_getrusage(args: [usize; 6]) -> SyscallResult {
     }
 }
 
+/// 不阻塞，熵池未初始化时返回 EAGAIN
+const GRND_NONBLOCK: usize = 0x1;
+/// 与 /dev/random 相同，自 Linux 5.6 起与默认行为一致
+const GRND_RANDOM: usize = 0x2;
+/// 熵池未初始化时也直接返回随机数
+const GRND_INSECURE: usize = 0x4;
+
+/// 从内核熵池获取随机数
+///
+/// 熵池未初始化时，默认阻塞直到初始化完成，期间收到信号则返回 EINTR
 /// # Arguments
 /// * `buf` - *mut u8
 /// * `len` - usize
@@ -181,7 +127,14 @@ pub fn syscall_getrusage(args: [usize; 6]) -> SyscallResult {
 pub fn syscall_getrandom(args: [usize; 6]) -> SyscallResult {
     let buf = args[0] as *mut u8;
     let len = args[1];
-    let _flags = args[2];
+    let flags = args[2];
+    if flags & !(GRND_NONBLOCK | GRND_RANDOM | GRND_INSECURE) != 0
+        || flags & (GRND_RANDOM | GRND_INSECURE) == (GRND_RANDOM | GRND_INSECURE)
+    {
+        return Err(SyscallError::EINVAL);
+    }
+    // 单次调用最多返回 i32::MAX 字节
+    let len = len.min(i32::MAX as usize);
     let process = current_process();
 
     if process
@@ -196,11 +149,15 @@ pub fn syscall_getrandom(args: [usize; 6]) -> SyscallResult {
 
     let buf = unsafe { from_raw_parts_mut(buf, len) };
 
-    // TODO: flags
-    // - GRND_RANDOM: use /dev/random or /dev/urandom
-    // - GRND_NONBLOCK: EAGAIN when block
-    let mut rng = SmallRng::from_seed([0; 32]);
-    buf.try_fill(&mut rng).unwrap();
+    if !axrand::is_ready() && flags & GRND_INSECURE == 0 {
+        if flags & GRND_NONBLOCK != 0 {
+            return Err(SyscallError::EAGAIN);
+        }
+        if !axrand::wait_for_ready(|| process.have_signals().is_some()) {
+            return Err(SyscallError::EINTR);
+        }
+    }
+    axrand::fill_bytes(buf);
 
     Ok(buf.len() as isize)
 }
@@ -256,7 +213,6 @@ pub fn syscall_clock_nanosleep(args: [usize; 6]) -> SyscallResult {
     let flags = args[1];
     let request = args[2] as *const TimeSecs;
     let remain = args[3] as *mut TimeSecs;
-    const TIMER_ABSTIME: usize = 1;
     let id = if let Ok(opt) = ClockId::try_from(id) {
         opt
     } else {
@@ -39,6 +39,9 @@ pub fn task_syscall(syscall_id: task_syscall_id::TaskSyscallId, args: [usize; 6]
 
         TGKILL => syscall_tgkill(args),
 
+        RT_SIGQUEUEINFO => syscall_rt_sigqueueinfo(args),
+        RT_TGSIGQUEUEINFO => syscall_rt_tgsigqueueinfo(args),
+
         SIGPROCMASK => syscall_sigprocmask(args),
         SIGALTSTACK => syscall_sigaltstack(args),
         SIGRETURN => syscall_sigreturn(),
@@ -58,12 +61,17 @@ pub fn task_syscall(syscall_id: task_syscall_id::TaskSyscallId, args: [usize; 6]
         SYSINFO => syscall_sysinfo(args),
         SETITIMER => syscall_settimer(args),
         GETTIMER => syscall_gettimer(args),
+        TIMER_CREATE => syscall_timer_create(args),
+        TIMER_SETTIME => syscall_timer_settime(args),
+        TIMER_GETTIME => syscall_timer_gettime(args),
+        TIMER_GETOVERRUN => syscall_timer_getoverrun(args),
+        TIMER_DELETE => syscall_timer_delete(args),
         SETSID => syscall_setsid(),
         GETRUSAGE => syscall_getrusage(args),
         UMASK => syscall_umask(args),
+        SIGTIMEDWAIT => syscall_rt_sigtimedwait(args),
+        SYSLOG => syscall_syslog(args),
         // 不做处理即可
-        SIGTIMEDWAIT => Ok(0),
-        SYSLOG => Ok(0),
         MADVICE => Ok(0),
         SCHED_SETAFFINITY => Ok(0),
         SCHED_GETAFFINITY => syscall_sched_getaffinity(args),
@@ -77,6 +85,7 @@ pub fn task_syscall(syscall_id: task_syscall_id::TaskSyscallId, args: [usize; 6]
         CLOCK_GETRES => syscall_clock_getres(args),
         CLOCK_NANOSLEEP => syscall_clock_nanosleep(args),
         PRCTL => syscall_prctl(args),
+        PERSONALITY => syscall_personality(args),
         PIDFD_SEND_SIGNAL => syscall_pidfd_send_signal(args),
         // syscall below just for x86_64
         #[cfg(target_arch = "x86_64")]
@@ -86,7 +95,7 @@ pub fn task_syscall(syscall_id: task_syscall_id::TaskSyscallId, args: [usize; 6]
         #[cfg(target_arch = "x86_64")]
         FORK => syscall_fork(),
         #[cfg(target_arch = "x86_64")]
-        ALARM => Ok(0),
+        ALARM => syscall_alarm(args),
         #[cfg(target_arch = "x86_64")]
         RSEQ => Ok(0),
         #[cfg(target_arch = "x86_64")]
@@ -21,6 +21,11 @@ pub enum TaskSyscallId {
     NANO_SLEEP = 101,
     GETTIMER = 102,
     SETITIMER = 103,
+    TIMER_CREATE = 107,
+    TIMER_GETTIME = 108,
+    TIMER_GETOVERRUN = 109,
+    TIMER_SETTIME = 110,
+    TIMER_DELETE = 111,
     CLOCK_GETRES = 114,
     CLOCK_NANOSLEEP = 115,
     SYSLOG = 116,
@@ -33,6 +38,7 @@ pub enum TaskSyscallId {
     SETPGID = 154,
     GETPGID = 155,
     SETSID = 157,
+    PERSONALITY = 92,
     GETRUSAGE = 165,
     UMASK = 166,
     PRCTL = 167,
@@ -66,6 +72,8 @@ pub enum TaskSyscallId {
     SIGACTION = 134,
     SIGPROCMASK = 135,
     SIGRETURN = 139,
+    RT_SIGQUEUEINFO = 138,
+    RT_TGSIGQUEUEINFO = 240,
     PIDFD_SEND_SIGNAL = 424,
 }
 }
@@ -87,6 +95,11 @@ numeric_enum_macro::numeric_enum! {
         NANO_SLEEP = 35,
         GETTIMER = 36,
         SETITIMER = 38,
+        TIMER_CREATE = 222,
+        TIMER_SETTIME = 223,
+        TIMER_GETTIME = 224,
+        TIMER_GETOVERRUN = 225,
+        TIMER_DELETE = 226,
         CLOCK_GETRES = 229,
         CLOCK_NANOSLEEP = 230,
         TIME = 201,
@@ -99,6 +112,7 @@ numeric_enum_macro::numeric_enum! {
         SCHED_GETAFFINITY = 204,
         GET_MEMPOLICY = 239,
         SETSID = 112,
+        PERSONALITY = 135,
         GETRUSAGE = 98,
         UMASK = 95,
         PRCTL = 157,
@@ -135,6 +149,8 @@ numeric_enum_macro::numeric_enum! {
         SIGACTION = 13,
         SIGPROCMASK = 14,
         SIGRETURN = 15,
+        RT_SIGQUEUEINFO = 129,
+        RT_TGSIGQUEUEINFO = 297,
         FORK = 57,
         VFORK = 58,
         ALARM = 37,
@@ -88,6 +88,73 @@ fi
 
 mkdir -p mnt
 
+# 在镜像中建立 `$2` 指向 `$1` 的符号链接，两者都是镜像中的绝对路径
+# fat32 不能保存符号链接，改为复制链接目标；目标不存在时报错退出
+make_link()
+{
+	target=$1
+	link=mnt$2
+	if [ ! -e "mnt$target" ]; then
+		echo "Missing target $target of link $2"
+		exit 1
+	fi
+	if [ -e "$link" ] || [ -L "$link" ]; then
+		return
+	fi
+	$SUDO mkdir -p "`dirname "$link"`"
+	if [ "$fs" = "ext4" ]; then
+		$SUDO ln -s "$target" "$link"
+	else
+		$SUDO cp -rL "mnt$target" "$link"
+	fi
+}
+
+# 测例依赖的动态库与命令的链接
+make_links()
+{
+	make_link /libc.so /lib/ld-musl-$arch-sf.so.1
+	make_link /libc.so /lib/ld-musl-$arch.so.1
+	for cmd in ls mkdir touch mv busybox sh which cp; do
+		make_link /busybox /usr/sbin/$cmd
+		make_link /busybox /usr/bin/$cmd
+		make_link /busybox /bin/$cmd
+	done
+	if [ "$arch" = "x86_64" ]; then
+		for lib in ld-linux-x86-64.so.2 libssl.so.3 libcrypto.so.3 libstdc++.so.6 libm.so.6 libgcc_s.so.1 libc.so.6; do
+			make_link /$lib /lib/$lib
+		done
+	fi
+	# 以下测例不一定在所选的测例集中
+	if [ -e mnt/tls_get_new-dtv_dso.so ]; then
+		make_link /tls_get_new-dtv_dso.so /lib/tls_get_new-dtv_dso.so
+	fi
+	if [ -e mnt/lmbench_all ]; then
+		make_link /lmbench_all /bin/lmbench_all
+	fi
+	if [ -e mnt/iozone ]; then
+		make_link /iozone /bin/iozone
+	fi
+	# gcc 相关的链接，可以在 testcases/gcc/riscv64-linux-musl-native/lib 目录下使用 ls -al 查看
+	gcc_dir=/riscv64-linux-musl-native
+	gcc_lib=$gcc_dir/lib
+	if [ -d mnt$gcc_dir ]; then
+		make_link /lib/libc.so $gcc_lib/ld-musl-riscv64.so.1
+		make_link $gcc_lib/libatomic.so.1.2.0 $gcc_lib/libatomic.so
+		make_link $gcc_lib/libatomic.so.1.2.0 $gcc_lib/libatomic.so.1
+		make_link $gcc_lib/libgfortran.so.5.0.0 $gcc_lib/libgfortran.so
+		make_link $gcc_lib/libgfortran.so.5.0.0 $gcc_lib/libgfortran.so.5
+		make_link $gcc_lib/libgomp.so.1.0.0 $gcc_lib/libgomp.so
+		make_link $gcc_lib/libgomp.so.1.0.0 $gcc_lib/libgomp.so.1
+		make_link $gcc_lib/libssp.so.0.0.0 $gcc_lib/libssp.so
+		make_link $gcc_lib/libssp.so.0.0.0 $gcc_lib/libssp.so.0
+		make_link $gcc_lib/libstdc++.so.6.0.29 $gcc_lib/libstdc++.so
+		make_link $gcc_lib/libstdc++.so.6.0.29 $gcc_lib/libstdc++.so.6
+		# gcc 和 musl 的头文件目录都指向 riscv64-linux-musl-native/include
+		make_link $gcc_dir/include $gcc_lib/gcc/riscv64-linux-musl/11.2.1/include
+		make_link $gcc_dir/include $gcc_dir/riscv64-linux-musl/include
+	fi
+}
+
 #### 添加 MacOS 支持
 os=`uname -s`
 if [ "x$os" = "xDarwin" ];then
@@ -95,6 +162,7 @@ if [ "x$os" = "xDarwin" ];then
 	hdiutil attach disk.img -mountpoint mnt
 	echo "Copying $arch $fs $FILE/* to disk"
 	cp -r ./testcases/$FILE/* ./mnt/
+	SUDO= make_links
 	hdiutil detach mnt
 	chmod 777 disk.img
 else
@@ -102,6 +170,7 @@ else
 	# 根据命令行参数生成对应的测例
 	echo "Copying $arch $fs $FILE/* to disk"
 	sudo cp -r ./testcases/$FILE/* ./mnt/
+	SUDO=sudo make_links
 	sudo umount mnt
 	sudo rm -rf mnt
 	sudo chmod 777 disk.img
@@ -1,13 +1,26 @@
 use axconfig::{SMP, TASK_STACK_SIZE};
 use axhal::mem::{virt_to_phys, VirtAddr};
 
+/// Boot stacks of the secondary CPUs. [`SMP`] is the maximum number of CPUs
+/// supported, and the CPUs beyond it are never started.
 #[link_section = ".bss.stack"]
 static mut SECONDARY_BOOT_STACK: [[u8; TASK_STACK_SIZE]; SMP - 1] = [[0; TASK_STACK_SIZE]; SMP - 1];
 
 /// To start secondary CPUs after the primary CPU has been started.
+///
+/// Only the CPUs present in the device tree are started, and at most [`SMP`]
+/// of them.
 pub(crate) fn start_secondary_cpus(primary_cpu_id: usize) {
     let mut logic_cpu_id = 0;
-    for i in 0..SMP {
+    let cpu_num = axhal::cpu::cpu_num();
+    if let Some(count) = axhal::dtb::cpu_count().filter(|&count| count > SMP) {
+        log::warn!(
+            "{} CPUs found in the device tree, but only {} are supported, the others are not started",
+            count,
+            SMP
+        );
+    }
+    for i in 0..cpu_num {
         if i != primary_cpu_id {
             let stack_top = virt_to_phys(VirtAddr::from(unsafe {
                 SECONDARY_BOOT_STACK[logic_cpu_id].as_ptr_range().end as usize
@@ -27,7 +40,7 @@ pub(crate) fn start_secondary_cpus(primary_cpu_id: usize) {
 }
 
 pub(crate) unsafe fn mp_boot_stack(sp: usize) -> *mut u8 {
-    for i in 0..SMP {
+    for i in 0..SMP - 1 {
         let stack_low = SECONDARY_BOOT_STACK[i].as_ptr() as usize;
         let stack_high = stack_low + TASK_STACK_SIZE;
 
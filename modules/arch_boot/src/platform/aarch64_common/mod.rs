@@ -16,11 +16,12 @@ pub(crate) unsafe extern "C" fn rust_entry(cpu_id: usize, dtb: usize) {
     use axhal::mem::phys_to_virt;
     axhal::mem::clear_bss();
 
-    axhal::cpu::init_primary(cpu_id);
-
     // init fdt
     axhal::platform::mem::idmap_device(dtb);
     of::init_fdt_ptr(phys_to_virt(dtb.into()).as_usize() as *const u8);
+    axhal::dtb::init(phys_to_virt(dtb.into()).as_usize(), dtb);
+
+    axhal::cpu::init_primary(cpu_id);
 
     // HugeMap all device memory for allocator
     of::memory_nodes().map(|nodes| {
@@ -9,6 +9,9 @@ unsafe fn init_boot_page_table() {
     BOOT_PT_SV39[2] = (0x80000 << 10) | 0xef;
     // 0xffff_ffc0_8000_0000..0xffff_ffc0_c000_0000, VRWX_GAD, 1G block
     BOOT_PT_SV39[0x102] = (0x80000 << 10) | 0xef;
+    // 0xc000_0000..0x1_0000_0000, VRW_GAD, 1G block, for the device tree that
+    // QEMU puts at the end of a larger memory
+    BOOT_PT_SV39[3] = (0xc0000 << 10) | 0xe7;
 }
 
 unsafe fn init_mmu() {
@@ -8,8 +8,8 @@ extern "C" {
 }
 unsafe extern "C" fn rust_entry(cpu_id: usize, dtb: usize) {
     axhal::mem::clear_bss();
-    axhal::cpu::init_primary(cpu_id);
     axhal::platform::time::init_board_info(dtb);
+    axhal::cpu::init_primary(cpu_id);
     axtrap::init_interrupt();
     axlog::init();
     axlog::set_max_level(option_env!("AX_LOG").unwrap_or("")); // no effect if set `log-level-*` features
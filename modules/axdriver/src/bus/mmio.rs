@@ -1,23 +1,60 @@
 #[allow(unused_imports)]
 use crate::{prelude::*, AllDevices};
 
+/// Returns the VirtIO MMIO slots as (`base_paddr`, `size`, IRQ number).
+///
+/// The slots are taken from the device tree if it has any, otherwise from
+/// [`axconfig::VIRTIO_MMIO_REGIONS`].
+#[allow(dead_code)]
+pub(crate) fn virtio_mmio_slots() -> impl Iterator<Item = (usize, usize, Option<usize>)> {
+    let from_dtb = !axhal::dtb::virtio_mmio_regions().is_empty();
+    let dtb_slots = axhal::dtb::virtio_mmio_regions()
+        .iter()
+        .map(|dev| (dev.paddr, dev.size, dev.irq));
+    let config_slots = axconfig::VIRTIO_MMIO_REGIONS
+        .iter()
+        .enumerate()
+        .filter(move |_| !from_dtb)
+        .map(|(slot, reg)| {
+            let irq_base = axconfig::VIRTIO_MMIO_IRQ_BASE;
+            (reg.0, reg.1, (irq_base != 0).then_some(irq_base + slot))
+        });
+    dtb_slots.chain(config_slots)
+}
+
 impl AllDevices {
     pub(crate) fn probe_bus_devices(&mut self) {
-        // TODO: parse device tree
         #[cfg(feature = "virtio")]
-        for reg in axconfig::VIRTIO_MMIO_REGIONS {
+        for (_slot, (base, size, _irq)) in virtio_mmio_slots().enumerate() {
             for_each_drivers!(type Driver, {
-                if let Some(dev) = Driver::probe_mmio(reg.0, reg.1) {
+                if let Some(dev) = Driver::probe_mmio(base, size) {
                     info!(
                         "registered a new {:?} device at [PA:{:#x}, PA:{:#x}): {:?}",
                         dev.device_type(),
-                        reg.0, reg.0 + reg.1,
+                        base, base + size,
                         dev.device_name(),
                     );
+                    #[cfg(feature = "irq")]
+                    if let Some(irq) = _irq {
+                        crate::virtio::register_mmio_irq(_slot, base, irq, dev.device_type());
+                    }
                     self.add_device(dev);
                     continue; // skip to the next device
                 }
             });
+            #[cfg(feature = "virtio-queue")]
+            if let Some(name) = self.probe_virtio_mmio_ext(base) {
+                info!(
+                    "registered a new {} device at [PA:{:#x}, PA:{:#x})",
+                    name,
+                    base,
+                    base + size,
+                );
+                #[cfg(feature = "irq")]
+                if let Some(irq) = _irq {
+                    crate::virtio::register_mmio_irq(_slot, base, irq, DeviceType::Char);
+                }
+            }
         }
     }
 }
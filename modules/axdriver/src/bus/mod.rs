@@ -1,4 +1,4 @@
 #[cfg(bus = "mmio")]
-mod mmio;
+pub(crate) mod mmio;
 #[cfg(bus = "pci")]
 mod pci;
@@ -84,33 +84,49 @@ fn config_pci_device(
 
 impl AllDevices {
     pub(crate) fn probe_bus_devices(&mut self) {
-        let base_vaddr = phys_to_virt(axconfig::PCI_ECAM_BASE.into());
+        // the host bridge in the device tree, or the one in the platform config
+        let host = axhal::dtb::pci_host();
+        let ecam_base = host.map_or(axconfig::PCI_ECAM_BASE, |host| host.ecam.0);
+        let bus_end = host.map_or(axconfig::PCI_BUS_END, |host| host.bus_end);
+        let base_vaddr = phys_to_virt(ecam_base.into());
         let mut root = unsafe { PciRoot::new(base_vaddr.as_mut_ptr(), Cam::Ecam) };
 
         // PCI 32-bit MMIO space
-        let mut allocator = axconfig::PCI_RANGES
-            .get(1)
-            .map(|range| PciRangeAllocator::new(range.0 as u64, range.1 as u64));
+        let mem32 = match host {
+            Some(host) => host.mem32,
+            None => axconfig::PCI_RANGES.get(1).copied(),
+        };
+        let mut allocator =
+            mem32.map(|range| PciRangeAllocator::new(range.0 as u64, range.1 as u64));
 
-        for bus in 0..=axconfig::PCI_BUS_END as u8 {
+        for bus in 0..=bus_end as u8 {
             for (bdf, dev_info) in root.enumerate_bus(bus) {
                 debug!("PCI {}: {}", bdf, dev_info);
                 if dev_info.header_type != HeaderType::Standard {
                     continue;
                 }
                 match config_pci_device(&mut root, bdf, &mut allocator) {
-                    Ok(_) => for_each_drivers!(type Driver, {
-                        if let Some(dev) = Driver::probe_pci(&mut root, bdf, &dev_info) {
-                            info!(
-                                "registered a new {:?} device at {}: {:?}",
-                                dev.device_type(),
-                                bdf,
-                                dev.device_name(),
-                            );
-                            self.add_device(dev);
-                            continue; // skip to the next device
+                    Ok(_) => {
+                        for_each_drivers!(type Driver, {
+                            let devs = Driver::probe_pci_all(&mut root, bdf, &dev_info);
+                            if !devs.is_empty() {
+                                for dev in devs {
+                                    info!(
+                                        "registered a new {:?} device at {}: {:?}",
+                                        dev.device_type(),
+                                        bdf,
+                                        dev.device_name(),
+                                    );
+                                    self.add_device(dev);
+                                }
+                                continue; // skip to the next device
+                            }
+                        });
+                        #[cfg(feature = "virtio-queue")]
+                        if let Some(name) = self.probe_virtio_pci_ext(&mut root, bdf, &dev_info) {
+                            info!("registered a new {} device at {}", name, bdf);
                         }
-                    }),
+                    }
                     Err(e) => warn!(
                         "failed to enable PCI device at {}({}): {:?}",
                         bdf, dev_info, e
@@ -33,6 +33,17 @@ pub trait DriverProbe {
     ) -> Option<AxDeviceEnum> {
         None
     }
+
+    /// Probes a PCI function that may have more than one device, e.g., an
+    /// NVMe controller with multiple namespaces.
+    #[cfg(bus = "pci")]
+    fn probe_pci_all(
+        root: &mut PciRoot,
+        bdf: DeviceFunction,
+        dev_info: &DeviceFunctionInfo,
+    ) -> alloc::vec::Vec<AxDeviceEnum> {
+        Self::probe_pci(root, bdf, dev_info).into_iter().collect()
+    }
 }
 
 #[cfg(net_dev = "virtio-net")]
@@ -83,6 +94,43 @@ cfg_if::cfg_if! {
     }
 }
 
+cfg_if::cfg_if! {
+    if #[cfg(block_dev = "nvme")] {
+        pub struct NvmeDriver;
+        register_block_driver!(NvmeDriver, crate::nvme::NvmeNamespace);
+
+        impl DriverProbe for NvmeDriver {
+            #[cfg(bus = "pci")]
+            fn probe_pci_all(
+                root: &mut PciRoot,
+                bdf: DeviceFunction,
+                dev_info: &DeviceFunctionInfo,
+            ) -> alloc::vec::Vec<AxDeviceEnum> {
+                // mass storage controller, non-volatile memory, NVM Express
+                if (dev_info.class, dev_info.subclass, dev_info.prog_if) != (0x01, 0x08, 0x02) {
+                    return alloc::vec::Vec::new();
+                }
+                info!("NVMe PCI device found at {:?}", bdf);
+                let address = match root.bar_info(bdf, 0).unwrap() {
+                    driver_pci::BarInfo::Memory { address, .. } => address,
+                    driver_pci::BarInfo::IO { .. } => {
+                        error!("nvme: BAR0 is of I/O type");
+                        return alloc::vec::Vec::new();
+                    }
+                };
+                let regs = axhal::mem::phys_to_virt((address as usize).into()).as_usize();
+                match crate::nvme::NvmeNamespace::probe(regs) {
+                    Ok(namespaces) => namespaces.into_iter().map(AxDeviceEnum::from_block).collect(),
+                    Err(e) => {
+                        error!("failed to initialize NVMe controller at {}: {:?}", bdf, e);
+                        alloc::vec::Vec::new()
+                    }
+                }
+            }
+        }
+    }
+}
+
 cfg_if::cfg_if! {
     if #[cfg(net_dev = "ixgbe")] {
         use crate::ixgbe::IxgbeHalImpl;
@@ -0,0 +1,115 @@
+//! Interrupt-driven I/O support.
+//!
+//! Each VirtIO MMIO slot has an [`IrqWaiter`], which is notified by the IRQ
+//! handler of the slot. Tasks waiting for a device request sleep on it instead
+//! of spinning, and fall back to polling when IRQs are disabled or the device
+//! has no interrupt routing (e.g., on the PCI bus).
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::time::Duration;
+
+use axtask::WaitQueue;
+
+/// Number of VirtIO MMIO slots that can deliver interrupts.
+pub(crate) const MAX_SLOTS: usize = 8;
+
+/// A wait queue woken by the IRQs of a device queue.
+///
+/// It counts the delivered IRQs, so a waiter can take a snapshot with
+/// [`IrqWaiter::events`] before checking the device, and will not miss an IRQ
+/// that arrives between the check and the sleep.
+pub struct IrqWaiter {
+    events: AtomicUsize,
+    wq: WaitQueue,
+}
+
+impl IrqWaiter {
+    const fn new() -> Self {
+        Self {
+            events: AtomicUsize::new(0),
+            wq: WaitQueue::new(),
+        }
+    }
+
+    /// Records an event and wakes up all waiting tasks.
+    ///
+    /// It is called by IRQ handlers, and also by software that produces events
+    /// without an IRQ (e.g., the loopback network interface).
+    pub fn notify(&self) {
+        self.events.fetch_add(1, Ordering::Release);
+        self.wq.notify_all();
+    }
+
+    /// Returns the number of events so far.
+    pub fn events(&self) -> usize {
+        self.events.load(Ordering::Acquire)
+    }
+
+    /// Blocks the current task until an event newer than the `seen` snapshot
+    /// arrives, or the `timeout` has elapsed.
+    pub fn wait_since(&self, seen: usize, timeout: Duration) {
+        if !axhal::arch::irqs_enabled() {
+            axtask::yield_now();
+            return;
+        }
+        self.wq
+            .wait_timeout_until(timeout, || self.events() != seen);
+    }
+
+    /// Blocks the current task until `done` returns `true`. `done` is checked
+    /// again every time an event arrives.
+    pub fn wait_until(&self, mut done: impl FnMut() -> bool) {
+        loop {
+            let seen = self.events();
+            if done() {
+                return;
+            }
+            if axhal::arch::irqs_enabled() {
+                self.wq.wait_until(|| self.events() != seen);
+            } else {
+                // during initialization, before IRQs are enabled
+                core::hint::spin_loop();
+            }
+        }
+    }
+}
+
+static SLOT_WAITERS: [IrqWaiter; MAX_SLOTS] = [const { IrqWaiter::new() }; MAX_SLOTS];
+
+/// The slot of the network device, `usize::MAX` if none.
+static NET_SLOT: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+/// Returns the VirtIO MMIO slot at the given physical address.
+#[cfg(bus = "mmio")]
+fn slot_of(mmio_base: usize) -> Option<usize> {
+    crate::bus::mmio::virtio_mmio_slots()
+        .enumerate()
+        .find(|(_, (base, _, irq))| *base == mmio_base && irq.is_some())
+        .map(|(slot, _)| slot)
+        .filter(|&slot| slot < MAX_SLOTS)
+}
+
+/// Returns the waiter of the VirtIO MMIO device at the given physical address,
+/// or [`None`] if its interrupts are not routed.
+#[cfg(bus = "mmio")]
+pub(crate) fn mmio_waiter(mmio_base: usize) -> Option<&'static IrqWaiter> {
+    slot_of(mmio_base).map(|slot| &SLOT_WAITERS[slot])
+}
+
+/// Called by the IRQ handler of the given slot.
+#[cfg(bus = "mmio")]
+pub(crate) fn notify_slot(slot: usize) {
+    SLOT_WAITERS[slot].notify();
+}
+
+/// Marks the given slot as the one of the network device.
+#[cfg(bus = "mmio")]
+pub(crate) fn set_net_slot(slot: usize) {
+    NET_SLOT.store(slot, Ordering::Release);
+}
+
+/// Returns the waiter notified by the IRQs of the network device, or [`None`]
+/// if the network device is polled.
+pub fn net_waiter() -> Option<&'static IrqWaiter> {
+    SLOT_WAITERS.get(NET_SLOT.load(Ordering::Acquire))
+}
@@ -32,8 +32,13 @@
 //! |-|-|-|
 //! | Block | `ramdisk` | A RAM disk that stores data in a vector |
 //! | Block | `virtio-blk` | VirtIO block device |
+//! | Block | `nvme` | NVMe controller on the PCI bus, one device per namespace |
 //! | Network | `virtio-net` | VirtIO network device |
 //! | Display | `virtio-gpu` | VirtIO graphics device |
+//! | Console | `virtio-console` | VirtIO console device, with multiple ports |
+//! | Entropy | `virtio-rng` | VirtIO entropy device |
+//! | 9P | `virtio-9p` | VirtIO 9P transport, for sharing host directories |
+//! | Input | `virtio-input` | VirtIO input device, e.g., keyboard and mouse |
 //!
 //! # Other Cargo Features
 //!
@@ -43,11 +48,19 @@
 //!    enabeld by default.
 //! - `virtio`: use VirtIO devices. This is enabled if any of `virtio-blk`,
 //!   `virtio-net` or `virtio-gpu` is enabled.
+//! - `virtio-queue`: drive VirtIO devices that `driver_virtio` does not support
+//!    with the virtqueues of this crate. These devices (e.g., `virtio-console`)
+//!    are not in the device categories, and are collected in their own lists
+//!    of [`AllDevices`].
 //! - `net`: use network devices. This is enabled if any feature of network
 //!    devices is selected. If this feature is enabled without any network device
 //!    features, a dummy struct is used for [`AxNetDevice`].
 //! - `block`: use block storage devices. Similar to the `net` feature.
 //! - `display`: use graphics display devices. Similar to the `net` feature.
+//! - `irq`: route the interrupts of VirtIO MMIO devices through `axhal::irq`.
+//! - `irq-io`: tasks waiting for block requests or network packets sleep until
+//!    the device interrupts, instead of polling it. Devices without interrupt
+//!    routing are still polled.
 //!
 //! [`VirtioNetDev`]: driver_virtio::VirtIoNetDev
 //! [`Box<dyn NetDriverOps>`]: driver_net::NetDriverOps
@@ -61,7 +74,6 @@
 #[macro_use]
 extern crate log;
 
-#[cfg(feature = "dyn")]
 extern crate alloc;
 
 #[macro_use]
@@ -74,9 +86,24 @@ mod structs;
 
 #[cfg(feature = "virtio")]
 mod virtio;
+#[cfg(feature = "virtio-9p")]
+mod virtio_9p;
+#[cfg(feature = "virtio-console")]
+mod virtio_console;
+#[cfg(feature = "virtio-input")]
+mod virtio_input;
+#[cfg(feature = "virtio-rng")]
+mod virtio_rng;
+#[cfg(feature = "virtio-queue")]
+mod virtqueue;
 
 #[cfg(feature = "ixgbe")]
 mod ixgbe;
+#[cfg(feature = "nvme")]
+mod nvme;
+
+#[cfg(feature = "irq-io")]
+pub mod irq_io;
 
 pub mod prelude;
 
@@ -90,6 +117,16 @@ pub use self::structs::AxBlockDevice;
 pub use self::structs::AxDisplayDevice;
 #[cfg(feature = "net")]
 pub use self::structs::AxNetDevice;
+#[cfg(feature = "virtio-9p")]
+pub use self::virtio::Ax9pDevice;
+#[cfg(feature = "virtio-console")]
+pub use self::virtio::AxConsoleDevice;
+#[cfg(feature = "virtio-input")]
+pub use self::virtio::AxInputDevice;
+#[cfg(feature = "virtio-rng")]
+pub use self::virtio::AxRngDevice;
+#[cfg(feature = "virtio-input")]
+pub use self::virtio_input::{AbsInfo, InputEvent, InputIds, ABS_CNT, EV_CNT};
 
 /// A structure that contains all device drivers, organized by their category.
 #[derive(Default)]
@@ -103,6 +140,18 @@ pub struct AllDevices {
     /// All graphics device drivers.
     #[cfg(feature = "display")]
     pub display: AxDeviceContainer<AxDisplayDevice>,
+    /// All VirtIO console devices.
+    #[cfg(feature = "virtio-console")]
+    pub console: alloc::vec::Vec<AxConsoleDevice>,
+    /// All VirtIO entropy devices.
+    #[cfg(feature = "virtio-rng")]
+    pub rng: alloc::vec::Vec<AxRngDevice>,
+    /// All VirtIO 9P transport devices.
+    #[cfg(feature = "virtio-9p")]
+    pub p9: alloc::vec::Vec<Ax9pDevice>,
+    /// All VirtIO input devices.
+    #[cfg(feature = "virtio-input")]
+    pub input: alloc::vec::Vec<AxInputDevice>,
 }
 
 #[cfg(feature = "img")]
@@ -207,6 +256,29 @@ pub fn init_drivers() -> AllDevices {
             debug!("  graphics device {}: {:?}", i, dev.device_name());
         }
     }
+    #[cfg(feature = "virtio-console")]
+    {
+        debug!("number of console devices: {}", all_devs.console.len());
+        for (i, dev) in all_devs.console.iter().enumerate() {
+            debug!("  console device {}: {} ports", i, dev.port_ids().count());
+        }
+    }
+    #[cfg(feature = "virtio-rng")]
+    debug!("number of entropy devices: {}", all_devs.rng.len());
+    #[cfg(feature = "virtio-9p")]
+    {
+        debug!("number of 9P devices: {}", all_devs.p9.len());
+        for (i, dev) in all_devs.p9.iter().enumerate() {
+            debug!("  9P device {}: tag {:?}", i, dev.mount_tag());
+        }
+    }
+    #[cfg(feature = "virtio-input")]
+    {
+        debug!("number of input devices: {}", all_devs.input.len());
+        for (i, dev) in all_devs.input.iter().enumerate() {
+            debug!("  input device {}: {:?}", i, dev.name());
+        }
+    }
 
     all_devs
 }
@@ -0,0 +1,567 @@
+//! NVMe controllers on the PCI bus.
+//!
+//! The controller is driven with one admin queue pair and one I/O queue pair,
+//! and completions are polled. Every active namespace becomes a block device,
+//! and all namespaces of a controller share it.
+//!
+//! Data is transferred through a DMA bounce buffer, which is described by a
+//! PRP list when it spans more than two pages. Namespaces are always exposed
+//! with 512-byte blocks, larger logical blocks are read and written as a
+//! whole.
+
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::ptr::{read_volatile, write_volatile};
+use core::time::Duration;
+
+use axalloc::global_allocator;
+use axhal::mem::virt_to_phys;
+use driver_block::BlockDriverOps;
+use driver_common::{BaseDriverOps, DevError, DevResult, DeviceType};
+use spinlock::SpinNoIrq;
+
+const PAGE_SIZE: usize = 0x1000;
+/// The block size exposed to the filesystems.
+const BLOCK_SIZE: usize = 512;
+/// Upper limit of the size of one transfer, i.e., the bounce buffer.
+const MAX_TRANSFER: usize = 128 * 1024;
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(5);
+
+const ADMIN_QUEUE_SIZE: u16 = 32;
+const IO_QUEUE_SIZE: u16 = 64;
+const IO_QUEUE_ID: u16 = 1;
+
+// Controller registers
+const REG_CAP: usize = 0x00;
+const REG_VS: usize = 0x08;
+const REG_INTMS: usize = 0x0c;
+const REG_CC: usize = 0x14;
+const REG_CSTS: usize = 0x1c;
+const REG_AQA: usize = 0x24;
+const REG_ASQ: usize = 0x28;
+const REG_ACQ: usize = 0x30;
+const REG_DOORBELL: usize = 0x1000;
+
+const CC_EN: u32 = 1 << 0;
+/// 64-byte submission and 16-byte completion queue entries.
+const CC_IOSQES_IOCQES: u32 = (6 << 16) | (4 << 20);
+const CSTS_RDY: u32 = 1 << 0;
+const CSTS_CFS: u32 = 1 << 1;
+
+// Admin commands
+const ADMIN_CREATE_SQ: u8 = 0x01;
+const ADMIN_CREATE_CQ: u8 = 0x05;
+const ADMIN_IDENTIFY: u8 = 0x06;
+const ADMIN_SET_FEATURES: u8 = 0x09;
+// I/O commands
+const IO_FLUSH: u8 = 0x00;
+const IO_WRITE: u8 = 0x01;
+const IO_READ: u8 = 0x02;
+
+const CNS_NAMESPACE: u32 = 0x00;
+const CNS_CONTROLLER: u32 = 0x01;
+const CNS_ACTIVE_NAMESPACES: u32 = 0x02;
+const FEAT_NUM_QUEUES: u32 = 0x07;
+/// The queue is physically contiguous.
+const QUEUE_PC: u32 = 1 << 0;
+
+/// Physically contiguous pages for DMA.
+struct DmaPages {
+    vaddr: usize,
+    paddr: usize,
+    pages: usize,
+}
+
+impl DmaPages {
+    fn alloc(pages: usize) -> DevResult<Self> {
+        let vaddr = global_allocator()
+            .alloc_pages(pages, PAGE_SIZE)
+            .map_err(|_| DevError::NoMemory)?;
+        unsafe { core::ptr::write_bytes(vaddr as *mut u8, 0, pages * PAGE_SIZE) };
+        Ok(Self {
+            vaddr,
+            paddr: virt_to_phys(vaddr.into()).as_usize(),
+            pages,
+        })
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        unsafe { core::slice::from_raw_parts(self.vaddr as *const u8, self.pages * PAGE_SIZE) }
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { core::slice::from_raw_parts_mut(self.vaddr as *mut u8, self.pages * PAGE_SIZE) }
+    }
+}
+
+impl Drop for DmaPages {
+    fn drop(&mut self) {
+        global_allocator().dealloc_pages(self.vaddr, self.pages);
+    }
+}
+
+/// A submission queue entry.
+#[allow(dead_code)]
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct Command {
+    cdw0: u32,
+    nsid: u32,
+    _reserved: u64,
+    mptr: u64,
+    prp1: u64,
+    prp2: u64,
+    cdw10: u32,
+    cdw11: u32,
+    cdw12: u32,
+    cdw13: u32,
+    cdw14: u32,
+    cdw15: u32,
+}
+
+impl Command {
+    fn new(opcode: u8) -> Self {
+        Self {
+            cdw0: opcode as u32,
+            ..Default::default()
+        }
+    }
+}
+
+const _: () = assert!(core::mem::size_of::<Command>() == 64);
+
+/// A completion queue entry.
+#[allow(dead_code)]
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Completion {
+    result: u32,
+    _reserved: u32,
+    sq_head: u16,
+    sq_id: u16,
+    cid: u16,
+    /// Bit 0 is the phase tag, and the others are the status.
+    status: u16,
+}
+
+const _: () = assert!(core::mem::size_of::<Completion>() == 16);
+
+/// A submission queue and its completion queue.
+struct QueuePair {
+    id: u16,
+    size: u16,
+    sq: DmaPages,
+    cq: DmaPages,
+    sq_tail: u16,
+    cq_head: u16,
+    phase: bool,
+    next_cid: u16,
+}
+
+impl QueuePair {
+    fn new(id: u16, size: u16) -> DevResult<Self> {
+        let pages = |entry_size: usize| (size as usize * entry_size).div_ceil(PAGE_SIZE);
+        Ok(Self {
+            id,
+            size,
+            sq: DmaPages::alloc(pages(core::mem::size_of::<Command>()))?,
+            cq: DmaPages::alloc(pages(core::mem::size_of::<Completion>()))?,
+            sq_tail: 0,
+            cq_head: 0,
+            phase: true,
+            next_cid: 0,
+        })
+    }
+}
+
+/// An NVMe controller.
+pub struct NvmeController {
+    regs: usize,
+    /// Stride of the doorbell registers in bytes.
+    doorbell_stride: usize,
+    admin: QueuePair,
+    io: QueuePair,
+    /// The bounce buffer of the data.
+    buf: DmaPages,
+    /// The PRP list that describes `buf`.
+    prp_list: DmaPages,
+    /// Maximum size of one transfer.
+    max_transfer: usize,
+}
+
+unsafe impl Send for NvmeController {}
+
+impl NvmeController {
+    fn read32(&self, reg: usize) -> u32 {
+        unsafe { read_volatile((self.regs + reg) as *const u32) }
+    }
+
+    fn write32(&self, reg: usize, value: u32) {
+        unsafe { write_volatile((self.regs + reg) as *mut u32, value) }
+    }
+
+    fn write64(&self, reg: usize, value: u64) {
+        self.write32(reg, value as u32);
+        self.write32(reg + 4, (value >> 32) as u32);
+    }
+
+    /// Waits until `CSTS.RDY` becomes `ready`.
+    fn wait_ready(&self, ready: bool, timeout: Duration) -> DevResult {
+        let deadline = axhal::time::current_time() + timeout;
+        loop {
+            let csts = self.read32(REG_CSTS);
+            if csts & CSTS_CFS != 0 {
+                error!("nvme: controller fatal status");
+                return Err(DevError::Io);
+            }
+            if (csts & CSTS_RDY != 0) == ready {
+                return Ok(());
+            }
+            if axhal::time::current_time() > deadline {
+                return Err(DevError::Io);
+            }
+            core::hint::spin_loop();
+        }
+    }
+
+    /// Resets and enables the controller, and sets up the queues.
+    fn init(regs: usize) -> DevResult<Self> {
+        let cap = unsafe { read_volatile((regs + REG_CAP) as *const u64) };
+        let max_entries = ((cap & 0xffff) as u16).saturating_add(1);
+        let doorbell_stride = 4 << ((cap >> 32) & 0xf);
+        // in 500 ms units
+        let timeout = Duration::from_millis(((cap >> 24) & 0xff).max(1) * 500);
+        let mpsmin = (cap >> 48) & 0xf;
+        if mpsmin != 0 {
+            error!("nvme: 4 KiB pages are not supported");
+            return Err(DevError::Unsupported);
+        }
+
+        let mut ctrl = Self {
+            regs,
+            doorbell_stride,
+            admin: QueuePair::new(0, ADMIN_QUEUE_SIZE.min(max_entries))?,
+            io: QueuePair::new(IO_QUEUE_ID, IO_QUEUE_SIZE.min(max_entries))?,
+            buf: DmaPages::alloc(MAX_TRANSFER / PAGE_SIZE)?,
+            prp_list: DmaPages::alloc(1)?,
+            max_transfer: MAX_TRANSFER,
+        };
+        let vs = ctrl.read32(REG_VS);
+        debug!(
+            "nvme: version {}.{}, {} queue entries, timeout {:?}",
+            vs >> 16,
+            (vs >> 8) & 0xff,
+            max_entries,
+            timeout
+        );
+
+        // reset
+        let cc = ctrl.read32(REG_CC);
+        if cc & CC_EN != 0 {
+            ctrl.write32(REG_CC, cc & !CC_EN);
+        }
+        ctrl.wait_ready(false, timeout)?;
+
+        // the admin queues, and polling only
+        let admin_size = ctrl.admin.size as u32 - 1;
+        ctrl.write32(REG_AQA, (admin_size << 16) | admin_size);
+        ctrl.write64(REG_ASQ, ctrl.admin.sq.paddr as u64);
+        ctrl.write64(REG_ACQ, ctrl.admin.cq.paddr as u64);
+        ctrl.write32(REG_INTMS, !0);
+        ctrl.write32(REG_CC, CC_EN | CC_IOSQES_IOCQES);
+        ctrl.wait_ready(true, timeout)?;
+
+        // Identify Controller: the maximum data transfer size
+        ctrl.identify(CNS_CONTROLLER, 0)?;
+        let mdts = ctrl.buf.as_slice()[77];
+        if mdts != 0 {
+            ctrl.max_transfer = ctrl.max_transfer.min(PAGE_SIZE << mdts.min(16));
+        }
+
+        // one I/O queue pair
+        let mut cmd = Command::new(ADMIN_SET_FEATURES);
+        cmd.cdw10 = FEAT_NUM_QUEUES;
+        cmd.cdw11 = 0;
+        ctrl.submit_admin(cmd)?;
+
+        let io_size = ctrl.io.size as u32 - 1;
+        let mut cmd = Command::new(ADMIN_CREATE_CQ);
+        cmd.prp1 = ctrl.io.cq.paddr as u64;
+        cmd.cdw10 = (io_size << 16) | IO_QUEUE_ID as u32;
+        cmd.cdw11 = QUEUE_PC;
+        ctrl.submit_admin(cmd)?;
+
+        let mut cmd = Command::new(ADMIN_CREATE_SQ);
+        cmd.prp1 = ctrl.io.sq.paddr as u64;
+        cmd.cdw10 = (io_size << 16) | IO_QUEUE_ID as u32;
+        cmd.cdw11 = ((IO_QUEUE_ID as u32) << 16) | QUEUE_PC;
+        ctrl.submit_admin(cmd)?;
+        Ok(ctrl)
+    }
+
+    /// Submits a command, and polls its completion. Returns the result
+    /// (dword 0) of the completion.
+    fn submit(&mut self, admin: bool, mut cmd: Command) -> DevResult<u32> {
+        let stride = self.doorbell_stride;
+        let queue = if admin { &mut self.admin } else { &mut self.io };
+        let cid = queue.next_cid;
+        queue.next_cid = queue.next_cid.wrapping_add(1);
+        cmd.cdw0 = (cmd.cdw0 & 0xffff) | (cid as u32) << 16;
+
+        let sq = queue.sq.vaddr as *mut Command;
+        unsafe { write_volatile(sq.add(queue.sq_tail as usize), cmd) };
+        queue.sq_tail = (queue.sq_tail + 1) % queue.size;
+        let sq_doorbell = REG_DOORBELL + (2 * queue.id as usize) * stride;
+        let cq_doorbell = REG_DOORBELL + (2 * queue.id as usize + 1) * stride;
+        let (sq_tail, regs) = (queue.sq_tail, self.regs);
+        unsafe { write_volatile((regs + sq_doorbell) as *mut u32, sq_tail as u32) };
+
+        let deadline = axhal::time::current_time() + COMMAND_TIMEOUT;
+        let cq = queue.cq.vaddr as *const Completion;
+        loop {
+            let entry = unsafe { read_volatile(cq.add(queue.cq_head as usize)) };
+            if (entry.status & 1 != 0) == queue.phase {
+                queue.cq_head += 1;
+                if queue.cq_head == queue.size {
+                    queue.cq_head = 0;
+                    queue.phase = !queue.phase;
+                }
+                let cq_head = queue.cq_head;
+                unsafe { write_volatile((regs + cq_doorbell) as *mut u32, cq_head as u32) };
+                if entry.cid != cid {
+                    // a command that timed out before
+                    continue;
+                }
+                let status = entry.status >> 1;
+                if status != 0 {
+                    warn!(
+                        "nvme: command {:#x} failed, status {:#x}",
+                        cmd.cdw0 & 0xff,
+                        status
+                    );
+                    return Err(DevError::Io);
+                }
+                return Ok(entry.result);
+            }
+            if axhal::time::current_time() > deadline {
+                error!("nvme: command {:#x} timed out", cmd.cdw0 & 0xff);
+                return Err(DevError::Io);
+            }
+            core::hint::spin_loop();
+        }
+    }
+
+    fn submit_admin(&mut self, cmd: Command) -> DevResult<u32> {
+        self.submit(true, cmd)
+    }
+
+    /// Runs an Identify command, the data is in the bounce buffer.
+    fn identify(&mut self, cns: u32, nsid: u32) -> DevResult {
+        let mut cmd = Command::new(ADMIN_IDENTIFY);
+        cmd.nsid = nsid;
+        cmd.prp1 = self.buf.paddr as u64;
+        cmd.cdw10 = cns;
+        self.submit_admin(cmd)?;
+        Ok(())
+    }
+
+    /// Returns the active namespaces as (`nsid`, number of logical blocks,
+    /// logical block size).
+    fn namespaces(&mut self) -> DevResult<Vec<(u32, u64, usize)>> {
+        self.identify(CNS_ACTIVE_NAMESPACES, 0)?;
+        let nsids: Vec<u32> = self.buf.as_slice()[..PAGE_SIZE]
+            .chunks_exact(4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .take_while(|&nsid| nsid != 0)
+            .collect();
+        let mut namespaces = Vec::new();
+        for nsid in nsids {
+            self.identify(CNS_NAMESPACE, nsid)?;
+            let data = self.buf.as_slice();
+            let nsze = u64::from_le_bytes(data[..8].try_into().unwrap());
+            let flbas = (data[26] & 0xf) as usize;
+            let lbaf = &data[128 + flbas * 4..128 + flbas * 4 + 4];
+            let metadata_size = u16::from_le_bytes([lbaf[0], lbaf[1]]);
+            let lba_size = 1usize << lbaf[2];
+            if nsze == 0 || (metadata_size != 0 && data[26] & 0x10 != 0) {
+                // empty, or with metadata interleaved with the data
+                warn!("nvme: namespace {} is not supported", nsid);
+                continue;
+            }
+            if lba_size < BLOCK_SIZE || lba_size > self.max_transfer {
+                warn!(
+                    "nvme: namespace {} has unsupported block size {}",
+                    nsid, lba_size
+                );
+                continue;
+            }
+            namespaces.push((nsid, nsze, lba_size));
+        }
+        Ok(namespaces)
+    }
+
+    /// Reads or writes `count` logical blocks between the namespace and the
+    /// bounce buffer.
+    fn transfer(
+        &mut self,
+        opcode: u8,
+        nsid: u32,
+        lba: u64,
+        count: usize,
+        lba_size: usize,
+    ) -> DevResult {
+        let len = count * lba_size;
+        let pages = len.div_ceil(PAGE_SIZE);
+        let mut cmd = Command::new(opcode);
+        cmd.nsid = nsid;
+        cmd.prp1 = self.buf.paddr as u64;
+        cmd.prp2 = match pages {
+            1 => 0,
+            2 => (self.buf.paddr + PAGE_SIZE) as u64,
+            _ => {
+                // the pages after the first one
+                let list = self.prp_list.as_mut_slice();
+                for (i, entry) in list.chunks_exact_mut(8).take(pages - 1).enumerate() {
+                    let addr = (self.buf.paddr + (i + 1) * PAGE_SIZE) as u64;
+                    entry.copy_from_slice(&addr.to_le_bytes());
+                }
+                self.prp_list.paddr as u64
+            }
+        };
+        cmd.cdw10 = lba as u32;
+        cmd.cdw11 = (lba >> 32) as u32;
+        cmd.cdw12 = count as u32 - 1;
+        self.submit(false, cmd)?;
+        Ok(())
+    }
+
+    fn flush(&mut self, nsid: u32) -> DevResult {
+        let mut cmd = Command::new(IO_FLUSH);
+        cmd.nsid = nsid;
+        self.submit(false, cmd)?;
+        Ok(())
+    }
+}
+
+/// The buffer of a read or write request.
+enum IoBuf<'a> {
+    Read(&'a mut [u8]),
+    Write(&'a [u8]),
+}
+
+/// A namespace of an NVMe controller, as a block device with 512-byte blocks.
+pub struct NvmeNamespace {
+    ctrl: Arc<SpinNoIrq<NvmeController>>,
+    nsid: u32,
+    /// Number of logical blocks.
+    num_lbas: u64,
+    /// Logical block size of the namespace.
+    lba_size: usize,
+}
+
+impl NvmeNamespace {
+    /// Initializes the controller whose registers are mapped at `regs`, and
+    /// returns all of its active namespaces.
+    pub fn probe(regs: usize) -> DevResult<Vec<Self>> {
+        let mut ctrl = NvmeController::init(regs)?;
+        let namespaces = ctrl.namespaces()?;
+        let ctrl = Arc::new(SpinNoIrq::new(ctrl));
+        Ok(namespaces
+            .into_iter()
+            .map(|(nsid, num_lbas, lba_size)| {
+                info!(
+                    "nvme: namespace {}: {} blocks of {} bytes",
+                    nsid, num_lbas, lba_size
+                );
+                Self {
+                    ctrl: ctrl.clone(),
+                    nsid,
+                    num_lbas,
+                    lba_size,
+                }
+            })
+            .collect())
+    }
+
+    /// The namespace ID.
+    pub fn nsid(&self) -> u32 {
+        self.nsid
+    }
+
+    /// Reads or writes the 512-byte blocks from `block_id`. The logical blocks
+    /// that are partially written are read first.
+    fn read_write(&mut self, block_id: u64, mut buf: IoBuf) -> DevResult {
+        let len = match &buf {
+            IoBuf::Read(buf) => buf.len(),
+            IoBuf::Write(buf) => buf.len(),
+        };
+        if len % BLOCK_SIZE != 0 {
+            return Err(DevError::InvalidParam);
+        }
+        let lba_size = self.lba_size as u64;
+        let start = block_id * BLOCK_SIZE as u64;
+        let end = start + len as u64;
+        if end > self.num_lbas * lba_size {
+            return Err(DevError::InvalidParam);
+        }
+        let mut ctrl = self.ctrl.lock();
+        let max_lbas = (ctrl.max_transfer / self.lba_size) as u64;
+        let mut lba = start / lba_size;
+        let end_lba = end.div_ceil(lba_size);
+        while lba < end_lba {
+            let count = (end_lba - lba).min(max_lbas);
+            let chunk_start = lba * lba_size;
+            let chunk_end = chunk_start + count * lba_size;
+            let (lo, hi) = (start.max(chunk_start), end.min(chunk_end));
+            let data = (lo - chunk_start) as usize..(hi - chunk_start) as usize;
+            let user = (lo - start) as usize..(hi - start) as usize;
+            match &mut buf {
+                IoBuf::Read(buf) => {
+                    ctrl.transfer(IO_READ, self.nsid, lba, count as usize, self.lba_size)?;
+                    buf[user].copy_from_slice(&ctrl.buf.as_slice()[data]);
+                }
+                IoBuf::Write(buf) => {
+                    if lo > chunk_start || hi < chunk_end {
+                        ctrl.transfer(IO_READ, self.nsid, lba, count as usize, self.lba_size)?;
+                    }
+                    ctrl.buf.as_mut_slice()[data].copy_from_slice(&buf[user]);
+                    ctrl.transfer(IO_WRITE, self.nsid, lba, count as usize, self.lba_size)?;
+                }
+            }
+            lba += count;
+        }
+        Ok(())
+    }
+}
+
+impl BaseDriverOps for NvmeNamespace {
+    fn device_name(&self) -> &str {
+        "nvme"
+    }
+
+    fn device_type(&self) -> DeviceType {
+        DeviceType::Block
+    }
+}
+
+impl BlockDriverOps for NvmeNamespace {
+    fn num_blocks(&self) -> u64 {
+        self.num_lbas * (self.lba_size / BLOCK_SIZE) as u64
+    }
+
+    fn block_size(&self) -> usize {
+        BLOCK_SIZE
+    }
+
+    fn read_block(&mut self, block_id: u64, buf: &mut [u8]) -> DevResult {
+        self.read_write(block_id, IoBuf::Read(buf))
+    }
+
+    fn write_block(&mut self, block_id: u64, buf: &[u8]) -> DevResult {
+        self.read_write(block_id, IoBuf::Write(buf))
+    }
+
+    fn flush(&mut self) -> DevResult {
+        self.ctrl.lock().flush(self.nsid)
+    }
+}
@@ -26,6 +26,13 @@ pub trait VirtIoDevMeta {
     type Driver = VirtIoDriver<Self>;
 
     fn try_new(transport: VirtIoTransport) -> DevResult<AxDeviceEnum>;
+
+    /// Creates the device in the VirtIO MMIO slot at `mmio_base`. Devices that
+    /// support interrupt-driven I/O bind to the IRQ waiter of the slot.
+    #[cfg(all(bus = "mmio", feature = "irq-io"))]
+    fn try_new_mmio(transport: VirtIoTransport, _mmio_base: usize) -> DevResult<AxDeviceEnum> {
+        Self::try_new(transport)
+    }
 }
 
 cfg_if! {
@@ -49,11 +56,20 @@ cfg_if! {
 
         impl VirtIoDevMeta for VirtIoBlk {
             const DEVICE_TYPE: DeviceType = DeviceType::Block;
+            #[cfg(not(feature = "irq-io"))]
             type Device = driver_virtio::VirtIoBlkDev<VirtIoHalImpl, VirtIoTransport>;
+            #[cfg(feature = "irq-io")]
+            type Device = blk_irq::VirtIoBlkIrqDev<VirtIoHalImpl, VirtIoTransport>;
 
             fn try_new(transport: VirtIoTransport) -> DevResult<AxDeviceEnum> {
                 Ok(AxDeviceEnum::from_block(Self::Device::try_new(transport)?))
             }
+
+            #[cfg(all(bus = "mmio", feature = "irq-io"))]
+            fn try_new_mmio(transport: VirtIoTransport, mmio_base: usize) -> DevResult<AxDeviceEnum> {
+                let waiter = crate::irq_io::mmio_waiter(mmio_base);
+                Ok(AxDeviceEnum::from_block(Self::Device::try_new_with_waiter(transport, waiter)?))
+            }
         }
     }
 }
@@ -73,6 +89,22 @@ cfg_if! {
     }
 }
 
+/// The VirtIO console device.
+#[cfg(feature = "virtio-console")]
+pub type AxConsoleDevice = crate::virtio_console::VirtIoConsoleDev<VirtIoHalImpl, VirtIoTransport>;
+
+/// The VirtIO entropy device.
+#[cfg(feature = "virtio-rng")]
+pub type AxRngDevice = crate::virtio_rng::VirtIoRngDev<VirtIoHalImpl, VirtIoTransport>;
+
+/// The VirtIO 9P transport device.
+#[cfg(feature = "virtio-9p")]
+pub type Ax9pDevice = crate::virtio_9p::VirtIo9pDev<VirtIoHalImpl, VirtIoTransport>;
+
+/// The VirtIO input device.
+#[cfg(feature = "virtio-input")]
+pub type AxInputDevice = crate::virtio_input::VirtIoInputDev<VirtIoHalImpl, VirtIoTransport>;
+
 /// A common driver for all VirtIO devices that implements [`DriverProbe`].
 pub struct VirtIoDriver<D: VirtIoDevMeta + ?Sized>(PhantomData<D>);
 
@@ -84,7 +116,11 @@ impl<D: VirtIoDevMeta> DriverProbe for VirtIoDriver<D> {
             driver_virtio::probe_mmio_device(base_vaddr.as_mut_ptr(), mmio_size)
         {
             if ty == D::DEVICE_TYPE {
-                match D::try_new(transport) {
+                #[cfg(not(feature = "irq-io"))]
+                let dev = D::try_new(transport);
+                #[cfg(feature = "irq-io")]
+                let dev = D::try_new_mmio(transport, mmio_base);
+                match dev {
                     Ok(dev) => return Some(dev),
                     Err(e) => {
                         warn!(
@@ -170,3 +206,304 @@ unsafe impl VirtIoHal for VirtIoHalImpl {
     #[inline]
     unsafe fn unshare(_paddr: PhysAddr, _buffer: NonNull<[u8]>, _direction: BufferDirection) {}
 }
+
+#[cfg(all(bus = "mmio", feature = "irq"))]
+mod mmio_irq {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use axhal::mem::phys_to_virt;
+    use driver_common::DeviceType;
+
+    /// Offset of the `InterruptStatus` register of the VirtIO MMIO transport.
+    const INTERRUPT_STATUS: usize = 0x60;
+    /// Offset of the `InterruptACK` register of the VirtIO MMIO transport.
+    const INTERRUPT_ACK: usize = 0x64;
+
+    #[cfg(not(feature = "irq-io"))]
+    const MAX_SLOTS: usize = 8;
+    #[cfg(feature = "irq-io")]
+    use crate::irq_io::MAX_SLOTS;
+
+    /// Virtual base addresses of the registered MMIO slots, 0 if unused.
+    static SLOT_BASES: [AtomicUsize; MAX_SLOTS] = [const { AtomicUsize::new(0) }; MAX_SLOTS];
+
+    /// IRQ handlers are plain function pointers, so each slot gets its own
+    /// instance.
+    static SLOT_HANDLERS: [axhal::irq::IrqHandler; MAX_SLOTS] = [
+        handle_slot::<0>,
+        handle_slot::<1>,
+        handle_slot::<2>,
+        handle_slot::<3>,
+        handle_slot::<4>,
+        handle_slot::<5>,
+        handle_slot::<6>,
+        handle_slot::<7>,
+    ];
+
+    /// Acknowledges the interrupt of the device, the driver finds the completed
+    /// requests from the used ring. With `irq-io`, the tasks waiting for the
+    /// device are woken up.
+    fn handle_slot<const SLOT: usize>() {
+        let base = SLOT_BASES[SLOT].load(Ordering::Acquire);
+        if base == 0 {
+            return;
+        }
+        let status = unsafe {
+            let status = core::ptr::read_volatile((base + INTERRUPT_STATUS) as *const u32);
+            core::ptr::write_volatile((base + INTERRUPT_ACK) as *mut u32, status);
+            status
+        };
+        trace!("VirtIO MMIO slot {} IRQ, status {:#x}", SLOT, status);
+        #[cfg(feature = "irq-io")]
+        crate::irq_io::notify_slot(SLOT);
+    }
+
+    /// Routes the IRQ of the device in the given VirtIO MMIO slot.
+    pub(crate) fn register_mmio_irq(
+        slot: usize,
+        mmio_base: usize,
+        irq_num: usize,
+        _ty: DeviceType,
+    ) {
+        if slot >= MAX_SLOTS {
+            warn!("VirtIO MMIO slot {} has no IRQ handler", slot);
+            return;
+        }
+        SLOT_BASES[slot].store(phys_to_virt(mmio_base.into()).as_usize(), Ordering::Release);
+        if axhal::irq::register_handler(irq_num, SLOT_HANDLERS[slot]) {
+            debug!("VirtIO MMIO slot {} uses IRQ {}", slot, irq_num);
+            #[cfg(feature = "irq-io")]
+            if _ty == DeviceType::Net {
+                crate::irq_io::set_net_slot(slot);
+            }
+        }
+    }
+}
+
+#[cfg(all(bus = "mmio", feature = "irq"))]
+pub(crate) use mmio_irq::register_mmio_irq;
+
+#[cfg(all(block_dev = "virtio-blk", feature = "irq-io"))]
+mod blk_irq {
+    use driver_block::BlockDriverOps;
+    use driver_common::{BaseDriverOps, DevError, DevResult, DeviceType};
+    use virtio_drivers::device::blk::{BlkReq, BlkResp, VirtIOBlk, SECTOR_SIZE};
+    use virtio_drivers::{transport::Transport, Hal};
+
+    use crate::irq_io::IrqWaiter;
+
+    fn as_dev_err(e: virtio_drivers::Error) -> DevError {
+        use virtio_drivers::Error::*;
+        match e {
+            QueueFull => DevError::BadState,
+            NotReady => DevError::Again,
+            WrongToken => DevError::BadState,
+            AlreadyUsed => DevError::AlreadyExists,
+            InvalidParam => DevError::InvalidParam,
+            DmaError => DevError::NoMemory,
+            IoError => DevError::Io,
+            Unsupported => DevError::Unsupported,
+            _ => DevError::BadState,
+        }
+    }
+
+    /// The VirtIO block device, which sleeps until the request is completed
+    /// instead of spinning.
+    pub struct VirtIoBlkIrqDev<H: Hal, T: Transport> {
+        inner: VirtIOBlk<H, T>,
+        waiter: Option<&'static IrqWaiter>,
+    }
+
+    unsafe impl<H: Hal, T: Transport> Send for VirtIoBlkIrqDev<H, T> {}
+    unsafe impl<H: Hal, T: Transport> Sync for VirtIoBlkIrqDev<H, T> {}
+
+    impl<H: Hal, T: Transport> VirtIoBlkIrqDev<H, T> {
+        /// Creates a new driver instance whose requests are polled.
+        pub fn try_new(transport: T) -> DevResult<Self> {
+            Self::try_new_with_waiter(transport, None)
+        }
+
+        /// Creates a new driver instance, which waits on `waiter` for the
+        /// completion of requests if it is given.
+        pub fn try_new_with_waiter(
+            transport: T,
+            waiter: Option<&'static IrqWaiter>,
+        ) -> DevResult<Self> {
+            Ok(Self {
+                inner: VirtIOBlk::new(transport).map_err(as_dev_err)?,
+                waiter,
+            })
+        }
+
+        /// Waits until the request of `token` is in the used ring.
+        fn wait_for(&mut self, token: u16) {
+            let inner = &mut self.inner;
+            match self.waiter {
+                Some(waiter) => waiter.wait_until(|| inner.peek_used() == Some(token)),
+                None => {
+                    while inner.peek_used() != Some(token) {
+                        core::hint::spin_loop();
+                    }
+                }
+            }
+        }
+    }
+
+    impl<H: Hal, T: Transport> BaseDriverOps for VirtIoBlkIrqDev<H, T> {
+        fn device_name(&self) -> &str {
+            "virtio-blk"
+        }
+
+        fn device_type(&self) -> DeviceType {
+            DeviceType::Block
+        }
+    }
+
+    impl<H: Hal, T: Transport> BlockDriverOps for VirtIoBlkIrqDev<H, T> {
+        #[inline]
+        fn num_blocks(&self) -> u64 {
+            self.inner.capacity()
+        }
+
+        #[inline]
+        fn block_size(&self) -> usize {
+            SECTOR_SIZE
+        }
+
+        fn read_block(&mut self, block_id: u64, buf: &mut [u8]) -> DevResult {
+            let mut req = BlkReq::default();
+            let mut resp = BlkResp::default();
+            // SAFETY: `req`, `buf` and `resp` outlive the request, as we wait
+            // for its completion before returning.
+            let token = unsafe {
+                self.inner
+                    .read_blocks_nb(block_id as _, &mut req, buf, &mut resp)
+                    .map_err(as_dev_err)?
+            };
+            self.wait_for(token);
+            unsafe {
+                self.inner
+                    .complete_read_blocks(token, &req, buf, &mut resp)
+                    .map_err(as_dev_err)
+            }
+        }
+
+        fn write_block(&mut self, block_id: u64, buf: &[u8]) -> DevResult {
+            let mut req = BlkReq::default();
+            let mut resp = BlkResp::default();
+            // SAFETY: see `read_block`.
+            let token = unsafe {
+                self.inner
+                    .write_blocks_nb(block_id as _, &mut req, buf, &mut resp)
+                    .map_err(as_dev_err)?
+            };
+            self.wait_for(token);
+            unsafe {
+                self.inner
+                    .complete_write_blocks(token, &req, buf, &mut resp)
+                    .map_err(as_dev_err)
+            }
+        }
+
+        fn flush(&mut self) -> DevResult {
+            self.inner.flush().map_err(as_dev_err)
+        }
+    }
+}
+
+/// Probing of the VirtIO devices that `driver_virtio` does not support. They
+/// are not in the device categories of [`AxDeviceEnum`], and each kind has its
+/// own list in [`AllDevices`](crate::AllDevices).
+#[cfg(feature = "virtio-queue")]
+mod ext {
+    #[allow(unused_imports)]
+    use virtio_drivers::transport::{DeviceType as VirtIoDevType, Transport};
+
+    use super::*;
+    use crate::AllDevices;
+
+    impl AllDevices {
+        /// Adds the device if it is supported, returns its name.
+        #[allow(unused_variables)]
+        fn add_virtio_ext(
+            &mut self,
+            transport: VirtIoTransport,
+            mmio_base: Option<usize>,
+        ) -> Option<&'static str> {
+            let name: DevResult<&'static str> = match transport.device_type() {
+                #[cfg(feature = "virtio-console")]
+                VirtIoDevType::Console => AxConsoleDevice::try_new(transport).map(|mut dev| {
+                    #[cfg(all(bus = "mmio", feature = "irq-io"))]
+                    dev.set_waiter(mmio_base.and_then(crate::irq_io::mmio_waiter));
+                    self.console.push(dev);
+                    "virtio-console"
+                }),
+                #[cfg(feature = "virtio-rng")]
+                VirtIoDevType::EntropySource => AxRngDevice::try_new(transport).map(|dev| {
+                    self.rng.push(dev);
+                    "virtio-rng"
+                }),
+                #[cfg(feature = "virtio-9p")]
+                VirtIoDevType::_9P => Ax9pDevice::try_new(transport).map(|dev| {
+                    self.p9.push(dev);
+                    "virtio-9p"
+                }),
+                #[cfg(feature = "virtio-input")]
+                VirtIoDevType::Input => AxInputDevice::try_new(transport).map(|mut dev| {
+                    #[cfg(all(bus = "mmio", feature = "irq-io"))]
+                    dev.set_waiter(mmio_base.and_then(crate::irq_io::mmio_waiter));
+                    self.input.push(dev);
+                    "virtio-input"
+                }),
+                _ => return None,
+            };
+            name.map_err(|e| warn!("failed to initialize VirtIO device: {:?}", e))
+                .ok()
+        }
+
+        /// Probes the VirtIO MMIO slot at `mmio_base`.
+        #[cfg(bus = "mmio")]
+        pub(crate) fn probe_virtio_mmio_ext(&mut self, mmio_base: usize) -> Option<&'static str> {
+            use virtio_drivers::transport::mmio::VirtIOHeader;
+
+            let header =
+                NonNull::new(phys_to_virt(mmio_base.into()).as_mut_ptr() as *mut VirtIOHeader)?;
+            let transport = unsafe { VirtIoTransport::new(header) }.ok()?;
+            self.add_virtio_ext(transport, Some(mmio_base))
+        }
+
+        /// Probes the PCI function at `bdf`.
+        #[cfg(bus = "pci")]
+        pub(crate) fn probe_virtio_pci_ext(
+            &mut self,
+            root: &mut PciRoot,
+            bdf: DeviceFunction,
+            dev_info: &DeviceFunctionInfo,
+        ) -> Option<&'static str> {
+            if dev_info.vendor_id != 0x1af4 {
+                return None;
+            }
+            match dev_info.device_id {
+                #[cfg(feature = "virtio-console")]
+                0x1003 | 0x1043 => {}
+                #[cfg(feature = "virtio-rng")]
+                0x1005 | 0x1044 => {}
+                #[cfg(feature = "virtio-9p")]
+                0x1009 | 0x1049 => {}
+                #[cfg(feature = "virtio-input")]
+                0x1052 => {}
+                _ => return None,
+            }
+            match VirtIoTransport::new::<VirtIoHalImpl>(root, bdf) {
+                Ok(transport) => self.add_virtio_ext(transport, None),
+                Err(e) => {
+                    warn!(
+                        "failed to initialize PCI device at {}({}): {:?}",
+                        bdf, dev_info, e
+                    );
+                    None
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! VirtIO 9P transport (`virtio-9p`).
+//!
+//! The device carries the messages of a 9P server on the host, e.g., the
+//! `-virtfs` option of QEMU. Each request is a T-message from the driver and a
+//! buffer for the R-message, the protocol itself is implemented by the
+//! filesystem.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ptr::NonNull;
+
+use driver_common::{BaseDriverOps, DevError, DevResult, DeviceType};
+use virtio_drivers::transport::Transport;
+use virtio_drivers::Hal;
+
+use crate::virtqueue::{as_dev_err, begin_init, VirtQueue};
+
+/// The mount tag is in the config space.
+const VIRTIO_9P_F_MOUNT_TAG: u64 = 1 << 0;
+
+const QUEUE_SIZE: u16 = 16;
+const REQUEST_QUEUE: u16 = 0;
+
+/// The VirtIO 9P transport device.
+pub struct VirtIo9pDev<H: Hal, T: Transport> {
+    transport: T,
+    queue: VirtQueue<H>,
+    tag: String,
+}
+
+unsafe impl<H: Hal, T: Transport> Send for VirtIo9pDev<H, T> {}
+unsafe impl<H: Hal, T: Transport> Sync for VirtIo9pDev<H, T> {}
+
+impl<H: Hal, T: Transport> VirtIo9pDev<H, T> {
+    /// Initializes the device and reads its mount tag.
+    pub fn try_new(mut transport: T) -> DevResult<Self> {
+        let features = begin_init(&mut transport, VIRTIO_9P_F_MOUNT_TAG);
+        let tag = if features & VIRTIO_9P_F_MOUNT_TAG != 0 {
+            Self::read_tag(&transport)?
+        } else {
+            String::new()
+        };
+        let queue = VirtQueue::new(&mut transport, REQUEST_QUEUE, QUEUE_SIZE)?;
+        transport.finish_init();
+        Ok(Self {
+            transport,
+            queue,
+            tag,
+        })
+    }
+
+    /// The config space is a 16-bit length followed by the tag, which is not
+    /// NUL-terminated.
+    fn read_tag(transport: &T) -> DevResult<String> {
+        let config: NonNull<u16> = transport.config_space().map_err(as_dev_err)?;
+        let tag = unsafe {
+            let len = config.as_ptr().read_volatile() as usize;
+            let bytes = config.as_ptr().add(1) as *const u8;
+            (0..len)
+                .map(|i| bytes.add(i).read_volatile())
+                .collect::<Vec<u8>>()
+        };
+        String::from_utf8(tag).map_err(|_| DevError::InvalidParam)
+    }
+
+    /// The mount tag, which names the exported directory on the host.
+    pub fn mount_tag(&self) -> &str {
+        &self.tag
+    }
+
+    /// Sends a T-message in `request`, and receives the R-message in
+    /// `response`. Returns the number of bytes written to `response`.
+    ///
+    /// The server on the host completes most requests right away, so this
+    /// spins until the request is completed.
+    pub fn request(&mut self, request: &[u8], response: &mut [u8]) -> DevResult<usize> {
+        let len =
+            self.queue
+                .add_notify_wait_pop(&mut self.transport, &[request], &mut [response])?;
+        Ok(len as usize)
+    }
+
+    /// Acknowledges the interrupt of the device.
+    pub fn ack_interrupt(&mut self) -> bool {
+        self.transport.ack_interrupt()
+    }
+}
+
+impl<H: Hal, T: Transport> BaseDriverOps for VirtIo9pDev<H, T> {
+    fn device_name(&self) -> &str {
+        "virtio-9p"
+    }
+
+    fn device_type(&self) -> DeviceType {
+        DeviceType::Char
+    }
+}
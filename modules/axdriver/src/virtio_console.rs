@@ -0,0 +1,432 @@
+//! VirtIO console device, with the multiport extension.
+//!
+//! Every port has a pair of receive/transmit queues. With `VIRTIO_CONSOLE_F_MULTIPORT`,
+//! the device announces its ports on the control queues after the driver is
+//! ready, and may add or remove ports later. Without it, only port 0 exists.
+
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ptr::addr_of;
+
+use driver_common::{BaseDriverOps, DevError, DevResult, DeviceType};
+use virtio_drivers::{transport::Transport, Hal};
+
+use crate::virtqueue::{as_dev_err, begin_init, VirtQueue};
+
+/// The console size (`cols`, `rows`) is in the config space.
+const VIRTIO_CONSOLE_F_SIZE: u64 = 1 << 0;
+/// The device supports multiple ports and the control queues.
+const VIRTIO_CONSOLE_F_MULTIPORT: u64 = 1 << 1;
+
+/// Ports beyond this are refused.
+pub const MAX_PORTS: usize = 8;
+
+const QUEUE_SIZE: u16 = 16;
+const RX_BUF_LEN: usize = 128;
+const TX_BUF_LEN: usize = 512;
+/// Received bytes that are not read yet are dropped beyond this.
+const INPUT_LIMIT: usize = 4096;
+
+// Control events
+const VIRTIO_CONSOLE_DEVICE_READY: u16 = 0;
+const VIRTIO_CONSOLE_DEVICE_ADD: u16 = 1;
+const VIRTIO_CONSOLE_DEVICE_REMOVE: u16 = 2;
+const VIRTIO_CONSOLE_PORT_READY: u16 = 3;
+const VIRTIO_CONSOLE_CONSOLE_PORT: u16 = 4;
+const VIRTIO_CONSOLE_RESIZE: u16 = 5;
+const VIRTIO_CONSOLE_PORT_OPEN: u16 = 6;
+const VIRTIO_CONSOLE_PORT_NAME: u16 = 7;
+
+#[repr(C)]
+struct ConsoleConfig {
+    cols: u16,
+    rows: u16,
+    max_nr_ports: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+struct ControlMsg {
+    id: u32,
+    event: u16,
+    value: u16,
+}
+
+const CONTROL_MSG_LEN: usize = core::mem::size_of::<ControlMsg>();
+
+impl ControlMsg {
+    fn to_bytes(self) -> [u8; CONTROL_MSG_LEN] {
+        let mut buf = [0; CONTROL_MSG_LEN];
+        buf[..4].copy_from_slice(&self.id.to_le_bytes());
+        buf[4..6].copy_from_slice(&self.event.to_le_bytes());
+        buf[6..].copy_from_slice(&self.value.to_le_bytes());
+        buf
+    }
+
+    fn from_bytes(buf: &[u8]) -> Option<Self> {
+        let buf = buf.get(..CONTROL_MSG_LEN)?;
+        Some(Self {
+            id: u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]),
+            event: u16::from_le_bytes([buf[4], buf[5]]),
+            value: u16::from_le_bytes([buf[6], buf[7]]),
+        })
+    }
+}
+
+/// A receive queue whose entries are all posted with buffers of the driver.
+struct RxQueue<H: Hal> {
+    queue: VirtQueue<H>,
+    bufs: Vec<Box<[u8; RX_BUF_LEN]>>,
+    /// The buffer index of each request token.
+    tokens: [usize; QUEUE_SIZE as usize],
+}
+
+impl<H: Hal> RxQueue<H> {
+    fn new<T: Transport>(transport: &mut T, idx: u16) -> DevResult<Self> {
+        let queue = VirtQueue::new(transport, idx, QUEUE_SIZE)?;
+        let mut rx = Self {
+            bufs: (0..queue.size())
+                .map(|_| Box::new([0; RX_BUF_LEN]))
+                .collect(),
+            queue,
+            tokens: [0; QUEUE_SIZE as usize],
+        };
+        for i in 0..rx.bufs.len() {
+            rx.post(i)?;
+        }
+        Ok(rx)
+    }
+
+    fn post(&mut self, i: usize) -> DevResult {
+        // SAFETY: the buffers are owned by the queue and only read after the
+        // device returns them.
+        let token = unsafe { self.queue.add(&[], &mut [&mut self.bufs[i][..]])? };
+        self.tokens[token as usize] = i;
+        Ok(())
+    }
+
+    /// Takes a filled buffer, passes the received data to `f`, and gives the
+    /// buffer back to the device. Returns `false` if nothing was received.
+    fn pop<T: Transport>(&mut self, transport: &mut T, f: impl FnOnce(&[u8])) -> DevResult<bool> {
+        let Some((token, len)) = self.queue.pop_used() else {
+            return Ok(false);
+        };
+        let i = self.tokens[token as usize];
+        f(&self.bufs[i][..(len as usize).min(RX_BUF_LEN)]);
+        self.post(i)?;
+        self.queue.notify(transport);
+        Ok(true)
+    }
+}
+
+struct Port<H: Hal> {
+    rx: RxQueue<H>,
+    tx: VirtQueue<H>,
+    tx_buf: Box<[u8; TX_BUF_LEN]>,
+    input: VecDeque<u8>,
+    /// Announced by the device (always for port 0 without multiport).
+    added: bool,
+    /// Whether the port is a console, rather than a generic serial port.
+    console: bool,
+    /// Whether the host side is connected.
+    host_connected: bool,
+    name: Option<String>,
+}
+
+/// The VirtIO console device driver.
+pub struct VirtIoConsoleDev<H: Hal, T: Transport> {
+    transport: T,
+    multiport: bool,
+    ctrl: Option<(RxQueue<H>, VirtQueue<H>)>,
+    ports: Vec<Port<H>>,
+    /// (`cols`, `rows`), (0, 0) if unknown.
+    size: (u16, u16),
+    #[cfg(feature = "irq-io")]
+    waiter: Option<&'static crate::irq_io::IrqWaiter>,
+}
+
+unsafe impl<H: Hal, T: Transport> Send for VirtIoConsoleDev<H, T> {}
+unsafe impl<H: Hal, T: Transport> Sync for VirtIoConsoleDev<H, T> {}
+
+/// Queue indices of the given port: 0 and 1 for port 0, the control queues
+/// are 2 and 3, then 4 and 5 for port 1, and so on.
+const fn port_queues(id: usize) -> (u16, u16) {
+    let rx = if id == 0 { 0 } else { 2 * id + 2 };
+    (rx as u16, rx as u16 + 1)
+}
+
+impl<H: Hal, T: Transport> VirtIoConsoleDev<H, T> {
+    /// Creates a new driver instance and initializes the device.
+    pub fn try_new(mut transport: T) -> DevResult<Self> {
+        let features = begin_init(
+            &mut transport,
+            VIRTIO_CONSOLE_F_SIZE | VIRTIO_CONSOLE_F_MULTIPORT,
+        );
+        let multiport = features & VIRTIO_CONSOLE_F_MULTIPORT != 0;
+        let config = transport
+            .config_space::<ConsoleConfig>()
+            .map_err(as_dev_err)?;
+        let (size, max_nr_ports) = unsafe {
+            let config = config.as_ptr();
+            let size = if features & VIRTIO_CONSOLE_F_SIZE != 0 {
+                (
+                    addr_of!((*config).cols).read_volatile(),
+                    addr_of!((*config).rows).read_volatile(),
+                )
+            } else {
+                (0, 0)
+            };
+            (
+                size,
+                addr_of!((*config).max_nr_ports).read_volatile() as usize,
+            )
+        };
+        let nr_ports = if multiport {
+            max_nr_ports.clamp(1, MAX_PORTS)
+        } else {
+            1
+        };
+
+        let mut ports = Vec::with_capacity(nr_ports);
+        for id in 0..nr_ports {
+            let (rx, tx) = port_queues(id);
+            ports.push(Port {
+                rx: RxQueue::new(&mut transport, rx)?,
+                tx: VirtQueue::new(&mut transport, tx, QUEUE_SIZE)?,
+                tx_buf: Box::new([0; TX_BUF_LEN]),
+                input: VecDeque::new(),
+                added: !multiport,
+                console: !multiport,
+                host_connected: !multiport,
+                name: None,
+            });
+        }
+        let ctrl = if multiport {
+            Some((
+                RxQueue::new(&mut transport, 2)?,
+                VirtQueue::new(&mut transport, 3, QUEUE_SIZE)?,
+            ))
+        } else {
+            None
+        };
+        transport.finish_init();
+        // the receive buffers can only be notified after the device is ready
+        for port in ports.iter() {
+            port.rx.queue.notify(&mut transport);
+        }
+        if let Some((ctrl_rx, _)) = ctrl.as_ref() {
+            ctrl_rx.queue.notify(&mut transport);
+        }
+
+        let mut dev = Self {
+            transport,
+            multiport,
+            ctrl,
+            ports,
+            size,
+            #[cfg(feature = "irq-io")]
+            waiter: None,
+        };
+        if multiport {
+            // the device answers with a DEVICE_ADD message for each port
+            dev.send_control(0, VIRTIO_CONSOLE_DEVICE_READY, 1)?;
+            dev.poll()?;
+        }
+        Ok(dev)
+    }
+
+    /// Sets the waiter notified by the IRQs of the device.
+    #[cfg(feature = "irq-io")]
+    pub fn set_waiter(&mut self, waiter: Option<&'static crate::irq_io::IrqWaiter>) {
+        self.waiter = waiter;
+    }
+
+    /// Returns the waiter notified by the IRQs of the device, or [`None`] if
+    /// the device must be polled.
+    #[cfg(feature = "irq-io")]
+    pub fn waiter(&self) -> Option<&'static crate::irq_io::IrqWaiter> {
+        self.waiter
+    }
+
+    /// Returns whether the device supports multiple ports.
+    pub fn is_multiport(&self) -> bool {
+        self.multiport
+    }
+
+    /// The number of ports that the driver has set up queues for.
+    pub fn max_ports(&self) -> usize {
+        self.ports.len()
+    }
+
+    /// Returns the IDs of the ports announced by the device.
+    pub fn port_ids(&self) -> impl Iterator<Item = usize> + '_ {
+        self.ports
+            .iter()
+            .enumerate()
+            .filter(|(_, port)| port.added)
+            .map(|(id, _)| id)
+    }
+
+    /// Returns whether the port exists.
+    pub fn has_port(&self, id: usize) -> bool {
+        self.ports.get(id).is_some_and(|port| port.added)
+    }
+
+    /// Returns whether the port is a console port.
+    pub fn is_console_port(&self, id: usize) -> bool {
+        self.ports.get(id).is_some_and(|port| port.console)
+    }
+
+    /// Returns whether the host side of the port is connected.
+    pub fn is_host_connected(&self, id: usize) -> bool {
+        self.ports.get(id).is_some_and(|port| port.host_connected)
+    }
+
+    /// Returns the name of the port given by the host, if any.
+    pub fn port_name(&self, id: usize) -> Option<&str> {
+        self.ports.get(id).and_then(|port| port.name.as_deref())
+    }
+
+    /// Returns the console size as (`cols`, `rows`), (0, 0) if unknown.
+    pub fn size(&self) -> (u16, u16) {
+        self.size
+    }
+
+    /// Acknowledges the interrupt of the device.
+    pub fn ack_interrupt(&mut self) -> bool {
+        self.transport.ack_interrupt()
+    }
+
+    fn send_control(&mut self, id: u32, event: u16, value: u16) -> DevResult {
+        let Some((_, ctrl_tx)) = self.ctrl.as_mut() else {
+            return Err(DevError::Unsupported);
+        };
+        let msg = ControlMsg { id, event, value }.to_bytes();
+        ctrl_tx.add_notify_wait_pop(&mut self.transport, &[&msg], &mut [])?;
+        Ok(())
+    }
+
+    fn handle_control(&mut self, msg: ControlMsg, payload: &[u8]) -> DevResult {
+        let id = msg.id as usize;
+        trace!("virtio-console control: {:?}", msg);
+        match msg.event {
+            VIRTIO_CONSOLE_DEVICE_ADD => match self.ports.get_mut(id) {
+                Some(port) => {
+                    port.added = true;
+                    self.send_control(msg.id, VIRTIO_CONSOLE_PORT_READY, 1)?;
+                    self.send_control(msg.id, VIRTIO_CONSOLE_PORT_OPEN, 1)?;
+                }
+                None => {
+                    warn!("virtio-console: port {} exceeds the limit", id);
+                    self.send_control(msg.id, VIRTIO_CONSOLE_PORT_READY, 0)?;
+                }
+            },
+            VIRTIO_CONSOLE_DEVICE_REMOVE => {
+                if let Some(port) = self.ports.get_mut(id) {
+                    port.added = false;
+                    port.host_connected = false;
+                    port.input.clear();
+                }
+            }
+            VIRTIO_CONSOLE_CONSOLE_PORT => {
+                if let Some(port) = self.ports.get_mut(id) {
+                    port.console = true;
+                }
+            }
+            VIRTIO_CONSOLE_RESIZE => {
+                if let [c0, c1, r0, r1, ..] = *payload {
+                    self.size = (u16::from_le_bytes([c0, c1]), u16::from_le_bytes([r0, r1]));
+                }
+            }
+            VIRTIO_CONSOLE_PORT_OPEN => {
+                if let Some(port) = self.ports.get_mut(id) {
+                    port.host_connected = msg.value != 0;
+                }
+            }
+            VIRTIO_CONSOLE_PORT_NAME => {
+                if let Some(port) = self.ports.get_mut(id) {
+                    let name = payload.split(|&c| c == 0).next().unwrap_or_default();
+                    port.name = Some(String::from_utf8_lossy(name).into_owned());
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Processes the control messages and the received data of all ports.
+    pub fn poll(&mut self) -> DevResult {
+        let mut msg = None;
+        loop {
+            let mut payload = [0; RX_BUF_LEN];
+            let mut payload_len = 0;
+            if let Some((ctrl_rx, _)) = self.ctrl.as_mut() {
+                ctrl_rx.pop(&mut self.transport, |buf| {
+                    msg = ControlMsg::from_bytes(buf);
+                    payload_len = buf.len().saturating_sub(CONTROL_MSG_LEN);
+                    payload[..payload_len].copy_from_slice(&buf[buf.len() - payload_len..]);
+                })?;
+            }
+            match msg.take() {
+                Some(msg) => self.handle_control(msg, &payload[..payload_len])?,
+                None => break,
+            }
+        }
+        for port in self.ports.iter_mut() {
+            let input = &mut port.input;
+            while port.rx.pop(&mut self.transport, |buf| {
+                input.extend(buf);
+                let excess = input.len().saturating_sub(INPUT_LIMIT);
+                input.drain(..excess);
+            })? {}
+        }
+        Ok(())
+    }
+
+    /// Returns whether there is received data of the port.
+    pub fn can_read(&mut self, id: usize) -> DevResult<bool> {
+        self.poll()?;
+        let port = self.ports.get(id).ok_or(DevError::InvalidParam)?;
+        Ok(!port.input.is_empty())
+    }
+
+    /// Reads the received data of the port into `buf`, returns the number of
+    /// bytes read, which is 0 if nothing has been received.
+    pub fn read(&mut self, id: usize, buf: &mut [u8]) -> DevResult<usize> {
+        self.poll()?;
+        let port = self.ports.get_mut(id).ok_or(DevError::InvalidParam)?;
+        let len = buf.len().min(port.input.len());
+        for (dst, src) in buf.iter_mut().zip(port.input.drain(..len)) {
+            *dst = src;
+        }
+        Ok(len)
+    }
+
+    /// Sends `data` through the port, returns the number of bytes sent.
+    pub fn write(&mut self, id: usize, data: &[u8]) -> DevResult<usize> {
+        let port = self.ports.get_mut(id).ok_or(DevError::InvalidParam)?;
+        if !port.added {
+            return Err(DevError::BadState);
+        }
+        // the data may be in user memory, which is not physically contiguous
+        for chunk in data.chunks(TX_BUF_LEN) {
+            port.tx_buf[..chunk.len()].copy_from_slice(chunk);
+            let buf = &port.tx_buf[..chunk.len()];
+            port.tx
+                .add_notify_wait_pop(&mut self.transport, &[buf], &mut [])?;
+        }
+        Ok(data.len())
+    }
+}
+
+impl<H: Hal, T: Transport> BaseDriverOps for VirtIoConsoleDev<H, T> {
+    fn device_name(&self) -> &str {
+        "virtio-console"
+    }
+
+    fn device_type(&self) -> DeviceType {
+        DeviceType::Char
+    }
+}
@@ -0,0 +1,321 @@
+//! VirtIO input device (`virtio-input`), e.g., the keyboard, mouse and tablet
+//! of QEMU.
+//!
+//! The device sends `struct virtio_input_event`s, which are the same as the
+//! events of Linux evdev without the timestamps. Its capabilities (name, IDs,
+//! supported events and absolute axes) are queried from the config space
+//! once when the device is initialized.
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ptr::{addr_of, addr_of_mut, NonNull};
+
+use driver_common::{BaseDriverOps, DevResult, DeviceType};
+use virtio_drivers::{transport::Transport, Hal};
+
+use crate::virtqueue::{as_dev_err, begin_init, VirtQueue};
+
+/// Number of event types, i.e., `EV_CNT` of Linux.
+pub const EV_CNT: usize = 0x20;
+/// Number of absolute axes, i.e., `ABS_CNT` of Linux.
+pub const ABS_CNT: usize = 0x40;
+const EV_ABS: u8 = 0x03;
+
+// Selectors of the config space
+const VIRTIO_INPUT_CFG_ID_NAME: u8 = 0x01;
+const VIRTIO_INPUT_CFG_ID_SERIAL: u8 = 0x02;
+const VIRTIO_INPUT_CFG_ID_DEVIDS: u8 = 0x03;
+const VIRTIO_INPUT_CFG_PROP_BITS: u8 = 0x10;
+const VIRTIO_INPUT_CFG_EV_BITS: u8 = 0x11;
+const VIRTIO_INPUT_CFG_ABS_INFO: u8 = 0x12;
+
+const QUEUE_SIZE: u16 = 64;
+const EVENT_QUEUE: u16 = 0;
+const STATUS_QUEUE: u16 = 1;
+const EVENT_LEN: usize = 8;
+
+#[repr(C)]
+struct InputConfig {
+    select: u8,
+    subsel: u8,
+    size: u8,
+    _reserved: [u8; 5],
+    data: [u8; 128],
+}
+
+/// An input event, `struct virtio_input_event`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InputEvent {
+    pub event_type: u16,
+    pub code: u16,
+    pub value: u32,
+}
+
+impl InputEvent {
+    fn to_bytes(self) -> [u8; EVENT_LEN] {
+        let mut buf = [0; EVENT_LEN];
+        buf[..2].copy_from_slice(&self.event_type.to_le_bytes());
+        buf[2..4].copy_from_slice(&self.code.to_le_bytes());
+        buf[4..].copy_from_slice(&self.value.to_le_bytes());
+        buf
+    }
+
+    fn from_bytes(buf: &[u8; EVENT_LEN]) -> Self {
+        Self {
+            event_type: u16::from_le_bytes([buf[0], buf[1]]),
+            code: u16::from_le_bytes([buf[2], buf[3]]),
+            value: u32::from_le_bytes([buf[4], buf[5], buf[6], buf[7]]),
+        }
+    }
+}
+
+/// The IDs of the device, `struct input_id` of Linux.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InputIds {
+    pub bustype: u16,
+    pub vendor: u16,
+    pub product: u16,
+    pub version: u16,
+}
+
+/// The range of an absolute axis. The current value is not reported by the
+/// device.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AbsInfo {
+    pub min: u32,
+    pub max: u32,
+    pub fuzz: u32,
+    pub flat: u32,
+    pub res: u32,
+}
+
+/// The VirtIO input device.
+pub struct VirtIoInputDev<H: Hal, T: Transport> {
+    transport: T,
+    event_queue: VirtQueue<H>,
+    /// The buffers posted to the event queue.
+    event_bufs: Vec<Box<[u8; EVENT_LEN]>>,
+    /// The buffer index of each request token.
+    tokens: [usize; QUEUE_SIZE as usize],
+    status_queue: VirtQueue<H>,
+    name: String,
+    serial: String,
+    ids: InputIds,
+    prop_bits: Vec<u8>,
+    ev_bits: [Vec<u8>; EV_CNT],
+    abs_info: [Option<AbsInfo>; ABS_CNT],
+    #[cfg(feature = "irq-io")]
+    waiter: Option<&'static crate::irq_io::IrqWaiter>,
+}
+
+unsafe impl<H: Hal, T: Transport> Send for VirtIoInputDev<H, T> {}
+unsafe impl<H: Hal, T: Transport> Sync for VirtIoInputDev<H, T> {}
+
+/// Selects a config item, and returns the data of it, which is empty if the
+/// item is not supported.
+fn query_config(config: NonNull<InputConfig>, select: u8, subsel: u8) -> Vec<u8> {
+    let config = config.as_ptr();
+    // SAFETY: the config space is valid for the lifetime of the transport.
+    unsafe {
+        addr_of_mut!((*config).select).write_volatile(select);
+        addr_of_mut!((*config).subsel).write_volatile(subsel);
+        let size = addr_of!((*config).size).read_volatile() as usize;
+        let data = addr_of!((*config).data) as *const u8;
+        (0..size.min(128))
+            .map(|i| data.add(i).read_volatile())
+            .collect()
+    }
+}
+
+fn query_string(config: NonNull<InputConfig>, select: u8) -> String {
+    let data = query_config(config, select, 0);
+    let data = data.split(|&c| c == 0).next().unwrap_or_default();
+    String::from_utf8_lossy(data).into_owned()
+}
+
+fn u16_at(data: &[u8], i: usize) -> u16 {
+    data.get(i..i + 2)
+        .map_or(0, |b| u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn u32_at(data: &[u8], i: usize) -> u32 {
+    data.get(i..i + 4)
+        .map_or(0, |b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+impl<H: Hal, T: Transport> VirtIoInputDev<H, T> {
+    /// Creates a new driver instance and initializes the device.
+    pub fn try_new(mut transport: T) -> DevResult<Self> {
+        begin_init(&mut transport, 0);
+        let config = transport
+            .config_space::<InputConfig>()
+            .map_err(as_dev_err)?;
+
+        let name = query_string(config, VIRTIO_INPUT_CFG_ID_NAME);
+        let serial = query_string(config, VIRTIO_INPUT_CFG_ID_SERIAL);
+        let ids = query_config(config, VIRTIO_INPUT_CFG_ID_DEVIDS, 0);
+        let ids = InputIds {
+            bustype: u16_at(&ids, 0),
+            vendor: u16_at(&ids, 2),
+            product: u16_at(&ids, 4),
+            version: u16_at(&ids, 6),
+        };
+        let prop_bits = query_config(config, VIRTIO_INPUT_CFG_PROP_BITS, 0);
+        let ev_bits: [Vec<u8>; EV_CNT] = core::array::from_fn(|ty| {
+            // the bitmap of event types is built from the others below
+            if ty == 0 {
+                Vec::new()
+            } else {
+                query_config(config, VIRTIO_INPUT_CFG_EV_BITS, ty as u8)
+            }
+        });
+        let has_abs = |code: usize| {
+            ev_bits[EV_ABS as usize]
+                .get(code / 8)
+                .is_some_and(|b| b & (1 << (code % 8)) != 0)
+        };
+        let abs_info = core::array::from_fn(|code| {
+            if !has_abs(code) {
+                return None;
+            }
+            let data = query_config(config, VIRTIO_INPUT_CFG_ABS_INFO, code as u8);
+            Some(AbsInfo {
+                min: u32_at(&data, 0),
+                max: u32_at(&data, 4),
+                fuzz: u32_at(&data, 8),
+                flat: u32_at(&data, 12),
+                res: u32_at(&data, 16),
+            })
+        });
+
+        let event_queue = VirtQueue::new(&mut transport, EVENT_QUEUE, QUEUE_SIZE)?;
+        let status_queue = VirtQueue::new(&mut transport, STATUS_QUEUE, QUEUE_SIZE)?;
+        let mut dev = Self {
+            event_bufs: (0..event_queue.size())
+                .map(|_| Box::new([0; EVENT_LEN]))
+                .collect(),
+            event_queue,
+            tokens: [0; QUEUE_SIZE as usize],
+            status_queue,
+            transport,
+            name,
+            serial,
+            ids,
+            prop_bits,
+            ev_bits,
+            abs_info,
+            #[cfg(feature = "irq-io")]
+            waiter: None,
+        };
+        for i in 0..dev.event_bufs.len() {
+            dev.post(i)?;
+        }
+        dev.transport.finish_init();
+        // the event buffers can only be notified after the device is ready
+        dev.event_queue.notify(&mut dev.transport);
+
+        // the bitmap of event types
+        let mut types = [0u8; EV_CNT / 8];
+        for (ty, bits) in dev.ev_bits.iter().enumerate() {
+            if bits.iter().any(|&b| b != 0) {
+                types[ty / 8] |= 1 << (ty % 8);
+            }
+        }
+        types[0] |= 1; // EV_SYN
+        dev.ev_bits[0] = types.to_vec();
+        Ok(dev)
+    }
+
+    fn post(&mut self, i: usize) -> DevResult {
+        // SAFETY: the buffers are owned by the driver and only read after the
+        // device returns them.
+        let token = unsafe {
+            self.event_queue
+                .add(&[], &mut [&mut self.event_bufs[i][..]])?
+        };
+        self.tokens[token as usize] = i;
+        Ok(())
+    }
+
+    /// Sets the waiter notified by the IRQs of the device.
+    #[cfg(feature = "irq-io")]
+    pub fn set_waiter(&mut self, waiter: Option<&'static crate::irq_io::IrqWaiter>) {
+        self.waiter = waiter;
+    }
+
+    /// Returns the waiter notified by the IRQs of the device, or [`None`] if
+    /// the device must be polled.
+    #[cfg(feature = "irq-io")]
+    pub fn waiter(&self) -> Option<&'static crate::irq_io::IrqWaiter> {
+        self.waiter
+    }
+
+    /// The name of the device.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The serial number of the device, which may be empty.
+    pub fn serial(&self) -> &str {
+        &self.serial
+    }
+
+    /// The IDs of the device.
+    pub fn ids(&self) -> InputIds {
+        self.ids
+    }
+
+    /// The bitmap of the device properties (`INPUT_PROP_*`).
+    pub fn prop_bits(&self) -> &[u8] {
+        &self.prop_bits
+    }
+
+    /// The bitmap of the supported codes of the event type. For type 0
+    /// (`EV_SYN`), it is the bitmap of the supported event types.
+    pub fn ev_bits(&self, event_type: u8) -> &[u8] {
+        self.ev_bits
+            .get(event_type as usize)
+            .map_or(&[][..], Vec::as_slice)
+    }
+
+    /// The range of the absolute axis, or [`None`] if it is not supported.
+    pub fn abs_info(&self, code: u8) -> Option<AbsInfo> {
+        self.abs_info.get(code as usize).copied().flatten()
+    }
+
+    /// Acknowledges the interrupt of the device.
+    pub fn ack_interrupt(&mut self) -> bool {
+        self.transport.ack_interrupt()
+    }
+
+    /// Takes a received event, returns [`None`] if there is none.
+    pub fn pop_event(&mut self) -> DevResult<Option<InputEvent>> {
+        let Some((token, _)) = self.event_queue.pop_used() else {
+            return Ok(None);
+        };
+        let i = self.tokens[token as usize];
+        let event = InputEvent::from_bytes(&self.event_bufs[i]);
+        self.post(i)?;
+        self.event_queue.notify(&mut self.transport);
+        Ok(Some(event))
+    }
+
+    /// Sends an event to the device, e.g., to set the LEDs of a keyboard.
+    pub fn send_status(&mut self, event: InputEvent) -> DevResult {
+        let buf = event.to_bytes();
+        self.status_queue
+            .add_notify_wait_pop(&mut self.transport, &[&buf], &mut [])?;
+        Ok(())
+    }
+}
+
+impl<H: Hal, T: Transport> BaseDriverOps for VirtIoInputDev<H, T> {
+    fn device_name(&self) -> &str {
+        "virtio-input"
+    }
+
+    fn device_type(&self) -> DeviceType {
+        DeviceType::Char
+    }
+}
@@ -0,0 +1,63 @@
+//! VirtIO entropy device (`virtio-rng`).
+//!
+//! The device has a single request queue. The driver posts writable buffers,
+//! and the device fills them with random bytes from the entropy source of the
+//! host.
+
+use driver_common::{BaseDriverOps, DevResult, DeviceType};
+use virtio_drivers::transport::Transport;
+use virtio_drivers::Hal;
+
+use crate::virtqueue::{begin_init, VirtQueue};
+
+const QUEUE_SIZE: u16 = 8;
+const REQUEST_QUEUE: u16 = 0;
+
+/// The VirtIO entropy device.
+pub struct VirtIoRngDev<H: Hal, T: Transport> {
+    transport: T,
+    queue: VirtQueue<H>,
+}
+
+unsafe impl<H: Hal, T: Transport> Send for VirtIoRngDev<H, T> {}
+unsafe impl<H: Hal, T: Transport> Sync for VirtIoRngDev<H, T> {}
+
+impl<H: Hal, T: Transport> VirtIoRngDev<H, T> {
+    /// Initializes the device. It has no features.
+    pub fn try_new(mut transport: T) -> DevResult<Self> {
+        begin_init(&mut transport, 0);
+        let queue = VirtQueue::new(&mut transport, REQUEST_QUEUE, QUEUE_SIZE)?;
+        transport.finish_init();
+        Ok(Self { transport, queue })
+    }
+
+    /// Fills `buf` with random bytes from the device, returns the number of
+    /// bytes filled, which may be less than the length of `buf`.
+    ///
+    /// The request is completed by the host right away, unless the host limits
+    /// the rate of the device, in which case this spins until it is completed.
+    pub fn request(&mut self, buf: &mut [u8]) -> DevResult<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let len = self
+            .queue
+            .add_notify_wait_pop(&mut self.transport, &[], &mut [buf])?;
+        Ok(len as usize)
+    }
+
+    /// Acknowledges the interrupt of the device.
+    pub fn ack_interrupt(&mut self) -> bool {
+        self.transport.ack_interrupt()
+    }
+}
+
+impl<H: Hal, T: Transport> BaseDriverOps for VirtIoRngDev<H, T> {
+    fn device_name(&self) -> &str {
+        "virtio-rng"
+    }
+
+    fn device_type(&self) -> DeviceType {
+        DeviceType::Char
+    }
+}
@@ -0,0 +1,271 @@
+//! A minimal split virtqueue, for the VirtIO devices that are driven by this
+//! crate directly instead of `driver_virtio`.
+//!
+//! The queue memory uses the legacy layout (the used ring starts on a new page),
+//! which is also valid for modern transports, so the same code works for both
+//! the legacy VirtIO MMIO transport of QEMU and the PCI transport.
+
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use core::ptr::{self, NonNull};
+use core::sync::atomic::{fence, Ordering};
+
+use driver_common::{DevError, DevResult};
+use virtio_drivers::transport::{DeviceStatus, Transport};
+use virtio_drivers::{BufferDirection, Hal, PhysAddr, PAGE_SIZE};
+
+/// The device conforms to the VirtIO 1.0+ specification (modern devices).
+const VIRTIO_F_VERSION_1: u64 = 1 << 32;
+
+const DESC_F_NEXT: u16 = 1;
+const DESC_F_WRITE: u16 = 2;
+
+#[repr(C)]
+struct Descriptor {
+    addr: u64,
+    len: u32,
+    flags: u16,
+    next: u16,
+}
+
+const fn align_up(size: usize) -> usize {
+    (size + PAGE_SIZE - 1) & !(PAGE_SIZE - 1)
+}
+
+/// Resets the device and negotiates the features in `supported`, returns the
+/// negotiated features.
+///
+/// The queues should be created after this, and then the device is started by
+/// [`Transport::finish_init`].
+pub(crate) fn begin_init<T: Transport>(transport: &mut T, supported: u64) -> u64 {
+    transport.set_status(DeviceStatus::empty());
+    transport.set_status(DeviceStatus::ACKNOWLEDGE | DeviceStatus::DRIVER);
+    let features = transport.read_device_features() & (supported | VIRTIO_F_VERSION_1);
+    transport.write_driver_features(features);
+    transport
+        .set_status(DeviceStatus::ACKNOWLEDGE | DeviceStatus::DRIVER | DeviceStatus::FEATURES_OK);
+    transport.set_guest_page_size(PAGE_SIZE as u32);
+    features
+}
+
+/// Maps the errors of `virtio-drivers` to [`DevError`].
+pub(crate) fn as_dev_err(e: virtio_drivers::Error) -> DevError {
+    use virtio_drivers::Error::*;
+    match e {
+        QueueFull => DevError::Again,
+        NotReady => DevError::Again,
+        AlreadyUsed => DevError::AlreadyExists,
+        InvalidParam => DevError::InvalidParam,
+        DmaError => DevError::NoMemory,
+        IoError => DevError::Io,
+        Unsupported => DevError::Unsupported,
+        _ => DevError::BadState,
+    }
+}
+
+/// A split virtqueue.
+///
+/// Each request is a chain of descriptors, identified by the token (the index
+/// of its first descriptor) returned by [`VirtQueue::add`]. Completed requests
+/// are taken by [`VirtQueue::pop_used`] in the order of completion.
+pub(crate) struct VirtQueue<H: Hal> {
+    idx: u16,
+    size: u16,
+    desc: *mut Descriptor,
+    /// `flags`, `idx`, `ring[size]`
+    avail: *mut u16,
+    /// `flags`, `idx`, `ring[size]` of (`id`, `len`)
+    used: *mut u8,
+    free_head: u16,
+    num_free: u16,
+    avail_idx: u16,
+    last_used_idx: u16,
+    /// The buffers shared with the device, indexed by descriptor.
+    bufs: Vec<Option<(NonNull<[u8]>, BufferDirection)>>,
+    _hal: PhantomData<H>,
+}
+
+unsafe impl<H: Hal> Send for VirtQueue<H> {}
+unsafe impl<H: Hal> Sync for VirtQueue<H> {}
+
+impl<H: Hal> VirtQueue<H> {
+    /// Creates the queue `idx` of the device with at most `size` entries.
+    pub fn new<T: Transport>(transport: &mut T, idx: u16, size: u16) -> DevResult<Self> {
+        if transport.queue_used(idx) {
+            return Err(DevError::AlreadyExists);
+        }
+        let max_size = transport.max_queue_size(idx);
+        if max_size == 0 || !size.is_power_of_two() {
+            return Err(DevError::InvalidParam);
+        }
+        let size = size.min(max_size as u16);
+
+        let desc_size = core::mem::size_of::<Descriptor>() * size as usize;
+        let avail_size = 2 * (3 + size as usize);
+        let used_offset = align_up(desc_size + avail_size);
+        let used_size = 6 + 8 * size as usize;
+        let pages = (used_offset + align_up(used_size)) / PAGE_SIZE;
+
+        let (paddr, vaddr) = H::dma_alloc(pages, BufferDirection::Both);
+        if paddr == 0 {
+            return Err(DevError::NoMemory);
+        }
+        let base = vaddr.as_ptr();
+        unsafe { ptr::write_bytes(base, 0, pages * PAGE_SIZE) };
+
+        let desc = base as *mut Descriptor;
+        for i in 0..size - 1 {
+            unsafe { (*desc.add(i as usize)).next = i + 1 };
+        }
+        transport.queue_set(
+            idx,
+            size as u32,
+            paddr,
+            paddr + desc_size,
+            paddr + used_offset,
+        );
+        Ok(Self {
+            idx,
+            size,
+            desc,
+            avail: unsafe { base.add(desc_size) } as *mut u16,
+            used: unsafe { base.add(used_offset) },
+            free_head: 0,
+            num_free: size,
+            avail_idx: 0,
+            last_used_idx: 0,
+            bufs: (0..size).map(|_| None).collect(),
+            _hal: PhantomData,
+        })
+    }
+
+    /// The number of entries of the queue.
+    pub fn size(&self) -> u16 {
+        self.size
+    }
+
+    /// Adds a request to the available ring, returns its token.
+    ///
+    /// `inputs` are read by the device and `outputs` are written by it. The
+    /// device is not notified.
+    ///
+    /// # Safety
+    ///
+    /// The buffers must stay valid and must not be accessed until the request
+    /// is returned by [`VirtQueue::pop_used`].
+    pub unsafe fn add(&mut self, inputs: &[&[u8]], outputs: &mut [&mut [u8]]) -> DevResult<u16> {
+        let count = inputs.len() + outputs.len();
+        if count == 0 {
+            return Err(DevError::InvalidParam);
+        }
+        if count > self.num_free as usize {
+            return Err(DevError::Again);
+        }
+        let bufs = inputs
+            .iter()
+            .map(|buf| (NonNull::from(*buf), BufferDirection::DriverToDevice, 0))
+            .chain(outputs.iter_mut().map(|buf| {
+                (
+                    NonNull::from(&mut **buf),
+                    BufferDirection::DeviceToDriver,
+                    DESC_F_WRITE,
+                )
+            }));
+
+        let head = self.free_head;
+        let mut last = head;
+        for (buf, direction, flags) in bufs {
+            let i = self.free_head;
+            let desc = &mut *self.desc.add(i as usize);
+            desc.addr = H::share(buf, direction) as u64;
+            desc.len = buf.len() as u32;
+            desc.flags = flags | DESC_F_NEXT;
+            self.bufs[i as usize] = Some((buf, direction));
+            last = i;
+            self.free_head = desc.next;
+        }
+        (*self.desc.add(last as usize)).flags &= !DESC_F_NEXT;
+        self.num_free -= count as u16;
+
+        let slot = (self.avail_idx % self.size) as usize;
+        self.avail.add(2 + slot).write_volatile(head);
+        // the device must see the ring entry before the new index
+        fence(Ordering::SeqCst);
+        self.avail_idx = self.avail_idx.wrapping_add(1);
+        self.avail.add(1).write_volatile(self.avail_idx);
+        fence(Ordering::SeqCst);
+        Ok(head)
+    }
+
+    /// Notifies the device that there are new available requests.
+    pub fn notify<T: Transport>(&self, transport: &mut T) {
+        transport.notify(self.idx);
+    }
+
+    fn used_idx(&self) -> u16 {
+        fence(Ordering::SeqCst);
+        unsafe { (self.used.add(2) as *const u16).read_volatile() }
+    }
+
+    /// Returns whether there are completed requests.
+    pub fn can_pop(&self) -> bool {
+        self.used_idx() != self.last_used_idx
+    }
+
+    /// Takes the next completed request, returns its token and the number of
+    /// bytes written by the device.
+    pub fn pop_used(&mut self) -> Option<(u16, u32)> {
+        if !self.can_pop() {
+            return None;
+        }
+        let slot = (self.last_used_idx % self.size) as usize;
+        let (head, len) = unsafe {
+            let elem = self.used.add(4 + 8 * slot) as *const u32;
+            (elem.read_volatile() as u16, elem.add(1).read_volatile())
+        };
+        self.last_used_idx = self.last_used_idx.wrapping_add(1);
+
+        let mut i = head;
+        loop {
+            let desc = unsafe { &mut *self.desc.add(i as usize) };
+            if let Some((buf, direction)) = self.bufs[i as usize].take() {
+                unsafe { H::unshare(desc.addr as PhysAddr, buf, direction) };
+            }
+            desc.addr = 0;
+            desc.len = 0;
+            self.num_free += 1;
+            if desc.flags & DESC_F_NEXT == 0 {
+                desc.flags = 0;
+                desc.next = self.free_head;
+                break;
+            }
+            desc.flags = 0;
+            i = desc.next;
+        }
+        self.free_head = head;
+        Some((head, len))
+    }
+
+    /// Adds a request, notifies the device and spins until it is completed.
+    /// Returns the number of bytes written by the device.
+    ///
+    /// It is only used for short requests that the device completes right away.
+    pub fn add_notify_wait_pop<T: Transport>(
+        &mut self,
+        transport: &mut T,
+        inputs: &[&[u8]],
+        outputs: &mut [&mut [u8]],
+    ) -> DevResult<u32> {
+        // SAFETY: the buffers outlive the request, as it is completed before
+        // returning.
+        let token = unsafe { self.add(inputs, outputs)? };
+        self.notify(transport);
+        loop {
+            match self.pop_used() {
+                Some((used, len)) if used == token => return Ok(len),
+                Some(_) => return Err(DevError::BadState),
+                None => core::hint::spin_loop(),
+            }
+        }
+    }
+}
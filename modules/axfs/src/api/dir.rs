@@ -4,6 +4,7 @@ use core::fmt;
 
 use super::FileType;
 use crate::fops;
+use crate::notify::{self, FsEventMask};
 
 /// Iterator over the entries in a directory.
 pub struct ReadDir<'a> {
@@ -137,7 +138,9 @@ impl DirBuilder {
         if self.recursive {
             self.create_dir_all(path)
         } else {
-            crate::root::create_dir(None, path)
+            crate::root::create_dir(None, path)?;
+            notify::notify(None, path, FsEventMask::CREATE | FsEventMask::ISDIR);
+            Ok(())
         }
     }
 
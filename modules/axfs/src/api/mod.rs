@@ -12,6 +12,7 @@ use axfs_vfs::VfsNodeRef;
 pub use axio::{Read, Seek, SeekFrom, Write};
 pub use port::*;
 
+use crate::notify::{self, FsEventMask};
 use alloc::{string::String, vec::Vec};
 #[allow(unused_imports)]
 use axio::{self as io, prelude::*};
@@ -79,12 +80,16 @@ pub fn create_dir_all(path: &str) -> io::Result<()> {
 
 /// Removes an empty directory.
 pub fn remove_dir(path: &str) -> io::Result<()> {
-    crate::root::remove_dir(None, path)
+    crate::root::remove_dir(None, path)?;
+    notify::notify(None, path, FsEventMask::DELETE | FsEventMask::ISDIR);
+    Ok(())
 }
 
 /// Removes a file from the filesystem.
 pub fn remove_file(path: &str) -> io::Result<()> {
-    crate::root::remove_file(None, path)
+    crate::root::remove_file(None, path)?;
+    notify::notify(None, path, FsEventMask::DELETE);
+    Ok(())
 }
 
 /// Rename a file or directory to a new name.
@@ -92,7 +97,68 @@ pub fn remove_file(path: &str) -> io::Result<()> {
 ///
 /// This only works then the new path is in the same mounted fs.
 pub fn rename(old: &str, new: &str) -> io::Result<()> {
-    crate::root::rename(old, new)
+    let is_dir = metadata(old).is_ok_and(|meta| meta.is_dir());
+    crate::root::rename(old, new)?;
+    notify::notify_rename(old, new, is_dir);
+    Ok(())
+}
+
+/// Creates a new symbolic link `link` which points to `original`.
+pub fn symlink(original: &str, link: &str) -> io::Result<()> {
+    crate::root::symlink(original, link)?;
+    notify::notify(None, link, FsEventMask::CREATE);
+    Ok(())
+}
+
+/// Creates a new hard link `link` to the file `original`.
+///
+/// This only works when both paths are in the same mounted fs.
+pub fn hard_link(original: &str, link: &str) -> io::Result<()> {
+    crate::root::hard_link(original, link)?;
+    notify::notify(None, link, FsEventMask::CREATE);
+    Ok(())
+}
+
+/// Reads a symbolic link, returning the path it points to.
+///
+/// Returns `InvalidInput` if `path` is not a symbolic link.
+pub fn read_link(path: &str) -> io::Result<String> {
+    crate::root::read_link(path)
+}
+
+/// Queries the metadata of a file without following a symbolic link in the
+/// last component of `path`.
+pub fn symlink_metadata(path: &str) -> io::Result<Metadata> {
+    crate::root::symlink_attr(path).map(Metadata)
+}
+
+/// Returns the inode number and the number of hard links of a file. A
+/// symbolic link in the last component of `path` is followed if `follow` is
+/// set.
+///
+/// Returns `Unsupported` if the filesystem does not keep them, e.g. FAT.
+pub fn inode_info(path: &str, follow: bool) -> io::Result<(u64, u64)> {
+    crate::root::inode_info(path, follow)
+}
+
+/// Returns the absolute form of a path with all symbolic links resolved.
+///
+/// Returns `InvalidData` if too many symbolic links are encountered, which
+/// corresponds to `ELOOP`.
+pub fn real_path(path: &str) -> io::Result<String> {
+    crate::root::real_path(path)
+}
+
+/// Unmounts the filesystem mounted at `path`.
+///
+/// Fails with `InvalidInput` if no filesystem is mounted there.
+pub fn umount(path: &str) -> io::Result<()> {
+    crate::root::umount(path)
+}
+
+/// Writes back the cached data of all block devices.
+pub fn sync() -> io::Result<()> {
+    crate::blk::sync().map_err(|_| io::Error::Io)
 }
 
 /// Check if a path exists.
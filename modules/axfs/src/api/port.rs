@@ -346,6 +346,14 @@ pub trait FileIO: AsAny + Send + Sync {
     fn ioctl(&self, _request: usize, _arg1: usize) -> AxResult<isize> {
         Err(AxError::Unsupported)
     }
+
+    /// 设备内存从 `offset` 开始、长度为 `len` 的部分的物理地址
+    ///
+    /// 用于 mmap 时把设备的页面（如 framebuffer）直接映射到用户地址空间，
+    /// 普通文件不支持
+    fn mmap_phys(&self, _offset: usize, _len: usize) -> AxResult<usize> {
+        Err(AxError::Unsupported)
+    }
 }
 
 /// `FileExt` 需要满足 `AsAny` 的要求，即可以转化为 `Any` 类型，从而能够进行向下类型转换。
@@ -418,3 +426,15 @@ pub struct ConsoleWinSize {
     ws_xpixel: u16,
     ws_ypixel: u16,
 }
+
+impl ConsoleWinSize {
+    /// Creates the size of a window with `rows` x `cols` characters.
+    pub const fn new(rows: u16, cols: u16) -> Self {
+        Self {
+            ws_row: rows,
+            ws_col: cols,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        }
+    }
+}
@@ -0,0 +1,143 @@
+//! The LRU buffer cache of one block device.
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use super::BLOCK_SIZE;
+
+/// The data of one block.
+pub(super) type Block = Box<[u8; BLOCK_SIZE]>;
+
+struct Buffer {
+    data: Block,
+    dirty: bool,
+    /// The time of the last access, which is the key in [`BufferCache::lru`].
+    stamp: u64,
+}
+
+/// Cached blocks, which are evicted in the least recently used order.
+pub(super) struct BufferCache {
+    capacity: usize,
+    buffers: BTreeMap<u64, Buffer>,
+    /// IDs of the cached blocks, ordered by the time of their last access.
+    lru: BTreeMap<u64, u64>,
+    clock: u64,
+    dirty: usize,
+}
+
+impl BufferCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            buffers: BTreeMap::new(),
+            lru: BTreeMap::new(),
+            clock: 0,
+            dirty: 0,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.buffers.len()
+    }
+
+    pub fn dirty_count(&self) -> usize {
+        self.dirty
+    }
+
+    pub fn contains(&self, block_id: u64) -> bool {
+        self.buffers.contains_key(&block_id)
+    }
+
+    fn touch(&mut self, block_id: u64) -> Option<&mut Buffer> {
+        let buf = self.buffers.get_mut(&block_id)?;
+        self.lru.remove(&buf.stamp);
+        self.clock += 1;
+        buf.stamp = self.clock;
+        self.lru.insert(self.clock, block_id);
+        Some(buf)
+    }
+
+    /// Returns the data of a cached block, and marks it as recently used.
+    pub fn get(&mut self, block_id: u64) -> Option<&[u8; BLOCK_SIZE]> {
+        self.touch(block_id).map(|buf| &*buf.data)
+    }
+
+    /// Returns the data of a cached block to modify, and marks it as recently
+    /// used and dirty.
+    pub fn get_mut(&mut self, block_id: u64) -> Option<&mut [u8; BLOCK_SIZE]> {
+        if !self.buffers.get(&block_id)?.dirty {
+            self.dirty += 1;
+        }
+        let buf = self.touch(block_id)?;
+        buf.dirty = true;
+        Some(&mut *buf.data)
+    }
+
+    /// Returns the data of a cached block without changing the LRU order.
+    pub fn peek(&self, block_id: u64) -> Option<&[u8; BLOCK_SIZE]> {
+        self.buffers.get(&block_id).map(|buf| &*buf.data)
+    }
+
+    /// Adds a block that is not cached. There must be room for it.
+    pub fn insert(&mut self, block_id: u64, data: Block, dirty: bool) {
+        debug_assert!(self.len() < self.capacity);
+        self.clock += 1;
+        self.lru.insert(self.clock, block_id);
+        let old = self.buffers.insert(
+            block_id,
+            Buffer {
+                data,
+                dirty,
+                stamp: self.clock,
+            },
+        );
+        debug_assert!(old.is_none());
+        if dirty {
+            self.dirty += 1;
+        }
+    }
+
+    /// Returns the least recently used block, and whether it is dirty.
+    pub fn oldest(&self) -> Option<(u64, bool)> {
+        let (_, &block_id) = self.lru.first_key_value()?;
+        Some((block_id, self.buffers[&block_id].dirty))
+    }
+
+    /// Drops a clean block from the cache.
+    pub fn remove(&mut self, block_id: u64) {
+        if let Some(buf) = self.buffers.remove(&block_id) {
+            debug_assert!(!buf.dirty);
+            self.lru.remove(&buf.stamp);
+        }
+    }
+
+    /// Returns the runs of adjacent dirty blocks as `(first block, count)`,
+    /// in ascending order. A run has at most `max_len` blocks.
+    pub fn dirty_runs(&self, max_len: usize) -> Vec<(u64, usize)> {
+        let mut runs: Vec<(u64, usize)> = Vec::new();
+        for (&block_id, _) in self.buffers.iter().filter(|(_, buf)| buf.dirty) {
+            match runs.last_mut() {
+                Some((start, len)) if *start + *len as u64 == block_id && *len < max_len => {
+                    *len += 1
+                }
+                _ => runs.push((block_id, 1)),
+            }
+        }
+        runs
+    }
+
+    /// Marks a block as written back.
+    pub fn set_clean(&mut self, block_id: u64) {
+        if let Some(buf) = self.buffers.get_mut(&block_id) {
+            if buf.dirty {
+                buf.dirty = false;
+                self.dirty -= 1;
+            }
+        }
+    }
+}
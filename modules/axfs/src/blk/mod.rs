@@ -0,0 +1,293 @@
+//! The block layer between block device drivers and filesystems.
+//!
+//! Every block device used by the filesystems is wrapped in a
+//! [`BlockDevice`], which keeps its blocks in an LRU buffer cache:
+//!
+//! - Writes only modify the cache. Dirty blocks are written back when too many
+//!   of them are cached, when a dirty block is to be evicted, or on [`sync`].
+//! - Dirty blocks are written back in ascending order, and adjacent ones are
+//!   merged into one request.
+//! - A read that misses the cache also reads the following uncached blocks in
+//!   the same request.
+//!
+//! A request of several blocks is passed to the driver as one call of
+//! `read_block` or `write_block` with a buffer of several blocks.
+
+mod cache;
+
+use alloc::{boxed::Box, format, string::String, sync::Arc, vec, vec::Vec};
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use axdriver::prelude::*;
+use axsync::Mutex;
+
+use self::cache::BufferCache;
+
+/// The size of blocks in the block layer.
+pub(crate) const BLOCK_SIZE: usize = 512;
+
+/// The number of blocks cached for each device, i.e., 2 MiB.
+const CACHE_BLOCKS: usize = 4096;
+/// Dirty blocks are written back when there are more than this.
+const DIRTY_LIMIT: usize = CACHE_BLOCKS / 2;
+/// The number of blocks read at most when a read misses the cache.
+const READ_AHEAD: usize = 16;
+/// The maximum number of blocks in one request.
+const MAX_REQUEST: usize = 64;
+
+/// I/O statistics of a block device, like `/proc/diskstats` in Linux.
+///
+/// Sectors are 512 bytes, and merges count the blocks that are merged into
+/// the requests of other blocks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiskStats {
+    /// Read requests completed.
+    pub reads: u64,
+    /// Blocks merged into read requests.
+    pub read_merges: u64,
+    /// Sectors read.
+    pub read_sectors: u64,
+    /// Write requests completed.
+    pub writes: u64,
+    /// Blocks merged into write requests.
+    pub write_merges: u64,
+    /// Sectors written.
+    pub write_sectors: u64,
+    /// Requests being processed by the device.
+    pub in_flight: u64,
+    /// Block accesses served by the buffer cache.
+    pub cache_hits: u64,
+    /// Block accesses that missed the buffer cache.
+    pub cache_misses: u64,
+    /// Dirty blocks in the buffer cache.
+    pub dirty: u64,
+}
+
+#[derive(Default)]
+struct Counters {
+    reads: AtomicU64,
+    read_merges: AtomicU64,
+    read_sectors: AtomicU64,
+    writes: AtomicU64,
+    write_merges: AtomicU64,
+    write_sectors: AtomicU64,
+    in_flight: AtomicU64,
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+    dirty: AtomicU64,
+}
+
+struct Inner {
+    dev: AxBlockDevice,
+    cache: BufferCache,
+}
+
+/// A block device with a buffer cache.
+pub struct BlockDevice {
+    name: String,
+    num_blocks: u64,
+    inner: Mutex<Inner>,
+    stats: Counters,
+}
+
+static DEVICES: Mutex<Vec<Arc<BlockDevice>>> = Mutex::new(Vec::new());
+
+impl BlockDevice {
+    fn new(name: String, dev: AxBlockDevice) -> Self {
+        assert_eq!(BLOCK_SIZE, dev.block_size());
+        Self {
+            name,
+            num_blocks: dev.num_blocks(),
+            inner: Mutex::new(Inner {
+                dev,
+                cache: BufferCache::new(CACHE_BLOCKS),
+            }),
+            stats: Counters::default(),
+        }
+    }
+
+    /// The name of the device, e.g., `virtio-blk0`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The number of 512-byte blocks of the device.
+    pub fn num_blocks(&self) -> u64 {
+        self.num_blocks
+    }
+
+    /// Reads `buf.len()` bytes at `offset` of a block.
+    pub fn read(&self, block_id: u64, offset: usize, buf: &mut [u8]) -> DevResult {
+        assert!(offset + buf.len() <= BLOCK_SIZE);
+        let mut inner = self.inner.lock();
+        self.lookup(&mut inner, block_id)?;
+        let data = inner.cache.get(block_id).unwrap();
+        buf.copy_from_slice(&data[offset..offset + buf.len()]);
+        Ok(())
+    }
+
+    /// Writes `buf` at `offset` of a block. The data reaches the device when
+    /// it is written back.
+    pub fn write(&self, block_id: u64, offset: usize, buf: &[u8]) -> DevResult {
+        assert!(offset + buf.len() <= BLOCK_SIZE);
+        let mut inner = self.inner.lock();
+        if buf.len() == BLOCK_SIZE && !inner.cache.contains(block_id) {
+            // the old data is not needed
+            if block_id >= self.num_blocks {
+                return Err(DevError::InvalidParam);
+            }
+            self.stats.cache_misses.fetch_add(1, Ordering::Relaxed);
+            self.reserve(&mut inner, 1)?;
+            inner
+                .cache
+                .insert(block_id, Box::new(buf.try_into().unwrap()), true);
+        } else {
+            self.lookup(&mut inner, block_id)?;
+            let data = inner.cache.get_mut(block_id).unwrap();
+            data[offset..offset + buf.len()].copy_from_slice(buf);
+        }
+        if inner.cache.dirty_count() > DIRTY_LIMIT {
+            self.write_back(&mut inner)?;
+        }
+        self.update_dirty(&inner);
+        Ok(())
+    }
+
+    /// Writes back all dirty blocks, and flushes the device.
+    pub fn flush(&self) -> DevResult {
+        let mut inner = self.inner.lock();
+        self.write_back(&mut inner)?;
+        self.update_dirty(&inner);
+        inner.dev.flush()
+    }
+
+    /// Returns the I/O statistics of the device.
+    pub fn stats(&self) -> DiskStats {
+        let s = &self.stats;
+        DiskStats {
+            reads: s.reads.load(Ordering::Relaxed),
+            read_merges: s.read_merges.load(Ordering::Relaxed),
+            read_sectors: s.read_sectors.load(Ordering::Relaxed),
+            writes: s.writes.load(Ordering::Relaxed),
+            write_merges: s.write_merges.load(Ordering::Relaxed),
+            write_sectors: s.write_sectors.load(Ordering::Relaxed),
+            in_flight: s.in_flight.load(Ordering::Relaxed),
+            cache_hits: s.cache_hits.load(Ordering::Relaxed),
+            cache_misses: s.cache_misses.load(Ordering::Relaxed),
+            dirty: s.dirty.load(Ordering::Relaxed),
+        }
+    }
+
+    fn update_dirty(&self, inner: &Inner) {
+        let dirty = inner.cache.dirty_count() as u64;
+        self.stats.dirty.store(dirty, Ordering::Relaxed);
+    }
+
+    /// Makes sure that a block is cached, reading it and the following
+    /// uncached blocks in one request if it is not.
+    fn lookup(&self, inner: &mut Inner, block_id: u64) -> DevResult {
+        if inner.cache.contains(block_id) {
+            self.stats.cache_hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(());
+        }
+        if block_id >= self.num_blocks {
+            return Err(DevError::InvalidParam);
+        }
+        self.stats.cache_misses.fetch_add(1, Ordering::Relaxed);
+
+        let max = (self.num_blocks - block_id).min(READ_AHEAD as u64);
+        let count = (1..max)
+            .find(|&i| inner.cache.contains(block_id + i))
+            .unwrap_or(max) as usize;
+        self.reserve(inner, count)?;
+
+        let mut buf = vec![0; count * BLOCK_SIZE];
+        self.stats.in_flight.fetch_add(1, Ordering::Relaxed);
+        let res = inner.dev.read_block(block_id, &mut buf);
+        self.stats.in_flight.fetch_sub(1, Ordering::Relaxed);
+        res?;
+        self.stats.reads.fetch_add(1, Ordering::Relaxed);
+        self.stats
+            .read_merges
+            .fetch_add(count as u64 - 1, Ordering::Relaxed);
+        self.stats
+            .read_sectors
+            .fetch_add(count as u64, Ordering::Relaxed);
+
+        for (i, data) in buf.chunks_exact(BLOCK_SIZE).enumerate() {
+            let data = Box::new(data.try_into().unwrap());
+            inner.cache.insert(block_id + i as u64, data, false);
+        }
+        Ok(())
+    }
+
+    /// Evicts blocks until `count` more blocks can be cached.
+    fn reserve(&self, inner: &mut Inner, count: usize) -> DevResult {
+        while inner.cache.len() + count > inner.cache.capacity() {
+            match inner.cache.oldest() {
+                // write back all dirty blocks together, instead of one by one
+                Some((_, true)) => self.write_back(inner)?,
+                Some((block_id, false)) => inner.cache.remove(block_id),
+                None => break,
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes back all dirty blocks, in ascending order and with adjacent
+    /// blocks merged.
+    fn write_back(&self, inner: &mut Inner) -> DevResult {
+        for (start, count) in inner.cache.dirty_runs(MAX_REQUEST) {
+            let mut buf = Vec::with_capacity(count * BLOCK_SIZE);
+            for block_id in start..start + count as u64 {
+                buf.extend_from_slice(inner.cache.peek(block_id).unwrap());
+            }
+
+            self.stats.in_flight.fetch_add(1, Ordering::Relaxed);
+            let res = inner.dev.write_block(start, &buf);
+            self.stats.in_flight.fetch_sub(1, Ordering::Relaxed);
+            res?;
+            self.stats.writes.fetch_add(1, Ordering::Relaxed);
+            self.stats
+                .write_merges
+                .fetch_add(count as u64 - 1, Ordering::Relaxed);
+            self.stats
+                .write_sectors
+                .fetch_add(count as u64, Ordering::Relaxed);
+
+            for block_id in start..start + count as u64 {
+                inner.cache.set_clean(block_id);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Adds a block device to the block layer.
+pub(crate) fn register(dev: AxBlockDevice) -> Arc<BlockDevice> {
+    let mut devices = DEVICES.lock();
+    let name = format!("{}{}", dev.device_name(), devices.len());
+    let dev = Arc::new(BlockDevice::new(name, dev));
+    devices.push(dev.clone());
+    dev
+}
+
+/// Returns all block devices in the block layer.
+pub fn devices() -> Vec<Arc<BlockDevice>> {
+    DEVICES.lock().clone()
+}
+
+/// Writes back the dirty blocks of all block devices.
+///
+/// All devices are flushed even if some of them fail, and the first error is
+/// returned.
+pub fn sync() -> DevResult {
+    let mut result = Ok(());
+    for dev in devices() {
+        if let Err(e) = dev.flush() {
+            warn!("failed to flush block device {}: {:?}", dev.name(), e);
+            result = result.and(Err(e));
+        }
+    }
+    result
+}
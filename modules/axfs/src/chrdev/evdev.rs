@@ -0,0 +1,431 @@
+//! `/dev/input/eventN`: the evdev interface of VirtIO input devices.
+//!
+//! Every opened file has its own queue of events, and receives all events of
+//! the device after it is opened. Events are read as `struct input_event` of
+//! Linux, with the time when the kernel takes them from the device.
+
+use alloc::collections::VecDeque;
+use alloc::format;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use axdriver::{AxInputDevice, InputEvent, ABS_CNT};
+use axerrno::{AxError, AxResult};
+use axsync::Mutex;
+
+use super::{makedev, CharDevNode};
+use crate::api::{FileIO, FileIOType, Kstat, OpenFlags, FIOCLEX, FIONBIO};
+
+/// The major device number of input devices in Linux.
+const INPUT_MAJOR: u32 = 13;
+/// The first minor number of evdev devices.
+const EVDEV_MINOR_BASE: u32 = 64;
+/// Events that are not read yet are dropped beyond this.
+const EVDEV_BUF_EVENTS: usize = 256;
+const EV_VERSION: i32 = 0x010001;
+
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const EV_ABS: u16 = 0x03;
+const EV_LED: u16 = 0x11;
+const EV_SND: u16 = 0x12;
+const SYN_DROPPED: u16 = 3;
+const KEY_CNT: usize = 0x300;
+
+// `nr` of the evdev `ioctl`s, whose type is `'E'`
+const EVIOCGVERSION: usize = 0x01;
+const EVIOCGID: usize = 0x02;
+const EVIOCGNAME: usize = 0x06;
+const EVIOCGPHYS: usize = 0x07;
+const EVIOCGUNIQ: usize = 0x08;
+const EVIOCGPROP: usize = 0x09;
+const EVIOCGKEY: usize = 0x18;
+const EVIOCGLED: usize = 0x19;
+const EVIOCGSND: usize = 0x1a;
+const EVIOCGSW: usize = 0x1b;
+const EVIOCGBIT: usize = 0x20;
+const EVIOCGABS: usize = 0x40;
+const EVIOCGRAB: usize = 0x90;
+const EVIOCSCLOCKID: usize = 0xa0;
+
+const IOC_READ: usize = 2;
+
+const S_IFCHR: u32 = 0o020000;
+
+/// `struct input_event` of Linux.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct RawEvent {
+    tv_sec: i64,
+    tv_usec: i64,
+    event_type: u16,
+    code: u16,
+    value: i32,
+}
+
+const RAW_EVENT_LEN: usize = core::mem::size_of::<RawEvent>();
+
+/// `struct input_id` of Linux.
+#[repr(C)]
+struct InputId {
+    bustype: u16,
+    vendor: u16,
+    product: u16,
+    version: u16,
+}
+
+/// `struct input_absinfo` of Linux.
+#[repr(C)]
+struct InputAbsInfo {
+    value: i32,
+    minimum: i32,
+    maximum: i32,
+    fuzz: i32,
+    flat: i32,
+    resolution: i32,
+}
+
+/// The event queue of an opened file.
+struct Client {
+    events: Mutex<VecDeque<RawEvent>>,
+}
+
+impl Client {
+    fn push(&self, event: RawEvent) {
+        let mut events = self.events.lock();
+        if events.len() >= EVDEV_BUF_EVENTS {
+            // like Linux, drop all pending events and tell the reader
+            events.clear();
+            events.push_back(RawEvent {
+                event_type: EV_SYN,
+                code: SYN_DROPPED,
+                value: 0,
+                ..event
+            });
+        }
+        events.push_back(event);
+    }
+}
+
+/// An input device, shared by all files that open it.
+struct Evdev {
+    dev: Mutex<AxInputDevice>,
+    index: u32,
+    clients: Mutex<Vec<Weak<Client>>>,
+    /// The bitmap of the keys that are pressed.
+    key_state: Mutex<[u8; KEY_CNT / 8]>,
+    /// The last values of the absolute axes.
+    abs_values: Mutex<[i32; ABS_CNT]>,
+}
+
+impl Evdev {
+    /// Takes the events from the device, and passes them to all clients.
+    fn pump(&self) {
+        let mut dev = self.dev.lock();
+        loop {
+            let event = match dev.pop_event() {
+                Ok(Some(event)) => event,
+                Ok(None) => break,
+                Err(e) => {
+                    warn!("evdev: failed to receive event: {:?}", e);
+                    break;
+                }
+            };
+            self.dispatch(event);
+        }
+    }
+
+    fn dispatch(&self, event: InputEvent) {
+        let value = event.value as i32;
+        let code = event.code as usize;
+        match event.event_type {
+            EV_KEY if code < KEY_CNT => {
+                let mut keys = self.key_state.lock();
+                if value != 0 {
+                    keys[code / 8] |= 1 << (code % 8);
+                } else {
+                    keys[code / 8] &= !(1 << (code % 8));
+                }
+            }
+            EV_ABS if code < ABS_CNT => self.abs_values.lock()[code] = value,
+            _ => {}
+        }
+        let now = axhal::time::current_time();
+        let raw = RawEvent {
+            tv_sec: now.as_secs() as i64,
+            tv_usec: now.subsec_micros() as i64,
+            event_type: event.event_type,
+            code: event.code,
+            value,
+        };
+        let mut clients = self.clients.lock();
+        clients.retain(|client| match client.upgrade() {
+            Some(client) => {
+                client.push(raw);
+                true
+            }
+            None => false,
+        });
+    }
+
+    /// Blocks until the device sends events.
+    fn wait_for_events(&self, client: &Client) {
+        #[cfg(feature = "irq-io")]
+        {
+            let waiter = self.dev.lock().waiter();
+            if let Some(waiter) = waiter {
+                waiter.wait_until(|| {
+                    self.pump();
+                    !client.events.lock().is_empty()
+                });
+                return;
+            }
+        }
+        let _ = client;
+        axtask::yield_now();
+    }
+}
+
+/// Copies `src` to the user buffer of `size` bytes, returns the number of
+/// bytes copied.
+///
+/// The whole buffer is checked by `ioctl(2)` before, with the size encoded in
+/// the request.
+fn copy_to_user(data: usize, size: usize, src: &[u8]) -> isize {
+    let len = size.min(src.len());
+    unsafe { core::slice::from_raw_parts_mut(data as *mut u8, len) }.copy_from_slice(&src[..len]);
+    len as isize
+}
+
+/// Copies a NUL-terminated string to the user buffer of `size` bytes.
+fn copy_str_to_user(data: usize, size: usize, s: &str) -> isize {
+    let mut buf = Vec::with_capacity(s.len() + 1);
+    buf.extend_from_slice(s.as_bytes());
+    buf.push(0);
+    copy_to_user(data, size, &buf)
+}
+
+/// An opened `/dev/input/eventN`.
+struct EvdevFile {
+    evdev: Arc<Evdev>,
+    client: Arc<Client>,
+    flags: Mutex<OpenFlags>,
+}
+
+impl EvdevFile {
+    /// Handles the `ioctl`s of type `'E'`.
+    fn evdev_ioctl(&self, dir: usize, nr: usize, size: usize, data: usize) -> AxResult<isize> {
+        let evdev = &self.evdev;
+        match nr {
+            EVIOCGVERSION => {
+                unsafe { *(data as *mut i32) = EV_VERSION };
+                Ok(0)
+            }
+            EVIOCGID => {
+                let ids = evdev.dev.lock().ids();
+                unsafe {
+                    *(data as *mut InputId) = InputId {
+                        bustype: ids.bustype,
+                        vendor: ids.vendor,
+                        product: ids.product,
+                        version: ids.version,
+                    }
+                };
+                Ok(0)
+            }
+            EVIOCGNAME => Ok(copy_str_to_user(data, size, evdev.dev.lock().name())),
+            EVIOCGPHYS => {
+                let phys = format!("virtio{}/input0", evdev.index);
+                Ok(copy_str_to_user(data, size, &phys))
+            }
+            EVIOCGUNIQ => Ok(copy_str_to_user(data, size, evdev.dev.lock().serial())),
+            EVIOCGPROP => Ok(copy_to_user(data, size, evdev.dev.lock().prop_bits())),
+            EVIOCGKEY => Ok(copy_to_user(data, size, &*evdev.key_state.lock())),
+            // LEDs, sounds and switches are not reported by the device
+            EVIOCGLED | EVIOCGSND | EVIOCGSW => {
+                unsafe { core::ptr::write_bytes(data as *mut u8, 0, size) };
+                Ok(size as isize)
+            }
+            _ if (EVIOCGBIT..EVIOCGBIT + axdriver::EV_CNT).contains(&nr) => {
+                let dev = evdev.dev.lock();
+                Ok(copy_to_user(
+                    data,
+                    size,
+                    dev.ev_bits((nr - EVIOCGBIT) as u8),
+                ))
+            }
+            _ if dir == IOC_READ && (EVIOCGABS..EVIOCGABS + ABS_CNT).contains(&nr) => {
+                let code = nr - EVIOCGABS;
+                let info = evdev
+                    .dev
+                    .lock()
+                    .abs_info(code as u8)
+                    .ok_or(AxError::InvalidInput)?;
+                unsafe {
+                    *(data as *mut InputAbsInfo) = InputAbsInfo {
+                        value: evdev.abs_values.lock()[code],
+                        minimum: info.min as i32,
+                        maximum: info.max as i32,
+                        fuzz: info.fuzz as i32,
+                        flat: info.flat as i32,
+                        resolution: info.res as i32,
+                    }
+                };
+                Ok(0)
+            }
+            // there is no other reader to exclude, and the events always use
+            // the same clock
+            EVIOCGRAB | EVIOCSCLOCKID => Ok(0),
+            _ => Err(AxError::Unsupported),
+        }
+    }
+}
+
+impl FileIO for EvdevFile {
+    fn read(&self, buf: &mut [u8]) -> AxResult<usize> {
+        if buf.len() < RAW_EVENT_LEN {
+            return Err(AxError::InvalidInput);
+        }
+        loop {
+            self.evdev.pump();
+            let mut events = self.client.events.lock();
+            let count = events.len().min(buf.len() / RAW_EVENT_LEN);
+            if count > 0 {
+                for (chunk, event) in buf
+                    .chunks_exact_mut(RAW_EVENT_LEN)
+                    .zip(events.drain(..count))
+                {
+                    unsafe { (chunk.as_mut_ptr() as *mut RawEvent).write_unaligned(event) };
+                }
+                return Ok(count * RAW_EVENT_LEN);
+            }
+            drop(events);
+            if self.flags.lock().contains(OpenFlags::NON_BLOCK) {
+                return Err(AxError::WouldBlock);
+            }
+            self.evdev.wait_for_events(&self.client);
+        }
+    }
+
+    /// Only the LED and sound events are sent to the device, others are
+    /// ignored.
+    fn write(&self, buf: &[u8]) -> AxResult<usize> {
+        if buf.len() < RAW_EVENT_LEN {
+            return Err(AxError::InvalidInput);
+        }
+        let chunks = buf.chunks_exact(RAW_EVENT_LEN);
+        for chunk in chunks.clone() {
+            let event = unsafe { (chunk.as_ptr() as *const RawEvent).read_unaligned() };
+            if matches!(event.event_type, EV_LED | EV_SND) {
+                self.evdev
+                    .dev
+                    .lock()
+                    .send_status(InputEvent {
+                        event_type: event.event_type,
+                        code: event.code,
+                        value: event.value as u32,
+                    })
+                    .map_err(|_| AxError::Io)?;
+            }
+        }
+        Ok(chunks.len() * RAW_EVENT_LEN)
+    }
+
+    fn flush(&self) -> AxResult {
+        Ok(())
+    }
+
+    fn readable(&self) -> bool {
+        self.flags.lock().readable()
+    }
+
+    fn writable(&self) -> bool {
+        self.flags.lock().writable()
+    }
+
+    fn executable(&self) -> bool {
+        false
+    }
+
+    fn get_type(&self) -> FileIOType {
+        FileIOType::Other
+    }
+
+    fn get_stat(&self) -> AxResult<Kstat> {
+        Ok(Kstat {
+            st_mode: S_IFCHR | 0o660,
+            st_nlink: 1,
+            st_rdev: makedev(INPUT_MAJOR, EVDEV_MINOR_BASE + self.evdev.index),
+            st_blksize: 4096,
+            ..Default::default()
+        })
+    }
+
+    fn set_status(&self, flags: OpenFlags) -> bool {
+        *self.flags.lock() = flags;
+        true
+    }
+
+    fn get_status(&self) -> OpenFlags {
+        *self.flags.lock()
+    }
+
+    fn set_close_on_exec(&self, is_set: bool) -> bool {
+        self.flags.lock().set(OpenFlags::CLOEXEC, is_set);
+        true
+    }
+
+    fn ready_to_read(&self) -> bool {
+        self.evdev.pump();
+        !self.client.events.lock().is_empty()
+    }
+
+    fn ready_to_write(&self) -> bool {
+        true
+    }
+
+    fn ioctl(&self, request: usize, data: usize) -> AxResult<isize> {
+        match request {
+            FIOCLEX => Ok(0),
+            FIONBIO => {
+                let nonblock = unsafe { *(data as *const u32) } != 0;
+                self.flags.lock().set(OpenFlags::NON_BLOCK, nonblock);
+                Ok(0)
+            }
+            _ if (request >> 8) & 0xff == b'E' as usize => {
+                let dir = request >> 30;
+                let size = (request >> 16) & 0x3fff;
+                self.evdev_ioctl(dir, request & 0xff, size, data)
+            }
+            _ => Err(AxError::Unsupported),
+        }
+    }
+}
+
+/// Adds a `/dev/input/eventN` node for every VirtIO input device, numbered in
+/// the order of devices.
+pub fn init_inputs(devs: Vec<AxInputDevice>) {
+    for (index, dev) in (0..).zip(devs) {
+        info!("input device {}: {:?}", index, dev.name());
+        let evdev = Arc::new(Evdev {
+            dev: Mutex::new(dev),
+            index,
+            clients: Mutex::new(Vec::new()),
+            key_state: Mutex::new([0; KEY_CNT / 8]),
+            abs_values: Mutex::new([0; ABS_CNT]),
+        });
+        let node = CharDevNode::new(INPUT_MAJOR, EVDEV_MINOR_BASE + index, move |flags| {
+            let client = Arc::new(Client {
+                events: Mutex::new(VecDeque::new()),
+            });
+            evdev.clients.lock().push(Arc::downgrade(&client));
+            Ok(Arc::new(EvdevFile {
+                evdev: evdev.clone(),
+                client,
+                flags: Mutex::new(flags),
+            }))
+        });
+        if let Err(e) = super::register(&format!("input/event{}", index), node) {
+            warn!("failed to register /dev/input/event{}: {:?}", index, e);
+        }
+    }
+}
@@ -0,0 +1,318 @@
+//! `/dev/fb0`: the Linux framebuffer interface of the main display.
+//!
+//! The framebuffer of the display can be read and written as a file, or
+//! mapped in user space with `mmap`. The display only shows the new contents
+//! after a flush, which is done by `fsync`, `FBIOPAN_DISPLAY` or the
+//! [`FBIOFLUSH`] `ioctl`.
+//!
+//! The resolution is fixed by the device, so `FBIOPUT_VSCREENINFO` only
+//! accepts the current mode.
+
+use alloc::sync::Arc;
+use axerrno::{AxError, AxResult};
+use axio::SeekFrom;
+use axsync::Mutex;
+
+use super::{makedev, CharDevNode};
+use crate::api::{FileIO, FileIOType, Kstat, OpenFlags, FIOCLEX};
+
+/// The major device number of framebuffers in Linux.
+const FB_MAJOR: u32 = 29;
+
+const FBIOGET_VSCREENINFO: usize = 0x4600;
+const FBIOPUT_VSCREENINFO: usize = 0x4601;
+const FBIOGET_FSCREENINFO: usize = 0x4602;
+const FBIOPAN_DISPLAY: usize = 0x4606;
+const FBIOBLANK: usize = 0x4611;
+/// Shows the contents of the framebuffer on the display. It is not in Linux,
+/// whose framebuffers need no flush.
+pub const FBIOFLUSH: usize = 0x4630;
+
+const FB_TYPE_PACKED_PIXELS: u32 = 0;
+const FB_VISUAL_TRUECOLOR: u32 = 2;
+const BITS_PER_PIXEL: u32 = 32;
+
+const S_IFCHR: u32 = 0o020000;
+const PAGE_SIZE: usize = 0x1000;
+
+/// `struct fb_bitfield` of Linux.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct FbBitfield {
+    offset: u32,
+    length: u32,
+    msb_right: u32,
+}
+
+/// `struct fb_var_screeninfo` of Linux.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct FbVarScreeninfo {
+    xres: u32,
+    yres: u32,
+    xres_virtual: u32,
+    yres_virtual: u32,
+    xoffset: u32,
+    yoffset: u32,
+    bits_per_pixel: u32,
+    grayscale: u32,
+    red: FbBitfield,
+    green: FbBitfield,
+    blue: FbBitfield,
+    transp: FbBitfield,
+    nonstd: u32,
+    activate: u32,
+    height: u32,
+    width: u32,
+    accel_flags: u32,
+    pixclock: u32,
+    left_margin: u32,
+    right_margin: u32,
+    upper_margin: u32,
+    lower_margin: u32,
+    hsync_len: u32,
+    vsync_len: u32,
+    sync: u32,
+    vmode: u32,
+    rotate: u32,
+    colorspace: u32,
+    reserved: [u32; 4],
+}
+
+/// `struct fb_fix_screeninfo` of Linux.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct FbFixScreeninfo {
+    id: [u8; 16],
+    smem_start: usize,
+    smem_len: u32,
+    fb_type: u32,
+    type_aux: u32,
+    visual: u32,
+    xpanstep: u16,
+    ypanstep: u16,
+    ywrapstep: u16,
+    line_length: u32,
+    mmio_start: usize,
+    mmio_len: u32,
+    accel: u32,
+    capabilities: u16,
+    reserved: [u16; 2],
+}
+
+/// The framebuffer of the main display, in the `BGRX8888` format.
+struct Framebuffer {
+    vaddr: usize,
+    paddr: usize,
+    size: usize,
+    width: u32,
+    height: u32,
+}
+
+impl Framebuffer {
+    fn get() -> Self {
+        let info = axdisplay::framebuffer_info();
+        Self {
+            vaddr: info.fb_base_vaddr,
+            paddr: axhal::mem::virt_to_phys(info.fb_base_vaddr.into()).as_usize(),
+            size: info.fb_size,
+            width: info.width,
+            height: info.height,
+        }
+    }
+
+    fn var_screeninfo(&self) -> FbVarScreeninfo {
+        let bitfield = |offset| FbBitfield {
+            offset,
+            length: 8,
+            msb_right: 0,
+        };
+        FbVarScreeninfo {
+            xres: self.width,
+            yres: self.height,
+            xres_virtual: self.width,
+            yres_virtual: self.height,
+            bits_per_pixel: BITS_PER_PIXEL,
+            red: bitfield(16),
+            green: bitfield(8),
+            blue: bitfield(0),
+            // the size is unknown
+            height: u32::MAX,
+            width: u32::MAX,
+            ..Default::default()
+        }
+    }
+
+    fn fix_screeninfo(&self) -> FbFixScreeninfo {
+        let mut id = [0; 16];
+        id[..10].copy_from_slice(b"virtio_gpu");
+        FbFixScreeninfo {
+            id,
+            smem_start: self.paddr,
+            smem_len: self.size as u32,
+            fb_type: FB_TYPE_PACKED_PIXELS,
+            visual: FB_VISUAL_TRUECOLOR,
+            line_length: self.width * BITS_PER_PIXEL / 8,
+            ..Default::default()
+        }
+    }
+}
+
+/// An opened `/dev/fb0`.
+struct FbFile {
+    fb: Framebuffer,
+    pos: Mutex<usize>,
+    flags: Mutex<OpenFlags>,
+}
+
+impl FileIO for FbFile {
+    fn read(&self, buf: &mut [u8]) -> AxResult<usize> {
+        let mut pos = self.pos.lock();
+        let len = buf.len().min(self.fb.size.saturating_sub(*pos));
+        unsafe {
+            let src = (self.fb.vaddr + *pos) as *const u8;
+            core::ptr::copy_nonoverlapping(src, buf.as_mut_ptr(), len);
+        }
+        *pos += len;
+        Ok(len)
+    }
+
+    fn write(&self, buf: &[u8]) -> AxResult<usize> {
+        let mut pos = self.pos.lock();
+        if !buf.is_empty() && *pos >= self.fb.size {
+            return Err(AxError::StorageFull);
+        }
+        let len = buf.len().min(self.fb.size - *pos);
+        unsafe {
+            let dst = (self.fb.vaddr + *pos) as *mut u8;
+            core::ptr::copy_nonoverlapping(buf.as_ptr(), dst, len);
+        }
+        *pos += len;
+        Ok(len)
+    }
+
+    fn flush(&self) -> AxResult {
+        axdisplay::framebuffer_flush();
+        Ok(())
+    }
+
+    fn seek(&self, pos: SeekFrom) -> AxResult<u64> {
+        let mut cur = self.pos.lock();
+        let new = match pos {
+            SeekFrom::Start(off) => Some(off as i64),
+            SeekFrom::Current(off) => (*cur as i64).checked_add(off),
+            SeekFrom::End(off) => (self.fb.size as i64).checked_add(off),
+        };
+        match new {
+            Some(new) if new >= 0 => {
+                *cur = new as usize;
+                Ok(new as u64)
+            }
+            _ => Err(AxError::InvalidInput),
+        }
+    }
+
+    fn readable(&self) -> bool {
+        self.flags.lock().readable()
+    }
+
+    fn writable(&self) -> bool {
+        self.flags.lock().writable()
+    }
+
+    fn executable(&self) -> bool {
+        false
+    }
+
+    fn get_type(&self) -> FileIOType {
+        FileIOType::Other
+    }
+
+    fn get_stat(&self) -> AxResult<Kstat> {
+        Ok(Kstat {
+            st_mode: S_IFCHR | 0o660,
+            st_nlink: 1,
+            st_rdev: makedev(FB_MAJOR, 0),
+            st_blksize: 4096,
+            ..Default::default()
+        })
+    }
+
+    fn set_status(&self, flags: OpenFlags) -> bool {
+        *self.flags.lock() = flags;
+        true
+    }
+
+    fn get_status(&self) -> OpenFlags {
+        *self.flags.lock()
+    }
+
+    fn set_close_on_exec(&self, is_set: bool) -> bool {
+        self.flags.lock().set(OpenFlags::CLOEXEC, is_set);
+        true
+    }
+
+    fn ready_to_read(&self) -> bool {
+        true
+    }
+
+    fn ready_to_write(&self) -> bool {
+        true
+    }
+
+    fn ioctl(&self, request: usize, data: usize) -> AxResult<isize> {
+        match request {
+            FBIOGET_VSCREENINFO => {
+                unsafe { *(data as *mut FbVarScreeninfo) = self.fb.var_screeninfo() };
+                Ok(0)
+            }
+            FBIOPUT_VSCREENINFO => {
+                let var = unsafe { &mut *(data as *mut FbVarScreeninfo) };
+                let cur = self.fb.var_screeninfo();
+                if var.xres > cur.xres
+                    || var.yres > cur.yres
+                    || (var.bits_per_pixel != 0 && var.bits_per_pixel != BITS_PER_PIXEL)
+                {
+                    return Err(AxError::InvalidInput);
+                }
+                // report the mode that is actually used
+                *var = cur;
+                Ok(0)
+            }
+            FBIOGET_FSCREENINFO => {
+                unsafe { *(data as *mut FbFixScreeninfo) = self.fb.fix_screeninfo() };
+                Ok(0)
+            }
+            FBIOPAN_DISPLAY | FBIOFLUSH => {
+                axdisplay::framebuffer_flush();
+                Ok(0)
+            }
+            FBIOBLANK | FIOCLEX => Ok(0),
+            _ => Err(AxError::Unsupported),
+        }
+    }
+
+    fn mmap_phys(&self, offset: usize, len: usize) -> AxResult<usize> {
+        // the framebuffer is allocated in whole pages
+        let size = (self.fb.size + PAGE_SIZE - 1) & !(PAGE_SIZE - 1);
+        match offset.checked_add(len) {
+            Some(end) if offset % PAGE_SIZE == 0 && end <= size => Ok(self.fb.paddr + offset),
+            _ => Err(AxError::InvalidInput),
+        }
+    }
+}
+
+/// Adds `/dev/fb0` for the main display. It must be called after the display
+/// is initialized.
+pub fn init_framebuffer() {
+    let node = CharDevNode::new(FB_MAJOR, 0, |flags| {
+        Ok(Arc::new(FbFile {
+            fb: Framebuffer::get(),
+            pos: Mutex::new(0),
+            flags: Mutex::new(flags),
+        }))
+    });
+    if let Err(e) = super::register("fb0", node) {
+        warn!("failed to register /dev/fb0: {:?}", e);
+    }
+}
@@ -0,0 +1,240 @@
+//! `/dev/hvcN`: the ports of VirtIO console devices.
+//!
+//! The ports are raw byte streams. The termios settings are kept for the
+//! programs that query or change them, but no line discipline is applied.
+
+use alloc::format;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use axdriver::prelude::DevError;
+use axdriver::AxConsoleDevice;
+use axerrno::{AxError, AxResult};
+use axsync::Mutex;
+
+use super::{makedev, CharDevNode};
+use crate::api::{
+    ConsoleWinSize, FileIO, FileIOType, Kstat, OpenFlags, FIOCLEX, FIONBIO, TCGETS, TIOCGPGRP,
+    TIOCGWINSZ, TIOCSPGRP,
+};
+
+/// The major device number of `hvc` in Linux.
+const HVC_MAJOR: u32 = 229;
+
+const TCSETS: usize = 0x5402;
+const TCSETSW: usize = 0x5403;
+const TCSETSF: usize = 0x5404;
+
+const S_IFCHR: u32 = 0o020000;
+
+/// `struct termios` of Linux.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Termios {
+    c_iflag: u32,
+    c_oflag: u32,
+    c_cflag: u32,
+    c_lflag: u32,
+    c_line: u8,
+    c_cc: [u8; 19],
+}
+
+impl Termios {
+    /// `B38400 | CS8 | CREAD` without any processing, and `VMIN` is 1.
+    const RAW: Self = {
+        let mut c_cc = [0; 19];
+        c_cc[6] = 1;
+        Self {
+            c_iflag: 0,
+            c_oflag: 0,
+            c_cflag: 0o277,
+            c_lflag: 0,
+            c_line: 0,
+            c_cc,
+        }
+    };
+}
+
+fn as_ax_err(e: DevError) -> AxError {
+    match e {
+        DevError::Again => AxError::WouldBlock,
+        DevError::InvalidParam => AxError::InvalidInput,
+        DevError::NoMemory => AxError::NoMemory,
+        DevError::Unsupported => AxError::Unsupported,
+        _ => AxError::Io,
+    }
+}
+
+/// A port of a console device, shared by all files that open it.
+struct HvcPort {
+    dev: Arc<Mutex<AxConsoleDevice>>,
+    id: usize,
+    rdev: u64,
+    termios: Mutex<Termios>,
+}
+
+impl HvcPort {
+    fn can_read(&self) -> bool {
+        self.dev.lock().can_read(self.id).unwrap_or(false)
+    }
+
+    /// Blocks until the port receives data.
+    fn wait_for_input(&self) {
+        #[cfg(feature = "irq-io")]
+        {
+            let waiter = self.dev.lock().waiter();
+            if let Some(waiter) = waiter {
+                waiter.wait_until(|| self.can_read());
+                return;
+            }
+        }
+        axtask::yield_now();
+    }
+}
+
+/// An opened `/dev/hvcN`.
+struct HvcFile {
+    port: Arc<HvcPort>,
+    flags: Mutex<OpenFlags>,
+}
+
+impl FileIO for HvcFile {
+    fn read(&self, buf: &mut [u8]) -> AxResult<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            let len = self
+                .port
+                .dev
+                .lock()
+                .read(self.port.id, buf)
+                .map_err(as_ax_err)?;
+            if len > 0 {
+                return Ok(len);
+            }
+            if self.flags.lock().contains(OpenFlags::NON_BLOCK) {
+                return Err(AxError::WouldBlock);
+            }
+            self.port.wait_for_input();
+        }
+    }
+
+    fn write(&self, buf: &[u8]) -> AxResult<usize> {
+        self.port
+            .dev
+            .lock()
+            .write(self.port.id, buf)
+            .map_err(as_ax_err)
+    }
+
+    fn flush(&self) -> AxResult {
+        Ok(())
+    }
+
+    fn readable(&self) -> bool {
+        self.flags.lock().readable()
+    }
+
+    fn writable(&self) -> bool {
+        self.flags.lock().writable()
+    }
+
+    fn executable(&self) -> bool {
+        false
+    }
+
+    fn get_type(&self) -> FileIOType {
+        FileIOType::Other
+    }
+
+    fn get_stat(&self) -> AxResult<Kstat> {
+        Ok(Kstat {
+            st_mode: S_IFCHR | 0o620,
+            st_nlink: 1,
+            st_rdev: self.port.rdev,
+            st_blksize: 1024,
+            ..Default::default()
+        })
+    }
+
+    fn set_status(&self, flags: OpenFlags) -> bool {
+        *self.flags.lock() = flags;
+        true
+    }
+
+    fn get_status(&self) -> OpenFlags {
+        *self.flags.lock()
+    }
+
+    fn set_close_on_exec(&self, is_set: bool) -> bool {
+        self.flags.lock().set(OpenFlags::CLOEXEC, is_set);
+        true
+    }
+
+    fn ready_to_read(&self) -> bool {
+        self.port.can_read()
+    }
+
+    fn ready_to_write(&self) -> bool {
+        true
+    }
+
+    fn ioctl(&self, request: usize, data: usize) -> AxResult<isize> {
+        match request {
+            TIOCGWINSZ => {
+                let (cols, rows) = self.port.dev.lock().size();
+                unsafe { *(data as *mut ConsoleWinSize) = ConsoleWinSize::new(rows, cols) };
+                Ok(0)
+            }
+            TCGETS => {
+                unsafe { *(data as *mut Termios) = *self.port.termios.lock() };
+                Ok(0)
+            }
+            TCSETS | TCSETSW | TCSETSF => {
+                *self.port.termios.lock() = unsafe { *(data as *const Termios) };
+                Ok(0)
+            }
+            TIOCGPGRP => {
+                unsafe { *(data as *mut u32) = 0 };
+                Ok(0)
+            }
+            TIOCSPGRP | FIOCLEX => Ok(0),
+            FIONBIO => {
+                let nonblock = unsafe { *(data as *const u32) } != 0;
+                self.flags.lock().set(OpenFlags::NON_BLOCK, nonblock);
+                Ok(0)
+            }
+            _ => Err(AxError::Unsupported),
+        }
+    }
+}
+
+/// Adds a `/dev/hvcN` node for every port of the VirtIO console devices,
+/// numbered in the order of devices and ports.
+///
+/// Ports that are added by the host later are not registered.
+pub fn init_consoles(devs: Vec<AxConsoleDevice>) {
+    let mut index = 0;
+    for dev in devs {
+        let ids: Vec<usize> = dev.port_ids().collect();
+        let dev = Arc::new(Mutex::new(dev));
+        for id in ids {
+            let port = Arc::new(HvcPort {
+                dev: dev.clone(),
+                id,
+                rdev: makedev(HVC_MAJOR, index),
+                termios: Mutex::new(Termios::RAW),
+            });
+            let node = CharDevNode::new(HVC_MAJOR, index, move |flags| {
+                Ok(Arc::new(HvcFile {
+                    port: port.clone(),
+                    flags: Mutex::new(flags),
+                }))
+            });
+            if let Err(e) = super::register(&format!("hvc{}", index), node) {
+                warn!("failed to register /dev/hvc{}: {:?}", index, e);
+            }
+            index += 1;
+        }
+    }
+}
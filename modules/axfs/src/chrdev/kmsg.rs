@@ -0,0 +1,224 @@
+//! `/dev/kmsg`, the kernel log buffer of [`axlog`].
+//!
+//! Each read returns one record in the format of Linux, i.e.,
+//! `<level>,<seq>,<usec>,-,caller=T<tid>;<text>`, followed by a ` CPU=<id>`
+//! line. Every opened file has its own reading position, which starts from the
+//! oldest record. Data written to it is logged as a new record, with an
+//! optional `<level>` prefix.
+//!
+//! Readers waiting for new records, including `syslog(2)`, sleep on a wait
+//! queue that is notified whenever a record is saved.
+
+use alloc::string::String;
+use alloc::sync::Arc;
+use axerrno::{AxError, AxResult};
+use axio::SeekFrom;
+use axlog::kmsg::{self, KmsgCursor, LOG_LINE_MAX};
+use axsync::Mutex;
+use axtask::WaitQueue;
+use core::fmt::Write;
+use core::time::Duration;
+
+use super::{makedev, CharDevNode};
+use crate::api::{FileIO, FileIOType, Kstat, OpenFlags};
+
+/// The major device number of memory devices in Linux.
+const MEM_MAJOR: u32 = 1;
+const KMSG_MINOR: u32 = 11;
+
+/// The level of written messages without a `<level>` prefix, i.e., `KERN_WARNING`.
+const DEFAULT_MESSAGE_LOGLEVEL: u8 = 4;
+
+const S_IFCHR: u32 = 0o020000;
+
+/// How often the waiting readers check the buffer for records that did not
+/// wake them up, see [`notify_readers`].
+const RECHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The tasks waiting for new records.
+static READERS: WaitQueue = WaitQueue::new();
+
+/// Wakes up the readers after a record is saved.
+///
+/// Records logged with IRQs disabled may come from the scheduler or a wait
+/// queue while it holds its locks, so they do not wake up the readers, which
+/// find them at the next check instead.
+fn notify_readers() {
+    if axhal::arch::irqs_enabled() {
+        READERS.notify_all();
+    }
+}
+
+/// Whether there are records at `cursor`.
+fn has_record(cursor: &KmsgCursor) -> bool {
+    let mut cursor = *cursor;
+    kmsg::read_record(&mut cursor, &mut []).is_some()
+}
+
+/// Blocks until there are records at `cursor` or `interrupted` returns true.
+pub fn wait_for_record(cursor: &KmsgCursor, interrupted: impl Fn() -> bool) {
+    while !interrupted() && !has_record(cursor) {
+        READERS.wait_timeout_until(RECHECK_INTERVAL, || interrupted() || has_record(cursor));
+    }
+}
+
+/// An opened `/dev/kmsg`.
+struct KmsgFile {
+    cursor: Mutex<KmsgCursor>,
+    flags: Mutex<OpenFlags>,
+}
+
+/// Formats the record at `cursor` without moving it, returns [`None`] if there
+/// are no more records.
+fn format_record(cursor: &KmsgCursor) -> Option<(String, KmsgCursor)> {
+    let mut cursor = *cursor;
+    let mut text = [0; LOG_LINE_MAX];
+    let record = kmsg::read_record(&mut cursor, &mut text)?;
+    let mut line = String::new();
+    let _ = write!(
+        line,
+        "{},{},{},-",
+        record.syslog_level(),
+        record.seq,
+        record.time.as_micros()
+    );
+    match (record.task_id, record.cpu_id) {
+        (Some(tid), _) => {
+            let _ = write!(line, ",caller=T{}", tid);
+        }
+        (None, Some(cpu_id)) => {
+            let _ = write!(line, ",caller=C{}", cpu_id);
+        }
+        (None, None) => {}
+    }
+    line.push(';');
+    // escape the non-printable characters like Linux
+    for c in record.text.chars() {
+        if c < ' ' || c == '\x7f' {
+            let _ = write!(line, "\\x{:02x}", c as u32);
+        } else {
+            line.push(c);
+        }
+    }
+    line.push('\n');
+    if let Some(cpu_id) = record.cpu_id {
+        let _ = writeln!(line, " CPU={}", cpu_id);
+    }
+    Some((line, cursor))
+}
+
+impl FileIO for KmsgFile {
+    fn read(&self, buf: &mut [u8]) -> AxResult<usize> {
+        loop {
+            let mut cursor = self.cursor.lock();
+            if let Some((line, next)) = format_record(&cursor) {
+                // a record is never split across reads
+                if line.len() > buf.len() {
+                    return Err(AxError::InvalidInput);
+                }
+                buf[..line.len()].copy_from_slice(line.as_bytes());
+                *cursor = next;
+                return Ok(line.len());
+            }
+            let pos = *cursor;
+            drop(cursor);
+            if self.flags.lock().contains(OpenFlags::NON_BLOCK) {
+                return Err(AxError::WouldBlock);
+            }
+            wait_for_record(&pos, || false);
+        }
+    }
+
+    fn write(&self, buf: &[u8]) -> AxResult<usize> {
+        let text = core::str::from_utf8(buf).map_err(|_| AxError::InvalidInput)?;
+        let mut level = DEFAULT_MESSAGE_LOGLEVEL;
+        let mut message = text;
+        if let Some(rest) = text.strip_prefix('<') {
+            if let Some((prefix, rest)) = rest.split_once('>') {
+                if let Ok(prefix) = prefix.parse::<u32>() {
+                    // the facility in the higher bits is ignored
+                    level = (prefix & 7) as u8;
+                    message = rest;
+                }
+            }
+        }
+        kmsg::log_user(
+            kmsg::level_from_syslog(level),
+            message.trim_end_matches('\n'),
+        );
+        Ok(buf.len())
+    }
+
+    fn flush(&self) -> AxResult {
+        Ok(())
+    }
+
+    fn seek(&self, pos: SeekFrom) -> AxResult<u64> {
+        let cursor = match pos {
+            SeekFrom::Start(0) => KmsgCursor::oldest(),
+            SeekFrom::End(0) => KmsgCursor::end(),
+            _ => return Err(AxError::InvalidInput),
+        };
+        *self.cursor.lock() = cursor;
+        Ok(0)
+    }
+
+    fn readable(&self) -> bool {
+        self.flags.lock().readable()
+    }
+
+    fn writable(&self) -> bool {
+        self.flags.lock().writable()
+    }
+
+    fn executable(&self) -> bool {
+        false
+    }
+
+    fn get_type(&self) -> FileIOType {
+        FileIOType::Other
+    }
+
+    fn get_stat(&self) -> AxResult<Kstat> {
+        Ok(Kstat {
+            st_mode: S_IFCHR | 0o644,
+            st_nlink: 1,
+            st_rdev: makedev(MEM_MAJOR, KMSG_MINOR),
+            st_blksize: 4096,
+            ..Default::default()
+        })
+    }
+
+    fn set_status(&self, flags: OpenFlags) -> bool {
+        *self.flags.lock() = flags;
+        true
+    }
+
+    fn get_status(&self) -> OpenFlags {
+        *self.flags.lock()
+    }
+
+    fn set_close_on_exec(&self, is_set: bool) -> bool {
+        self.flags.lock().set(OpenFlags::CLOEXEC, is_set);
+        true
+    }
+
+    fn ready_to_read(&self) -> bool {
+        has_record(&self.cursor.lock())
+    }
+
+    fn ready_to_write(&self) -> bool {
+        true
+    }
+}
+
+/// Returns the node of `/dev/kmsg`, and lets new records wake up its readers.
+pub(crate) fn kmsg() -> CharDevNode {
+    kmsg::set_record_hook(notify_readers);
+    CharDevNode::new(MEM_MAJOR, KMSG_MINOR, |flags| {
+        Ok(Arc::new(KmsgFile {
+            cursor: Mutex::new(KmsgCursor::oldest()),
+            flags: Mutex::new(flags),
+        }))
+    })
+}
@@ -0,0 +1,131 @@
+//! Character devices in `/dev` that are backed by device drivers.
+//!
+//! A [`CharDevNode`] only represents the device in the directory tree. Opening
+//! it with [`open`] creates a [`FileIO`] object of the device, which handles
+//! the reads, writes, `ioctl`s and polling instead of the regular file
+//! operations.
+
+#[cfg(feature = "virtio-input")]
+mod evdev;
+#[cfg(feature = "fbdev")]
+mod fb;
+#[cfg(feature = "virtio-console")]
+mod hvc;
+#[cfg(feature = "monolithic")]
+pub(crate) mod kmsg;
+pub(crate) mod random;
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::Arc;
+use axerrno::{AxError, AxResult};
+use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeType, VfsResult};
+use axsync::Mutex;
+
+use crate::api::{FileIO, OpenFlags};
+use crate::fs::devfs::DirNode;
+
+#[cfg(feature = "virtio-input")]
+pub use self::evdev::init_inputs;
+#[cfg(feature = "fbdev")]
+pub use self::fb::{init_framebuffer, FBIOFLUSH};
+#[cfg(feature = "virtio-console")]
+pub use self::hvc::init_consoles;
+#[cfg(feature = "monolithic")]
+pub use self::kmsg::wait_for_record;
+
+/// Encodes a device number in the same way as `makedev` of Linux.
+pub fn makedev(major: u32, minor: u32) -> u64 {
+    let (major, minor) = (major as u64, minor as u64);
+    ((major & 0xffff_f000) << 32)
+        | ((major & 0xfff) << 8)
+        | ((minor & 0xffff_ff00) << 12)
+        | (minor & 0xff)
+}
+
+/// Creates the [`FileIO`] object of a device when it is opened.
+pub type OpenFn = dyn Fn(OpenFlags) -> AxResult<Arc<dyn FileIO>> + Send + Sync;
+
+/// A character device node in devfs.
+pub struct CharDevNode {
+    major: u32,
+    minor: u32,
+    open: Box<OpenFn>,
+}
+
+impl CharDevNode {
+    /// Creates a device node with the given device numbers, `open` is called
+    /// every time the device is opened.
+    pub fn new(
+        major: u32,
+        minor: u32,
+        open: impl Fn(OpenFlags) -> AxResult<Arc<dyn FileIO>> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            major,
+            minor,
+            open: Box::new(open),
+        }
+    }
+
+    /// Returns the device number.
+    pub fn rdev(&self) -> u64 {
+        makedev(self.major, self.minor)
+    }
+
+    /// Opens the device.
+    pub fn open(&self, flags: OpenFlags) -> AxResult<Arc<dyn FileIO>> {
+        (self.open)(flags)
+    }
+}
+
+impl VfsNodeOps for CharDevNode {
+    axfs_vfs::impl_vfs_non_dir_default! {}
+
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(VfsNodeAttr::new(
+            VfsNodePerm::from_bits_truncate(0o666),
+            VfsNodeType::CharDevice,
+            0,
+            0,
+        ))
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+}
+
+/// Subdirectories of devfs created by [`register`].
+static SUBDIRS: Mutex<BTreeMap<String, Arc<DirNode>>> = Mutex::new(BTreeMap::new());
+
+/// Adds a device node at `path` relative to `/dev`, e.g., `hvc0` or
+/// `input/event0`. Only one level of subdirectories is supported.
+///
+/// It must be called after the filesystems are initialized.
+pub fn register(path: &str, node: CharDevNode) -> AxResult {
+    let devfs = crate::mounts::DEVFS.try_get().ok_or(AxError::BadState)?;
+    // devfs only takes static names, and devices are never removed
+    let leak = |name: &str| -> &'static str { Box::leak(String::from(name).into_boxed_str()) };
+    match path.split_once('/') {
+        None => devfs.add(leak(path), Arc::new(node)),
+        Some((dir, name)) if !name.contains('/') => {
+            let mut subdirs = SUBDIRS.lock();
+            let dir = subdirs
+                .entry(String::from(dir))
+                .or_insert_with(|| devfs.mkdir(leak(dir)));
+            dir.add(leak(name), Arc::new(node));
+        }
+        _ => return Err(AxError::InvalidInput),
+    }
+    info!("registered character device /dev/{}", path);
+    Ok(())
+}
+
+/// Opens `path` if it is a character device node, returns [`None`] if not.
+pub fn open(path: &str, flags: OpenFlags) -> Option<AxResult<Arc<dyn FileIO>>> {
+    let node = crate::root::lookup(None, path).ok()?;
+    let dev = node.as_any().downcast_ref::<CharDevNode>()?;
+    Some(dev.open(flags))
+}
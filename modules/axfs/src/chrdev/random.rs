@@ -0,0 +1,141 @@
+//! `/dev/random` and `/dev/urandom`, backed by the kernel entropy pool.
+//!
+//! Both read from the same CRNG. `/dev/random` blocks until the CRNG is
+//! seeded, while `/dev/urandom` never blocks. Data written to either of them
+//! is mixed into the pool without being credited as entropy.
+
+use alloc::sync::Arc;
+use axerrno::{AxError, AxResult};
+use axsync::Mutex;
+
+use super::{makedev, CharDevNode};
+use crate::api::{FileIO, FileIOType, Kstat, OpenFlags, FIOCLEX, FIONBIO};
+
+/// The major device number of memory devices in Linux.
+const MEM_MAJOR: u32 = 1;
+const RANDOM_MINOR: u32 = 8;
+const URANDOM_MINOR: u32 = 9;
+
+/// `RNDGETENTCNT`: gets the entropy count.
+const RNDGETENTCNT: usize = 0x8004_5200;
+
+const S_IFCHR: u32 = 0o020000;
+
+/// An opened `/dev/random` or `/dev/urandom`.
+struct RandomFile {
+    minor: u32,
+    flags: Mutex<OpenFlags>,
+}
+
+impl RandomFile {
+    fn blocking(&self) -> bool {
+        self.minor == RANDOM_MINOR
+    }
+}
+
+impl FileIO for RandomFile {
+    fn read(&self, buf: &mut [u8]) -> AxResult<usize> {
+        if self.blocking() && !axrand::is_ready() {
+            if self.flags.lock().contains(OpenFlags::NON_BLOCK) {
+                return Err(AxError::WouldBlock);
+            }
+            axrand::wait_for_ready(|| false);
+        }
+        axrand::fill_bytes(buf);
+        Ok(buf.len())
+    }
+
+    fn write(&self, buf: &[u8]) -> AxResult<usize> {
+        axrand::add_device_randomness(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&self) -> AxResult {
+        Ok(())
+    }
+
+    fn readable(&self) -> bool {
+        self.flags.lock().readable()
+    }
+
+    fn writable(&self) -> bool {
+        self.flags.lock().writable()
+    }
+
+    fn executable(&self) -> bool {
+        false
+    }
+
+    fn get_type(&self) -> FileIOType {
+        FileIOType::Other
+    }
+
+    fn get_stat(&self) -> AxResult<Kstat> {
+        Ok(Kstat {
+            st_mode: S_IFCHR | 0o666,
+            st_nlink: 1,
+            st_rdev: makedev(MEM_MAJOR, self.minor),
+            st_blksize: 4096,
+            ..Default::default()
+        })
+    }
+
+    fn set_status(&self, flags: OpenFlags) -> bool {
+        *self.flags.lock() = flags;
+        true
+    }
+
+    fn get_status(&self) -> OpenFlags {
+        *self.flags.lock()
+    }
+
+    fn set_close_on_exec(&self, is_set: bool) -> bool {
+        self.flags.lock().set(OpenFlags::CLOEXEC, is_set);
+        true
+    }
+
+    fn ready_to_read(&self) -> bool {
+        !self.blocking() || axrand::is_ready()
+    }
+
+    fn ready_to_write(&self) -> bool {
+        true
+    }
+
+    fn ioctl(&self, request: usize, data: usize) -> AxResult<isize> {
+        match request {
+            RNDGETENTCNT => {
+                // the pool is either seeded with enough entropy or not
+                let count = if axrand::is_ready() { 256 } else { 0 };
+                unsafe { *(data as *mut i32) = count };
+                Ok(0)
+            }
+            FIOCLEX => Ok(0),
+            FIONBIO => {
+                let nonblock = unsafe { *(data as *const u32) } != 0;
+                self.flags.lock().set(OpenFlags::NON_BLOCK, nonblock);
+                Ok(0)
+            }
+            _ => Err(AxError::Unsupported),
+        }
+    }
+}
+
+fn node(minor: u32) -> CharDevNode {
+    CharDevNode::new(MEM_MAJOR, minor, move |flags| {
+        Ok(Arc::new(RandomFile {
+            minor,
+            flags: Mutex::new(flags),
+        }))
+    })
+}
+
+/// Returns the node of `/dev/random`.
+pub(crate) fn random() -> CharDevNode {
+    node(RANDOM_MINOR)
+}
+
+/// Returns the node of `/dev/urandom`.
+pub(crate) fn urandom() -> CharDevNode {
+    node(URANDOM_MINOR)
+}
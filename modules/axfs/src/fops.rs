@@ -1,11 +1,14 @@
 //! Low-level filesystem operations.
 
+use alloc::string::String;
 use axerrno::{ax_err, ax_err_type, AxResult};
 use axfs_vfs::{VfsError, VfsNodeRef};
 use axio::SeekFrom;
 use capability::{Cap, WithCap};
 use core::fmt;
 
+use crate::notify::{self, FsEventMask};
+
 #[cfg(feature = "myfs")]
 pub use crate::dev::Disk;
 #[cfg(feature = "myfs")]
@@ -26,6 +29,8 @@ pub struct File {
     node: WithCap<VfsNodeRef>,
     is_append: bool,
     offset: u64,
+    /// The absolute path used to report events, `None` if unknown.
+    path: Option<String>,
 }
 
 /// An opened directory object, with open permissions and a cursor for
@@ -120,6 +125,7 @@ impl File {
         }
 
         let node_option = crate::root::lookup(dir, path);
+        let mut created = false;
         let node = if opts.create || opts.create_new {
             match node_option {
                 Ok(node) => {
@@ -130,7 +136,10 @@ impl File {
                     node
                 }
                 // not exists, create new
-                Err(VfsError::NotFound) => crate::root::create_file(dir, path)?,
+                Err(VfsError::NotFound) => {
+                    created = true;
+                    crate::root::create_file(dir, path)?
+                }
                 Err(e) => return Err(e),
             }
         } else {
@@ -151,11 +160,24 @@ impl File {
         if opts.truncate {
             node.truncate(0)?;
         }
-        Ok(Self {
+        let file = Self {
             node: WithCap::new(node, access_cap),
             is_append: opts.append,
             offset: 0,
-        })
+            path: notify::event_path(dir, path),
+        };
+        if created {
+            file.notify(FsEventMask::CREATE);
+        } else if opts.truncate {
+            file.notify(FsEventMask::MODIFY);
+        }
+        Ok(file)
+    }
+
+    fn notify(&self, mask: FsEventMask) {
+        if let Some(path) = self.path.as_deref() {
+            notify::notify(None, path, mask);
+        }
     }
 
     /// Opens a file at the path relative to the current directory. Returns a
@@ -167,6 +189,7 @@ impl File {
     /// Truncates the file to the specified size.
     pub fn truncate(&self, size: u64) -> AxResult {
         self.node.access(Cap::WRITE)?.truncate(size)?;
+        self.notify(FsEventMask::MODIFY);
         Ok(())
     }
 
@@ -202,6 +225,7 @@ impl File {
         };
         let write_len = node.write_at(self.offset, buf)?;
         self.offset += write_len as u64;
+        self.notify(FsEventMask::MODIFY);
         Ok(write_len)
     }
 
@@ -212,6 +236,7 @@ impl File {
     pub fn write_at(&self, offset: u64, buf: &[u8]) -> AxResult<usize> {
         let node = self.node.access(Cap::WRITE)?;
         let write_len = node.write_at(offset, buf)?;
+        self.notify(FsEventMask::MODIFY);
         Ok(write_len)
     }
 
@@ -314,22 +339,34 @@ impl Directory {
 
     /// Creates an empty file at the path relative to this directory.
     pub fn create_file(&self, path: &str) -> AxResult<VfsNodeRef> {
-        crate::root::create_file(self.access_at(path)?, path)
+        let dir = self.access_at(path)?;
+        let node = crate::root::create_file(dir, path)?;
+        notify::notify(dir, path, FsEventMask::CREATE);
+        Ok(node)
     }
 
     /// Creates an empty directory at the path relative to this directory.
     pub fn create_dir(&self, path: &str) -> AxResult {
-        crate::root::create_dir(self.access_at(path)?, path)
+        let dir = self.access_at(path)?;
+        crate::root::create_dir(dir, path)?;
+        notify::notify(dir, path, FsEventMask::CREATE | FsEventMask::ISDIR);
+        Ok(())
     }
 
     /// Removes a file at the path relative to this directory.
     pub fn remove_file(&self, path: &str) -> AxResult {
-        crate::root::remove_file(self.access_at(path)?, path)
+        let dir = self.access_at(path)?;
+        crate::root::remove_file(dir, path)?;
+        notify::notify(dir, path, FsEventMask::DELETE);
+        Ok(())
     }
 
     /// Removes a directory at the path relative to this directory.
     pub fn remove_dir(&self, path: &str) -> AxResult {
-        crate::root::remove_dir(self.access_at(path)?, path)
+        let dir = self.access_at(path)?;
+        crate::root::remove_dir(dir, path)?;
+        notify::notify(dir, path, FsEventMask::DELETE | FsEventMask::ISDIR);
+        Ok(())
     }
 
     /// Reads directory entries starts from the current position into the
@@ -351,13 +388,20 @@ impl Directory {
     ///
     /// This only works then the new path is in the same mounted fs.
     pub fn rename(&self, old: &str, new: &str) -> AxResult {
-        crate::root::rename(old, new)
+        let is_dir = crate::root::lookup(None, old)
+            .is_ok_and(|node| node.get_attr().is_ok_and(|attr| attr.is_dir()));
+        crate::root::rename(old, new)?;
+        notify::notify_rename(old, new, is_dir);
+        Ok(())
     }
 }
 
 impl Drop for File {
     fn drop(&mut self) {
         unsafe { self.node.access_unchecked().release().ok() };
+        if self.node.can_access(Cap::WRITE) {
+            self.notify(FsEventMask::CLOSE_WRITE);
+        }
     }
 }
 
@@ -1,5 +1,8 @@
 use crate::dev::Disk;
+use crate::link::{downcast_link_ops, VfsLinkOps};
+use alloc::string::String;
 use alloc::sync::Arc;
+use alloc::vec;
 use another_ext4::{
     Block, BlockDevice, ErrCode as Ext4ErrorCode, Ext4, Ext4Error, FileType as EXt4FileType,
     InodeMode as Ext4InodeMode, BLOCK_SIZE as EXT4_BLOCK_SIZE, EXT4_ROOT_INO,
@@ -64,6 +67,13 @@ impl VfsOps for Ext4FileSystem {
     }
 }
 
+impl Ext4FileSystem {
+    /// Finds the link operations of a node of the filesystem.
+    pub fn link_ops(node: &VfsNodeRef) -> Option<&dyn VfsLinkOps> {
+        downcast_link_ops::<Ext4VirtInode>(node)
+    }
+}
+
 pub struct Ext4VirtInode {
     id: u32,
     fs: Arc<Ext4>,
@@ -194,6 +204,50 @@ impl VfsNodeOps for Ext4VirtInode {
     }
 }
 
+impl VfsLinkOps for Ext4VirtInode {
+    fn ino(&self) -> VfsResult<u64> {
+        Ok(self.id as u64)
+    }
+
+    fn nlink(&self) -> VfsResult<u64> {
+        self.fs
+            .getattr(self.id)
+            .map(|attr| attr.links as u64)
+            .map_err(map_error)
+    }
+
+    fn readlink(&self) -> VfsResult<String> {
+        let attr = self.fs.getattr(self.id).map_err(map_error)?;
+        if !matches!(attr.ftype, EXt4FileType::SymLink) {
+            return Err(VfsError::InvalidInput);
+        }
+        let mut buf = vec![0u8; attr.size as usize];
+        let len = self.fs.read(self.id, 0, &mut buf).map_err(map_error)?;
+        buf.truncate(len);
+        String::from_utf8(buf).map_err(|_| VfsError::InvalidData)
+    }
+
+    fn symlink(&self, name: &str, target: &str) -> VfsResult {
+        let mode = Ext4InodeMode::from_type_and_perm(EXt4FileType::SymLink, Ext4InodeMode::ALL_RWX);
+        let id = self
+            .fs
+            .generic_create(self.id, name, mode)
+            .map_err(map_error)?;
+        self.fs
+            .write(id, 0, target.as_bytes())
+            .map(|_| ())
+            .map_err(map_error)
+    }
+
+    fn link(&self, name: &str, node: &VfsNodeRef) -> VfsResult {
+        let child = node
+            .as_any()
+            .downcast_ref::<Ext4VirtInode>()
+            .ok_or(VfsError::PermissionDenied)?;
+        self.fs.link(child.id, self.id, name).map_err(map_error)
+    }
+}
+
 fn map_error(ext4_err: Ext4Error) -> VfsError {
     log::warn!("Ext4 error: {:?}", ext4_err);
     match ext4_err.code() {
@@ -1,4 +1,6 @@
 use crate::dev::Disk;
+use crate::link::{downcast_link_ops, VfsLinkOps};
+use alloc::string::String;
 use alloc::sync::Arc;
 use alloc::vec;
 use alloc::vec::*;
@@ -106,6 +108,16 @@ impl VfsOps for Ext4FileSystem {
     }
 }
 
+impl Ext4FileSystem {
+    /// Finds the link operations of a node of the filesystem.
+    pub fn link_ops(node: &VfsNodeRef) -> Option<&dyn VfsLinkOps> {
+        downcast_link_ops::<Ext4FileWrapper>(node)
+    }
+}
+
+/// The longest target of a symbolic link that is stored in the inode itself.
+const FAST_SYMLINK_MAX: usize = 60;
+
 pub struct Ext4FileWrapper {
     ext4_file: Mutex<Ext4File>,
     ext4: Arc<Ext4>,
@@ -303,6 +315,47 @@ impl VfsNodeOps for Ext4FileWrapper {
     }
 }
 
+/// Only reads the links already on the disk: the `ext4_rs` crate cannot add a
+/// directory entry for an existing inode or write a symlink inode, so creating
+/// links falls back to the default `PermissionDenied`.
+impl VfsLinkOps for Ext4FileWrapper {
+    fn ino(&self) -> VfsResult<u64> {
+        Ok(self.ext4_file.lock().inode as u64)
+    }
+
+    fn nlink(&self) -> VfsResult<u64> {
+        let inode = self.ext4_file.lock().inode;
+        let inode_ref = Ext4InodeRef::get_inode_ref(Arc::downgrade(&self.ext4), inode);
+        Ok(inode_ref.inner.inode.links_count as u64)
+    }
+
+    fn readlink(&self) -> VfsResult<String> {
+        if self.get_attr()?.file_type() != VfsNodeType::SymLink {
+            return Err(VfsError::InvalidInput);
+        }
+        let mut ext4_file = self.ext4_file.lock();
+        let size = ext4_file.fsize as usize;
+        let mut buf = vec![0u8; size];
+        if size < FAST_SYMLINK_MAX {
+            // the target is stored in the block pointers
+            let inode_ref =
+                Ext4InodeRef::get_inode_ref(Arc::downgrade(&self.ext4), ext4_file.inode);
+            let blocks = inode_ref.inner.inode.block;
+            for (i, byte) in buf.iter_mut().enumerate() {
+                *byte = blocks[i / 4].to_le_bytes()[i % 4];
+            }
+        } else {
+            ext4_file.fpos = 0;
+            let mut read_cnt = 0;
+            self.ext4
+                .ext4_file_read(&mut ext4_file, &mut buf, size, &mut read_cnt)
+                .map_err(|_| VfsError::Io)?;
+            buf.truncate(read_cnt);
+        }
+        String::from_utf8(buf).map_err(|_| VfsError::InvalidData)
+    }
+}
+
 fn map_dir_imode(imode: u16) -> (VfsNodeType, VfsNodePerm) {
     let diren_type = imode;
     let type_code = ext4_rs::DirEntryType::from_bits(diren_type as u8).unwrap();
@@ -1,15 +1,19 @@
 use crate::alloc::string::String;
+use alloc::ffi::CString;
 use alloc::sync::Arc;
+use alloc::vec;
 use axerrno::AxError;
 use axfs_vfs::{VfsDirEntry, VfsError, VfsNodePerm, VfsResult};
 use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType, VfsOps};
 use axsync::Mutex;
 use lwext4_rust::bindings::{
-    O_CREAT, O_RDONLY, O_RDWR, O_TRUNC, O_WRONLY, SEEK_CUR, SEEK_END, SEEK_SET,
+    ext4_flink, ext4_fsymlink, ext4_inode, ext4_raw_inode_fill, ext4_readlink, O_CREAT, O_RDONLY,
+    O_RDWR, O_TRUNC, O_WRONLY, SEEK_CUR, SEEK_END, SEEK_SET,
 };
 use lwext4_rust::{Ext4BlockWrapper, Ext4File, InodeTypes, KernelDevOp};
 
 use crate::dev::Disk;
+use crate::link::{downcast_link_ops, VfsLinkOps};
 pub const BLOCK_SIZE: usize = 512;
 
 #[allow(dead_code)]
@@ -52,6 +56,21 @@ impl VfsOps for Ext4FileSystem {
     }
 }
 
+impl Ext4FileSystem {
+    /// Finds the link operations of a node of the filesystem.
+    pub fn link_ops(node: &VfsNodeRef) -> Option<&dyn VfsLinkOps> {
+        downcast_link_ops::<FileWrapper>(node)
+    }
+}
+
+fn c_path(path: &str) -> VfsResult<CString> {
+    CString::new(path).map_err(|_| VfsError::InvalidInput)
+}
+
+fn map_errno(e: i32) -> VfsError {
+    <i32 as TryInto<AxError>>::try_into(e).unwrap_or(AxError::Io)
+}
+
 pub struct FileWrapper(Mutex<Ext4File>);
 
 unsafe impl Send for FileWrapper {}
@@ -65,6 +84,21 @@ impl FileWrapper {
         Self(Mutex::new(Ext4File::new(path, types)))
     }
 
+    fn path(&self) -> String {
+        String::from(self.0.lock().get_path().to_str().unwrap())
+    }
+
+    /// Reads the inode number and the on-disk inode of the node.
+    fn raw_inode(&self) -> VfsResult<(u32, ext4_inode)> {
+        let path = c_path(&self.path())?;
+        let mut ino = 0;
+        let mut inode: ext4_inode = unsafe { core::mem::zeroed() };
+        match unsafe { ext4_raw_inode_fill(path.as_ptr(), &mut ino, &mut inode) } {
+            0 => Ok((ino, inode)),
+            e => Err(map_errno(e)),
+        }
+    }
+
     fn path_deal_with(&self, path: &str) -> String {
         if path.starts_with('/') {
             warn!("path_deal_with: {}", path);
@@ -263,6 +297,9 @@ impl VfsNodeOps for FileWrapper {
         } else if file.check_inode_exist(fpath, InodeTypes::EXT4_DE_REG_FILE) {
             debug!("lookup new FILE FileWrapper");
             Ok(Arc::new(Self::new(fpath, InodeTypes::EXT4_DE_REG_FILE)))
+        } else if file.check_inode_exist(fpath, InodeTypes::EXT4_DE_SYMLINK) {
+            debug!("lookup new SYMLINK FileWrapper");
+            Ok(Arc::new(Self::new(fpath, InodeTypes::EXT4_DE_SYMLINK)))
         } else {
             Err(VfsError::NotFound)
         }
@@ -323,6 +360,63 @@ impl VfsNodeOps for FileWrapper {
     }
 }
 
+/// Links are created with the path-based lwext4 API, which sees the
+/// filesystem mounted at `/`.
+impl VfsLinkOps for FileWrapper {
+    fn ino(&self) -> VfsResult<u64> {
+        self.raw_inode().map(|(ino, _)| ino as u64)
+    }
+
+    fn nlink(&self) -> VfsResult<u64> {
+        self.raw_inode().map(|(_, inode)| inode.links_count as u64)
+    }
+
+    fn readlink(&self) -> VfsResult<String> {
+        if self.0.lock().get_type() != InodeTypes::EXT4_DE_SYMLINK {
+            return Err(VfsError::InvalidInput);
+        }
+        let path = c_path(&self.path())?;
+        let mut buf = vec![0u8; 4096]; // PATH_MAX
+        let mut len = 0;
+        match unsafe {
+            ext4_readlink(
+                path.as_ptr(),
+                buf.as_mut_ptr() as _,
+                buf.len() as _,
+                &mut len,
+            )
+        } {
+            0 => {
+                buf.truncate(len as usize);
+                String::from_utf8(buf).map_err(|_| VfsError::InvalidData)
+            }
+            e => Err(map_errno(e)),
+        }
+    }
+
+    fn symlink(&self, name: &str, target: &str) -> VfsResult {
+        let target = CString::new(target).map_err(|_| VfsError::InvalidInput)?;
+        let path = c_path(&self.path_deal_with(name))?;
+        match unsafe { ext4_fsymlink(target.as_ptr(), path.as_ptr()) } {
+            0 => Ok(()),
+            e => Err(map_errno(e)),
+        }
+    }
+
+    fn link(&self, name: &str, node: &VfsNodeRef) -> VfsResult {
+        let old = node
+            .as_any()
+            .downcast_ref::<FileWrapper>()
+            .ok_or(VfsError::PermissionDenied)?;
+        let old_path = c_path(&old.path())?;
+        let new_path = c_path(&self.path_deal_with(name))?;
+        match unsafe { ext4_flink(old_path.as_ptr(), new_path.as_ptr()) } {
+            0 => Ok(()),
+            e => Err(map_errno(e)),
+        }
+    }
+}
+
 impl Drop for FileWrapper {
     fn drop(&mut self) {
         let mut file = self.0.lock();
@@ -369,7 +463,8 @@ impl KernelDevOp for Disk {
         debug!("WRITE rt len={}", write_len);
         Ok(write_len)
     }
-    fn flush(_dev: &mut Self::DevType) -> Result<usize, i32> {
+    fn flush(dev: &mut Self::DevType) -> Result<usize, i32> {
+        dev.flush().map_err(|_| -1)?;
         Ok(0)
     }
     fn seek(dev: &mut Disk, off: i64, whence: i32) -> Result<i64, i32> {
@@ -19,8 +19,14 @@ cfg_if::cfg_if! {
     }
 }
 
+#[cfg(feature = "virtio-9p")]
+pub mod p9;
+
 #[cfg(feature = "devfs")]
 pub use axfs_devfs as devfs;
 
 #[cfg(feature = "ramfs")]
 pub use axfs_ramfs as ramfs;
+
+#[cfg(feature = "ramfs")]
+pub mod tmpfs;
@@ -0,0 +1,698 @@
+//! 9P2000.L client over VirtIO 9P transports, for sharing host directories.
+//!
+//! Each share is exported by a VirtIO 9P device (e.g., `-virtfs` of QEMU) and
+//! named by its mount tag. A share is mounted at `/mnt/<tag>` at boot, and can
+//! also be mounted elsewhere by mount(2) with the filesystem type `9p`:
+//!
+//! ```text
+//! mount -t 9p -o trans=virtio,version=9p2000.L <tag> <dir>
+//! ```
+//!
+//! Every node holds a fid walked from the root of the share, which is clunked
+//! when the node is dropped. Reads, writes and directory listings use another
+//! fid opened on the first access, since an opened fid can not be walked.
+
+mod proto;
+
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use axdriver::Ax9pDevice;
+use axerrno::{ax_err, AxError, AxResult};
+use axfs_vfs::{VfsDirEntry, VfsError, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeRef};
+use axfs_vfs::{VfsNodeType, VfsOps, VfsResult};
+use axsync::Mutex;
+
+use self::proto::*;
+use crate::link::{downcast_link_ops, VfsLinkOps};
+
+/// The default maximum message size.
+const DEFAULT_MSIZE: u32 = 64 * 1024;
+/// The fid of the root of a share, other fids are allocated from 1.
+const ROOT_FID: u32 = 0;
+/// Enough for the R-messages without data, e.g., `Rlerror` and `Rgetattr`.
+const SMALL_RESP: usize = 256;
+
+/// A directory exported by a VirtIO 9P device.
+struct Share {
+    tag: String,
+    dev: Mutex<Ax9pDevice>,
+    /// Only one session can be established with a device at a time.
+    mounted: AtomicBool,
+}
+
+static SHARES: Mutex<Vec<Arc<Share>>> = Mutex::new(Vec::new());
+
+impl Share {
+    /// Sends a T-message, and returns the body of the R-message of at most
+    /// `body_len` bytes.
+    fn rpc(&self, msg: Msg, body_len: usize) -> VfsResult<Vec<u8>> {
+        let req = msg.finish();
+        let mut resp = vec![0; 7 + body_len.max(SMALL_RESP)];
+        let len = self
+            .dev
+            .lock()
+            .request(&req, &mut resp)
+            .map_err(|_| VfsError::Io)?;
+        let mut r = Reader::new(&resp[..len]);
+        let size = r.u32()? as usize;
+        let ty = r.u8()?;
+        let _tag = r.u16()?;
+        if size < 7 || size > len {
+            return Err(VfsError::InvalidData);
+        }
+        if ty == RLERROR {
+            return Err(as_vfs_err(r.u32()?));
+        }
+        if ty != req[4] + 1 {
+            warn!("9p: unexpected R-message {} for T-message {}", ty, req[4]);
+            return Err(VfsError::InvalidData);
+        }
+        resp.truncate(size);
+        resp.drain(..7);
+        Ok(resp)
+    }
+}
+
+/// A session with a share.
+struct Client {
+    share: Arc<Share>,
+    msize: u32,
+    next_fid: AtomicU32,
+    free_fids: Mutex<Vec<u32>>,
+    /// Where the share is mounted, to make the destination of renames
+    /// relative to the root of the share.
+    mount_path: Mutex<String>,
+}
+
+impl Client {
+    /// Negotiates the protocol version and the message size, and attaches
+    /// the root of the share to [`ROOT_FID`].
+    fn connect(share: Arc<Share>, opts: &P9MountOptions) -> VfsResult<(Self, Qid)> {
+        let msg = Msg::new(TVERSION, NOTAG).u32(opts.msize).str(VERSION);
+        let resp = share.rpc(msg, SMALL_RESP)?;
+        let mut r = Reader::new(&resp);
+        let msize = r.u32()?.min(opts.msize);
+        let version = r.str()?;
+        if version != VERSION {
+            warn!("9p: server does not support {}: {}", VERSION, version);
+            return Err(VfsError::Unsupported);
+        }
+        if msize <= IOHDRSZ {
+            return Err(VfsError::InvalidData);
+        }
+
+        let msg = Msg::new(TATTACH, 0)
+            .u32(ROOT_FID)
+            .u32(NOFID)
+            .str("root")
+            .str(&opts.aname)
+            .u32(0);
+        let qid = Reader::new(&share.rpc(msg, SMALL_RESP)?).qid()?;
+        let client = Self {
+            share,
+            msize,
+            next_fid: AtomicU32::new(ROOT_FID + 1),
+            free_fids: Mutex::new(Vec::new()),
+            mount_path: Mutex::new(String::new()),
+        };
+        Ok((client, qid))
+    }
+
+    fn rpc(&self, msg: Msg, body_len: usize) -> VfsResult<Vec<u8>> {
+        self.share.rpc(msg, body_len)
+    }
+
+    /// The maximum size of the data in a read or write.
+    fn io_size(&self, iounit: u32) -> usize {
+        match iounit {
+            0 => (self.msize - IOHDRSZ) as usize,
+            n => n.min(self.msize - IOHDRSZ) as usize,
+        }
+    }
+
+    fn alloc_fid(&self) -> u32 {
+        self.free_fids
+            .lock()
+            .pop()
+            .unwrap_or_else(|| self.next_fid.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// Walks `names` from `fid` to a new fid, returns it with the qid of the
+    /// last name, or [`None`] if `names` is empty (i.e. `fid` is cloned).
+    fn walk(self: &Arc<Self>, fid: u32, names: &[&str]) -> VfsResult<(Fid, Option<Qid>)> {
+        let mut new: Option<Fid> = None;
+        let mut last = None;
+        let chunks: Vec<&[&str]> = if names.is_empty() {
+            vec![&[]]
+        } else {
+            names.chunks(MAXWELEM).collect()
+        };
+        for chunk in chunks {
+            let from = new.as_ref().map_or(fid, |new| new.id);
+            let newfid = new.as_ref().map_or_else(|| self.alloc_fid(), |new| new.id);
+            let mut msg = Msg::new(TWALK, 0)
+                .u32(from)
+                .u32(newfid)
+                .u16(chunk.len() as u16);
+            for name in chunk {
+                msg = msg.str(name);
+            }
+            let nwqid = self
+                .rpc(msg, SMALL_RESP + 13 * MAXWELEM)
+                .and_then(|resp| {
+                    let mut r = Reader::new(&resp);
+                    let nwqid = r.u16()? as usize;
+                    for _ in 0..nwqid {
+                        last = Some(r.qid()?);
+                    }
+                    Ok(nwqid)
+                })
+                .and_then(|nwqid| {
+                    // the new fid is not created if the walk is incomplete
+                    if nwqid < chunk.len() {
+                        Err(VfsError::NotFound)
+                    } else {
+                        Ok(nwqid)
+                    }
+                });
+            if let Err(e) = nwqid {
+                if new.is_none() {
+                    self.free_fids.lock().push(newfid);
+                }
+                return Err(e);
+            }
+            if new.is_none() {
+                new = Some(Fid {
+                    client: self.clone(),
+                    id: newfid,
+                });
+            }
+        }
+        Ok((new.unwrap(), last))
+    }
+
+    /// Walks to the parent directory of `path`, returns its fid and the last
+    /// name of `path`.
+    fn walk_parent<'a>(self: &Arc<Self>, fid: u32, path: &'a str) -> VfsResult<(Fid, &'a str)> {
+        let names = split_path(path);
+        let (name, parent) = names.split_last().ok_or(VfsError::InvalidInput)?;
+        if *name == ".." {
+            return Err(VfsError::InvalidInput);
+        }
+        let (dir, _) = self.walk(fid, parent)?;
+        Ok((dir, name))
+    }
+
+    /// Opens `fid` for I/O, returns the `iounit`.
+    fn lopen(&self, fid: u32, flags: u32) -> VfsResult<u32> {
+        let resp = self.rpc(Msg::new(TLOPEN, 0).u32(fid).u32(flags), SMALL_RESP)?;
+        let mut r = Reader::new(&resp);
+        r.qid()?;
+        r.u32()
+    }
+
+    fn getattr(&self, fid: u32) -> VfsResult<Attr> {
+        let msg = Msg::new(TGETATTR, 0).u32(fid).u64(GETATTR_BASIC);
+        Reader::new(&self.rpc(msg, SMALL_RESP)?).attr()
+    }
+
+    fn read(&self, fid: u32, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let msg = Msg::new(TREAD, 0)
+            .u32(fid)
+            .u64(offset)
+            .u32(buf.len() as u32);
+        let resp = self.rpc(msg, 4 + buf.len())?;
+        let mut r = Reader::new(&resp);
+        let count = r.u32()? as usize;
+        let data = r.bytes(count.min(buf.len()))?;
+        buf[..data.len()].copy_from_slice(data);
+        Ok(data.len())
+    }
+
+    fn write(&self, fid: u32, offset: u64, data: &[u8]) -> VfsResult<usize> {
+        let msg = Msg::new(TWRITE, 0)
+            .u32(fid)
+            .u64(offset)
+            .u32(data.len() as u32)
+            .data(data);
+        let resp = self.rpc(msg, SMALL_RESP)?;
+        Ok(Reader::new(&resp).u32()? as usize)
+    }
+
+    fn readdir(&self, fid: u32, offset: u64, count: usize) -> VfsResult<Vec<u8>> {
+        let msg = Msg::new(TREADDIR, 0).u32(fid).u64(offset).u32(count as u32);
+        let mut resp = self.rpc(msg, 4 + count)?;
+        let len = Reader::new(&resp).u32()? as usize;
+        if 4 + len > resp.len() {
+            return Err(VfsError::InvalidData);
+        }
+        resp.truncate(4 + len);
+        resp.drain(..4);
+        Ok(resp)
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        let _ = self.rpc(Msg::new(TCLUNK, 0).u32(ROOT_FID), SMALL_RESP);
+        self.share.mounted.store(false, Ordering::Release);
+    }
+}
+
+/// A fid that is clunked when dropped.
+struct Fid {
+    client: Arc<Client>,
+    id: u32,
+}
+
+impl Drop for Fid {
+    fn drop(&mut self) {
+        if let Err(e) = self
+            .client
+            .rpc(Msg::new(TCLUNK, 0).u32(self.id), SMALL_RESP)
+        {
+            warn!("9p: failed to clunk fid {}: {:?}", self.id, e);
+        }
+        self.client.free_fids.lock().push(self.id);
+    }
+}
+
+/// Splits `path` into names, skipping the empty ones and `.`.
+fn split_path(path: &str) -> Vec<&str> {
+    path.split('/')
+        .filter(|name| !name.is_empty() && *name != ".")
+        .collect()
+}
+
+/// The fid of a node that is opened for I/O.
+struct IoFid {
+    fid: Fid,
+    writable: bool,
+    iounit: u32,
+}
+
+/// A file, directory or symbolic link in a share.
+pub struct P9Node {
+    client: Arc<Client>,
+    /// The fid of the root is [`ROOT_FID`], which is clunked by the client.
+    fid: Option<Fid>,
+    qid: Qid,
+    io: Mutex<Option<IoFid>>,
+    /// The index and the server offset of the next entry, so that listing a
+    /// directory does not start over for every `read_dir`.
+    dir_pos: Mutex<(usize, u64)>,
+}
+
+impl P9Node {
+    fn new(client: Arc<Client>, fid: Option<Fid>, qid: Qid) -> Arc<Self> {
+        Arc::new(Self {
+            client,
+            fid,
+            qid,
+            io: Mutex::new(None),
+            dir_pos: Mutex::new((0, 0)),
+        })
+    }
+
+    fn fid(&self) -> u32 {
+        self.fid.as_ref().map_or(ROOT_FID, |fid| fid.id)
+    }
+
+    /// Calls `f` with the fid opened for I/O and the maximum size of a
+    /// request. The fid is opened on the first call, and reopened if it is
+    /// read-only but `write` is requested.
+    fn with_io<T>(&self, write: bool, f: impl FnOnce(u32, usize) -> VfsResult<T>) -> VfsResult<T> {
+        let mut io = self.io.lock();
+        if !io.as_ref().is_some_and(|io| io.writable || !write) {
+            let (fid, _) = self.client.walk(self.fid(), &[])?;
+            let modes: &[u32] = if self.qid.is_dir() {
+                &[O_RDONLY]
+            } else if write {
+                &[O_RDWR]
+            } else {
+                // read-only files can still be read
+                &[O_RDWR, O_RDONLY]
+            };
+            let mut opened = Err(VfsError::PermissionDenied);
+            for &mode in modes {
+                opened = self.client.lopen(fid.id, mode).map(|iounit| (mode, iounit));
+                if opened.is_ok() {
+                    break;
+                }
+            }
+            let (mode, iounit) = opened?;
+            *io = Some(IoFid {
+                fid,
+                writable: mode == O_RDWR,
+                iounit,
+            });
+        }
+        let io = io.as_ref().unwrap();
+        f(io.fid.id, self.client.io_size(io.iounit))
+    }
+}
+
+impl VfsNodeOps for P9Node {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let attr = self.client.getattr(self.fid())?;
+        Ok(VfsNodeAttr::new(
+            VfsNodePerm::from_bits_truncate((attr.mode & 0o777) as _),
+            attr.node_type(),
+            attr.size,
+            attr.blocks,
+        ))
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        if self.qid.is_dir() {
+            return ax_err!(IsADirectory);
+        }
+        self.with_io(false, |fid, max| {
+            let mut read = 0;
+            while read < buf.len() {
+                let count = (buf.len() - read).min(max);
+                let n =
+                    self.client
+                        .read(fid, offset + read as u64, &mut buf[read..read + count])?;
+                read += n;
+                if n < count {
+                    break; // end of file
+                }
+            }
+            Ok(read)
+        })
+    }
+
+    fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        if self.qid.is_dir() {
+            return ax_err!(IsADirectory);
+        }
+        self.with_io(true, |fid, max| {
+            let mut written = 0;
+            while written < buf.len() {
+                let count = (buf.len() - written).min(max);
+                let n = self.client.write(
+                    fid,
+                    offset + written as u64,
+                    &buf[written..written + count],
+                )?;
+                if n == 0 {
+                    break;
+                }
+                written += n;
+            }
+            Ok(written)
+        })
+    }
+
+    fn fsync(&self) -> VfsResult {
+        if let Some(io) = self.io.lock().as_ref() {
+            self.client
+                .rpc(Msg::new(TFSYNC, 0).u32(io.fid.id).u32(0), SMALL_RESP)?;
+        }
+        Ok(())
+    }
+
+    fn truncate(&self, size: u64) -> VfsResult {
+        if self.qid.is_dir() {
+            return ax_err!(IsADirectory);
+        }
+        let msg = Msg::new(TSETATTR, 0)
+            .u32(self.fid())
+            .u32(SETATTR_SIZE)
+            .u32(0) // mode
+            .u32(0) // uid
+            .u32(0) // gid
+            .u64(size)
+            .u64(0) // atime
+            .u64(0)
+            .u64(0) // mtime
+            .u64(0);
+        self.client.rpc(msg, SMALL_RESP)?;
+        Ok(())
+    }
+
+    fn parent(&self) -> Option<VfsNodeRef> {
+        let (fid, qid) = self.client.walk(self.fid(), &[".."]).ok()?;
+        Some(P9Node::new(self.client.clone(), Some(fid), qid?))
+    }
+
+    fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
+        debug!("lookup at 9p: {}", path);
+        let names = split_path(path);
+        if names.is_empty() {
+            return Ok(self);
+        }
+        if !self.qid.is_dir() {
+            return ax_err!(NotADirectory);
+        }
+        let (fid, qid) = self.client.walk(self.fid(), &names)?;
+        Ok(P9Node::new(self.client.clone(), Some(fid), qid.unwrap()))
+    }
+
+    fn create(&self, path: &str, ty: VfsNodeType) -> VfsResult {
+        debug!("create {:?} at 9p: {}", ty, path);
+        if split_path(path).is_empty() {
+            return Ok(());
+        }
+        let (dir, name) = self.client.walk_parent(self.fid(), path)?;
+        match ty {
+            VfsNodeType::File => {
+                // the fid of the directory becomes the opened file, which is
+                // clunked when dropped
+                let msg = Msg::new(TLCREATE, 0)
+                    .u32(dir.id)
+                    .str(name)
+                    .u32(O_RDWR | O_CREAT)
+                    .u32(0o644)
+                    .u32(0);
+                self.client.rpc(msg, SMALL_RESP)?;
+            }
+            VfsNodeType::Dir => {
+                let msg = Msg::new(TMKDIR, 0).u32(dir.id).str(name).u32(0o755).u32(0);
+                self.client.rpc(msg, SMALL_RESP)?;
+            }
+            _ => return ax_err!(Unsupported),
+        }
+        Ok(())
+    }
+
+    fn remove(&self, path: &str) -> VfsResult {
+        debug!("remove at 9p: {}", path);
+        let (dir, name) = self.client.walk_parent(self.fid(), path)?;
+        let unlink = |flags| {
+            let msg = Msg::new(TUNLINKAT, 0).u32(dir.id).str(name).u32(flags);
+            self.client.rpc(msg, SMALL_RESP).map(|_| ())
+        };
+        match unlink(0) {
+            Err(AxError::IsADirectory) => unlink(AT_REMOVEDIR),
+            res => res,
+        }
+    }
+
+    fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
+        if !self.qid.is_dir() {
+            return ax_err!(NotADirectory);
+        }
+        let mut pos = self.dir_pos.lock();
+        if start_idx < pos.0 {
+            *pos = (0, 0); // start over
+        }
+        self.with_io(false, |fid, max| {
+            let mut filled = 0;
+            while filled < dirents.len() {
+                let data = self.client.readdir(fid, pos.1, max)?;
+                if data.is_empty() {
+                    break; // end of directory
+                }
+                let mut r = Reader::new(&data);
+                while !r.is_empty() && filled < dirents.len() {
+                    let entry = r.dir_entry()?;
+                    if pos.0 >= start_idx {
+                        dirents[filled] = VfsDirEntry::new(entry.name, entry.ty);
+                        filled += 1;
+                    }
+                    *pos = (pos.0 + 1, entry.offset);
+                }
+            }
+            Ok(filled)
+        })
+    }
+
+    fn rename(&self, src_path: &str, dst_path: &str) -> VfsResult {
+        debug!("rename at 9p: {} -> {}", src_path, dst_path);
+        // `dst_path` is an absolute path
+        let mount_path = self.client.mount_path.lock().clone();
+        let dst_path = dst_path
+            .trim_start_matches('/')
+            .strip_prefix(mount_path.trim_start_matches('/'))
+            .unwrap_or(dst_path);
+        let (src_dir, src_name) = self.client.walk_parent(self.fid(), src_path)?;
+        let (dst_dir, dst_name) = self.client.walk_parent(ROOT_FID, dst_path)?;
+        let msg = Msg::new(TRENAMEAT, 0)
+            .u32(src_dir.id)
+            .str(src_name)
+            .u32(dst_dir.id)
+            .str(dst_name);
+        self.client.rpc(msg, SMALL_RESP)?;
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+}
+
+impl VfsLinkOps for P9Node {
+    fn ino(&self) -> VfsResult<u64> {
+        Ok(self.qid.path)
+    }
+
+    fn nlink(&self) -> VfsResult<u64> {
+        Ok(self.client.getattr(self.fid())?.nlink)
+    }
+
+    fn readlink(&self) -> VfsResult<String> {
+        if !self.qid.is_symlink() {
+            return ax_err!(InvalidInput);
+        }
+        let resp = self
+            .client
+            .rpc(Msg::new(TREADLINK, 0).u32(self.fid()), SMALL_RESP + 4096)?;
+        Ok(Reader::new(&resp).str()?.into())
+    }
+
+    fn symlink(&self, name: &str, target: &str) -> VfsResult {
+        let msg = Msg::new(TSYMLINK, 0)
+            .u32(self.fid())
+            .str(name)
+            .str(target)
+            .u32(0);
+        self.client.rpc(msg, SMALL_RESP)?;
+        Ok(())
+    }
+
+    fn link(&self, name: &str, node: &VfsNodeRef) -> VfsResult {
+        let node = node
+            .as_any()
+            .downcast_ref::<P9Node>()
+            .ok_or(VfsError::PermissionDenied)?;
+        let msg = Msg::new(TLINK, 0).u32(self.fid()).u32(node.fid()).str(name);
+        self.client.rpc(msg, SMALL_RESP)?;
+        Ok(())
+    }
+}
+
+/// A mounted share.
+pub struct P9FileSystem {
+    client: Arc<Client>,
+    root: Arc<P9Node>,
+}
+
+impl P9FileSystem {
+    fn new(share: Arc<Share>, opts: &P9MountOptions) -> VfsResult<Self> {
+        let (client, qid) = Client::connect(share, opts)?;
+        let client = Arc::new(client);
+        let root = P9Node::new(client.clone(), None, qid);
+        Ok(Self { client, root })
+    }
+}
+
+impl VfsOps for P9FileSystem {
+    fn mount(&self, path: &str, _mount_point: VfsNodeRef) -> VfsResult {
+        *self.client.mount_path.lock() = path.to_string();
+        Ok(())
+    }
+
+    fn root_dir(&self) -> VfsNodeRef {
+        self.root.clone()
+    }
+}
+
+/// Options of a 9P mount.
+pub struct P9MountOptions {
+    /// The directory in the share to mount, empty for the whole share.
+    pub aname: String,
+    /// The maximum message size.
+    pub msize: u32,
+}
+
+impl Default for P9MountOptions {
+    fn default() -> Self {
+        Self {
+            aname: String::new(),
+            msize: DEFAULT_MSIZE,
+        }
+    }
+}
+
+impl P9MountOptions {
+    /// Parses the options passed to mount(2), e.g.,
+    /// `trans=virtio,version=9p2000.L,msize=65536`. Unknown options are
+    /// ignored, like the caching and access options of Linux.
+    pub fn parse(data: &str) -> AxResult<Self> {
+        let mut opts = Self::default();
+        for opt in data.split(',').filter(|opt| !opt.is_empty()) {
+            let (key, value) = opt.split_once('=').unwrap_or((opt, ""));
+            match key {
+                "trans" if value != "virtio" => return ax_err!(Unsupported),
+                "version" if !value.eq_ignore_ascii_case(VERSION) => return ax_err!(Unsupported),
+                "msize" => {
+                    opts.msize = value.parse().map_err(|_| AxError::InvalidInput)?;
+                }
+                "aname" => opts.aname = value.into(),
+                _ => debug!("9p: ignore mount option {:?}", opt),
+            }
+        }
+        Ok(opts)
+    }
+}
+
+/// Mounts the share with the mount tag `tag` at `path`.
+pub fn mount_9p(tag: &str, path: &str, opts: &P9MountOptions) -> AxResult {
+    let share = SHARES
+        .lock()
+        .iter()
+        .find(|share| share.tag == tag)
+        .cloned()
+        .ok_or(AxError::NotFound)?;
+    if share.mounted.swap(true, Ordering::AcqRel) {
+        return ax_err!(ResourceBusy, "the 9p share is already mounted");
+    }
+    let fs = match P9FileSystem::new(share.clone(), opts) {
+        Ok(fs) => Arc::new(fs),
+        Err(e) => {
+            share.mounted.store(false, Ordering::Release);
+            return Err(e);
+        }
+    };
+    crate::root::mount(path, fs, downcast_link_ops::<P9Node>)?;
+    info!("mounted 9p share {:?} at {}", tag, path);
+    Ok(())
+}
+
+/// Registers the shares of the VirtIO 9P devices, and mounts each of them at
+/// `/mnt/<tag>`.
+pub fn init_9p(devs: Vec<Ax9pDevice>) {
+    for dev in devs {
+        let tag = dev.mount_tag().to_string();
+        if tag.is_empty() || tag.contains('/') {
+            warn!("9p: ignore the device with invalid mount tag {:?}", tag);
+            continue;
+        }
+        SHARES.lock().push(Arc::new(Share {
+            tag: tag.clone(),
+            dev: Mutex::new(dev),
+            mounted: AtomicBool::new(false),
+        }));
+        let path = "/mnt/".to_string() + &tag;
+        let _ = crate::api::create_dir("/mnt");
+        if let Err(e) = mount_9p(&tag, &path, &P9MountOptions::default()) {
+            warn!("failed to mount 9p share {:?} at {}: {:?}", tag, path, e);
+        }
+    }
+}
@@ -0,0 +1,267 @@
+//! Encoding of the 9P2000.L messages.
+//!
+//! Every message starts with `size[4] type[1] tag[2]`, and all integers are
+//! little-endian. Strings are a 16-bit length followed by UTF-8 bytes.
+
+use alloc::vec::Vec;
+use axfs_vfs::{VfsError, VfsNodeType, VfsResult};
+
+pub const VERSION: &str = "9P2000.L";
+pub const NOTAG: u16 = !0;
+pub const NOFID: u32 = !0;
+/// Size of the header of `Rread` and `Twrite`, i.e. the overhead of the data.
+pub const IOHDRSZ: u32 = 24;
+/// Maximum number of names in a `Twalk`.
+pub const MAXWELEM: usize = 16;
+
+pub const RLERROR: u8 = 7;
+pub const TLOPEN: u8 = 12;
+pub const TLCREATE: u8 = 14;
+pub const TSYMLINK: u8 = 16;
+pub const TREADLINK: u8 = 22;
+pub const TGETATTR: u8 = 24;
+pub const TSETATTR: u8 = 26;
+pub const TREADDIR: u8 = 40;
+pub const TFSYNC: u8 = 50;
+pub const TLINK: u8 = 70;
+pub const TMKDIR: u8 = 72;
+pub const TRENAMEAT: u8 = 74;
+pub const TUNLINKAT: u8 = 76;
+pub const TVERSION: u8 = 100;
+pub const TATTACH: u8 = 104;
+pub const TWALK: u8 = 110;
+pub const TREAD: u8 = 116;
+pub const TWRITE: u8 = 118;
+pub const TCLUNK: u8 = 120;
+
+/// `request_mask` of `Tgetattr`: mode, nlink, uid, gid, rdev, times, size and
+/// blocks.
+pub const GETATTR_BASIC: u64 = 0x7ff;
+/// `valid` of `Tsetattr`: the size is set.
+pub const SETATTR_SIZE: u32 = 0x8;
+
+/// Flags of `Tlopen` and `Tlcreate`, the same as `open(2)` of Linux.
+pub const O_RDONLY: u32 = 0;
+pub const O_RDWR: u32 = 2;
+pub const O_CREAT: u32 = 0o100;
+/// Flag of `Tunlinkat`.
+pub const AT_REMOVEDIR: u32 = 0x200;
+
+pub const QTDIR: u8 = 0x80;
+pub const QTSYMLINK: u8 = 0x02;
+
+const S_IFMT: u32 = 0o170000;
+
+/// The unique identity of a file on the server.
+#[derive(Debug, Clone, Copy)]
+pub struct Qid {
+    pub ty: u8,
+    pub path: u64,
+}
+
+impl Qid {
+    pub fn is_dir(&self) -> bool {
+        self.ty & QTDIR != 0
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.ty & QTSYMLINK != 0
+    }
+}
+
+/// The attributes returned by `Tgetattr`.
+#[derive(Debug, Clone, Copy)]
+pub struct Attr {
+    pub mode: u32,
+    pub nlink: u64,
+    pub size: u64,
+    pub blocks: u64,
+}
+
+impl Attr {
+    pub fn node_type(&self) -> VfsNodeType {
+        match self.mode & S_IFMT {
+            0o010000 => VfsNodeType::Fifo,
+            0o020000 => VfsNodeType::CharDevice,
+            0o040000 => VfsNodeType::Dir,
+            0o060000 => VfsNodeType::BlockDevice,
+            0o120000 => VfsNodeType::SymLink,
+            0o140000 => VfsNodeType::Socket,
+            _ => VfsNodeType::File,
+        }
+    }
+}
+
+/// An entry returned by `Treaddir`.
+pub struct DirEntry<'a> {
+    /// The offset of the next entry.
+    pub offset: u64,
+    pub ty: VfsNodeType,
+    pub name: &'a str,
+}
+
+/// Converts the `d_type` of a directory entry.
+fn dirent_type(ty: u8) -> VfsNodeType {
+    match ty {
+        1 => VfsNodeType::Fifo,
+        2 => VfsNodeType::CharDevice,
+        4 => VfsNodeType::Dir,
+        6 => VfsNodeType::BlockDevice,
+        10 => VfsNodeType::SymLink,
+        12 => VfsNodeType::Socket,
+        _ => VfsNodeType::File,
+    }
+}
+
+/// Converts the Linux error number of `Rlerror`.
+pub fn as_vfs_err(ecode: u32) -> VfsError {
+    match ecode {
+        1 | 13 => VfsError::PermissionDenied, // EPERM, EACCES
+        2 => VfsError::NotFound,              // ENOENT
+        11 => VfsError::WouldBlock,           // EAGAIN
+        12 => VfsError::NoMemory,             // ENOMEM
+        16 => VfsError::ResourceBusy,         // EBUSY
+        17 => VfsError::AlreadyExists,        // EEXIST
+        20 => VfsError::NotADirectory,        // ENOTDIR
+        21 => VfsError::IsADirectory,         // EISDIR
+        22 => VfsError::InvalidInput,         // EINVAL
+        28 | 122 => VfsError::StorageFull,    // ENOSPC, EDQUOT
+        38 | 95 => VfsError::Unsupported,     // ENOSYS, EOPNOTSUPP
+        39 => VfsError::DirectoryNotEmpty,    // ENOTEMPTY
+        40 => VfsError::InvalidData,          // ELOOP
+        _ => VfsError::Io,
+    }
+}
+
+/// Builds a T-message.
+pub struct Msg(Vec<u8>);
+
+impl Msg {
+    pub fn new(ty: u8, tag: u16) -> Self {
+        let mut buf = Vec::with_capacity(64);
+        buf.extend_from_slice(&[0; 4]); // size, filled by `finish`
+        buf.push(ty);
+        buf.extend_from_slice(&tag.to_le_bytes());
+        Self(buf)
+    }
+
+    pub fn u8(mut self, v: u8) -> Self {
+        self.0.push(v);
+        self
+    }
+
+    pub fn u16(mut self, v: u16) -> Self {
+        self.0.extend_from_slice(&v.to_le_bytes());
+        self
+    }
+
+    pub fn u32(mut self, v: u32) -> Self {
+        self.0.extend_from_slice(&v.to_le_bytes());
+        self
+    }
+
+    pub fn u64(mut self, v: u64) -> Self {
+        self.0.extend_from_slice(&v.to_le_bytes());
+        self
+    }
+
+    pub fn str(self, s: &str) -> Self {
+        let mut msg = self.u16(s.len() as u16);
+        msg.0.extend_from_slice(s.as_bytes());
+        msg
+    }
+
+    /// Appends raw data, e.g., the data of `Twrite`.
+    pub fn data(mut self, data: &[u8]) -> Self {
+        self.0.extend_from_slice(data);
+        self
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        let size = self.0.len() as u32;
+        self.0[..4].copy_from_slice(&size.to_le_bytes());
+        self.0
+    }
+}
+
+/// Parses the body of an R-message.
+pub struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    pub fn bytes(&mut self, len: usize) -> VfsResult<&'a [u8]> {
+        let bytes = self
+            .buf
+            .get(self.pos..self.pos + len)
+            .ok_or(VfsError::InvalidData)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos >= self.buf.len()
+    }
+
+    pub fn u8(&mut self) -> VfsResult<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn u16(&mut self) -> VfsResult<u16> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    pub fn u32(&mut self) -> VfsResult<u32> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    pub fn u64(&mut self) -> VfsResult<u64> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+
+    pub fn str(&mut self) -> VfsResult<&'a str> {
+        let len = self.u16()? as usize;
+        core::str::from_utf8(self.bytes(len)?).map_err(|_| VfsError::InvalidData)
+    }
+
+    pub fn qid(&mut self) -> VfsResult<Qid> {
+        let ty = self.u8()?;
+        let _version = self.u32()?;
+        let path = self.u64()?;
+        Ok(Qid { ty, path })
+    }
+
+    /// Parses the body of `Rgetattr`.
+    pub fn attr(&mut self) -> VfsResult<Attr> {
+        let _valid = self.u64()?;
+        let _qid = self.qid()?;
+        let mode = self.u32()?;
+        let _uid = self.u32()?;
+        let _gid = self.u32()?;
+        let nlink = self.u64()?;
+        let _rdev = self.u64()?;
+        let size = self.u64()?;
+        let _blksize = self.u64()?;
+        let blocks = self.u64()?;
+        Ok(Attr {
+            mode,
+            nlink,
+            size,
+            blocks,
+        })
+    }
+
+    /// Parses an entry in the data of `Rreaddir`.
+    pub fn dir_entry(&mut self) -> VfsResult<DirEntry<'a>> {
+        let _qid = self.qid()?;
+        let offset = self.u64()?;
+        let ty = dirent_type(self.u8()?);
+        let name = self.str()?;
+        Ok(DirEntry { offset, ty, name })
+    }
+}
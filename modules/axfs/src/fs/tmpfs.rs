@@ -0,0 +1,426 @@
+//! An in-memory filesystem with symbolic and hard links, mounted on `/tmp`,
+//! `/var` and `/dev/shm`.
+//!
+//! Unlike [`axfs_ramfs`], the directories here can hold symbolic links, and a
+//! file can be reached from several directories by hard links.
+
+use alloc::{
+    collections::BTreeMap,
+    string::{String, ToString},
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use axfs_vfs::{VfsDirEntry, VfsError, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeRef};
+use axfs_vfs::{VfsNodeType, VfsOps, VfsResult};
+use axsync::Mutex;
+
+use crate::link::VfsLinkOps;
+
+/// The next inode number, shared by all tmpfs instances.
+static NEXT_INO: AtomicU64 = AtomicU64::new(1);
+
+fn alloc_ino() -> u64 {
+    NEXT_INO.fetch_add(1, Ordering::Relaxed)
+}
+
+/// An in-memory filesystem with links.
+pub struct TmpFileSystem {
+    root: Arc<DirNode>,
+}
+
+impl TmpFileSystem {
+    /// Creates an empty filesystem.
+    pub fn new() -> Self {
+        Self {
+            root: DirNode::new(None, Arc::new(Mutex::new(String::new()))),
+        }
+    }
+
+    /// Finds the link operations of a node of tmpfs.
+    pub fn link_ops(node: &VfsNodeRef) -> Option<&dyn VfsLinkOps> {
+        let any = node.as_any();
+        if let Some(dir) = any.downcast_ref::<DirNode>() {
+            Some(dir)
+        } else if let Some(file) = any.downcast_ref::<FileNode>() {
+            Some(file)
+        } else {
+            any.downcast_ref::<SymlinkNode>()
+                .map(|link| link as &dyn VfsLinkOps)
+        }
+    }
+}
+
+impl Default for TmpFileSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VfsOps for TmpFileSystem {
+    fn mount(&self, path: &str, mount_point: VfsNodeRef) -> VfsResult {
+        *self.root.mount_path.lock() = path.to_string();
+        *self.root.parent.lock() = mount_point
+            .parent()
+            .map_or(Weak::<DirNode>::new() as _, |parent| {
+                Arc::downgrade(&parent)
+            });
+        Ok(())
+    }
+
+    fn root_dir(&self) -> VfsNodeRef {
+        self.root.clone()
+    }
+}
+
+/// Returns the hard link count of a node that can have several names.
+fn link_count(node: &VfsNodeRef) -> Option<&AtomicU64> {
+    let any = node.as_any();
+    if let Some(file) = any.downcast_ref::<FileNode>() {
+        Some(&file.nlink)
+    } else {
+        any.downcast_ref::<SymlinkNode>().map(|link| &link.nlink)
+    }
+}
+
+/// A directory.
+pub struct DirNode {
+    ino: u64,
+    this: Weak<DirNode>,
+    parent: Mutex<Weak<dyn VfsNodeOps>>,
+    children: Mutex<BTreeMap<String, VfsNodeRef>>,
+    /// Where the filesystem is mounted, shared by all directories.
+    mount_path: Arc<Mutex<String>>,
+}
+
+impl DirNode {
+    fn new(parent: Option<Weak<dyn VfsNodeOps>>, mount_path: Arc<Mutex<String>>) -> Arc<Self> {
+        Arc::new_cyclic(|this| Self {
+            ino: alloc_ino(),
+            this: this.clone(),
+            parent: Mutex::new(parent.unwrap_or_else(|| Weak::<Self>::new())),
+            children: Mutex::new(BTreeMap::new()),
+            mount_path,
+        })
+    }
+
+    /// Adds `node` as `name`, which must not exist.
+    fn insert(&self, name: &str, node: VfsNodeRef) -> VfsResult {
+        let mut children = self.children.lock();
+        if children.contains_key(name) {
+            return Err(VfsError::AlreadyExists);
+        }
+        if let Some(nlink) = link_count(&node) {
+            nlink.fetch_add(1, Ordering::Relaxed);
+        }
+        children.insert(name.to_string(), node);
+        Ok(())
+    }
+
+    fn create_node(&self, name: &str, ty: VfsNodeType) -> VfsResult {
+        if self.children.lock().contains_key(name) {
+            return Ok(()); // already exists
+        }
+        let node: VfsNodeRef = match ty {
+            VfsNodeType::File => Arc::new(FileNode::new()),
+            VfsNodeType::Dir => Self::new(Some(self.this.clone()), self.mount_path.clone()),
+            _ => return Err(VfsError::Unsupported),
+        };
+        self.insert(name, node)
+    }
+
+    fn remove_node(&self, name: &str) -> VfsResult {
+        let mut children = self.children.lock();
+        let node = children.get(name).ok_or(VfsError::NotFound)?;
+        if let Some(dir) = node.as_any().downcast_ref::<DirNode>() {
+            if !dir.children.lock().is_empty() {
+                return Err(VfsError::DirectoryNotEmpty);
+            }
+        }
+        if let Some(nlink) = link_count(node) {
+            nlink.fetch_sub(1, Ordering::Relaxed);
+        }
+        children.remove(name);
+        Ok(())
+    }
+
+    /// Finds the directory that holds the last component of `path`, and
+    /// returns it with the name of the component.
+    fn parent_of<'a>(self: Arc<Self>, path: &'a str) -> VfsResult<(Arc<DirNode>, &'a str)> {
+        let path = path.trim_matches('/');
+        let (dir, name) = match path.rsplit_once('/') {
+            Some((parent, name)) => (self.lookup(parent)?, name),
+            None => (self as VfsNodeRef, path),
+        };
+        if name.is_empty() || name == "." || name == ".." {
+            return Err(VfsError::InvalidInput);
+        }
+        let dir = dir
+            .as_any()
+            .downcast_ref::<DirNode>()
+            .and_then(|dir| dir.this.upgrade())
+            .ok_or(VfsError::NotADirectory)?;
+        Ok((dir, name))
+    }
+}
+
+impl VfsNodeOps for DirNode {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(VfsNodeAttr::new_dir(4096, 0))
+    }
+
+    fn parent(&self) -> Option<VfsNodeRef> {
+        self.parent.lock().upgrade()
+    }
+
+    fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
+        let (name, rest) = split_path(path);
+        let node = match name {
+            "" | "." => Ok(self.clone() as VfsNodeRef),
+            ".." => self.parent().ok_or(VfsError::NotFound),
+            _ => self
+                .children
+                .lock()
+                .get(name)
+                .cloned()
+                .ok_or(VfsError::NotFound),
+        }?;
+        match rest {
+            Some(rest) => node.lookup(rest),
+            None => Ok(node),
+        }
+    }
+
+    fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
+        let children = self.children.lock();
+        let mut children = children.iter().skip(start_idx.max(2) - 2);
+        for (i, ent) in dirents.iter_mut().enumerate() {
+            match i + start_idx {
+                0 => *ent = VfsDirEntry::new(".", VfsNodeType::Dir),
+                1 => *ent = VfsDirEntry::new("..", VfsNodeType::Dir),
+                _ => match children.next() {
+                    Some((name, node)) => {
+                        *ent = VfsDirEntry::new(name, node.get_attr()?.file_type());
+                    }
+                    None => return Ok(i),
+                },
+            }
+        }
+        Ok(dirents.len())
+    }
+
+    fn create(&self, path: &str, ty: VfsNodeType) -> VfsResult {
+        let (name, rest) = split_path(path);
+        match (name, rest) {
+            ("" | "." | "..", None) => Ok(()), // already exists
+            ("" | ".", Some(rest)) => self.create(rest, ty),
+            ("..", Some(rest)) => self.parent().ok_or(VfsError::NotFound)?.create(rest, ty),
+            (_, Some(rest)) => {
+                let subdir = self
+                    .children
+                    .lock()
+                    .get(name)
+                    .cloned()
+                    .ok_or(VfsError::NotFound)?;
+                subdir.create(rest, ty)
+            }
+            (_, None) => self.create_node(name, ty),
+        }
+    }
+
+    fn remove(&self, path: &str) -> VfsResult {
+        let (name, rest) = split_path(path);
+        match (name, rest) {
+            ("" | "." | "..", None) => Err(VfsError::InvalidInput),
+            ("" | ".", Some(rest)) => self.remove(rest),
+            ("..", Some(rest)) => self.parent().ok_or(VfsError::NotFound)?.remove(rest),
+            (_, Some(rest)) => {
+                let subdir = self
+                    .children
+                    .lock()
+                    .get(name)
+                    .cloned()
+                    .ok_or(VfsError::NotFound)?;
+                subdir.remove(rest)
+            }
+            (_, None) => self.remove_node(name),
+        }
+    }
+
+    fn rename(&self, src_path: &str, dst_path: &str) -> VfsResult {
+        // `dst_path` is an absolute path
+        let mount_path = self.mount_path.lock().clone();
+        let dst_path = dst_path
+            .trim_start_matches('/')
+            .strip_prefix(mount_path.trim_start_matches('/'))
+            .unwrap_or(dst_path);
+        let this = self.this.upgrade().ok_or(VfsError::NotFound)?;
+        let (src_dir, src_name) = this.clone().parent_of(src_path)?;
+        let (dst_dir, dst_name) = this.parent_of(dst_path)?;
+        let node = src_dir
+            .children
+            .lock()
+            .remove(src_name)
+            .ok_or(VfsError::NotFound)?;
+        if let Some(dir) = node.as_any().downcast_ref::<DirNode>() {
+            let parent: Weak<dyn VfsNodeOps> = dst_dir.this.clone();
+            *dir.parent.lock() = parent;
+        }
+        dst_dir.children.lock().insert(dst_name.to_string(), node);
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+
+    axfs_vfs::impl_vfs_dir_default! {}
+}
+
+impl VfsLinkOps for DirNode {
+    fn ino(&self) -> VfsResult<u64> {
+        Ok(self.ino)
+    }
+
+    fn nlink(&self) -> VfsResult<u64> {
+        let children = self.children.lock();
+        let subdirs = children
+            .values()
+            .filter(|node| node.as_any().is::<DirNode>())
+            .count();
+        Ok(2 + subdirs as u64)
+    }
+
+    fn symlink(&self, name: &str, target: &str) -> VfsResult {
+        self.insert(name, Arc::new(SymlinkNode::new(target)))
+    }
+
+    fn link(&self, name: &str, node: &VfsNodeRef) -> VfsResult {
+        if link_count(node).is_none() {
+            // directories have exactly one name, other filesystems cannot be
+            // linked to
+            return Err(VfsError::PermissionDenied);
+        }
+        self.insert(name, node.clone())
+    }
+}
+
+/// A regular file.
+pub struct FileNode {
+    ino: u64,
+    nlink: AtomicU64,
+    content: Mutex<Vec<u8>>,
+}
+
+impl FileNode {
+    fn new() -> Self {
+        Self {
+            ino: alloc_ino(),
+            nlink: AtomicU64::new(0),
+            content: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl VfsNodeOps for FileNode {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let size = self.content.lock().len() as u64;
+        Ok(VfsNodeAttr::new_file(size, size.div_ceil(512)))
+    }
+
+    fn truncate(&self, size: u64) -> VfsResult {
+        self.content.lock().resize(size as usize, 0);
+        Ok(())
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let content = self.content.lock();
+        let start = content.len().min(offset as usize);
+        let end = content.len().min(start + buf.len());
+        let src = &content[start..end];
+        buf[..src.len()].copy_from_slice(src);
+        Ok(src.len())
+    }
+
+    fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        let offset = offset as usize;
+        let mut content = self.content.lock();
+        if offset + buf.len() > content.len() {
+            content.resize(offset + buf.len(), 0);
+        }
+        content[offset..offset + buf.len()].copy_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+
+    axfs_vfs::impl_vfs_non_dir_default! {}
+}
+
+impl VfsLinkOps for FileNode {
+    fn ino(&self) -> VfsResult<u64> {
+        Ok(self.ino)
+    }
+
+    fn nlink(&self) -> VfsResult<u64> {
+        Ok(self.nlink.load(Ordering::Relaxed))
+    }
+}
+
+/// A symbolic link.
+pub struct SymlinkNode {
+    ino: u64,
+    nlink: AtomicU64,
+    target: String,
+}
+
+impl SymlinkNode {
+    fn new(target: &str) -> Self {
+        Self {
+            ino: alloc_ino(),
+            nlink: AtomicU64::new(0),
+            target: target.to_string(),
+        }
+    }
+}
+
+impl VfsNodeOps for SymlinkNode {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(VfsNodeAttr::new(
+            VfsNodePerm::from_bits_truncate(0o777),
+            VfsNodeType::SymLink,
+            self.target.len() as u64,
+            0,
+        ))
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+
+    axfs_vfs::impl_vfs_non_dir_default! {}
+}
+
+impl VfsLinkOps for SymlinkNode {
+    fn ino(&self) -> VfsResult<u64> {
+        Ok(self.ino)
+    }
+
+    fn nlink(&self) -> VfsResult<u64> {
+        Ok(self.nlink.load(Ordering::Relaxed))
+    }
+
+    fn readlink(&self) -> VfsResult<String> {
+        Ok(self.target.clone())
+    }
+}
+
+fn split_path(path: &str) -> (&str, Option<&str>) {
+    let trimmed_path = path.trim_start_matches('/');
+    trimmed_path.find('/').map_or((trimmed_path, None), |n| {
+        (&trimmed_path[..n], Some(&trimmed_path[n + 1..]))
+    })
+}
@@ -2,20 +2,38 @@
 //!
 //! It provides unified filesystem operations for various filesystems.
 //!
+//! All block devices are accessed through the block layer in [`blk`], which
+//! caches their blocks and writes them back on [`api::sync`].
+//!
 //! # Cargo Features
 //!
 //! - `fatfs`: Use [FAT] as the main filesystem and mount it on `/`. This feature
 //!    is **enabled** by default.
 //! - `devfs`: Mount [`axfs_devfs::DeviceFileSystem`] on `/dev`. This feature is
 //!    **enabled** by default.
-//! - `ramfs`: Mount [`axfs_ramfs::RamFileSystem`] on `/tmp`. This feature is
-//!    **enabled** by default.
+//! - `ramfs`: Mount an in-memory filesystem that supports symbolic and hard
+//!    links on `/tmp`, `/var` and `/dev/shm`. This feature is **enabled** by
+//!    default.
 //! - `myfs`: Allow users to define their custom filesystems to override the
 //!    default. In this case, [`MyFileSystemIf`] is required to be implemented
 //!    to create and initialize other filesystems. This feature is **disabled** by
 //!    by default, but it will override other filesystem selection features if
 //!    both are enabled.
 //!
+//! - `procfs`: Mount a ramfs on `/proc`, where the kernel can add generated
+//!    files, see [`procfs`].
+//! - `monolithic`: Provide the extra interfaces needed by a monolithic kernel,
+//!    such as advisory file locks, the kernel log device `/dev/kmsg` and the
+//!    log filter `/proc/sys/kernel/axlog`.
+//! - `virtio-console`: Add the ports of VirtIO console devices to devfs as
+//!    `/dev/hvcN`, see [`chrdev`].
+//! - `virtio-input`: Add the evdev interface of VirtIO input devices to devfs
+//!    as `/dev/input/eventN`.
+//! - `fbdev`: Add the framebuffer of the main display to devfs as `/dev/fb0`,
+//!    which can be mapped in user space.
+//! - `virtio-9p`: Mount the host directories shared by VirtIO 9P devices, see
+//!    [`mount_9p`].
+//!
 //! [FAT]: https://en.wikipedia.org/wiki/File_Allocation_Table
 //! [`MyFileSystemIf`]: fops::MyFileSystemIf
 
@@ -26,9 +44,18 @@
 extern crate log;
 extern crate alloc;
 
+pub mod blk;
+#[cfg(feature = "devfs")]
+pub mod chrdev;
 mod dev;
 mod fs;
+mod link;
+#[cfg(feature = "monolithic")]
+pub mod lock;
 mod mounts;
+pub mod notify;
+#[cfg(feature = "procfs")]
+pub mod procfs;
 mod root;
 
 pub use fs::BLOCK_SIZE;
@@ -38,6 +65,9 @@ pub mod fops;
 pub use axfs_devfs;
 pub use axfs_ramfs;
 
+#[cfg(feature = "virtio-9p")]
+pub use fs::p9::{init_9p, mount_9p, P9MountOptions};
+
 use axdriver::{prelude::*, AxDeviceContainer};
 
 /// Initializes filesystems by block devices.
@@ -47,4 +77,6 @@ pub fn init_filesystems(mut blk_devs: AxDeviceContainer<AxBlockDevice>) {
     let dev = blk_devs.take_one().expect("No block device found!");
     info!("  use block device 0: {:?}", dev.device_name());
     self::root::init_rootfs(self::dev::Disk::new(dev));
+    #[cfg(feature = "procfs")]
+    self::procfs::init();
 }
@@ -0,0 +1,62 @@
+//! Symbolic and hard link support of filesystem nodes.
+//!
+//! [`axfs_vfs::VfsNodeOps`] has no notion of links, so the nodes of the
+//! filesystems that can store them (the ext4 backends, tmpfs and 9P) also
+//! implement [`VfsLinkOps`]. When such a filesystem is mounted, it provides a
+//! [`LinkOpsFn`] that finds the link operations of its nodes. Filesystems
+//! without one, such as FAT, procfs or devfs, refuse to create links.
+//!
+//! The `ext4_rs` backend is read-only in this respect: the version of the
+//! `ext4_rs` crate in use has no interface for adding a directory entry to an
+//! existing inode or writing a symlink inode, so it reads links already on the
+//! disk but `link(2)` and `symlink(2)` on it fail with `EPERM`. Use the
+//! `lwext4_rust` or `another_ext4` backend to create links on ext4.
+
+use alloc::string::String;
+use axerrno::ax_err;
+use axfs_vfs::{VfsNodeRef, VfsResult};
+
+/// Maximum number of symbolic links followed while resolving one path, the
+/// same as `MAXSYMLINKS` in Linux.
+pub const MAX_SYMLINK_DEPTH: usize = 40;
+
+/// Finds the link operations of a node of a mounted filesystem.
+pub type LinkOpsFn = for<'a> fn(&'a VfsNodeRef) -> Option<&'a dyn VfsLinkOps>;
+
+/// Link operations of a node.
+pub trait VfsLinkOps {
+    /// Returns the inode number of the node.
+    fn ino(&self) -> VfsResult<u64>;
+
+    /// Returns the number of hard links to the node.
+    fn nlink(&self) -> VfsResult<u64>;
+
+    /// Returns the target of the node, which is a symbolic link.
+    ///
+    /// Returns [`InvalidInput`](axerrno::AxError::InvalidInput) if the node
+    /// is not a symbolic link.
+    fn readlink(&self) -> VfsResult<String> {
+        ax_err!(InvalidInput)
+    }
+
+    /// Creates a symbolic link `name` which points to `target` in the node,
+    /// which is a directory.
+    fn symlink(&self, _name: &str, _target: &str) -> VfsResult {
+        ax_err!(PermissionDenied)
+    }
+
+    /// Creates a new name `name` for the existing file `node` in the node,
+    /// which is a directory.
+    fn link(&self, _name: &str, _node: &VfsNodeRef) -> VfsResult {
+        ax_err!(PermissionDenied)
+    }
+}
+
+/// Returns the link operations of `node` if it is a `T`.
+///
+/// The node must implement [`as_any`](axfs_vfs::VfsNodeOps::as_any).
+pub fn downcast_link_ops<T: VfsLinkOps + 'static>(node: &VfsNodeRef) -> Option<&dyn VfsLinkOps> {
+    node.as_any()
+        .downcast_ref::<T>()
+        .map(|node| node as &dyn VfsLinkOps)
+}
@@ -0,0 +1,460 @@
+//! Advisory file locks, i.e. the locks of `flock(2)` and the record locks of
+//! `fcntl(2)`.
+//!
+//! Locks are kept per inode, which is identified by its filesystem and inode
+//! number, so that all the hard links of a file share the same locks. On
+//! filesystems without inode numbers, such as FAT, the real path of the file
+//! with all symbolic links resolved is used instead. Two kinds of locks are
+//! supported:
+//!
+//! - Whole-file locks of `flock(2)`, owned by an open file description.
+//! - Byte-range record locks of `fcntl(2)`, owned either by a process
+//!   (traditional POSIX locks) or by an open file description (OFD locks).
+//!
+//! Locks are advisory only and never checked by `read`/`write`. Tasks blocked
+//! on a lock sleep on a shared [`WaitQueue`] and are woken up whenever any lock
+//! is released.
+
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+use axerrno::{ax_err, AxResult};
+use axsync::Mutex;
+use axtask::WaitQueue;
+
+/// The type of a lock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockType {
+    /// A shared (read) lock.
+    Read,
+    /// An exclusive (write) lock.
+    Write,
+    /// Removes the lock.
+    Unlock,
+}
+
+/// The owner of a record lock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockOwner {
+    /// A traditional POSIX lock, owned by the process with the given pid.
+    Process(u64),
+    /// An OFD lock, owned by the open file description at the given address.
+    File(usize),
+}
+
+/// A byte-range record lock.
+#[derive(Debug, Clone, Copy)]
+pub struct RecordLock {
+    /// The owner of the lock.
+    pub owner: LockOwner,
+    /// The pid of the process which placed the lock.
+    pub pid: u64,
+    /// The type of the lock.
+    pub ty: LockType,
+    /// The first byte of the range.
+    pub start: u64,
+    /// The byte after the last byte of the range, `u64::MAX` for a range
+    /// extending to the end of the file.
+    pub end: u64,
+}
+
+impl RecordLock {
+    fn overlaps(&self, other: &Self) -> bool {
+        self.start < other.end && other.start < self.end
+    }
+
+    fn conflicts(&self, other: &Self) -> bool {
+        self.owner != other.owner
+            && self.overlaps(other)
+            && (self.ty == LockType::Write || other.ty == LockType::Write)
+    }
+}
+
+/// A `flock(2)` lock.
+struct Flock {
+    owner: usize,
+    ty: LockType,
+}
+
+#[derive(Default)]
+struct InodeLocks {
+    flocks: Vec<Flock>,
+    records: Vec<RecordLock>,
+}
+
+impl InodeLocks {
+    fn is_empty(&self) -> bool {
+        self.flocks.is_empty() && self.records.is_empty()
+    }
+
+    fn flock_conflicts(&self, owner: usize, ty: LockType) -> bool {
+        self.flocks.iter().any(|lock| {
+            lock.owner != owner && (lock.ty == LockType::Write || ty == LockType::Write)
+        })
+    }
+
+    fn record_conflict(&self, lock: &RecordLock) -> Option<RecordLock> {
+        self.records
+            .iter()
+            .find(|record| record.conflicts(lock))
+            .copied()
+    }
+
+    /// Replaces the range of `lock` in the locks of its owner, merging it
+    /// with the adjacent locks of the same type.
+    fn apply_record(&mut self, lock: RecordLock) {
+        self.records.sort_by_key(|record| record.start);
+        let mut merged = lock;
+        let mut records = Vec::with_capacity(self.records.len() + 2);
+        for record in self.records.drain(..) {
+            if record.owner != lock.owner {
+                records.push(record);
+            } else if record.ty == lock.ty
+                && record.start <= merged.end
+                && merged.start <= record.end
+            {
+                merged.start = merged.start.min(record.start);
+                merged.end = merged.end.max(record.end);
+            } else if !record.overlaps(&lock) {
+                records.push(record);
+            } else {
+                // keep the parts out of the new range
+                if record.start < lock.start {
+                    records.push(RecordLock {
+                        end: lock.start,
+                        ..record
+                    });
+                }
+                if record.end > lock.end {
+                    records.push(RecordLock {
+                        start: lock.end,
+                        ..record
+                    });
+                }
+            }
+        }
+        if lock.ty != LockType::Unlock {
+            records.push(merged);
+        }
+        self.records = records;
+    }
+}
+
+/// Identifies the inode that locks are placed on.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum InodeKey {
+    /// The filesystem, identified by the address of its `VfsOps`, and the
+    /// inode number in it.
+    Inode(usize, u64),
+    /// The real path of the file, on filesystems without inode numbers.
+    Path(String),
+}
+
+struct LockTable {
+    /// Inode of the file to its locks.
+    inodes: BTreeMap<InodeKey, InodeLocks>,
+    /// Pid of a process blocked on a POSIX lock to the pid of the lock owner,
+    /// used to detect deadlocks.
+    blocked_on: BTreeMap<u64, u64>,
+}
+
+static LOCK_TABLE: Mutex<LockTable> = Mutex::new(LockTable {
+    inodes: BTreeMap::new(),
+    blocked_on: BTreeMap::new(),
+});
+
+static LOCK_WAIT_QUEUE: WaitQueue = WaitQueue::new();
+
+fn inode_key(path: &str) -> InodeKey {
+    match crate::root::inode_id(path) {
+        Ok((fs, ino)) => InodeKey::Inode(fs, ino),
+        Err(_) => InodeKey::Path(crate::root::real_path(path).unwrap_or_else(|_| path.into())),
+    }
+}
+
+/// Applies a `flock(2)` operation of the open file description `owner` to
+/// the file at `path`.
+///
+/// Converting an existing lock is not atomic, the old lock is removed before
+/// the new one is acquired. If the lock is held by others, fails with
+/// `WouldBlock` when `nonblock` is set, otherwise blocks until the lock is
+/// acquired or `interrupted` returns true, in which case fails with
+/// `Interrupted`.
+pub fn flock(
+    path: &str,
+    owner: usize,
+    ty: LockType,
+    nonblock: bool,
+    interrupted: impl Fn() -> bool,
+) -> AxResult {
+    let key = inode_key(path);
+    let mut table = LOCK_TABLE.lock();
+    let mut released = false;
+    if let Some(locks) = table.inodes.get_mut(&key) {
+        let old_len = locks.flocks.len();
+        locks.flocks.retain(|lock| lock.owner != owner);
+        released = locks.flocks.len() != old_len;
+        if locks.is_empty() {
+            table.inodes.remove(&key);
+        }
+    }
+    if ty == LockType::Unlock {
+        drop(table);
+        if released {
+            LOCK_WAIT_QUEUE.notify_all();
+        }
+        return Ok(());
+    }
+    drop(table);
+    if released {
+        LOCK_WAIT_QUEUE.notify_all();
+    }
+
+    loop {
+        let mut table = LOCK_TABLE.lock();
+        let conflicts = table
+            .inodes
+            .get(&key)
+            .is_some_and(|locks| locks.flock_conflicts(owner, ty));
+        if !conflicts {
+            let locks = table.inodes.entry(key.clone()).or_default();
+            locks.flocks.push(Flock { owner, ty });
+            return Ok(());
+        }
+        drop(table);
+        if nonblock {
+            return ax_err!(WouldBlock);
+        }
+        if interrupted() {
+            return ax_err!(Interrupted);
+        }
+        LOCK_WAIT_QUEUE.wait_until(|| {
+            interrupted()
+                || LOCK_TABLE
+                    .lock()
+                    .inodes
+                    .get(&key)
+                    .map_or(true, |locks| !locks.flock_conflicts(owner, ty))
+        });
+    }
+}
+
+/// Returns the first lock on the file at `path` which prevents `lock` from
+/// being placed, the same as `F_GETLK` of `fcntl(2)`.
+pub fn get_record_lock(path: &str, lock: &RecordLock) -> Option<RecordLock> {
+    let key = inode_key(path);
+    LOCK_TABLE
+        .lock()
+        .inodes
+        .get(&key)
+        .and_then(|locks| locks.record_conflict(lock))
+}
+
+/// Places or removes the record lock `lock` on the file at `path`, the same
+/// as `F_SETLK` and `F_SETLKW` of `fcntl(2)`.
+///
+/// If a conflicting lock is held by others, fails with `WouldBlock` when
+/// `wait` is not set, otherwise blocks until the lock is placed or
+/// `interrupted` returns true, in which case fails with `Interrupted`. Fails
+/// with `ResourceBusy` (`EDEADLK`) if waiting for a POSIX lock would deadlock.
+pub fn set_record_lock(
+    path: &str,
+    lock: RecordLock,
+    wait: bool,
+    interrupted: impl Fn() -> bool,
+) -> AxResult {
+    let key = inode_key(path);
+    if lock.ty == LockType::Unlock {
+        let mut table = LOCK_TABLE.lock();
+        if let Some(locks) = table.inodes.get_mut(&key) {
+            locks.apply_record(lock);
+            if locks.is_empty() {
+                table.inodes.remove(&key);
+            }
+        }
+        drop(table);
+        LOCK_WAIT_QUEUE.notify_all();
+        return Ok(());
+    }
+
+    loop {
+        let mut table = LOCK_TABLE.lock();
+        let locks = table.inodes.entry(key.clone()).or_default();
+        let conflict = match locks.record_conflict(&lock) {
+            Some(conflict) => conflict,
+            None => {
+                locks.apply_record(lock);
+                // some locks may have been split or downgraded
+                drop(table);
+                LOCK_WAIT_QUEUE.notify_all();
+                return Ok(());
+            }
+        };
+        if !wait {
+            return ax_err!(WouldBlock);
+        }
+        if let (LockOwner::Process(pid), LockOwner::Process(owner)) = (lock.owner, conflict.owner) {
+            if would_deadlock(&table.blocked_on, pid, owner) {
+                return ax_err!(ResourceBusy, "deadlock detected");
+            }
+            table.blocked_on.insert(pid, owner);
+        }
+        drop(table);
+        if interrupted() {
+            unblock(lock.owner);
+            return ax_err!(Interrupted);
+        }
+        LOCK_WAIT_QUEUE.wait_until(|| {
+            interrupted()
+                || LOCK_TABLE
+                    .lock()
+                    .inodes
+                    .get(&key)
+                    .map_or(true, |locks| locks.record_conflict(&lock).is_none())
+        });
+        unblock(lock.owner);
+    }
+}
+
+/// Whether process `pid` waiting for a lock held by process `owner` would
+/// form a cycle in the wait-for graph.
+fn would_deadlock(blocked_on: &BTreeMap<u64, u64>, pid: u64, owner: u64) -> bool {
+    let mut current = owner;
+    // every process waits for at most one lock, so the chain is bounded
+    for _ in 0..=blocked_on.len() {
+        if current == pid {
+            return true;
+        }
+        match blocked_on.get(&current) {
+            Some(&next) => current = next,
+            None => return false,
+        }
+    }
+    false
+}
+
+fn unblock(owner: LockOwner) {
+    if let LockOwner::Process(pid) = owner {
+        LOCK_TABLE.lock().blocked_on.remove(&pid);
+    }
+}
+
+fn release(mut f: impl FnMut(&InodeKey, &mut InodeLocks) -> bool) {
+    let mut table = LOCK_TABLE.lock();
+    let mut released = false;
+    table.inodes.retain(|key, locks| {
+        released |= f(key, locks);
+        !locks.is_empty()
+    });
+    drop(table);
+    if released {
+        LOCK_WAIT_QUEUE.notify_all();
+    }
+}
+
+/// Releases all the `flock(2)` and OFD locks owned by the open file
+/// description `owner`, called when it is closed for the last time.
+pub fn release_file_locks(owner: usize) {
+    release(|_, locks| {
+        let old_len = locks.flocks.len() + locks.records.len();
+        locks.flocks.retain(|lock| lock.owner != owner);
+        locks
+            .records
+            .retain(|lock| lock.owner != LockOwner::File(owner));
+        old_len != locks.flocks.len() + locks.records.len()
+    });
+}
+
+/// Releases the POSIX locks of process `pid` on the file at `path`, or on all
+/// files if `path` is `None`.
+///
+/// POSIX locks are released when the process closes any file descriptor of
+/// the file, or exits.
+pub fn release_process_locks(pid: u64, path: Option<&str>) {
+    let key = path.map(inode_key);
+    release(|inode, locks| {
+        if key.as_ref().is_some_and(|key| key != inode) {
+            return false;
+        }
+        let old_len = locks.records.len();
+        locks
+            .records
+            .retain(|lock| lock.owner != LockOwner::Process(pid));
+        old_len != locks.records.len()
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{InodeLocks, LockOwner, LockType, RecordLock};
+
+    const OWNER: LockOwner = LockOwner::Process(1);
+
+    fn record(owner: LockOwner, ty: LockType, start: u64, end: u64) -> RecordLock {
+        RecordLock {
+            owner,
+            pid: 1,
+            ty,
+            start,
+            end,
+        }
+    }
+
+    fn ranges(locks: &InodeLocks) -> Vec<(LockType, u64, u64)> {
+        let mut ranges: Vec<_> = locks
+            .records
+            .iter()
+            .map(|lock| (lock.ty, lock.start, lock.end))
+            .collect();
+        ranges.sort_by_key(|&(_, start, _)| start);
+        ranges
+    }
+
+    #[test]
+    fn test_unlock_splits() {
+        let mut locks = InodeLocks::default();
+        locks.apply_record(record(OWNER, LockType::Write, 0, 100));
+        locks.apply_record(record(OWNER, LockType::Unlock, 40, 60));
+        assert_eq!(
+            ranges(&locks),
+            [(LockType::Write, 0, 40), (LockType::Write, 60, 100)]
+        );
+        locks.apply_record(record(OWNER, LockType::Unlock, 0, u64::MAX));
+        assert!(locks.is_empty());
+    }
+
+    #[test]
+    fn test_adjacent_locks_merge() {
+        let mut locks = InodeLocks::default();
+        locks.apply_record(record(OWNER, LockType::Read, 0, 10));
+        locks.apply_record(record(OWNER, LockType::Read, 20, 30));
+        locks.apply_record(record(OWNER, LockType::Read, 10, 20));
+        assert_eq!(ranges(&locks), [(LockType::Read, 0, 30)]);
+    }
+
+    #[test]
+    fn test_type_change_splits() {
+        let mut locks = InodeLocks::default();
+        locks.apply_record(record(OWNER, LockType::Write, 0, 100));
+        locks.apply_record(record(OWNER, LockType::Read, 40, 60));
+        assert_eq!(
+            ranges(&locks),
+            [
+                (LockType::Write, 0, 40),
+                (LockType::Read, 40, 60),
+                (LockType::Write, 60, 100)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_other_owners_untouched() {
+        let other = LockOwner::File(0x1000);
+        let mut locks = InodeLocks::default();
+        locks.apply_record(record(other, LockType::Read, 0, 50));
+        locks.apply_record(record(OWNER, LockType::Read, 25, 75));
+        locks.apply_record(record(OWNER, LockType::Unlock, 0, 100));
+        assert_eq!(ranges(&locks), [(LockType::Read, 0, 50)]);
+        assert!(locks
+            .record_conflict(&record(OWNER, LockType::Write, 40, 60))
+            .is_some());
+    }
+}
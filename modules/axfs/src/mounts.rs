@@ -3,13 +3,18 @@ use axfs_vfs::{VfsNodeType, VfsOps, VfsResult};
 
 use crate::fs;
 
+/// The devfs mounted on `/dev`, where device drivers add their nodes later.
+#[cfg(feature = "devfs")]
+pub(crate) static DEVFS: lazy_init::LazyInit<Arc<fs::devfs::DeviceFileSystem>> =
+    lazy_init::LazyInit::new();
+
 #[cfg(feature = "devfs")]
 pub(crate) fn devfs() -> Arc<fs::devfs::DeviceFileSystem> {
     let null = fs::devfs::NullDev;
     let zero = fs::devfs::ZeroDev;
     let bar = fs::devfs::ZeroDev;
-    let random = fs::devfs::RandomDev::default();
-    let urandom = fs::devfs::RandomDev::default();
+    let random = crate::chrdev::random::random();
+    let urandom = crate::chrdev::random::urandom();
 
     let devfs = fs::devfs::DeviceFileSystem::new();
     let foo_dir = devfs.mkdir("foo");
@@ -27,13 +32,18 @@ pub(crate) fn devfs() -> Arc<fs::devfs::DeviceFileSystem> {
         let _shm_dir = devfs.mkdir("shm");
         let rtc_dir = devfs.mkdir("misc");
         rtc_dir.add("rtc", Arc::new(testrtc));
+        devfs.add("kmsg", Arc::new(crate::chrdev::kmsg::kmsg()));
+    }
+    let devfs = Arc::new(devfs);
+    if !DEVFS.is_init() {
+        DEVFS.init_by(devfs.clone());
     }
-    Arc::new(devfs)
+    devfs
 }
 
 #[cfg(feature = "ramfs")]
-pub(crate) fn ramfs() -> Arc<fs::ramfs::RamFileSystem> {
-    Arc::new(fs::ramfs::RamFileSystem::new())
+pub(crate) fn tmpfs() -> Arc<fs::tmpfs::TmpFileSystem> {
+    Arc::new(fs::tmpfs::TmpFileSystem::new())
 }
 
 #[cfg(feature = "procfs")]
@@ -55,6 +65,14 @@ pub(crate) fn procfs() -> VfsResult<Arc<fs::ramfs::RamFileSystem>> {
     let file_over = proc_root.clone().lookup("./sys/vm/overcommit_memory")?;
     file_over.write_at(0, b"0\n")?;
 
+    // Create /proc/sys/fs/binfmt_misc, whose files are registered by the
+    // process module
+    proc_root.create("sys/fs", VfsNodeType::Dir)?;
+    proc_root.create("sys/fs/binfmt_misc", VfsNodeType::Dir)?;
+
+    // Create /proc/sys/kernel, whose files are registered by the process module
+    proc_root.create("sys/kernel", VfsNodeType::Dir)?;
+
     // Create /proc/self/stat
     proc_root.create("self", VfsNodeType::Dir)?;
     proc_root.create("self/stat", VfsNodeType::File)?;
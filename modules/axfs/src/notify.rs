@@ -0,0 +1,146 @@
+//! Notifications of filesystem changes, which back `inotify(7)`.
+//!
+//! Events are generated by the high-level operations in [`fops`](crate::fops)
+//! and [`api`](crate::api), so they are reported the same way for every
+//! filesystem. Changes made below these layers, e.g. directly through
+//! [`VfsNodeOps`](axfs_vfs::VfsNodeOps), are not reported.
+
+use alloc::{
+    string::String,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use axfs_vfs::VfsNodeRef;
+use axsync::Mutex;
+use bitflags::bitflags;
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+bitflags! {
+    /// Kinds of filesystem events, the values are the same as the `IN_*`
+    /// masks of inotify.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct FsEventMask: u32 {
+        /// File was modified.
+        const MODIFY = 0x2;
+        /// File opened for writing was closed.
+        const CLOSE_WRITE = 0x8;
+        /// File was moved out of its directory.
+        const MOVED_FROM = 0x40;
+        /// File was moved into a directory.
+        const MOVED_TO = 0x80;
+        /// File was created in a directory.
+        const CREATE = 0x100;
+        /// File was deleted from a directory.
+        const DELETE = 0x200;
+        /// The subject of the event is a directory.
+        const ISDIR = 0x4000_0000;
+    }
+}
+
+/// A filesystem event.
+#[derive(Debug)]
+pub struct FsEvent<'a> {
+    /// What happened.
+    pub mask: FsEventMask,
+    /// The absolute path of the file, with the symbolic links in its parent
+    /// directories resolved.
+    pub path: &'a str,
+    /// Connects the `MOVED_FROM` and `MOVED_TO` events of the same rename,
+    /// 0 for other events.
+    pub cookie: u32,
+}
+
+/// An observer of filesystem events.
+pub trait FsWatcher: Send + Sync {
+    /// Called after a change is made to the filesystem.
+    fn on_event(&self, event: &FsEvent);
+}
+
+static WATCHERS: Mutex<Vec<Weak<dyn FsWatcher>>> = Mutex::new(Vec::new());
+
+/// Whether [`WATCHERS`] may be non-empty, checked first so that file
+/// operations do not take the lock when nobody is watching.
+static HAS_WATCHERS: AtomicBool = AtomicBool::new(false);
+
+static NEXT_COOKIE: AtomicU32 = AtomicU32::new(1);
+
+/// Registers `watcher` to receive all filesystem events until it is dropped.
+pub fn add_watcher(watcher: &Arc<dyn FsWatcher>) {
+    let mut watchers = WATCHERS.lock();
+    watchers.push(Arc::downgrade(watcher));
+    HAS_WATCHERS.store(true, Ordering::Release);
+}
+
+/// Whether any watcher may be registered.
+fn has_watchers() -> bool {
+    HAS_WATCHERS.load(Ordering::Acquire)
+}
+
+fn watchers() -> Vec<Arc<dyn FsWatcher>> {
+    if !has_watchers() {
+        return Vec::new();
+    }
+    let mut watchers = WATCHERS.lock();
+    watchers.retain(|watcher| watcher.strong_count() > 0);
+    if watchers.is_empty() {
+        HAS_WATCHERS.store(false, Ordering::Release);
+    }
+    watchers.iter().filter_map(Weak::upgrade).collect()
+}
+
+/// The path reported in the events of `path`, i.e. its absolute form with the
+/// links in the parent directories resolved.
+///
+/// Returns `None` if `path` is relative to an opened directory, whose path
+/// is unknown.
+pub(crate) fn event_path(dir: Option<&VfsNodeRef>, path: &str) -> Option<String> {
+    if dir.is_some() && !path.starts_with('/') {
+        return None;
+    }
+    let path = crate::root::absolute_path(path).ok()?;
+    let path = path.trim_end_matches('/');
+    let (parent, name) = path.rsplit_once('/')?;
+    if name.is_empty() {
+        return None;
+    }
+    let parent = crate::root::real_path(&(String::from(parent) + "/"))
+        .unwrap_or_else(|_| String::from(parent) + "/");
+    let mut path = String::from(parent.trim_end_matches('/'));
+    path.push('/');
+    path.push_str(name);
+    Some(path)
+}
+
+/// Reports an event of the file at `path` to all watchers.
+pub(crate) fn notify(dir: Option<&VfsNodeRef>, path: &str, mask: FsEventMask) {
+    notify_with_cookie(dir, path, mask, 0)
+}
+
+fn notify_with_cookie(dir: Option<&VfsNodeRef>, path: &str, mask: FsEventMask, cookie: u32) {
+    let watchers = watchers();
+    if watchers.is_empty() {
+        return;
+    }
+    if let Some(path) = event_path(dir, path) {
+        let event = FsEvent {
+            mask,
+            path: &path,
+            cookie,
+        };
+        for watcher in watchers {
+            watcher.on_event(&event);
+        }
+    }
+}
+
+/// Reports the `MOVED_FROM` and `MOVED_TO` events of a rename.
+pub(crate) fn notify_rename(old: &str, new: &str, is_dir: bool) {
+    let cookie = NEXT_COOKIE.fetch_add(1, Ordering::Relaxed);
+    let isdir = if is_dir {
+        FsEventMask::ISDIR
+    } else {
+        FsEventMask::empty()
+    };
+    notify_with_cookie(None, old, FsEventMask::MOVED_FROM | isdir, cookie);
+    notify_with_cookie(None, new, FsEventMask::MOVED_TO | isdir, cookie);
+}
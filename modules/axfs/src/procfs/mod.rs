@@ -0,0 +1,93 @@
+//! Files in `/proc` whose contents are generated by the kernel.
+//!
+//! procfs is a ramfs, so such a file only has a placeholder regular file in
+//! the directory tree. The kernel registers how to open it by [`register`], and
+//! the syscall layer calls [`open`] to create a [`FileIO`] object of the file
+//! instead of opening the placeholder, the same as [`chrdev`](crate::chrdev).
+//!
+//! Most of such files are small text files, which can be registered by
+//! [`register_text`] with the functions to generate and parse their contents.
+
+mod text;
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use axerrno::AxResult;
+use axsync::Mutex;
+
+use crate::api::{FileIO, OpenFlags};
+
+pub use self::text::{ShowFn, StoreFn};
+
+/// Creates the [`FileIO`] object of a procfs file when it is opened.
+pub type OpenFn = dyn Fn(OpenFlags) -> AxResult<Arc<dyn FileIO>> + Send + Sync;
+
+/// The registered files, keyed by their paths relative to `/proc`.
+static FILES: Mutex<BTreeMap<String, Box<OpenFn>>> = Mutex::new(BTreeMap::new());
+
+/// Registers a file at `path` relative to `/proc`, e.g., `sys/kernel/axlog`,
+/// `open` is called every time the file is opened.
+///
+/// The placeholder file is created if it does not exist, but its parent
+/// directory must exist.
+pub fn register(
+    path: &str,
+    open: impl Fn(OpenFlags) -> AxResult<Arc<dyn FileIO>> + Send + Sync + 'static,
+) -> AxResult {
+    let path = path.trim_start_matches('/');
+    let abs_path = format!("/proc/{}", path);
+    if crate::root::lookup(None, &abs_path).is_err() {
+        crate::root::create_file(None, &abs_path)?;
+    }
+    FILES.lock().insert(String::from(path), Box::new(open));
+    Ok(())
+}
+
+/// Registers a text file at `path` relative to `/proc`, whose contents are
+/// generated by `show` when it is opened.
+///
+/// Data written to it is passed to `store` with the surrounding whitespaces
+/// trimmed, and the file cannot be opened for writing if `store` is [`None`].
+pub fn register_text(path: &str, show: ShowFn, store: Option<StoreFn>) -> AxResult {
+    register(path, move |flags| {
+        Ok(Arc::new(text::TextFile::new(show, store, flags)?))
+    })
+}
+
+/// Opens the file at `path` if it is a registered procfs file, otherwise
+/// returns [`None`]. `path` must be an absolute path without links.
+pub fn open(path: &str, flags: OpenFlags) -> Option<AxResult<Arc<dyn FileIO>>> {
+    let path = path.strip_prefix("/proc/")?;
+    let files = FILES.lock();
+    let open = files.get(path)?;
+    Some(open(flags))
+}
+
+/// Registers the procfs files provided by this crate.
+pub(crate) fn init() {
+    #[cfg(feature = "monolithic")]
+    register_text("sys/kernel/axlog", show_log_filter, Some(store_log_filter))
+        .expect("fail to create /proc/sys/kernel/axlog");
+}
+
+/// Shows the log filter of [`axlog`] in `/proc/sys/kernel/axlog`.
+#[cfg(feature = "monolithic")]
+fn show_log_filter() -> String {
+    let mut spec = String::new();
+    let _ = axlog::write_log_filter(&mut spec);
+    spec.push('\n');
+    spec
+}
+
+/// Sets the log filter of [`axlog`] written to `/proc/sys/kernel/axlog`, see
+/// [`axlog::set_log_filter`] for the format.
+#[cfg(feature = "monolithic")]
+fn store_log_filter(spec: &str) -> AxResult {
+    axlog::set_log_filter(spec).map_err(|err| {
+        warn!("invalid log filter {:?}: {}", spec, err);
+        axerrno::AxError::InvalidInput
+    })
+}
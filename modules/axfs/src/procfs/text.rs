@@ -0,0 +1,124 @@
+//! Text files in procfs, such as `/proc/sys/kernel/axlog`.
+//!
+//! The contents are generated when the file is opened, so that reading it in
+//! several calls gets a consistent snapshot. Each write is passed to the
+//! handler as a whole, like the sysctl files of Linux.
+
+use alloc::string::String;
+use axerrno::{AxError, AxResult};
+use axio::SeekFrom;
+use axsync::Mutex;
+
+use crate::api::{FileIO, FileIOType, Kstat, OpenFlags};
+
+const S_IFREG: u32 = 0o100000;
+
+/// Generates the contents of a text file.
+pub type ShowFn = fn() -> String;
+
+/// Handles the data written to a text file.
+pub type StoreFn = fn(&str) -> AxResult;
+
+/// An opened text file.
+pub(super) struct TextFile {
+    content: String,
+    pos: Mutex<usize>,
+    store: Option<StoreFn>,
+    flags: Mutex<OpenFlags>,
+}
+
+impl TextFile {
+    pub(super) fn new(show: ShowFn, store: Option<StoreFn>, flags: OpenFlags) -> AxResult<Self> {
+        if flags.writable() && store.is_none() {
+            return Err(AxError::PermissionDenied);
+        }
+        Ok(Self {
+            content: show(),
+            pos: Mutex::new(0),
+            store,
+            flags: Mutex::new(flags),
+        })
+    }
+}
+
+impl FileIO for TextFile {
+    fn read(&self, buf: &mut [u8]) -> AxResult<usize> {
+        let mut pos = self.pos.lock();
+        let start = (*pos).min(self.content.len());
+        let len = buf.len().min(self.content.len() - start);
+        buf[..len].copy_from_slice(&self.content.as_bytes()[start..start + len]);
+        *pos = start + len;
+        Ok(len)
+    }
+
+    fn write(&self, buf: &[u8]) -> AxResult<usize> {
+        let store = self.store.ok_or(AxError::PermissionDenied)?;
+        let text = core::str::from_utf8(buf).map_err(|_| AxError::InvalidInput)?;
+        store(text.trim())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&self) -> AxResult {
+        Ok(())
+    }
+
+    fn seek(&self, pos: SeekFrom) -> AxResult<u64> {
+        let mut cur = self.pos.lock();
+        let new = match pos {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::Current(off) => (*cur as u64).checked_add_signed(off),
+            SeekFrom::End(off) => (self.content.len() as u64).checked_add_signed(off),
+        };
+        let new = new.ok_or(AxError::InvalidInput)?;
+        *cur = new as usize;
+        Ok(new)
+    }
+
+    fn readable(&self) -> bool {
+        self.flags.lock().readable()
+    }
+
+    fn writable(&self) -> bool {
+        self.flags.lock().writable()
+    }
+
+    fn executable(&self) -> bool {
+        false
+    }
+
+    fn get_type(&self) -> FileIOType {
+        FileIOType::FileDesc
+    }
+
+    fn get_stat(&self) -> AxResult<Kstat> {
+        let mode = if self.store.is_some() { 0o644 } else { 0o444 };
+        Ok(Kstat {
+            st_mode: S_IFREG | mode,
+            st_nlink: 1,
+            st_blksize: 4096,
+            ..Default::default()
+        })
+    }
+
+    fn set_status(&self, flags: OpenFlags) -> bool {
+        *self.flags.lock() = flags;
+        true
+    }
+
+    fn get_status(&self) -> OpenFlags {
+        *self.flags.lock()
+    }
+
+    fn set_close_on_exec(&self, is_set: bool) -> bool {
+        self.flags.lock().set(OpenFlags::CLOEXEC, is_set);
+        true
+    }
+
+    fn ready_to_read(&self) -> bool {
+        true
+    }
+
+    fn ready_to_write(&self) -> bool {
+        true
+    }
+}
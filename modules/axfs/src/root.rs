@@ -12,26 +12,31 @@ use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType, VfsOps, VfsResu
 use axsync::Mutex;
 use lazy_init::LazyInit;
 
+use crate::link::{LinkOpsFn, VfsLinkOps, MAX_SYMLINK_DEPTH};
 use crate::{api::FileType, fs, mounts};
 
 static CURRENT_DIR_PATH: Mutex<String> = Mutex::new(String::new());
 static CURRENT_DIR: LazyInit<Mutex<VfsNodeRef>> = LazyInit::new();
 
 struct MountPoint {
-    path: &'static str,
+    path: String,
     fs: Arc<dyn VfsOps>,
+    links: Option<LinkOpsFn>,
 }
 
 struct RootDirectory {
     main_fs: Arc<dyn VfsOps>,
-    mounts: Vec<MountPoint>,
+    main_links: Option<LinkOpsFn>,
+    /// Filesystems can be mounted and unmounted after the root directory is
+    /// created, e.g., by mount(2).
+    mounts: Mutex<Vec<MountPoint>>,
 }
 
 static ROOT_DIR: LazyInit<Arc<RootDirectory>> = LazyInit::new();
 
 impl MountPoint {
-    pub fn new(path: &'static str, fs: Arc<dyn VfsOps>) -> Self {
-        Self { path, fs }
+    pub fn new(path: String, fs: Arc<dyn VfsOps>, links: Option<LinkOpsFn>) -> Self {
+        Self { path, fs, links }
     }
 }
 
@@ -42,46 +47,70 @@ impl Drop for MountPoint {
 }
 
 impl RootDirectory {
-    pub const fn new(main_fs: Arc<dyn VfsOps>) -> Self {
+    pub const fn new(main_fs: Arc<dyn VfsOps>, main_links: Option<LinkOpsFn>) -> Self {
         Self {
             main_fs,
-            mounts: Vec::new(),
+            main_links,
+            mounts: Mutex::new(Vec::new()),
         }
     }
 
-    pub fn mount(&mut self, path: &'static str, fs: Arc<dyn VfsOps>) -> AxResult {
+    pub fn mount(&self, path: &str, fs: Arc<dyn VfsOps>) -> AxResult {
+        self.mount_with_links(path, fs, None)
+    }
+
+    /// Mounts `fs` at `path`, whose nodes support links if `links` is given.
+    pub fn mount_with_links(
+        &self,
+        path: &str,
+        fs: Arc<dyn VfsOps>,
+        links: Option<LinkOpsFn>,
+    ) -> AxResult {
         if path == "/" {
             return ax_err!(InvalidInput, "cannot mount root filesystem");
         }
         if !path.starts_with('/') {
             return ax_err!(InvalidInput, "mount path must start with '/'");
         }
-        if self.mounts.iter().any(|mp| mp.path == path) {
+        let mut mounts = self.mounts.lock();
+        if mounts.iter().any(|mp| mp.path == path) {
             return ax_err!(InvalidInput, "mount point already exists");
         }
         // create the mount point in the main filesystem if it does not exist
         self.main_fs.root_dir().create(path, FileType::Dir)?;
         fs.mount(path, self.main_fs.root_dir().lookup(path)?)?;
-        self.mounts.push(MountPoint::new(path, fs));
+        mounts.push(MountPoint::new(path.into(), fs, links));
         Ok(())
     }
 
-    pub fn _umount(&mut self, path: &str) {
-        self.mounts.retain(|mp| mp.path != path);
+    /// Unmounts the filesystem at `path`.
+    pub fn umount(&self, path: &str) -> AxResult {
+        let mut mounts = self.mounts.lock();
+        let idx = mounts
+            .iter()
+            .position(|mp| mp.path == path)
+            .ok_or(AxError::InvalidInput)?;
+        let prefix = path.to_string() + "/";
+        if mounts.iter().any(|mp| mp.path.starts_with(&prefix)) {
+            return ax_err!(ResourceBusy, "other filesystems are mounted under it");
+        }
+        let mp = mounts.remove(idx);
+        drop(mounts);
+        drop(mp); // unmounts the filesystem
+        Ok(())
     }
 
     pub fn contains(&self, path: &str) -> bool {
-        self.mounts.iter().any(|mp| mp.path == path)
+        self.mounts.lock().iter().any(|mp| mp.path == path)
     }
 
-    fn lookup_mounted_fs<F, T>(&self, path: &str, f: F) -> AxResult<T>
-    where
-        F: FnOnce(Arc<dyn VfsOps>, &str) -> AxResult<T>,
-    {
+    /// Finds the filesystem that `path` belongs to, and returns it with its
+    /// link operations and the path relative to its root.
+    fn mounted_at<'a>(&self, path: &'a str) -> (Arc<dyn VfsOps>, Option<LinkOpsFn>, &'a str) {
         debug!("lookup at root: {}", path);
         let path = path.trim_matches('/');
         if let Some(rest) = path.strip_prefix("./") {
-            return self.lookup_mounted_fs(rest, f);
+            return self.mounted_at(rest);
         }
 
         let mut idx = 0;
@@ -90,7 +119,8 @@ impl RootDirectory {
         // Find the filesystem that has the longest mounted path match
         // TODO: more efficient, e.g. trie
 
-        for (i, mp) in self.mounts.iter().enumerate() {
+        let mounts = self.mounts.lock();
+        for (i, mp) in mounts.iter().enumerate() {
             // skip the first '/'
             // two conditions
             // 1. path == mp.path, e.g. dev
@@ -105,11 +135,84 @@ impl RootDirectory {
             }
         }
         if max_len == 0 {
-            f(self.main_fs.clone(), path) // not matched any mount point
+            // not matched any mount point
+            (self.main_fs.clone(), self.main_links, path)
         } else {
-            f(self.mounts[idx].fs.clone(), &path[max_len..]) // matched at `idx`
+            let mp = &mounts[idx];
+            (mp.fs.clone(), mp.links, &path[max_len..]) // matched at `idx`
         }
     }
+
+    fn lookup_mounted_fs<F, T>(&self, path: &str, f: F) -> AxResult<T>
+    where
+        F: FnOnce(Arc<dyn VfsOps>, &str) -> AxResult<T>,
+    {
+        let (fs, _, rest_path) = self.mounted_at(path);
+        f(fs, rest_path)
+    }
+
+    /// Calls `f` with the link operations of the node at `path`.
+    ///
+    /// Fails with `PermissionDenied` if the filesystem does not support links.
+    fn with_link_ops<F, T>(&self, path: &str, f: F) -> AxResult<T>
+    where
+        F: FnOnce(&dyn VfsLinkOps) -> AxResult<T>,
+    {
+        let (fs, links, rest_path) = self.mounted_at(path);
+        let node = fs.root_dir().lookup(rest_path)?;
+        match links.and_then(|links| links(&node)) {
+            Some(ops) => f(ops),
+            None => ax_err!(
+                PermissionDenied,
+                "links are not supported by this filesystem"
+            ),
+        }
+    }
+
+    /// Creates a symbolic link `name` in the directory `dir`.
+    fn symlink(&self, target: &str, dir: &str, name: &str) -> AxResult {
+        self.with_link_ops(dir, |dir| dir.symlink(name, target))
+    }
+
+    /// Creates a hard link `name` in the directory `dir` to `old_path`.
+    fn link(&self, old_path: &str, dir: &str, name: &str) -> AxResult {
+        let (old_fs, _, old_rest) = self.mounted_at(old_path);
+        let (new_fs, _, _) = self.mounted_at(dir);
+        if !Arc::ptr_eq(&old_fs, &new_fs) {
+            return ax_err!(InvalidInput, "cannot link across filesystems");
+        }
+        if old_rest.is_empty() {
+            return ax_err!(PermissionDenied); // cannot link mount points
+        }
+        let node = old_fs.root_dir().lookup(old_rest)?;
+        self.with_link_ops(dir, |dir| dir.link(name, &node))
+    }
+
+    fn readlink(&self, path: &str) -> AxResult<String> {
+        match self.with_link_ops(path, |node| node.readlink()) {
+            Err(AxError::PermissionDenied) => ax_err!(InvalidInput), // not a link
+            res => res,
+        }
+    }
+
+    /// Returns the inode number and the number of hard links of the node at
+    /// `path`.
+    fn inode_info(&self, path: &str) -> AxResult<(u64, u64)> {
+        self.with_link_ops(path, |node| Ok((node.ino()?, node.nlink()?)))
+            .map_err(|e| match e {
+                AxError::PermissionDenied => AxError::Unsupported,
+                e => e,
+            })
+    }
+
+    /// Returns the identity of the node at `path`, i.e. the filesystem it
+    /// belongs to, identified by the address of its [`VfsOps`], and its
+    /// inode number in that filesystem.
+    fn inode_id(&self, path: &str) -> AxResult<(usize, u64)> {
+        let (fs, _, _) = self.mounted_at(path);
+        let (ino, _) = self.inode_info(path)?;
+        Ok((Arc::as_ptr(&fs) as *const () as usize, ino))
+    }
 }
 
 impl VfsNodeOps for RootDirectory {
@@ -161,28 +264,34 @@ pub(crate) fn init_rootfs(disk: crate::dev::Disk) {
     cfg_if::cfg_if! {
         if #[cfg(feature = "myfs")] { // override the default filesystem
             let main_fs = fs::myfs::new_myfs(disk);
+            let main_links = None;
         } else if #[cfg(feature = "lwext4_rust")] {
             static EXT4_FS: LazyInit<Arc<fs::lwext4_rust::Ext4FileSystem>> = LazyInit::new();
             EXT4_FS.init_by(Arc::new(fs::lwext4_rust::Ext4FileSystem::new(disk)));
             let main_fs = EXT4_FS.clone();
+            let main_links: Option<LinkOpsFn> = Some(fs::lwext4_rust::Ext4FileSystem::link_ops);
         } else if #[cfg(feature = "ext4_rs")] {
             static EXT4_FS: LazyInit<Arc<fs::ext4_rs::Ext4FileSystem>> = LazyInit::new();
             EXT4_FS.init_by(Arc::new(fs::ext4_rs::Ext4FileSystem::new(disk)));
             let main_fs = EXT4_FS.clone();
+            let main_links: Option<LinkOpsFn> = Some(fs::ext4_rs::Ext4FileSystem::link_ops);
         } else if #[cfg(feature = "another_ext4")] {
             static EXT4_FS: LazyInit<Arc<fs::another_ext4::Ext4FileSystem>> = LazyInit::new();
             EXT4_FS.init_by(Arc::new(fs::another_ext4::Ext4FileSystem::new(disk)));
             let main_fs = EXT4_FS.clone();
+            let main_links: Option<LinkOpsFn> = Some(fs::another_ext4::Ext4FileSystem::link_ops);
         } else if #[cfg(feature = "fatfs")] {
             // default to be fatfs
             static FAT_FS: LazyInit<Arc<fs::fatfs::FatFileSystem>> = LazyInit::new();
             FAT_FS.init_by(Arc::new(fs::fatfs::FatFileSystem::new(disk)));
             FAT_FS.init();
             let main_fs = FAT_FS.clone();
+            // FAT cannot store links
+            let main_links = None;
         }
     }
 
-    let mut root_dir = RootDirectory::new(main_fs);
+    let root_dir = RootDirectory::new(main_fs, main_links);
 
     #[cfg(feature = "devfs")]
     root_dir
@@ -191,18 +300,30 @@ pub(crate) fn init_rootfs(disk: crate::dev::Disk) {
 
     #[cfg(feature = "ramfs")]
     root_dir
-        .mount("/dev/shm", mounts::ramfs())
-        .expect("failed to mount devfs at /dev/shm");
+        .mount_with_links(
+            "/dev/shm",
+            mounts::tmpfs(),
+            Some(fs::tmpfs::TmpFileSystem::link_ops),
+        )
+        .expect("failed to mount tmpfs at /dev/shm");
 
     #[cfg(feature = "ramfs")]
     root_dir
-        .mount("/tmp", mounts::ramfs())
-        .expect("failed to mount ramfs at /tmp");
+        .mount_with_links(
+            "/tmp",
+            mounts::tmpfs(),
+            Some(fs::tmpfs::TmpFileSystem::link_ops),
+        )
+        .expect("failed to mount tmpfs at /tmp");
 
     #[cfg(feature = "ramfs")]
     root_dir
-        .mount("/var", mounts::ramfs())
-        .expect("failed to mount ramfs at /tmp");
+        .mount_with_links(
+            "/var",
+            mounts::tmpfs(),
+            Some(fs::tmpfs::TmpFileSystem::link_ops),
+        )
+        .expect("failed to mount tmpfs at /var");
 
     // Mount another ramfs as procfs
     #[cfg(feature = "procfs")]
@@ -221,6 +342,19 @@ pub(crate) fn init_rootfs(disk: crate::dev::Disk) {
     *CURRENT_DIR_PATH.lock() = "/".into();
 }
 
+/// Mounts `fs` at `path` after the filesystems are initialized.
+#[allow(unused)]
+pub(crate) fn mount(path: &str, fs: Arc<dyn VfsOps>, links: LinkOpsFn) -> AxResult {
+    let path = absolute_path(path)?;
+    ROOT_DIR.mount_with_links(path.trim_end_matches('/'), fs, Some(links))
+}
+
+/// Unmounts the filesystem mounted at `path`.
+pub(crate) fn umount(path: &str) -> AxResult {
+    let path = absolute_path(path)?;
+    ROOT_DIR.umount(path.trim_end_matches('/'))
+}
+
 fn parent_node_of(dir: Option<&VfsNodeRef>, path: &str) -> VfsNodeRef {
     if path.starts_with('/') {
         ROOT_DIR.clone()
@@ -238,11 +372,109 @@ pub(crate) fn absolute_path(path: &str) -> AxResult<String> {
     }
 }
 
-pub(crate) fn lookup(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<VfsNodeRef> {
+/// Resolves the symbolic links in `path`, following the link in the last
+/// component only if `follow_last` is set.
+///
+/// Returns `None` if no link is encountered, so that `path` can be used as it
+/// is. Otherwise returns the resolved absolute path. Fails with `InvalidData`
+/// (`ELOOP`) if more than [`MAX_SYMLINK_DEPTH`] links are followed.
+fn resolve_links(
+    dir: Option<&VfsNodeRef>,
+    path: &str,
+    follow_last: bool,
+) -> AxResult<Option<String>> {
+    if dir.is_some() && !path.starts_with('/') {
+        // the path of an opened directory is unknown, links are not resolved
+        return Ok(None);
+    }
+    if let Ok(node) = parent_node_of(dir, path).lookup(path) {
+        if !must_follow(&node, path, follow_last) {
+            return Ok(None);
+        }
+    }
+    // components to be resolved, in reverse order
+    let mut pending: Vec<String> = path.split('/').rev().map(String::from).collect();
+    if !path.starts_with('/') {
+        let cwd = CURRENT_DIR_PATH.lock().clone();
+        pending.extend(cwd.split('/').rev().map(String::from));
+    }
+    let follow_last = follow_last || path.ends_with('/');
+    let mut resolved: Vec<String> = Vec::new();
+    let mut depth = 0;
+    // the number of resolved components when one of them is missing, as
+    // nothing under it can be a link
+    let mut missing_at = None;
+    while let Some(name) = pending.pop() {
+        match name.as_str() {
+            "" | "." => continue,
+            ".." => {
+                resolved.pop();
+                if missing_at.is_some_and(|len| resolved.len() < len) {
+                    missing_at = None;
+                }
+                continue;
+            }
+            _ => resolved.push(name),
+        }
+        if missing_at.is_some() || (pending.is_empty() && !follow_last) {
+            continue;
+        }
+        let current = String::from("/") + &resolved.join("/");
+        let node = match ROOT_DIR.clone().lookup(&current) {
+            Ok(node) => node,
+            Err(_) => {
+                missing_at = Some(resolved.len());
+                continue;
+            }
+        };
+        if is_symlink(&node) {
+            let target = ROOT_DIR.readlink(&current)?;
+            depth += 1;
+            if depth > MAX_SYMLINK_DEPTH {
+                return ax_err!(InvalidData, "too many levels of symbolic links");
+            }
+            resolved.pop();
+            if target.starts_with('/') {
+                resolved.clear();
+            }
+            pending.extend(target.split('/').rev().map(String::from));
+        }
+    }
+    if depth == 0 {
+        return Ok(None);
+    }
+    let mut real_path = String::from("/") + &resolved.join("/");
+    if path.ends_with('/') && !real_path.ends_with('/') {
+        real_path.push('/');
+    }
+    Ok(Some(real_path))
+}
+
+fn is_symlink(node: &VfsNodeRef) -> bool {
+    node.get_attr()
+        .is_ok_and(|attr| attr.file_type() == VfsNodeType::SymLink)
+}
+
+/// Whether the link `node` found at `path` has to be followed.
+fn must_follow(node: &VfsNodeRef, path: &str, follow_last: bool) -> bool {
+    (follow_last || path.ends_with('/')) && is_symlink(node)
+}
+
+fn lookup_at(dir: Option<&VfsNodeRef>, path: &str, follow_last: bool) -> AxResult<VfsNodeRef> {
     if path.is_empty() {
         return ax_err!(NotFound);
     }
-    let node = parent_node_of(dir, path).lookup(path)?;
+    // The filesystems do not follow links by themselves, so the lookup only
+    // succeeds if there is no link in the middle of `path`, which is the
+    // common case.
+    let node = match parent_node_of(dir, path).lookup(path) {
+        Ok(node) if !must_follow(&node, path, follow_last) => node,
+        _ => {
+            let resolved = resolve_links(dir, path, follow_last)?;
+            let path = resolved.as_deref().unwrap_or(path);
+            parent_node_of(dir, path).lookup(path)?
+        }
+    };
     if path.ends_with('/') && !node.get_attr()?.is_dir() {
         ax_err!(NotADirectory)
     } else {
@@ -250,19 +482,28 @@ pub(crate) fn lookup(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<VfsNodeRe
     }
 }
 
+pub(crate) fn lookup(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<VfsNodeRef> {
+    lookup_at(dir, path, true)
+}
+
 pub(crate) fn create_file(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<VfsNodeRef> {
     if path.is_empty() {
         return ax_err!(NotFound);
     } else if path.ends_with('/') {
         return ax_err!(NotADirectory);
     }
+    // creating through a dangling link creates its target
+    let resolved = resolve_links(dir, path, true)?;
+    let path = resolved.as_deref().unwrap_or(path);
     let parent = parent_node_of(dir, path);
     parent.create(path, VfsNodeType::File)?;
     parent.lookup(path)
 }
 
 pub(crate) fn create_dir(dir: Option<&VfsNodeRef>, path: &str) -> AxResult {
-    match lookup(dir, path) {
+    let resolved = resolve_links(dir, path.trim_end_matches('/'), false)?;
+    let path = resolved.as_deref().unwrap_or(path);
+    match lookup_at(dir, path, false) {
         Ok(_) => ax_err!(AlreadyExists),
         Err(AxError::NotFound) => parent_node_of(dir, path).create(path, VfsNodeType::Dir),
         Err(e) => Err(e),
@@ -270,7 +511,9 @@ pub(crate) fn create_dir(dir: Option<&VfsNodeRef>, path: &str) -> AxResult {
 }
 
 pub(crate) fn remove_file(dir: Option<&VfsNodeRef>, path: &str) -> AxResult {
-    let node = lookup(dir, path)?;
+    let resolved = resolve_links(dir, path, false)?;
+    let path = resolved.as_deref().unwrap_or(path);
+    let node = lookup_at(dir, path, false)?;
     let attr = node.get_attr()?;
     if attr.is_dir() {
         ax_err!(IsADirectory)
@@ -305,8 +548,11 @@ pub(crate) fn remove_dir(dir: Option<&VfsNodeRef>, path: &str) -> AxResult {
         ax_err!(NotADirectory)
     } else if !attr.perm().owner_writable() {
         ax_err!(PermissionDenied)
+    } else if is_link(dir, path.trim_end_matches('/')) {
+        ax_err!(NotADirectory) // rmdir does not follow the last link
     } else {
-        parent_node_of(dir, path).remove(path)
+        let resolved = resolve_links(dir, path, false)?;
+        parent_node_of(dir, path).remove(resolved.as_deref().unwrap_or(path))
     }
 }
 
@@ -315,7 +561,10 @@ pub(crate) fn current_dir() -> AxResult<String> {
 }
 
 pub(crate) fn set_current_dir(path: &str) -> AxResult {
-    let mut abs_path = absolute_path(path)?;
+    let mut abs_path = match resolve_links(None, path, true)? {
+        Some(real_path) => real_path,
+        None => absolute_path(path)?,
+    };
     if !abs_path.ends_with('/') {
         abs_path += "/";
     }
@@ -339,9 +588,86 @@ pub(crate) fn set_current_dir(path: &str) -> AxResult {
 }
 
 pub(crate) fn rename(old: &str, new: &str) -> AxResult {
-    if parent_node_of(None, new).lookup(new).is_ok() {
+    let old = resolve_links(None, old, false)?.unwrap_or_else(|| old.into());
+    let new = resolve_links(None, new, false)?.unwrap_or_else(|| new.into());
+    if parent_node_of(None, &new).lookup(&new).is_ok() {
         warn!("dst file already exist, now remove it");
-        remove_file(None, new)?;
+        remove_file(None, &new)?;
+    }
+    parent_node_of(None, &old).rename(&old, &new)
+}
+
+/// Whether `path` itself is a symbolic link.
+fn is_link(dir: Option<&VfsNodeRef>, path: &str) -> bool {
+    lookup_at(dir, path, false).is_ok_and(|node| is_symlink(&node))
+}
+
+pub(crate) fn symlink(target: &str, path: &str) -> AxResult {
+    if path.is_empty() || target.is_empty() {
+        return ax_err!(NotFound);
+    }
+    let resolved = resolve_links(None, path, false)?;
+    let path = absolute_path(resolved.as_deref().unwrap_or(path))?;
+    if lookup_at(None, &path, false).is_ok() {
+        return ax_err!(AlreadyExists);
+    }
+    let (parent, name) = parent_dir_of(&path)?;
+    ROOT_DIR.symlink(target, &parent, name)
+}
+
+/// Splits an absolute path into its parent directory, with the links in it
+/// resolved, and its last component.
+fn parent_dir_of(path: &str) -> AxResult<(String, &str)> {
+    let (parent, name) = path.rsplit_once('/').unwrap_or(("", path));
+    let parent = real_path(&(String::from(parent) + "/"))?;
+    if !lookup(None, &parent)?.get_attr()?.is_dir() {
+        return ax_err!(NotADirectory);
+    }
+    Ok((parent, name))
+}
+
+pub(crate) fn hard_link(old: &str, new: &str) -> AxResult {
+    let old = absolute_path(&resolve_links(None, old, false)?.unwrap_or_else(|| old.into()))?;
+    let new = absolute_path(&resolve_links(None, new, false)?.unwrap_or_else(|| new.into()))?;
+    if lookup_at(None, &old, false)?.get_attr()?.is_dir() {
+        return ax_err!(PermissionDenied); // hard links to directories are not allowed
+    }
+    if lookup_at(None, &new, false).is_ok() {
+        return ax_err!(AlreadyExists);
+    }
+    let (parent, name) = parent_dir_of(&new)?;
+    ROOT_DIR.link(&old, &parent, name)
+}
+
+pub(crate) fn read_link(path: &str) -> AxResult<String> {
+    let resolved = resolve_links(None, path, false)?;
+    let path = absolute_path(resolved.as_deref().unwrap_or(path))?;
+    match ROOT_DIR.readlink(&path) {
+        Ok(target) => Ok(target),
+        // distinguish between not a link and not found
+        Err(_) => lookup_at(None, &path, false).and_then(|_| ax_err!(InvalidInput)),
+    }
+}
+
+pub(crate) fn inode_info(path: &str, follow_last: bool) -> AxResult<(u64, u64)> {
+    let path = match resolve_links(None, path, follow_last)? {
+        Some(real_path) => real_path,
+        None => absolute_path(path)?,
+    };
+    ROOT_DIR.inode_info(&path)
+}
+
+pub(crate) fn inode_id(path: &str) -> AxResult<(usize, u64)> {
+    ROOT_DIR.inode_id(&real_path(path)?)
+}
+
+pub(crate) fn symlink_attr(path: &str) -> AxResult<VfsNodeAttr> {
+    lookup_at(None, path, false)?.get_attr()
+}
+
+pub(crate) fn real_path(path: &str) -> AxResult<String> {
+    match resolve_links(None, path, true)? {
+        Some(real_path) => absolute_path(&real_path),
+        None => absolute_path(path),
     }
-    parent_node_of(None, old).rename(old, new)
 }
@@ -252,6 +252,68 @@ fn test_devfs_ramfs() -> Result<()> {
     Ok(())
 }
 
+fn test_symlink() -> Result<()> {
+    let fname = "./short.txt";
+    let lname = "/tmp/short-link";
+    println!("test symlink {:?} -> {:?}:", lname, fname);
+    assert_err!(fs::symlink("short.txt", "/short-link"), PermissionDenied);
+    assert_eq!(fs::symlink("../short.txt", lname), Ok(()));
+    assert_err!(fs::symlink("../short.txt", lname), AlreadyExists);
+    assert_eq!(fs::read_link(lname), Ok("../short.txt".into()));
+    assert_err!(fs::read_link(fname), InvalidInput);
+    assert_err!(fs::read_link("/tmp/no-such-link"), NotFound);
+    assert_eq!(fs::read_to_string(lname)?, "Rust is cool!\n");
+    assert_eq!(fs::real_path(lname), Ok("/short.txt".into()));
+    assert!(fs::symlink_metadata(lname)?.file_type().is_symlink());
+    assert!(fs::read_dir("tmp")?.any(|e| e.is_ok_and(|e| e.file_name() == "short-link")));
+
+    // links to directories are followed in the middle of a path
+    assert_eq!(fs::symlink("/very/long/path", "/tmp/path-link"), Ok(()));
+    assert_eq!(
+        fs::read_to_string("/tmp/path-link/../path/test.txt")?,
+        "Rust is cool!\n"
+    );
+    assert_eq!(fs::metadata("tmp/path-link/")?.file_type(), FileType::Dir);
+    assert_err!(fs::remove_dir("tmp/path-link"), NotADirectory);
+
+    // dangling and looping links
+    assert_eq!(fs::symlink("no-such-file", "/tmp/dangling"), Ok(()));
+    assert_err!(fs::metadata("/tmp/dangling"), NotFound);
+    assert_eq!(fs::symlink("/tmp/loop-b", "/tmp/loop-a"), Ok(()));
+    assert_eq!(fs::symlink("/tmp/loop-a", "/tmp/loop-b"), Ok(()));
+    assert_err!(fs::metadata("/tmp/loop-a"), InvalidData);
+
+    // hard links share the inode and count the names
+    fs::write("/tmp/file.txt", "Rust is cool!\n")?;
+    assert_eq!(fs::hard_link("/tmp/file.txt", "/tmp/hard-link"), Ok(()));
+    assert_err!(fs::hard_link(fname, "/tmp/cross-link"), InvalidInput);
+    let (ino, nlink) = fs::inode_info("/tmp/file.txt", true)?;
+    assert_eq!(nlink, 2);
+    assert_eq!(fs::inode_info("/tmp/hard-link", true)?, (ino, 2));
+    assert_eq!(fs::remove_file("/tmp/file.txt"), Ok(()));
+    assert_eq!(fs::inode_info("/tmp/hard-link", true)?, (ino, 1));
+    assert_eq!(fs::read_to_string("/tmp/hard-link")?, "Rust is cool!\n");
+
+    // removing a link does not remove its target
+    assert_eq!(fs::remove_file(lname), Ok(()));
+    assert_err!(fs::read_link(lname), NotFound);
+    assert!(fs::metadata(fname)?.is_file());
+    for link in [
+        "/tmp/path-link",
+        "/tmp/dangling",
+        "/tmp/loop-a",
+        "/tmp/loop-b",
+        "/tmp/hard-link",
+    ] {
+        assert_eq!(fs::remove_file(link), Ok(()));
+    }
+    assert!(fs::metadata("/very/long/path")?.is_dir());
+    assert_eq!(fs::read_dir("tmp").unwrap().count(), 0);
+
+    println!("test_symlink() OK!");
+    Ok(())
+}
+
 pub fn test_all() {
     test_read_write_file().expect("test_read_write_file() failed");
     test_read_dir().expect("test_read_dir() failed");
@@ -259,4 +321,5 @@ pub fn test_all() {
     test_create_file_dir().expect("test_create_file_dir() failed");
     test_remove_file_dir().expect("test_remove_file_dir() failed");
     test_devfs_ramfs().expect("test_devfs_ramfs() failed");
+    test_symlink().expect("test_symlink() failed");
 }
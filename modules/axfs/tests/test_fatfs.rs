@@ -24,4 +24,11 @@ fn test_fatfs() {
     axfs::init_filesystems(AxDeviceContainer::from_one(disk));
 
     test_common::test_all();
+
+    // the written data stays in the buffer cache until sync
+    axfs::api::sync().expect("failed to sync");
+    let stats = axfs::blk::devices()[0].stats();
+    assert!(stats.reads > 0 && stats.writes > 0);
+    assert_eq!((stats.dirty, stats.in_flight), (0, 0));
+    assert!(stats.cache_hits > stats.cache_misses);
 }
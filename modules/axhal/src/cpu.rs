@@ -22,10 +22,22 @@ pub fn this_cpu_is_bsp() -> bool {
     IS_BSP.read_current()
 }
 
+/// Returns the number of CPUs that can be brought up.
+///
+/// It is the number of CPUs in the device tree, but at most
+/// [`axconfig::SMP`], the maximum the kernel is configured for. Without a
+/// device tree, it is [`axconfig::SMP`].
+pub fn cpu_num() -> usize {
+    crate::dtb::cpu_count().map_or(axconfig::SMP, |n| n.min(axconfig::SMP))
+}
+
 #[allow(dead_code)]
 /// Initializes the primary CPU for its pointer.
+///
+/// The device tree must have been parsed, as it decides the number of per-CPU
+/// data areas.
 pub fn init_primary(cpu_id: usize) {
-    percpu::init(axconfig::SMP);
+    percpu::init(cpu_num());
     percpu::set_local_thread_pointer(cpu_id);
     unsafe {
         CPU_ID.write_current_raw(cpu_id);
@@ -0,0 +1,337 @@
+//! Platform discovery from the flattened device tree (FDT) passed by firmware.
+//!
+//! The device tree is parsed once at boot by [`init`], and the results are
+//! kept in static storage, so they can still be used after the boot page
+//! table (which maps the FDT) is replaced. Every query returns [`None`] or an
+//! empty slice if no device tree is available, and callers fall back to the
+//! static platform configuration in [`axconfig`].
+
+use fdt::node::FdtNode;
+use fdt::Fdt;
+use lazy_init::LazyInit;
+
+const MAX_MEM_REGIONS: usize = 8;
+const MAX_VIRTIO_MMIO: usize = 32;
+const MAX_BOOTARGS_LEN: usize = 256;
+
+/// A device found in the device tree.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DeviceInfo {
+    /// Physical address of the first register region.
+    pub paddr: usize,
+    /// Size of the first register region.
+    pub size: usize,
+    /// The IRQ number of the device, as used by [`crate::irq`].
+    pub irq: Option<usize>,
+}
+
+/// The GIC distributor and CPU interface (or redistributor, for GICv3).
+#[derive(Debug, Clone, Copy)]
+pub struct GicInfo {
+    /// Physical address and size of the distributor.
+    pub gicd: (usize, usize),
+    /// Physical address and size of the CPU interface or the redistributor.
+    pub gicc: (usize, usize),
+}
+
+/// A generic PCIe host bridge with ECAM.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PciHostInfo {
+    /// Physical address and size of the ECAM space.
+    pub ecam: (usize, usize),
+    /// End PCI bus number (`bus-range` property).
+    pub bus_end: usize,
+    /// CPU address and size of the I/O port window.
+    pub io: Option<(usize, usize)>,
+    /// CPU address and size of the 32-bit memory window.
+    pub mem32: Option<(usize, usize)>,
+    /// CPU address and size of the 64-bit memory window.
+    pub mem64: Option<(usize, usize)>,
+}
+
+struct PlatformInfo {
+    fdt_region: (usize, usize),
+    cpu_count: usize,
+    timer_frequency: Option<usize>,
+    memory: [(usize, usize); MAX_MEM_REGIONS],
+    memory_count: usize,
+    uart: Option<DeviceInfo>,
+    plic: Option<DeviceInfo>,
+    gic: Option<GicInfo>,
+    virtio_mmio: [DeviceInfo; MAX_VIRTIO_MMIO],
+    virtio_mmio_count: usize,
+    pci: Option<PciHostInfo>,
+    bootargs: [u8; MAX_BOOTARGS_LEN],
+    bootargs_len: usize,
+}
+
+static PLATFORM_INFO: LazyInit<PlatformInfo> = LazyInit::new();
+
+fn be_cells(value: &[u8]) -> impl Iterator<Item = usize> + '_ {
+    value
+        .chunks_exact(4)
+        .map(|c| u32::from_be_bytes([c[0], c[1], c[2], c[3]]) as usize)
+}
+
+fn is_compatible(node: &FdtNode, names: &[&str]) -> bool {
+    node.compatible()
+        .is_some_and(|c| c.all().any(|name| names.contains(&name)))
+}
+
+fn first_reg(node: &FdtNode) -> Option<(usize, usize)> {
+    let reg = node.reg()?.next()?;
+    Some((reg.starting_address as usize, reg.size.unwrap_or(0)))
+}
+
+/// Translates the first entry of the `interrupts` property to an IRQ number.
+///
+/// One cell is a PLIC source; three cells are a GIC interrupt specifier
+/// (type, number, flags), where SPIs start at 32 and PPIs start at 16.
+fn first_irq(node: &FdtNode) -> Option<usize> {
+    let prop = node.property("interrupts")?;
+    let cells = prop.value.len() / 4;
+    let mut iter = be_cells(prop.value);
+    if cells >= 3 && cells % 3 == 0 {
+        let (ty, num) = (iter.next()?, iter.next()?);
+        Some(match ty {
+            0 => num + 32,
+            _ => num + 16,
+        })
+    } else {
+        iter.next()
+    }
+}
+
+fn device_info(node: &FdtNode) -> Option<DeviceInfo> {
+    let (paddr, size) = first_reg(node)?;
+    Some(DeviceInfo {
+        paddr,
+        size,
+        irq: first_irq(node),
+    })
+}
+
+fn find_uart<'b, 'a>(fdt: &'b Fdt<'a>) -> Option<FdtNode<'b, 'a>> {
+    let stdout = fdt
+        .find_node("/chosen")
+        .and_then(|chosen| chosen.property("stdout-path"))
+        .and_then(|prop| prop.as_str())
+        .and_then(|path| fdt.find_node(path.split(':').next().unwrap_or(path)));
+    stdout.or_else(|| fdt.find_compatible(&["ns16550a", "arm,pl011", "snps,dw-apb-uart"]))
+}
+
+fn parse_pci_host(node: &FdtNode) -> Option<PciHostInfo> {
+    let mut info = PciHostInfo {
+        ecam: first_reg(node)?,
+        bus_end: 0xff,
+        ..Default::default()
+    };
+    if let Some(range) = node.property("bus-range") {
+        if let Some(end) = be_cells(range.value).nth(1) {
+            info.bus_end = end;
+        }
+    }
+    // (flags, pci addr hi, pci addr lo), cpu addr (2 cells), size (2 cells)
+    if let Some(ranges) = node.property("ranges") {
+        for entry in ranges.value.chunks_exact(7 * 4) {
+            let mut cells = [0; 7];
+            for (cell, value) in cells.iter_mut().zip(be_cells(entry)) {
+                *cell = value;
+            }
+            let cpu_addr = (cells[3] << 32) | cells[4];
+            let size = (cells[5] << 32) | cells[6];
+            match (cells[0] >> 24) & 0x3 {
+                1 => info.io = Some((cpu_addr, size)),
+                2 => info.mem32 = Some((cpu_addr, size)),
+                3 => info.mem64 = Some((cpu_addr, size)),
+                _ => {}
+            }
+        }
+    }
+    Some(info)
+}
+
+fn parse(fdt: &Fdt) -> PlatformInfo {
+    let mut info = PlatformInfo {
+        fdt_region: (0, fdt.total_size()),
+        cpu_count: fdt.cpus().count(),
+        timer_frequency: fdt
+            .find_node("/cpus")
+            .and_then(|cpus| cpus.property("timebase-frequency"))
+            .and_then(|prop| prop.as_usize()),
+        memory: [(0, 0); MAX_MEM_REGIONS],
+        memory_count: 0,
+        uart: find_uart(fdt).and_then(|node| device_info(&node)),
+        plic: fdt
+            .find_compatible(&["riscv,plic0", "sifive,plic-1.0.0"])
+            .and_then(|node| device_info(&node)),
+        gic: fdt
+            .find_compatible(&["arm,gic-400", "arm,cortex-a15-gic", "arm,gic-v3"])
+            .and_then(|node| {
+                let mut regs = node.reg()?;
+                let gicd = regs.next()?;
+                let gicc = regs.next()?;
+                Some(GicInfo {
+                    gicd: (gicd.starting_address as usize, gicd.size.unwrap_or(0)),
+                    gicc: (gicc.starting_address as usize, gicc.size.unwrap_or(0)),
+                })
+            }),
+        virtio_mmio: [DeviceInfo::default(); MAX_VIRTIO_MMIO],
+        virtio_mmio_count: 0,
+        pci: fdt
+            .find_compatible(&["pci-host-ecam-generic"])
+            .and_then(|node| parse_pci_host(&node)),
+        bootargs: [0; MAX_BOOTARGS_LEN],
+        bootargs_len: 0,
+    };
+
+    let bootargs = fdt
+        .find_node("/chosen")
+        .and_then(|chosen| chosen.property("bootargs"))
+        .and_then(|prop| prop.as_str());
+    if let Some(bootargs) = bootargs {
+        // truncated at a character boundary if it is too long
+        let mut len = bootargs.len().min(MAX_BOOTARGS_LEN);
+        while !bootargs.is_char_boundary(len) {
+            len -= 1;
+        }
+        info.bootargs[..len].copy_from_slice(&bootargs.as_bytes()[..len]);
+        info.bootargs_len = len;
+    }
+
+    for node in fdt.all_nodes() {
+        if node.name.starts_with("memory") {
+            for reg in node.reg().into_iter().flatten() {
+                let size = reg.size.unwrap_or(0);
+                if size > 0 && info.memory_count < MAX_MEM_REGIONS {
+                    info.memory[info.memory_count] = (reg.starting_address as usize, size);
+                    info.memory_count += 1;
+                }
+            }
+        } else if is_compatible(&node, &["virtio,mmio"]) {
+            if let Some(dev) = device_info(&node) {
+                if info.virtio_mmio_count < MAX_VIRTIO_MMIO {
+                    info.virtio_mmio[info.virtio_mmio_count] = dev;
+                    info.virtio_mmio_count += 1;
+                }
+            }
+        }
+    }
+    // QEMU lists the VirtIO MMIO slots in the reverse order of addresses
+    info.virtio_mmio[..info.virtio_mmio_count].sort_unstable_by_key(|dev| dev.paddr);
+    info
+}
+
+/// Parses the device tree at the given address, which must be accessible with
+/// the current page table.
+///
+/// `fdt_paddr` is the physical address of the device tree, which is reserved
+/// from the free memory.
+pub fn init(fdt_ptr: usize, fdt_paddr: usize) {
+    if fdt_ptr == 0 || PLATFORM_INFO.is_init() {
+        return;
+    }
+    match unsafe { Fdt::from_ptr(fdt_ptr as *const u8) } {
+        Ok(fdt) => {
+            let mut info = parse(&fdt);
+            info.fdt_region.0 = fdt_paddr;
+            PLATFORM_INFO.init_by(info);
+        }
+        Err(e) => warn!("invalid device tree at {:#x}: {:?}", fdt_paddr, e),
+    }
+}
+
+fn info() -> Option<&'static PlatformInfo> {
+    if PLATFORM_INFO.is_init() {
+        Some(&*PLATFORM_INFO)
+    } else {
+        None
+    }
+}
+
+/// Returns whether a device tree has been parsed.
+pub fn is_available() -> bool {
+    PLATFORM_INFO.is_init()
+}
+
+/// Returns the physical address and size of the device tree blob.
+pub fn fdt_region() -> Option<(usize, usize)> {
+    info().map(|info| info.fdt_region)
+}
+
+/// Returns the number of CPUs.
+pub fn cpu_count() -> Option<usize> {
+    info().map(|info| info.cpu_count).filter(|&n| n > 0)
+}
+
+/// Returns the frequency of the timer (`timebase-frequency` of the CPUs).
+pub fn timer_frequency() -> Option<usize> {
+    info().and_then(|info| info.timer_frequency)
+}
+
+/// Returns the physical memory regions as (`base_paddr`, `size`).
+pub fn memory_regions() -> &'static [(usize, usize)] {
+    info().map_or(&[], |info| &info.memory[..info.memory_count])
+}
+
+/// Returns the serial port used as the console.
+pub fn uart() -> Option<DeviceInfo> {
+    info().and_then(|info| info.uart)
+}
+
+/// Returns the RISC-V platform-level interrupt controller.
+pub fn plic() -> Option<DeviceInfo> {
+    info().and_then(|info| info.plic)
+}
+
+/// Returns the ARM generic interrupt controller.
+pub fn gic() -> Option<GicInfo> {
+    info().and_then(|info| info.gic)
+}
+
+/// Returns the VirtIO MMIO slots, in the order of addresses.
+pub fn virtio_mmio_regions() -> &'static [DeviceInfo] {
+    info().map_or(&[], |info| &info.virtio_mmio[..info.virtio_mmio_count])
+}
+
+/// Returns the kernel command line (`bootargs` of the `/chosen` node).
+pub fn bootargs() -> Option<&'static str> {
+    info()
+        .map(|info| core::str::from_utf8(&info.bootargs[..info.bootargs_len]).unwrap())
+        .filter(|bootargs| !bootargs.is_empty())
+}
+
+/// Returns the PCIe host bridge.
+pub fn pci_host() -> Option<PciHostInfo> {
+    info().and_then(|info| info.pci)
+}
+
+/// Returns the MMIO regions of all discovered devices as (`base_paddr`, `size`).
+pub fn mmio_regions() -> impl Iterator<Item = (usize, usize)> {
+    let info = info();
+    let devices = [
+        info.and_then(|info| info.uart),
+        info.and_then(|info| info.plic),
+    ];
+    let gic = info
+        .and_then(|info| info.gic)
+        .into_iter()
+        .flat_map(|gic| [gic.gicd, gic.gicc]);
+    let pci = info.and_then(|info| info.pci).into_iter().flat_map(|pci| {
+        [Some(pci.ecam), pci.io, pci.mem32, pci.mem64]
+            .into_iter()
+            .flatten()
+    });
+    devices
+        .into_iter()
+        .flatten()
+        .map(|dev| (dev.paddr, dev.size))
+        .chain(
+            virtio_mmio_regions()
+                .iter()
+                .map(|dev| (dev.paddr, dev.size)),
+        )
+        .chain(gic)
+        .chain(pci)
+        .filter(|&(_, size)| size > 0)
+}
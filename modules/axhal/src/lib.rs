@@ -43,6 +43,7 @@ pub mod platform;
 
 pub mod arch;
 pub mod cpu;
+pub mod dtb;
 pub mod mem;
 pub mod time;
 
@@ -126,6 +126,97 @@ pub(crate) fn default_mmio_regions() -> impl Iterator<Item = MemRegion> {
     })
 }
 
+/// Returns the MMIO memory regions of the devices found in the device tree, or
+/// the default ones if there is no device tree.
+#[allow(dead_code)]
+pub(crate) fn mmio_regions() -> impl Iterator<Item = MemRegion> {
+    let from_dtb = crate::dtb::is_available();
+    let dtb_regions = crate::dtb::mmio_regions().map(|(paddr, size)| {
+        let start = PhysAddr::from(paddr).align_down_4k();
+        let end = PhysAddr::from(paddr + size).align_up_4k();
+        MemRegion {
+            paddr: start,
+            size: end.as_usize() - start.as_usize(),
+            flags: MemRegionFlags::RESERVED
+                | MemRegionFlags::DEVICE
+                | MemRegionFlags::READ
+                | MemRegionFlags::WRITE,
+            name: "mmio",
+        }
+    });
+    dtb_regions.chain(default_mmio_regions().filter(move |_| !from_dtb))
+}
+
+/// Returns the free memory regions found in the device tree.
+///
+/// The firmware and the kernel image (everything below the kernel end), the
+/// device tree blob and the testcase image are excluded. With the
+/// `monolithic` feature, the part of [`extend_free_regions`] inside the
+/// reported memory is kept as a region of its own.
+#[allow(dead_code)]
+pub(crate) fn dtb_free_regions() -> impl Iterator<Item = MemRegion> {
+    let kernel_end = virt_to_phys((_ekernel as usize).into()).align_up_4k();
+    let (fdt_paddr, fdt_size) = crate::dtb::fdt_region().unwrap_or((0, 0));
+    let mut reserved = [
+        (0, kernel_end.as_usize()),
+        (
+            PhysAddr::from(fdt_paddr).align_down_4k().as_usize(),
+            PhysAddr::from(fdt_paddr + fdt_size)
+                .align_up_4k()
+                .as_usize(),
+        ),
+        (
+            axconfig::TESTCASE_MEMORY_START,
+            axconfig::TESTCASE_MEMORY_START + axconfig::TESTCASE_MEMORY_SIZE,
+        ),
+    ];
+    reserved.sort_unstable();
+
+    crate::dtb::memory_regions()
+        .iter()
+        .flat_map(move |&(base, size)| {
+            // at most one piece before each reserved range, and one at the end
+            let mut pieces = [None; 4];
+            let mut count = 0;
+            let end = PhysAddr::from(base + size).align_down_4k().as_usize();
+            let mut cur = PhysAddr::from(base).align_up_4k().as_usize();
+            for &(res_start, res_end) in reserved.iter() {
+                if res_end <= cur || res_start >= end {
+                    continue;
+                }
+                if res_start > cur {
+                    pieces[count] = Some((cur, res_start));
+                    count += 1;
+                }
+                cur = cur.max(res_end);
+            }
+            if cur < end {
+                pieces[count] = Some((cur, end));
+            }
+            pieces.into_iter().flatten()
+        })
+        .flat_map(|(start, end)| {
+            let (ext_start, ext_end) = if cfg!(feature = "monolithic") {
+                extend_free_range()
+            } else {
+                (end, end)
+            };
+            [
+                (start, end.min(ext_start), "free memory"),
+                (start.max(ext_start), end.min(ext_end), "extend free memory"),
+                (start.max(ext_end), end, "free memory"),
+            ]
+            .into_iter()
+            .filter(|&(start, end, _)| start < end)
+        })
+        .map(|(start, end, name)| MemRegion {
+            paddr: start.into(),
+            size: end - start,
+            flags: MemRegionFlags::FREE | MemRegionFlags::READ | MemRegionFlags::WRITE,
+            name,
+        })
+}
+
 /// Returns the default free memory regions (kernel image end to physical memory end).
 #[allow(dead_code)]
 pub(crate) fn default_free_regions() -> impl Iterator<Item = MemRegion> {
@@ -144,15 +235,22 @@ pub(crate) fn default_free_regions() -> impl Iterator<Item = MemRegion> {
 /// extend to [0xffff_ffc0_a000_0000, 0xffff_ffc0_f000_0000)
 #[allow(dead_code)]
 pub(crate) fn extend_free_regions() -> impl Iterator<Item = MemRegion> {
-    let start = virt_to_phys(VirtAddr::from(0xffff_ffc0_a000_0000)).align_up_4k();
-    let end: PhysAddr = PhysAddr::from(0x1_a000_0000).align_down_4k();
+    let (start, end) = extend_free_range();
     core::iter::once(MemRegion {
-        paddr: start,
-        size: end.as_usize() - start.as_usize(),
+        paddr: start.into(),
+        size: end - start,
         flags: MemRegionFlags::FREE | MemRegionFlags::READ | MemRegionFlags::WRITE,
         name: "extend free memory",
     })
 }
+
+/// The physical address range of [`extend_free_regions`].
+fn extend_free_range() -> (usize, usize) {
+    let start = virt_to_phys(VirtAddr::from(0xffff_ffc0_a000_0000)).align_up_4k();
+    let end = PhysAddr::from(0x1_a000_0000).align_down_4k();
+    (start.as_usize(), end.as_usize())
+}
+
 /// Fills the `.bss` section with zeros.
 #[allow(dead_code)]
 pub fn clear_bss() {
@@ -16,13 +16,13 @@ const GICC_BASE: PhysAddr = PhysAddr::from(axconfig::GICC_PADDR);
 
 cfg_if::cfg_if! {
     if #[cfg(platform_family= "aarch64-rk3588j")] {
-        use arm_gic::GicV3;
-        static mut GIC: SpinNoIrq<GicV3> =
-            SpinNoIrq::new(GicV3::new(phys_to_virt(GICD_BASE).as_mut_ptr(), phys_to_virt(GICC_BASE).as_mut_ptr()));
+        use arm_gic::GicV3 as Gic;
+        static mut GIC: SpinNoIrq<Gic> =
+            SpinNoIrq::new(Gic::new(phys_to_virt(GICD_BASE).as_mut_ptr(), phys_to_virt(GICC_BASE).as_mut_ptr()));
     } else {
-        use arm_gic::GicV2;
-        static mut GIC: SpinNoIrq<GicV2> =
-            SpinNoIrq::new(GicV2::new(phys_to_virt(GICD_BASE).as_mut_ptr(), phys_to_virt(GICC_BASE).as_mut_ptr()));
+        use arm_gic::GicV2 as Gic;
+        static mut GIC: SpinNoIrq<Gic> =
+            SpinNoIrq::new(Gic::new(phys_to_virt(GICD_BASE).as_mut_ptr(), phys_to_virt(GICC_BASE).as_mut_ptr()));
     }
 }
 
@@ -69,9 +69,20 @@ pub fn dispatch_irq(_unused: usize) {
 }
 
 /// Initializes GICD, GICC on the primary CPU.
+///
+/// The GIC in the device tree is used if found, instead of the addresses in
+/// [`axconfig`].
 pub(crate) fn init_primary() {
     info!("Initialize GICv2...");
-    unsafe { GIC.lock().init_primary() };
+    unsafe {
+        if let Some(gic) = crate::dtb::gic() {
+            GIC = SpinNoIrq::new(Gic::new(
+                phys_to_virt(gic.gicd.0.into()).as_mut_ptr(),
+                phys_to_virt(gic.gicc.0.into()).as_mut_ptr(),
+            ));
+        }
+        GIC.lock().init_primary()
+    };
 }
 
 /// Initializes GICC on secondary CPUs.
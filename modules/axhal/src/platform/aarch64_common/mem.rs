@@ -23,13 +23,13 @@ pub(crate) fn platform_regions() -> impl Iterator<Item = MemRegion> {
             })
             .chain(core::iter::once(fdt_region()))
             .chain(free_regions())
-            .chain(crate::mem::default_mmio_regions()),
+            .chain(crate::mem::mmio_regions()),
         )
     } else {
         Right(
             core::iter::once(fdt_region())
                 .chain(free_regions())
-                .chain(crate::mem::default_mmio_regions()),
+                .chain(crate::mem::mmio_regions()),
         )
     };
     iterator.into_iter()
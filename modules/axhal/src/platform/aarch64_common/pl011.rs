@@ -29,17 +29,27 @@ pub fn getchar() -> Option<u8> {
 }
 
 /// Initialize the UART
+///
+/// The UART in the device tree is used if found, instead of [`axconfig::UART_PADDR`].
 pub fn init_early() {
+    let base = crate::dtb::uart().map_or(UART_BASE, |uart| PhysAddr::from(uart.paddr));
     unsafe {
-        crate::platform::aarch64_common::mem::idmap_device(UART_BASE.as_usize());
+        crate::platform::aarch64_common::mem::idmap_device(base.as_usize());
     }
-    UART.lock().init();
+    let mut uart = UART.lock();
+    if base != UART_BASE {
+        *uart = Pl011Uart::new(phys_to_virt(base).as_mut_ptr());
+    }
+    uart.init();
 }
 
 /// Set UART IRQ Enable
 #[cfg(feature = "irq")]
 pub fn init_irq() {
-    crate::irq::set_enable(crate::platform::irq::UART_IRQ_NUM, true);
+    let irq_num = crate::dtb::uart()
+        .and_then(|uart| uart.irq)
+        .unwrap_or(crate::platform::irq::UART_IRQ_NUM);
+    crate::irq::set_enable(irq_num, true);
 }
 
 /// UART IRQ Handler
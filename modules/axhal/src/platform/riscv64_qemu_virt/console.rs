@@ -1,3 +1,6 @@
+#[cfg(feature = "irq")]
+use spinlock::SpinNoIrq;
+
 /// Writes a byte to the console.
 pub fn putchar(c: u8) {
     #[allow(deprecated)]
@@ -6,9 +9,120 @@ pub fn putchar(c: u8) {
 
 /// Reads a byte from the console, or returns [`None`] if no input is available.
 pub fn getchar() -> Option<u8> {
+    #[cfg(feature = "irq")]
+    if let Some(c) = uart::RX_BUF.lock().pop() {
+        return Some(c);
+    }
     #[allow(deprecated)]
     match sbi_rt::legacy::console_getchar() as isize {
         -1 => None,
         c => Some(c as u8),
     }
 }
+
+/// Enables the receive interrupt of the NS16550 UART, and registers its handler.
+#[cfg(feature = "irq")]
+pub fn init_irq() {
+    uart::enable_rx_interrupt();
+    let irq_num = crate::dtb::uart()
+        .and_then(|uart| uart.irq)
+        .unwrap_or(crate::platform::irq::UART_IRQ_NUM);
+    crate::irq::register_handler(irq_num, handle);
+}
+
+/// UART IRQ Handler
+///
+/// The RX interrupt stays asserted while the FIFO is not empty, so the received
+/// bytes are drained into a buffer and returned by [`getchar`] later.
+#[cfg(feature = "irq")]
+pub fn handle() {
+    let mut buf = uart::RX_BUF.lock();
+    while let Some(c) = uart::read_byte() {
+        buf.push(c);
+    }
+}
+
+#[cfg(feature = "irq")]
+mod uart {
+    use core::ptr::{read_volatile, write_volatile};
+
+    use memory_addr::PhysAddr;
+
+    use super::SpinNoIrq;
+    use crate::mem::phys_to_virt;
+
+    /// The console UART found in the device tree, or the one of QEMU virt.
+    fn uart_base() -> PhysAddr {
+        crate::dtb::uart()
+            .map_or(axconfig::UART_PADDR, |uart| uart.paddr)
+            .into()
+    }
+
+    /// Receiver buffer register
+    const RBR: usize = 0;
+    /// Interrupt enable register
+    const IER: usize = 1;
+    /// Line status register
+    const LSR: usize = 5;
+
+    const IER_RX_AVAILABLE: u8 = 1 << 0;
+    const LSR_DATA_READY: u8 = 1 << 0;
+
+    const RX_BUF_SIZE: usize = 256;
+
+    pub(super) struct RxBuffer {
+        buf: [u8; RX_BUF_SIZE],
+        head: usize,
+        len: usize,
+    }
+
+    impl RxBuffer {
+        const fn new() -> Self {
+            Self {
+                buf: [0; RX_BUF_SIZE],
+                head: 0,
+                len: 0,
+            }
+        }
+
+        /// Appends a byte, dropping the oldest one if the buffer is full.
+        pub(super) fn push(&mut self, c: u8) {
+            if self.len == RX_BUF_SIZE {
+                self.head = (self.head + 1) % RX_BUF_SIZE;
+                self.len -= 1;
+            }
+            self.buf[(self.head + self.len) % RX_BUF_SIZE] = c;
+            self.len += 1;
+        }
+
+        pub(super) fn pop(&mut self) -> Option<u8> {
+            if self.len == 0 {
+                return None;
+            }
+            let c = self.buf[self.head];
+            self.head = (self.head + 1) % RX_BUF_SIZE;
+            self.len -= 1;
+            Some(c)
+        }
+    }
+
+    pub(super) static RX_BUF: SpinNoIrq<RxBuffer> = SpinNoIrq::new(RxBuffer::new());
+
+    fn reg(offset: usize) -> *mut u8 {
+        (phys_to_virt(uart_base()).as_usize() + offset) as *mut u8
+    }
+
+    pub(super) fn enable_rx_interrupt() {
+        unsafe { write_volatile(reg(IER), IER_RX_AVAILABLE) };
+    }
+
+    pub(super) fn read_byte() -> Option<u8> {
+        unsafe {
+            if read_volatile(reg(LSR)) & LSR_DATA_READY != 0 {
+                Some(read_volatile(reg(RBR)))
+            } else {
+                None
+            }
+        }
+    }
+}
@@ -1,5 +1,7 @@
-//! TODO: PLIC
+//! Interrupt management on RISC-V: timer interrupts come from `scause`
+//! directly, and external interrupts are routed by the PLIC.
 
+use super::plic;
 use crate::irq::IrqHandler;
 use lazy_init::LazyInit;
 use riscv::register::sie;
@@ -19,12 +21,15 @@ pub(super) const S_EXT: usize = INTC_IRQ_BASE + 9;
 
 static TIMER_HANDLER: LazyInit<IrqHandler> = LazyInit::new();
 
-/// The maximum number of IRQs.
-pub const MAX_IRQ_COUNT: usize = 1024;
+/// The maximum number of IRQs (external interrupt sources of the PLIC).
+pub const MAX_IRQ_COUNT: usize = plic::PLIC_SOURCE_COUNT;
 
 /// The timer IRQ number (supervisor timer interrupt in `scause`).
 pub const TIMER_IRQ_NUM: usize = S_TIMER;
 
+/// The UART IRQ number (PLIC source).
+pub const UART_IRQ_NUM: usize = axconfig::UART_IRQ;
+
 macro_rules! with_cause {
     ($cause: expr, @TIMER => $timer_op: expr, @EXT => $ext_op: expr $(,)?) => {
         match $cause {
@@ -36,9 +41,13 @@ macro_rules! with_cause {
 }
 
 /// Enables or disables the given IRQ.
-pub fn set_enable(scause: usize, _enabled: bool) {
-    if scause == S_EXT {
-        // TODO: set enable in PLIC
+///
+/// External IRQs are numbered by their PLIC source, and are enabled in the
+/// PLIC context of the current hart.
+pub fn set_enable(irq_num: usize, enabled: bool) {
+    if irq_num & INTC_IRQ_BASE == 0 {
+        trace!("PLIC set enable: {} {}", irq_num, enabled);
+        plic::set_enable(irq_num, enabled);
     }
 }
 
@@ -46,17 +55,24 @@ pub fn set_enable(scause: usize, _enabled: bool) {
 ///
 /// It also enables the IRQ if the registration succeeds. It returns `false` if
 /// the registration failed.
-pub fn register_handler(scause: usize, handler: IrqHandler) -> bool {
-    with_cause!(
-        scause,
-        @TIMER => if !TIMER_HANDLER.is_init() {
-            TIMER_HANDLER.init_by(handler);
-            true
-        } else {
+pub fn register_handler(irq_num: usize, handler: IrqHandler) -> bool {
+    match irq_num {
+        S_TIMER => {
+            if !TIMER_HANDLER.is_init() {
+                TIMER_HANDLER.init_by(handler);
+                true
+            } else {
+                false
+            }
+        }
+        irq_num if irq_num & INTC_IRQ_BASE == 0 => {
+            crate::irq::register_handler_common(irq_num, handler)
+        }
+        _ => {
+            warn!("register handler for invalid IRQ {:#x}", irq_num);
             false
-        },
-        @EXT => crate::irq::register_handler_common(scause & !INTC_IRQ_BASE, handler),
-    )
+        }
+    }
 }
 
 /// Dispatches the IRQ.
@@ -71,11 +87,17 @@ pub fn dispatch_irq(scause: usize) {
             trace!("IRQ: timer");
             TIMER_HANDLER();
         },
-        @EXT => crate::irq::dispatch_irq_common(0), // TODO: get IRQ number from PLIC
+        @EXT => {
+            if let Some(irq_num) = plic::claim() {
+                crate::irq::dispatch_irq_common(irq_num);
+                plic::complete(irq_num);
+            }
+        },
     );
 }
 
 pub(super) fn init_percpu() {
+    plic::init_percpu();
     // enable soft interrupts, timer interrupts, and external interrupts
     unsafe {
         sie::set_ssoft();
@@ -1,12 +1,17 @@
 use crate::mem::MemRegion;
 
 /// Returns platform-specific memory regions.
+///
+/// The memory size and the devices are taken from the device tree if it is
+/// available, so the kernel works with any `-m` setting of QEMU. The extended
+/// free memory of the monolithic kernel is then clipped to the memory found
+/// in the device tree instead of being assumed to exist.
 pub(crate) fn platform_regions() -> impl Iterator<Item = MemRegion> {
-    #[cfg(not(feature = "monolithic"))]
-    return crate::mem::default_free_regions().chain(crate::mem::default_mmio_regions());
-
+    let from_dtb = !crate::dtb::memory_regions().is_empty();
+    let default_free = crate::mem::default_free_regions();
     #[cfg(feature = "monolithic")]
-    return crate::mem::default_free_regions()
-        .chain(crate::mem::default_mmio_regions())
-        .chain(crate::mem::extend_free_regions());
+    let default_free = default_free.chain(crate::mem::extend_free_regions());
+    crate::mem::dtb_free_regions()
+        .chain(default_free.filter(move |_| !from_dtb))
+        .chain(crate::mem::mmio_regions())
 }
@@ -5,6 +5,8 @@ pub mod time;
 
 #[cfg(feature = "irq")]
 pub mod irq;
+#[cfg(feature = "irq")]
+mod plic;
 
 /// Initializes the platform devices for the primary CPU.
 ///
@@ -13,6 +15,8 @@ pub fn platform_init() {
     #[cfg(feature = "irq")]
     self::irq::init_percpu();
     self::time::init_percpu();
+    #[cfg(feature = "irq")]
+    self::console::init_irq();
 }
 
 /// Initializes the platform devices for secondary CPUs.
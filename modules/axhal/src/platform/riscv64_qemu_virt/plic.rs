@@ -0,0 +1,106 @@
+//! RISC-V Platform-Level Interrupt Controller (PLIC).
+//!
+//! Each hart has its own S-mode context, with separate enable bits, priority
+//! threshold and claim/complete register.
+
+use core::ptr::{read_volatile, write_volatile};
+
+use memory_addr::PhysAddr;
+use spinlock::SpinNoIrq;
+
+use crate::mem::phys_to_virt;
+
+/// The PLIC found in the device tree, or the one of QEMU virt.
+fn plic_base() -> PhysAddr {
+    crate::dtb::plic()
+        .map_or(axconfig::PLIC_PADDR, |plic| plic.paddr)
+        .into()
+}
+
+/// Offset of the source priority registers.
+const PRIORITY_OFFSET: usize = 0x0;
+/// Offset of the per-context enable bits.
+const ENABLE_OFFSET: usize = 0x2000;
+const ENABLE_STRIDE: usize = 0x80;
+/// Offset of the per-context threshold and claim/complete registers.
+const CONTEXT_OFFSET: usize = 0x20_0000;
+const CONTEXT_STRIDE: usize = 0x1000;
+const CONTEXT_THRESHOLD: usize = 0x0;
+const CONTEXT_CLAIM: usize = 0x4;
+
+/// Number of interrupt sources supported by the PLIC (source 0 is reserved).
+pub const PLIC_SOURCE_COUNT: usize = 1024;
+
+/// Serializes read-modify-write of the enable bits.
+static ENABLE_LOCK: SpinNoIrq<()> = SpinNoIrq::new(());
+
+fn reg(offset: usize) -> *mut u32 {
+    (phys_to_virt(plic_base()).as_usize() + offset) as *mut u32
+}
+
+/// The S-mode context of the given hart. On QEMU virt, every hart has a M-mode
+/// context followed by a S-mode context.
+const fn s_context(hart_id: usize) -> usize {
+    2 * hart_id + 1
+}
+
+fn this_context() -> usize {
+    s_context(crate::cpu::this_cpu_id())
+}
+
+/// Register of the current hart's context at the given offset.
+fn context_reg(offset: usize) -> *mut u32 {
+    reg(CONTEXT_OFFSET + this_context() * CONTEXT_STRIDE + offset)
+}
+
+/// Enables or disables the interrupt source `irq` for all harts.
+///
+/// The source is routed to the S-mode context of every hart, so it is handled
+/// by whichever hart claims it first, no matter which hart registered it.
+pub fn set_enable(irq: usize, enabled: bool) {
+    if irq == 0 || irq >= PLIC_SOURCE_COUNT {
+        return;
+    }
+    let bit = 1 << (irq % 32);
+    let _guard = ENABLE_LOCK.lock();
+    unsafe {
+        if enabled {
+            // priority 0 means "never interrupt"
+            write_volatile(reg(PRIORITY_OFFSET + irq * 4), 1);
+        }
+        for hart_id in 0..crate::cpu::cpu_num() {
+            let enable = reg(ENABLE_OFFSET + s_context(hart_id) * ENABLE_STRIDE + (irq / 32) * 4);
+            if enabled {
+                write_volatile(enable, read_volatile(enable) | bit);
+            } else {
+                write_volatile(enable, read_volatile(enable) & !bit);
+            }
+        }
+    }
+}
+
+/// Claims the highest-priority pending interrupt of the current hart, returns
+/// [`None`] if no interrupt is pending.
+pub fn claim() -> Option<usize> {
+    let irq = unsafe { read_volatile(context_reg(CONTEXT_CLAIM)) };
+    if irq == 0 {
+        None
+    } else {
+        Some(irq as usize)
+    }
+}
+
+/// Signals the PLIC that the handling of `irq` is completed.
+pub fn complete(irq: usize) {
+    unsafe {
+        write_volatile(context_reg(CONTEXT_CLAIM), irq as u32);
+    }
+}
+
+/// Initializes the PLIC context of the current hart, accepting interrupts of
+/// any non-zero priority.
+pub(super) fn init_percpu() {
+    unsafe {
+        write_volatile(context_reg(CONTEXT_THRESHOLD), 0);
+    }
+}
@@ -61,17 +61,10 @@ pub(super) fn init_percpu() {
     sbi_rt::set_timer(0);
 }
 
+/// Parses the device tree at `dtb` (identity-mapped by the boot page table),
+/// and initializes the timer frequency from it.
 pub fn init_board_info(dtb: usize) {
-    unsafe {
-        of::init_fdt_ptr(dtb as *const u8);
-    }
-    let of_cpus = of::cpus();
-    let freq = {
-        if let Some(cpu) = of_cpus.expect("Failed to read cpu info").nth(0) {
-            cpu.timebase_frequency()
-        } else {
-            axconfig::TIMER_FREQUENCY
-        }
-    };
+    crate::dtb::init(dtb, dtb);
+    let freq = crate::dtb::timer_frequency().unwrap_or(axconfig::TIMER_FREQUENCY);
     init_cpu_freq(freq as u64);
 }
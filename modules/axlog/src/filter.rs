@@ -0,0 +1,252 @@
+//! Per-target log filtering.
+//!
+//! A filter is specified as comma-separated directives, e.g.,
+//! `warn,axnet=debug,axfs=warn,linux_syscall_api=off`:
+//!
+//! - A single level sets the default level of all targets.
+//! - `target=level` sets the level of the target and its submodules, e.g.,
+//!   `axfs` also matches `axfs::fops`. The longest matching target wins.
+//! - A single target enables all levels of it.
+//!
+//! A record is dropped before it is formatted if its level is higher than the
+//! level of its target. The maximum level of [`log`] is also set to the highest
+//! level in the filter, so that disabled log macros return early at the call
+//! site.
+
+use core::fmt;
+use core::str::FromStr;
+
+use log::{Level, LevelFilter};
+use spinlock::SpinNoIrq;
+
+/// The maximum number of `target=level` directives.
+pub const MAX_DIRECTIVES: usize = 16;
+
+/// The maximum length of the target of a directive.
+pub const MAX_TARGET_LEN: usize = 48;
+
+/// The error of parsing a log filter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterError {
+    /// The level is not one of `off`, `error`, `warn`, `info`, `debug` and
+    /// `trace`.
+    InvalidLevel,
+    /// The target is empty or longer than [`MAX_TARGET_LEN`].
+    InvalidTarget,
+    /// There are more than [`MAX_DIRECTIVES`] directives.
+    TooManyDirectives,
+}
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidLevel => f.write_str("invalid log level"),
+            Self::InvalidTarget => f.write_str("invalid log target"),
+            Self::TooManyDirectives => f.write_str("too many log filter directives"),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Directive {
+    target: [u8; MAX_TARGET_LEN],
+    len: usize,
+    level: LevelFilter,
+}
+
+impl Directive {
+    const EMPTY: Self = Self {
+        target: [0; MAX_TARGET_LEN],
+        len: 0,
+        level: LevelFilter::Off,
+    };
+
+    fn target(&self) -> &str {
+        // only built from `&str`
+        core::str::from_utf8(&self.target[..self.len]).unwrap()
+    }
+
+    /// Whether the directive applies to `target` or its submodules.
+    fn matches(&self, target: &str) -> bool {
+        let prefix = self.target();
+        target.starts_with(prefix)
+            && (target.len() == prefix.len() || target[prefix.len()..].starts_with("::"))
+    }
+}
+
+struct Filter {
+    default: LevelFilter,
+    directives: [Directive; MAX_DIRECTIVES],
+    count: usize,
+}
+
+impl Filter {
+    fn directives(&self) -> &[Directive] {
+        &self.directives[..self.count]
+    }
+
+    fn level_of(&self, target: &str) -> LevelFilter {
+        self.directives()
+            .iter()
+            .filter(|directive| directive.matches(target))
+            .max_by_key(|directive| directive.len)
+            .map_or(self.default, |directive| directive.level)
+    }
+
+    fn max_level(&self) -> LevelFilter {
+        self.directives()
+            .iter()
+            .map(|directive| directive.level)
+            .fold(self.default, Ord::max)
+    }
+
+    fn set(&mut self, target: &str, level: LevelFilter) -> Result<(), FilterError> {
+        if target.is_empty() || target.len() > MAX_TARGET_LEN {
+            return Err(FilterError::InvalidTarget);
+        }
+        let count = self.count;
+        let index = match self.directives[..count]
+            .iter()
+            .position(|directive| directive.target() == target)
+        {
+            Some(index) => index,
+            None if count < MAX_DIRECTIVES => {
+                self.count += 1;
+                count
+            }
+            None => return Err(FilterError::TooManyDirectives),
+        };
+        let directive = &mut self.directives[index];
+        directive.target[..target.len()].copy_from_slice(target.as_bytes());
+        directive.len = target.len();
+        directive.level = level;
+        Ok(())
+    }
+}
+
+static FILTER: SpinNoIrq<Filter> = SpinNoIrq::new(Filter {
+    default: LevelFilter::Off,
+    directives: [Directive::EMPTY; MAX_DIRECTIVES],
+    count: 0,
+});
+
+fn parse_level(level: &str) -> Result<LevelFilter, FilterError> {
+    LevelFilter::from_str(level.trim()).map_err(|_| FilterError::InvalidLevel)
+}
+
+fn level_name(level: LevelFilter) -> &'static str {
+    match level {
+        LevelFilter::Off => "off",
+        LevelFilter::Error => "error",
+        LevelFilter::Warn => "warn",
+        LevelFilter::Info => "info",
+        LevelFilter::Debug => "debug",
+        LevelFilter::Trace => "trace",
+    }
+}
+
+/// Whether a record of `level` from `target` passes the filter.
+pub(crate) fn enabled(level: Level, target: &str) -> bool {
+    level <= FILTER.lock().level_of(target)
+}
+
+/// Sets the log filter, see the [module-level documentation](self) for the
+/// format.
+///
+/// All previous `target=level` directives are replaced, while the default level
+/// is kept if `spec` does not contain one. Nothing is changed if `spec` is
+/// invalid.
+///
+/// Like [`set_max_level`](crate::set_max_level), it cannot enable the levels
+/// disabled by the `log-level-*` features.
+pub fn set_log_filter(spec: &str) -> Result<(), FilterError> {
+    let mut filter = FILTER.lock();
+    let mut new = Filter {
+        default: filter.default,
+        directives: [Directive::EMPTY; MAX_DIRECTIVES],
+        count: 0,
+    };
+    for directive in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        match directive.split_once('=') {
+            Some((target, level)) => new.set(target.trim(), parse_level(level)?)?,
+            None => match parse_level(directive) {
+                Ok(level) => new.default = level,
+                Err(_) => new.set(directive, LevelFilter::Trace)?,
+            },
+        }
+    }
+    *filter = new;
+    log::set_max_level(filter.max_level());
+    Ok(())
+}
+
+/// Sets the default level of the targets without a directive in the filter.
+pub(crate) fn set_default_level(level: LevelFilter) {
+    let mut filter = FILTER.lock();
+    filter.default = level;
+    log::set_max_level(filter.max_level());
+}
+
+/// Writes the current log filter in the format accepted by
+/// [`set_log_filter`].
+pub fn write_log_filter(out: &mut dyn fmt::Write) -> fmt::Result {
+    let filter = FILTER.lock();
+    out.write_str(level_name(filter.default))?;
+    for directive in filter.directives() {
+        write!(
+            out,
+            ",{}={}",
+            directive.target(),
+            level_name(directive.level)
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level_of(target: &str) -> LevelFilter {
+        FILTER.lock().level_of(target)
+    }
+
+    #[test]
+    fn test_set_log_filter() {
+        set_log_filter("warn, axfs=debug, axfs::fops=off ,axnet").unwrap();
+        assert_eq!(level_of("axtask"), LevelFilter::Warn);
+        assert_eq!(level_of("axfs"), LevelFilter::Debug);
+        assert_eq!(level_of("axfs::api"), LevelFilter::Debug);
+        // the longest matching target wins
+        assert_eq!(level_of("axfs::fops"), LevelFilter::Off);
+        assert_eq!(level_of("axfs::fops::file"), LevelFilter::Off);
+        // a target only matches itself and its submodules
+        assert_eq!(level_of("axfs_vfs"), LevelFilter::Warn);
+        assert_eq!(level_of("axnet::tcp"), LevelFilter::Trace);
+
+        let mut spec = String::new();
+        write_log_filter(&mut spec).unwrap();
+        assert_eq!(spec, "warn,axfs=debug,axfs::fops=off,axnet=trace");
+
+        // invalid filters change nothing
+        assert_eq!(
+            set_log_filter("info,axfs=loud"),
+            Err(FilterError::InvalidLevel)
+        );
+        assert_eq!(set_log_filter("=debug"), Err(FilterError::InvalidTarget));
+        let too_many = (0..=MAX_DIRECTIVES)
+            .map(|i| format!("t{}=info", i))
+            .collect::<Vec<_>>()
+            .join(",");
+        assert_eq!(
+            set_log_filter(&too_many),
+            Err(FilterError::TooManyDirectives)
+        );
+        assert_eq!(level_of("axfs::fops"), LevelFilter::Off);
+
+        // the default level is kept without a single level
+        set_log_filter("axfs=error").unwrap();
+        assert_eq!(level_of("axtask"), LevelFilter::Warn);
+        assert_eq!(level_of("axfs::fops"), LevelFilter::Error);
+    }
+}
@@ -0,0 +1,407 @@
+//! The kernel log buffer.
+//!
+//! Every log record is also saved in a fixed-size ring buffer together with
+//! its level, timestamp, CPU ID and task ID, so that the kernel logs can be
+//! read later, e.g., by `syslog(2)` or `/dev/kmsg`. When the buffer is full,
+//! the oldest records are dropped.
+//!
+//! The buffer is statically allocated, so logs can be recorded before the heap
+//! is initialized.
+
+use core::fmt::{self, Write};
+use core::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+use core::time::Duration;
+
+use log::Level;
+use spinlock::SpinNoIrq;
+
+/// The size of the kernel log buffer in bytes.
+pub const LOG_BUF_LEN: usize = 1 << 17;
+
+/// The maximum length of the text of a record, longer texts are truncated.
+pub const LOG_LINE_MAX: usize = 1024 - HEADER_LEN;
+
+/// The default console log level, which prints records of all levels.
+pub const DEFAULT_CONSOLE_LOGLEVEL: u8 = 8;
+
+/// Header layout: text length (u16), level (u8), flags (u8), CPU ID (u32),
+/// timestamp in nanoseconds (u64) and task ID (u64).
+const HEADER_LEN: usize = 24;
+
+const FLAG_CPU_ID: u8 = 1 << 0;
+const FLAG_TASK_ID: u8 = 1 << 1;
+
+/// Records whose syslog level is lower than it are printed to the console.
+static CONSOLE_LOGLEVEL: AtomicU8 = AtomicU8::new(DEFAULT_CONSOLE_LOGLEVEL);
+
+static LOG_BUF: SpinNoIrq<LogBuffer> = SpinNoIrq::new(LogBuffer::new());
+
+/// The function called after a record is saved, stored as `usize` (0 if none).
+static RECORD_HOOK: AtomicUsize = AtomicUsize::new(0);
+
+/// A record read from the kernel log buffer.
+pub struct KmsgRecord<'a> {
+    /// The sequence number of the record, starting from 0 at boot.
+    pub seq: u64,
+    /// The log level.
+    pub level: Level,
+    /// The time when the record is logged.
+    pub time: Duration,
+    /// The CPU that logged the record.
+    pub cpu_id: Option<usize>,
+    /// The task that logged the record.
+    pub task_id: Option<u64>,
+    /// The text of the record, which may be truncated by the read buffer.
+    pub text: &'a str,
+}
+
+impl KmsgRecord<'_> {
+    /// Returns the level of the record used by `syslog(2)`, from 0 (`KERN_EMERG`)
+    /// to 7 (`KERN_DEBUG`).
+    pub fn syslog_level(&self) -> u8 {
+        syslog_level(self.level)
+    }
+}
+
+/// A reading position in the kernel log buffer.
+///
+/// If the record it points to has been dropped, reading starts from the oldest
+/// record instead.
+#[derive(Clone, Copy, Default)]
+pub struct KmsgCursor {
+    seq: u64,
+    pos: usize,
+}
+
+impl KmsgCursor {
+    /// Returns the cursor pointing to the oldest record in the buffer.
+    pub const fn oldest() -> Self {
+        Self { seq: 0, pos: 0 }
+    }
+
+    /// Returns the cursor pointing to the first record after the last
+    /// [`clear`].
+    pub fn after_clear() -> Self {
+        let buf = LOG_BUF.lock();
+        Self {
+            seq: buf.clear_seq,
+            pos: buf.clear_pos,
+        }
+    }
+
+    /// Returns the cursor pointing to the next record to be logged.
+    pub fn end() -> Self {
+        let buf = LOG_BUF.lock();
+        Self {
+            seq: buf.next_seq,
+            pos: buf.tail,
+        }
+    }
+
+    /// Returns the sequence number of the record it points to.
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+}
+
+struct LogBuffer {
+    buf: [u8; LOG_BUF_LEN],
+    /// The offset of the oldest record. Offsets only increase and are taken
+    /// modulo [`LOG_BUF_LEN`] when accessing the buffer.
+    head: usize,
+    /// The offset where the next record is written.
+    tail: usize,
+    /// The sequence number of the record at `head`.
+    first_seq: u64,
+    /// The sequence number of the next record.
+    next_seq: u64,
+    /// The first record after the last [`clear`].
+    clear_seq: u64,
+    clear_pos: usize,
+}
+
+impl LogBuffer {
+    const fn new() -> Self {
+        Self {
+            buf: [0; LOG_BUF_LEN],
+            head: 0,
+            tail: 0,
+            first_seq: 0,
+            next_seq: 0,
+            clear_seq: 0,
+            clear_pos: 0,
+        }
+    }
+
+    fn copy_in(&mut self, pos: usize, data: &[u8]) {
+        let start = pos % LOG_BUF_LEN;
+        let first = data.len().min(LOG_BUF_LEN - start);
+        self.buf[start..start + first].copy_from_slice(&data[..first]);
+        self.buf[..data.len() - first].copy_from_slice(&data[first..]);
+    }
+
+    fn copy_out(&self, pos: usize, data: &mut [u8]) {
+        let start = pos % LOG_BUF_LEN;
+        let first = data.len().min(LOG_BUF_LEN - start);
+        data[..first].copy_from_slice(&self.buf[start..start + first]);
+        let len = data.len();
+        data[first..].copy_from_slice(&self.buf[..len - first]);
+    }
+
+    fn read_header(&self, pos: usize) -> [u8; HEADER_LEN] {
+        let mut header = [0; HEADER_LEN];
+        self.copy_out(pos, &mut header);
+        header
+    }
+
+    fn record_len(&self, pos: usize) -> usize {
+        let header = self.read_header(pos);
+        HEADER_LEN + u16::from_le_bytes([header[0], header[1]]) as usize
+    }
+
+    fn push(&mut self, header: &[u8; HEADER_LEN], text: &[u8]) {
+        let len = HEADER_LEN + text.len();
+        while self.tail + len - self.head > LOG_BUF_LEN {
+            self.head += self.record_len(self.head);
+            self.first_seq += 1;
+        }
+        if self.clear_seq < self.first_seq {
+            self.clear_seq = self.first_seq;
+            self.clear_pos = self.head;
+        }
+        self.copy_in(self.tail, header);
+        self.copy_in(self.tail + HEADER_LEN, text);
+        self.tail += len;
+        self.next_seq += 1;
+    }
+}
+
+/// Returns the syslog level of a log level.
+fn syslog_level(level: Level) -> u8 {
+    match level {
+        Level::Error => 3,
+        Level::Warn => 4,
+        Level::Info => 6,
+        Level::Debug | Level::Trace => 7,
+    }
+}
+
+/// Returns the log level of a syslog level, e.g., the `<N>` prefix of a
+/// message written to `/dev/kmsg`.
+pub fn level_from_syslog(level: u8) -> Level {
+    match level {
+        0..=3 => Level::Error,
+        4 => Level::Warn,
+        5 | 6 => Level::Info,
+        _ => Level::Debug,
+    }
+}
+
+/// A writer that truncates the text at [`LOG_LINE_MAX`] bytes.
+struct LineWriter {
+    buf: [u8; LOG_LINE_MAX],
+    len: usize,
+}
+
+impl Write for LineWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let room = LOG_LINE_MAX - self.len;
+        let mut len = s.len().min(room);
+        // do not split a UTF-8 character
+        while !s.is_char_boundary(len) {
+            len -= 1;
+        }
+        self.buf[self.len..self.len + len].copy_from_slice(&s.as_bytes()[..len]);
+        self.len += len;
+        Ok(())
+    }
+}
+
+/// Saves a record in the kernel log buffer.
+pub(crate) fn push(
+    level: Level,
+    time: Duration,
+    cpu_id: Option<usize>,
+    task_id: Option<u64>,
+    args: fmt::Arguments,
+) {
+    let mut line = LineWriter {
+        buf: [0; LOG_LINE_MAX],
+        len: 0,
+    };
+    let _ = line.write_fmt(args);
+
+    let mut flags = 0;
+    if cpu_id.is_some() {
+        flags |= FLAG_CPU_ID;
+    }
+    if task_id.is_some() {
+        flags |= FLAG_TASK_ID;
+    }
+    let mut header = [0; HEADER_LEN];
+    header[0..2].copy_from_slice(&(line.len as u16).to_le_bytes());
+    header[2] = level as u8;
+    header[3] = flags;
+    header[4..8].copy_from_slice(&(cpu_id.unwrap_or(0) as u32).to_le_bytes());
+    header[8..16].copy_from_slice(&(time.as_nanos() as u64).to_le_bytes());
+    header[16..24].copy_from_slice(&task_id.unwrap_or(0).to_le_bytes());
+    LOG_BUF.lock().push(&header, &line.buf[..line.len]);
+
+    let hook = RECORD_HOOK.load(Ordering::Acquire);
+    if hook != 0 {
+        // only stored by `set_record_hook`
+        let hook: fn() = unsafe { core::mem::transmute(hook) };
+        hook();
+    }
+}
+
+/// Sets the function called after each record is saved, e.g., to wake up the
+/// tasks waiting for new records.
+///
+/// It is called on every log, possibly with locks held by the caller, so it
+/// must not block or acquire locks that may be held while logging.
+pub fn set_record_hook(hook: fn()) {
+    RECORD_HOOK.store(hook as usize, Ordering::Release);
+}
+
+/// Reads the record at `cursor` into `buf` and moves the cursor to the next
+/// record.
+///
+/// The text is truncated if `buf` is too small. Returns [`None`] if there are
+/// no more records.
+pub fn read_record<'a>(cursor: &mut KmsgCursor, buf: &'a mut [u8]) -> Option<KmsgRecord<'a>> {
+    let log_buf = LOG_BUF.lock();
+    if cursor.seq < log_buf.first_seq {
+        cursor.seq = log_buf.first_seq;
+        cursor.pos = log_buf.head;
+    }
+    if cursor.seq >= log_buf.next_seq {
+        return None;
+    }
+    let header = log_buf.read_header(cursor.pos);
+    let text_len = u16::from_le_bytes([header[0], header[1]]) as usize;
+    let len = text_len.min(buf.len());
+    log_buf.copy_out(cursor.pos + HEADER_LEN, &mut buf[..len]);
+    drop(log_buf);
+
+    let seq = cursor.seq;
+    cursor.seq += 1;
+    cursor.pos += HEADER_LEN + text_len;
+
+    let level = match header[2] {
+        1 => Level::Error,
+        2 => Level::Warn,
+        3 => Level::Info,
+        4 => Level::Debug,
+        _ => Level::Trace,
+    };
+    let flags = header[3];
+    let cpu_id = u32::from_le_bytes(header[4..8].try_into().unwrap()) as usize;
+    let time = u64::from_le_bytes(header[8..16].try_into().unwrap());
+    let task_id = u64::from_le_bytes(header[16..24].try_into().unwrap());
+    let text = match core::str::from_utf8(&buf[..len]) {
+        Ok(text) => text,
+        Err(err) => core::str::from_utf8(&buf[..err.valid_up_to()]).unwrap(),
+    };
+    Some(KmsgRecord {
+        seq,
+        level,
+        time: Duration::from_nanos(time),
+        cpu_id: (flags & FLAG_CPU_ID != 0).then_some(cpu_id),
+        task_id: (flags & FLAG_TASK_ID != 0).then_some(task_id),
+        text,
+    })
+}
+
+/// Drops all records in the buffer for readers starting from
+/// [`KmsgCursor::after_clear`].
+pub fn clear() {
+    let mut buf = LOG_BUF.lock();
+    buf.clear_seq = buf.next_seq;
+    buf.clear_pos = buf.tail;
+}
+
+/// Returns the console log level.
+pub fn console_level() -> u8 {
+    CONSOLE_LOGLEVEL.load(Ordering::Relaxed)
+}
+
+/// Sets the console log level. Only records whose syslog level is lower than
+/// it are printed to the console, but all records are saved in the buffer.
+pub fn set_console_level(level: u8) {
+    CONSOLE_LOGLEVEL.store(level, Ordering::Relaxed);
+}
+
+/// Whether records of `level` are printed to the console.
+pub(crate) fn console_enabled(level: Level) -> bool {
+    syslog_level(level) < console_level()
+}
+
+/// Logs a message from user space, e.g., written to `/dev/kmsg`.
+pub fn log_user(level: Level, text: &str) {
+    let (time, cpu_id, task_id) = crate::log_context();
+    push(level, time, cpu_id, task_id, format_args!("{}", text));
+    if console_enabled(level) {
+        crate::__print_impl(format_args!(
+            "[{:>3}.{:06}] {}\n",
+            time.as_secs(),
+            time.subsec_micros(),
+            text
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log(text: &str) {
+        push(
+            Level::Warn,
+            Duration::from_nanos(42),
+            Some(1),
+            None,
+            format_args!("{}", text),
+        );
+    }
+
+    #[test]
+    fn test_wraparound_and_drop() {
+        let mut cursor = KmsgCursor::end();
+        let first = cursor.seq();
+        log("hello");
+        let mut buf = [0; LOG_LINE_MAX];
+        let record = read_record(&mut cursor, &mut buf).unwrap();
+        assert_eq!(record.seq, first);
+        assert_eq!(record.level, Level::Warn);
+        assert_eq!(record.time, Duration::from_nanos(42));
+        assert_eq!(record.cpu_id, Some(1));
+        assert_eq!(record.task_id, None);
+        assert_eq!(record.text, "hello");
+        assert!(read_record(&mut cursor, &mut buf).is_none());
+
+        // records of 924 bytes straddle the end of the buffer when it wraps
+        let count = 3 * LOG_BUF_LEN / (HEADER_LEN + 900);
+        for i in 0..count {
+            log(&format!("{:0>900}", i));
+        }
+
+        // the oldest records have been dropped
+        let mut cursor = KmsgCursor::oldest();
+        let record = read_record(&mut cursor, &mut buf).unwrap();
+        assert!(record.seq > first + 1);
+        let mut expected = record.seq - first - 1;
+        assert_eq!(record.text, format!("{:0>900}", expected));
+        while let Some(record) = read_record(&mut cursor, &mut buf) {
+            expected += 1;
+            assert_eq!(record.text, format!("{:0>900}", expected));
+        }
+        assert_eq!(expected, count as u64 - 1);
+
+        // the text is truncated by a small read buffer
+        let mut cursor = KmsgCursor::oldest();
+        let mut small = [0; 4];
+        let record = read_record(&mut cursor, &mut small).unwrap();
+        assert_eq!(record.text.len(), 4);
+    }
+}
@@ -7,6 +7,11 @@
 //! If it is used in `no_std` environment, the users need to implement the
 //! [`LogIf`] to provide external functions such as console output.
 //!
+//! All log records are also saved in the kernel log buffer, see [`kmsg`].
+//!
+//! The levels of different targets (module paths) can be set separately by
+//! [`set_log_filter`], e.g., `warn,axfs=debug,linux_syscall_api=off`.
+//!
 //! To use in the `std` environment, please enable the `std` feature:
 //!
 //! ```toml
@@ -46,10 +51,13 @@
 //! trace!("trace");
 //! ```
 
-#![cfg_attr(not(feature = "std"), no_std)]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
 extern crate log;
 
+pub mod filter;
+pub mod kmsg;
+
 use core::fmt::{self, Write};
 use core::str::FromStr;
 
@@ -58,6 +66,7 @@ use log::{Level, LevelFilter, Log, Metadata, Record};
 #[cfg(not(feature = "std"))]
 use crate_interface::call_interface;
 
+pub use filter::{set_log_filter, write_log_filter};
 pub use log::{debug, error, info, trace, warn};
 
 /// Prints to the console.
@@ -144,8 +153,8 @@ impl Write for Logger {
 
 impl Log for Logger {
     #[inline]
-    fn enabled(&self, _metadata: &Metadata) -> bool {
-        true
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        filter::enabled(metadata.level(), metadata.target())
     }
 
     fn log(&self, record: &Record) {
@@ -156,6 +165,17 @@ impl Log for Logger {
         let level = record.level();
         let line = record.line().unwrap_or(0);
         let path = record.target();
+        let (now, cpu_id, tid) = log_context();
+        kmsg::push(
+            level,
+            now,
+            cpu_id,
+            tid,
+            format_args!("{}:{} {}", path, line, record.args()),
+        );
+        if !kmsg::console_enabled(level) {
+            return;
+        }
         let args_color = match level {
             Level::Error => ColorCode::Red,
             Level::Warn => ColorCode::Yellow,
@@ -175,9 +195,6 @@ impl Log for Logger {
                     args = with_color!(args_color, "{}", record.args()),
                 ));
             } else {
-                let cpu_id = call_interface!(LogIf::current_cpu_id);
-                let tid = call_interface!(LogIf::current_task_id);
-                let now = call_interface!(LogIf::current_time);
                 if let Some(cpu_id) = cpu_id {
                     if let Some(tid) = tid {
                         // show CPU ID and task ID
@@ -224,6 +241,24 @@ impl Log for Logger {
     fn flush(&self) {}
 }
 
+/// Returns the current time, CPU ID and task ID to be recorded with a log.
+fn log_context() -> (core::time::Duration, Option<usize>, Option<u64>) {
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "std")] {
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default();
+            (now, None, None)
+        } else {
+            (
+                call_interface!(LogIf::current_time),
+                call_interface!(LogIf::current_cpu_id),
+                call_interface!(LogIf::current_task_id),
+            )
+        }
+    }
+}
+
 /// Prints the formatted string to the console.
 pub fn print_fmt(args: fmt::Arguments) -> fmt::Result {
     use spinlock::SpinNoIrq; // TODO: more efficient
@@ -244,7 +279,7 @@ pub fn __print_impl(args: fmt::Arguments) {
 /// nothing will be printed.
 pub fn init() {
     log::set_logger(&Logger).unwrap();
-    log::set_max_level(LevelFilter::Warn);
+    filter::set_default_level(LevelFilter::Warn);
 }
 
 /// Set the maximum log level.
@@ -254,9 +289,11 @@ pub fn init() {
 /// when those features are enabled.
 ///
 /// `level` should be one of `off`, `error`, `warn`, `info`, `debug`, `trace`.
+/// It is the default level of the targets that are not given in the log filter,
+/// see [`set_log_filter`].
 pub fn set_max_level(level: &str) {
     let lf = LevelFilter::from_str(level)
         .ok()
         .unwrap_or(LevelFilter::Off);
-    log::set_max_level(lf);
+    filter::set_default_level(lf);
 }
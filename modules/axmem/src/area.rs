@@ -26,6 +26,10 @@ pub struct MapArea {
     pub flags: MappingFlags,
     /// whether the area is backed by a file
     pub backend: Option<MemBackend>,
+    /// whether the area is a stack that grows down on page faults
+    grows_down: bool,
+    /// whether the area was mapped for a stack, e.g., with `MAP_STACK`
+    stack: bool,
 }
 
 impl MapArea {
@@ -52,6 +56,8 @@ impl MapArea {
             shared: false,
             flags,
             backend,
+            grows_down: false,
+            stack: false,
         }
     }
 
@@ -91,6 +97,8 @@ impl MapArea {
             shared: false,
             flags,
             backend,
+            grows_down: false,
+            stack: false,
         })
     }
 
@@ -104,6 +112,38 @@ impl MapArea {
         self.shared
     }
 
+    /// Return whether the area is a stack that grows down on page faults.
+    pub(crate) fn grows_down(&self) -> bool {
+        self.grows_down
+    }
+
+    /// Set whether the area is a stack that grows down on page faults.
+    pub(crate) fn set_grows_down(&mut self, grows_down: bool) {
+        self.grows_down = grows_down;
+    }
+
+    /// Return whether the area was mapped for a stack.
+    pub(crate) fn is_stack(&self) -> bool {
+        self.stack
+    }
+
+    /// Set whether the area was mapped for a stack.
+    pub(crate) fn set_stack(&mut self, stack: bool) {
+        self.stack = stack;
+    }
+
+    /// Extend the area down to `new_start` with lazily allocated pages.
+    pub fn grow_down(&mut self, new_start: VirtAddr, page_table: &mut PageTable) {
+        assert!(new_start.is_aligned_4k() && new_start < self.vaddr);
+        let grow_size = self.vaddr.as_usize() - new_start.as_usize();
+        page_table
+            .map_fault_region(new_start, grow_size, self.flags)
+            .unwrap();
+        self.pages
+            .splice(0..0, (0..grow_size / PAGE_SIZE_4K).map(|_| None));
+        self.vaddr = new_start;
+    }
+
     /// Deallocate all phys pages and unmap the area in page table.
     pub fn dealloc(&mut self, page_table: &mut PageTable) {
         page_table.unmap_region(self.vaddr, self.size()).unwrap();
@@ -263,6 +303,8 @@ impl MapArea {
             vaddr: addr,
             flags: self.flags,
             shared: self.shared,
+            grows_down: self.grows_down,
+            stack: self.stack,
             backend: self.backend.as_ref().map(|backend| {
                 let mut backend = backend.clone();
 
@@ -306,6 +348,8 @@ impl MapArea {
             vaddr: start,
             flags: self.flags,
             shared: self.shared,
+            grows_down: self.grows_down,
+            stack: self.stack,
             backend: self.backend.as_ref().map(|backend| {
                 let mut backend = backend.clone();
 
@@ -324,6 +368,8 @@ impl MapArea {
             vaddr: end,
             flags: self.flags,
             shared: self.shared,
+            grows_down: self.grows_down,
+            stack: self.stack,
             backend: self.backend.as_ref().map(|backend| {
                 let mut backend = backend.clone();
 
@@ -371,6 +417,8 @@ impl MapArea {
             vaddr: right_start,
             flags: self.flags,
             shared: self.shared,
+            grows_down: self.grows_down,
+            stack: self.stack,
             backend: self.backend.as_ref().map(|backend| {
                 let mut backend = backend.clone();
                 let _ = backend
@@ -515,19 +563,24 @@ impl MapArea {
                 vaddr: self.vaddr,
                 flags: self.flags,
                 shared: self.shared,
+                grows_down: self.grows_down,
+                stack: self.stack,
                 backend: self.backend.clone(),
             });
         }
         // All the pages have been allocated. Allocate a contiguous area in phys memory.
         if self.allocated() {
-            MapArea::new_alloc(
+            let mut area = MapArea::new_alloc(
                 self.vaddr,
                 self.pages.len(),
                 self.flags,
                 Some(unsafe { self.as_slice() }),
                 self.backend.clone(),
                 page_table,
-            )
+            )?;
+            area.grows_down = self.grows_down;
+            area.stack = self.stack;
+            Ok(area)
         } else {
             let pages: Vec<_> = self
                 .pages
@@ -571,6 +624,8 @@ impl MapArea {
                 vaddr: self.vaddr,
                 flags: self.flags,
                 shared: self.shared,
+                grows_down: self.grows_down,
+                stack: self.stack,
                 backend: self.backend.clone(),
             })
         }
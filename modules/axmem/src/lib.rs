@@ -22,6 +22,12 @@ use axhal::{
     paging::{MappingFlags, PageSize, PageTable, PagingError},
 };
 
+/// The default limit of the size of stacks, i.e., `RLIMIT_STACK`.
+pub const DEFAULT_STACK_LIMIT: usize = 8 << 20;
+/// A stack never grows within this distance above the area below it, so that
+/// an overflow hits the gap and causes a page fault that cannot be handled.
+pub const STACK_GUARD_GAP: usize = 16 * PAGE_SIZE_4K;
+
 // TODO: a real allocator
 static SHMID: AtomicI32 = AtomicI32::new(1);
 
@@ -36,6 +42,17 @@ pub static SHARED_MEMS: SpinNoIrq<BTreeMap<i32, Arc<SharedMem>>> = SpinNoIrq::ne
 /// The map from key to shmid. It's used to query shmid from key.
 pub static KEY_TO_SHMID: SpinNoIrq<BTreeMap<i32, i32>> = SpinNoIrq::new(BTreeMap::new());
 
+/// A region of device memory (e.g., a framebuffer) mapped in user space. The
+/// pages belong to the device driver, so they are never allocated or freed by
+/// the memory set.
+#[derive(Clone, Copy)]
+struct DeviceMem {
+    vaddr: VirtAddr,
+    paddr: PhysAddr,
+    size: usize,
+    flags: MappingFlags,
+}
+
 /// PageTable + MemoryArea for a process (task)
 pub struct MemorySet {
     page_table: PageTable,
@@ -43,6 +60,11 @@ pub struct MemorySet {
 
     private_mem: BTreeMap<i32, Arc<SharedMem>>,
     attached_mem: Vec<(VirtAddr, MappingFlags, Arc<SharedMem>)>,
+    device_mem: Vec<DeviceMem>,
+    /// Where to look for free areas when no address is given.
+    mmap_base: usize,
+    /// The maximum size of a stack that grows down, i.e., `RLIMIT_STACK`.
+    stack_limit: usize,
 }
 
 impl MemorySet {
@@ -58,6 +80,9 @@ impl MemorySet {
             owned_mem: BTreeMap::new(),
             private_mem: BTreeMap::new(),
             attached_mem: Vec::new(),
+            device_mem: Vec::new(),
+            mmap_base: axconfig::USER_MEMORY_START,
+            stack_limit: DEFAULT_STACK_LIMIT,
         }
     }
 
@@ -90,6 +115,9 @@ impl MemorySet {
             owned_mem: BTreeMap::new(),
             private_mem: BTreeMap::new(),
             attached_mem: Vec::new(),
+            device_mem: Vec::new(),
+            mmap_base: axconfig::USER_MEMORY_START,
+            stack_limit: DEFAULT_STACK_LIMIT,
         }
     }
 
@@ -233,31 +261,176 @@ impl MemorySet {
     }
 
     /// Find a free area with given start virtual address and size. Return the start address of the area.
+    ///
+    /// The area is searched at or above `hint`, or the mmap base if `hint` is zero, and then
+    /// from the lowest user address if there is no such area.
     pub fn find_free_area(&self, hint: VirtAddr, size: usize) -> Option<VirtAddr> {
-        let mut last_end = hint.max(axconfig::USER_MEMORY_START.into()).as_usize();
+        let hint = match hint.as_usize() {
+            0 => self.mmap_base,
+            hint => hint,
+        };
 
         // TODO: performance optimization
+        // keep the guard gaps below stacks free
         let mut segments: Vec<_> = self
             .owned_mem
             .iter()
-            .map(|(start, mem)| (*start, *start + mem.size()))
+            .map(|(start, mem)| {
+                let gap = if mem.grows_down() { STACK_GUARD_GAP } else { 0 };
+                (start.saturating_sub(gap), *start + mem.size())
+            })
             .collect();
         segments.extend(
             self.attached_mem
                 .iter()
                 .map(|(start, _, mem)| (start.as_usize(), start.as_usize() + mem.size())),
         );
+        segments.extend(
+            self.device_mem
+                .iter()
+                .map(|mem| (mem.vaddr.as_usize(), mem.vaddr.as_usize() + mem.size)),
+        );
 
         segments.sort();
 
-        for (start, end) in segments {
-            if last_end + size <= start {
-                return Some(last_end.into());
+        let search = |from: usize| {
+            let mut last_end = from.max(axconfig::USER_MEMORY_START);
+            for &(start, end) in segments.iter() {
+                if last_end + size <= start {
+                    return Some(last_end.into());
+                }
+                last_end = last_end.max(end);
+            }
+            None
+        };
+        // fall back to the lower addresses
+        search(hint).or_else(|| search(axconfig::USER_MEMORY_START))
+    }
+
+    /// The address where the search for free areas starts when no address is
+    /// given, e.g., by `mmap(NULL, ...)`.
+    pub fn mmap_base(&self) -> usize {
+        self.mmap_base
+    }
+
+    /// Sets the mmap base, which is randomized on exec by ASLR.
+    pub fn set_mmap_base(&mut self, base: usize) {
+        self.mmap_base = base;
+    }
+
+    /// The maximum size of a stack that grows down.
+    pub fn stack_limit(&self) -> usize {
+        self.stack_limit
+    }
+
+    /// Sets the maximum size of a stack that grows down, i.e., `RLIMIT_STACK`.
+    pub fn set_stack_limit(&mut self, limit: usize) {
+        self.stack_limit = limit;
+    }
+
+    /// Returns the anonymous private area containing `addr`.
+    fn anonymous_area_mut(&mut self, addr: VirtAddr) -> Option<&mut MapArea> {
+        self.owned_mem
+            .values_mut()
+            .find(|area| area.vaddr <= addr && addr < area.end_va())
+            .filter(|area| area.backend.is_none() && !area.is_shared())
+    }
+
+    /// Makes the anonymous private area containing `addr` a stack that grows
+    /// down on page faults, like `MAP_GROWSDOWN`. Returns false if there is no
+    /// such area.
+    pub fn set_grows_down(&mut self, addr: VirtAddr) -> bool {
+        match self.anonymous_area_mut(addr) {
+            Some(area) => {
+                area.set_stack(true);
+                area.set_grows_down(true);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Marks the anonymous private area containing `addr` as mapped for a
+    /// stack, like `MAP_STACK`. Returns false if there is no such area.
+    pub fn set_stack(&mut self, addr: VirtAddr) -> bool {
+        match self.anonymous_area_mut(addr) {
+            Some(area) => {
+                area.set_stack(true);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Makes the stack whose top is `top` grow down on page faults, e.g., the
+    /// stack of a new thread.
+    ///
+    /// Only an area mapped for a stack and ending at the page of `top` is
+    /// changed, so that a stack placed in the heap or `.bss` never grows into
+    /// the memory below it. Returns false if there is no such area.
+    pub fn set_stack_grows_down(&mut self, top: VirtAddr) -> bool {
+        let Some(last) = top.as_usize().checked_sub(1) else {
+            return false;
+        };
+        match self.anonymous_area_mut(last.into()) {
+            Some(area) if area.is_stack() && area.end_va() == top.align_up_4k() => {
+                area.set_grows_down(true);
+                true
             }
-            last_end = end;
+            _ => false,
         }
+    }
 
-        None
+    /// Returns whether `addr` is in an area owned by the memory set.
+    fn is_mapped(&self, addr: VirtAddr) -> bool {
+        self.owned_mem
+            .values()
+            .any(|area| area.vaddr <= addr && addr < area.end_va())
+    }
+
+    /// Extends the stack just above `addr` down to cover it.
+    ///
+    /// Returns false if there is no stack that grows down above `addr`, or the
+    /// stack would exceed the stack limit or come within the guard gap of the
+    /// area below.
+    fn grow_stack(&mut self, addr: VirtAddr) -> bool {
+        let new_start = addr.align_down_4k().as_usize();
+        let Some((&start, area)) = self.owned_mem.range(new_start..).next() else {
+            return false;
+        };
+        if !area.grows_down() || area.end_va().as_usize() - new_start > self.stack_limit {
+            return false;
+        }
+        let below = self
+            .owned_mem
+            .range(..new_start)
+            .map(|(_, area)| area.end_va().as_usize())
+            .chain(
+                self.attached_mem
+                    .iter()
+                    .map(|(vaddr, _, mem)| vaddr.as_usize() + mem.size()),
+            )
+            .chain(
+                self.device_mem
+                    .iter()
+                    .map(|mem| mem.vaddr.as_usize() + mem.size),
+            )
+            .filter(|&end| end <= start)
+            .max()
+            .unwrap_or(axconfig::USER_MEMORY_START);
+        if below + STACK_GUARD_GAP > new_start {
+            warn!("stack overflow at {:?}", addr);
+            return false;
+        }
+        let mut area = self.owned_mem.remove(&start).unwrap();
+        area.grow_down(new_start.into(), &mut self.page_table);
+        debug!(
+            "stack grows down to [{:?}, {:?})",
+            area.vaddr,
+            area.end_va()
+        );
+        self.owned_mem.insert(new_start, area);
+        true
     }
 
     /// mmap. You need to flush tlb after this.
@@ -314,6 +487,108 @@ impl MemorySet {
         info!("[munmap] [{:?}, {:?})", start, (start + size).align_up_4k());
 
         self.split_for_area(start, size);
+        self.unmap_device_mem(start, size);
+    }
+
+    /// Maps the device memory at `paddr` in user space, e.g., the framebuffer
+    /// of a display. You need to flush TLB after this.
+    pub fn map_device_mem(
+        &mut self,
+        start: VirtAddr,
+        size: usize,
+        paddr: PhysAddr,
+        flags: MappingFlags,
+        fixed: bool,
+    ) -> AxResult<usize> {
+        // align up to 4k
+        let size = (size + PAGE_SIZE_4K - 1) / PAGE_SIZE_4K * PAGE_SIZE_4K;
+        info!(
+            "[map_device_mem] vaddr: [{:?}, {:?}), paddr: {:?}, {:?}",
+            start,
+            start + size,
+            paddr,
+            flags
+        );
+        let start = if fixed {
+            self.split_for_area(start, size);
+            self.unmap_device_mem(start, size);
+            start
+        } else {
+            self.find_free_area(start, size).ok_or(AxError::NoMemory)?
+        };
+        self.page_table
+            .map_region(start, paddr, size, flags, false)
+            .map_err(|_| AxError::InvalidInput)?;
+        self.device_mem.push(DeviceMem {
+            vaddr: start,
+            paddr,
+            size,
+            flags,
+        });
+        Ok(start.as_usize())
+    }
+
+    /// Unmaps the device memory in the given range. The regions that are
+    /// partially in the range are split.
+    fn unmap_device_mem(&mut self, start: VirtAddr, size: usize) {
+        self.update_device_mem(start, size, |page_table, mem| {
+            page_table.unmap_region(mem.vaddr, mem.size).unwrap();
+            None
+        });
+    }
+
+    /// Changes the flags of the device memory in the given range, so that the
+    /// mappings re-created by fork have the new flags. The regions that are
+    /// partially in the range are split.
+    fn protect_device_mem(&mut self, start: VirtAddr, size: usize, flags: MappingFlags) {
+        self.update_device_mem(start, size, |page_table, mem| {
+            page_table
+                .update_region(mem.vaddr, mem.size, flags)
+                .unwrap();
+            Some(DeviceMem { flags, ..mem })
+        });
+    }
+
+    /// Splits the device memory regions at the bounds of the given range, and
+    /// replaces each part in the range with the result of `f`.
+    fn update_device_mem(
+        &mut self,
+        start: VirtAddr,
+        size: usize,
+        mut f: impl FnMut(&mut PageTable, DeviceMem) -> Option<DeviceMem>,
+    ) {
+        let (start, end) = (start.as_usize(), start.as_usize() + size);
+        let mut kept = Vec::new();
+        for mem in self.device_mem.drain(..) {
+            let (mem_start, mem_end) = (mem.vaddr.as_usize(), mem.vaddr.as_usize() + mem.size);
+            if mem_end <= start || end <= mem_start {
+                kept.push(mem);
+                continue;
+            }
+            let (lo, hi) = (mem_start.max(start), mem_end.min(end));
+            let inner = DeviceMem {
+                vaddr: lo.into(),
+                paddr: mem.paddr + (lo - mem_start),
+                size: hi - lo,
+                ..mem
+            };
+            kept.extend(f(&mut self.page_table, inner));
+            if mem_start < lo {
+                kept.push(DeviceMem {
+                    size: lo - mem_start,
+                    ..mem
+                });
+            }
+            if hi < mem_end {
+                kept.push(DeviceMem {
+                    vaddr: hi.into(),
+                    paddr: mem.paddr + (hi - mem_start),
+                    size: mem_end - hi,
+                    ..mem
+                });
+            }
+        }
+        self.device_mem = kept;
     }
 
     /// msync
@@ -396,11 +671,15 @@ impl MemorySet {
 
             assert!(self.owned_mem.insert(area.vaddr.into(), area).is_none());
         }
+        self.protect_device_mem(start, size, flags);
         axhal::arch::flush_tlb(None);
     }
 
     /// It will map newly allocated page in the page table. You need to flush TLB after this.
     pub fn handle_page_fault(&mut self, addr: VirtAddr, flags: MappingFlags) -> AxResult<()> {
+        if !self.is_mapped(addr) {
+            self.grow_stack(addr);
+        }
         match self
             .owned_mem
             .values_mut()
@@ -425,6 +704,9 @@ impl MemorySet {
             area.dealloc(&mut self.page_table);
         }
         self.owned_mem.clear();
+        for mem in self.device_mem.drain(..) {
+            self.page_table.unmap_region(mem.vaddr, mem.size).unwrap();
+        }
     }
 
     /// Query the page table to get the physical address, flags and page size of the given virtual
@@ -591,6 +873,9 @@ impl MemorySet {
     ///
     /// 若在内存集中，且已经分配了物理页面，则不做处理。
     pub fn manual_alloc_for_lazy(&mut self, addr: VirtAddr) -> AxResult<()> {
+        if !self.is_mapped(addr) {
+            self.grow_stack(addr);
+        }
         if let Some((_, area)) = self
             .owned_mem
             .iter_mut()
@@ -673,11 +958,23 @@ impl MemorySet {
 
             private_mem: self.private_mem.clone(),
             attached_mem: Vec::new(),
+            device_mem: Vec::new(),
+            mmap_base: self.mmap_base,
+            stack_limit: self.stack_limit,
         };
 
         for (addr, flags, mem) in &self.attached_mem {
             new_memory.attach_shared_mem(mem.clone(), *addr, *flags);
         }
+        // device memory is always shared with the child, only the mapped
+        // regions are recorded so that dropping on error unmaps just them
+        for mem in &self.device_mem {
+            new_memory
+                .page_table
+                .map_region(mem.vaddr, mem.paddr, mem.size, mem.flags, false)
+                .map_err(paging_err_to_ax_err)?;
+            new_memory.device_mem.push(*mem);
+        }
 
         Ok(new_memory)
     }
@@ -689,6 +986,16 @@ impl Drop for MemorySet {
     }
 }
 
+/// Converts an error of the page table to the corresponding [`AxError`].
+fn paging_err_to_ax_err(err: PagingError) -> AxError {
+    match err {
+        PagingError::NoMemory => AxError::NoMemory,
+        PagingError::AlreadyMapped => AxError::AlreadyExists,
+        PagingError::NotMapped => AxError::BadAddress,
+        _ => AxError::InvalidInput,
+    }
+}
+
 /// 验证地址是否已分配页面
 pub fn check_page_table_entry_validity(
     addr: VirtAddr,
@@ -53,6 +53,7 @@ impl DnsSocket {
                 }
             })?;
         loop {
+            let events = super::net_events();
             SOCKET_SET.poll_interfaces();
             match SOCKET_SET.with_socket_mut::<dns::Socket, _, _>(handle, |socket| {
                 socket.get_query_result(query_handle).map_err(|e| match e {
@@ -69,7 +70,7 @@ impl DnsSocket {
                     }
                     return Ok(res);
                 }
-                Err(AxError::WouldBlock) => axtask::yield_now(),
+                Err(AxError::WouldBlock) => super::wait_for_events(events),
                 Err(e) => return Err(e),
             }
         }
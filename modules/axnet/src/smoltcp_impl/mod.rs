@@ -149,11 +149,18 @@ impl<'a> SocketSetWrapper<'a> {
 
     pub fn poll_interfaces(&self) {
         #[cfg(feature = "monolithic")]
-        LOOPBACK.lock().poll(
+        let _loopback_changed = LOOPBACK.lock().poll(
             Instant::from_micros_const((current_time_nanos() / NANOS_PER_MICROS) as i64),
             LOOPBACK_DEV.lock().deref_mut(),
             &mut self.0.lock(),
         );
+        // the loopback interface has no IRQ to wake up the waiting sockets
+        #[cfg(all(feature = "monolithic", feature = "irq-io"))]
+        if _loopback_changed {
+            if let Some(waiter) = axdriver::irq_io::net_waiter() {
+                waiter.notify();
+            }
+        }
 
         ETH0.poll(&self.0);
     }
@@ -337,6 +344,35 @@ pub fn poll_interfaces() {
     SOCKET_SET.poll_interfaces();
 }
 
+/// Interval to poll the interfaces while waiting for NIC interrupts, so that
+/// the timers of smoltcp (e.g., TCP retransmission) still make progress.
+#[cfg(feature = "irq-io")]
+const IRQ_POLL_INTERVAL: core::time::Duration = core::time::Duration::from_millis(10);
+
+/// Returns a snapshot of the network events, to be passed to
+/// [`wait_for_events`] later.
+fn net_events() -> usize {
+    #[cfg(feature = "irq-io")]
+    if let Some(waiter) = axdriver::irq_io::net_waiter() {
+        return waiter.events();
+    }
+    0
+}
+
+/// Waits for network events newer than the `events` snapshot before a blocking
+/// operation retries.
+///
+/// With `irq-io` and an interrupt-driven NIC, the task sleeps until the NIC
+/// interrupts; otherwise it just yields.
+fn wait_for_events(_events: usize) {
+    #[cfg(feature = "irq-io")]
+    if let Some(waiter) = axdriver::irq_io::net_waiter() {
+        waiter.wait_since(_events, IRQ_POLL_INTERVAL);
+        return;
+    }
+    axtask::yield_now();
+}
+
 /// Benchmark raw socket transmit bandwidth.
 pub fn bench_transmit() {
     ETH0.dev.lock().bench_transmit_bandwidth();
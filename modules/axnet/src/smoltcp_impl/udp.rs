@@ -321,10 +321,11 @@ impl UdpSocket {
                     return Err(AxError::Interrupted);
                 }
 
+                let events = super::net_events();
                 SOCKET_SET.poll_interfaces();
                 match f() {
                     Ok(t) => return Ok(t),
-                    Err(AxError::WouldBlock) => axtask::yield_now(),
+                    Err(AxError::WouldBlock) => super::wait_for_events(events),
                     Err(e) => return Err(e),
                 }
             }
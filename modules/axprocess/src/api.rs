@@ -1,38 +1,33 @@
 use core::ops::Deref;
-use core::ptr::copy_nonoverlapping;
-use core::str::from_utf8;
 extern crate alloc;
 use alloc::sync::Arc;
-use alloc::{
-    string::{String, ToString},
-    vec,
-    vec::Vec,
-};
-use axconfig::{MAX_USER_HEAP_SIZE, MAX_USER_STACK_SIZE, USER_HEAP_BASE, USER_STACK_TOP};
-use axerrno::{AxError, AxResult};
+use alloc::{string::String, vec, vec::Vec};
+use axerrno::AxResult;
 use axfutex::flags::FutexFlags;
 use axhal::mem::VirtAddr;
 use axhal::paging::MappingFlags;
 use axhal::time::{current_time_nanos, NANOS_PER_MICROS, NANOS_PER_SEC};
 use axhal::KERNEL_PROCESS_ID;
-use axlog::{info, warn};
+use axlog::info;
 use axmem::MemorySet;
 
 use axsignal::signal_no::SignalNo;
 use axsync::Mutex;
 use axtask::{current, current_processor, yield_now, AxTaskRef, CurrentTask, TaskId, TaskState};
 use core::sync::atomic::AtomicI32;
-use elf_parser::{
-    get_app_stack_region, get_auxv_vector, get_elf_entry, get_elf_segments, get_relocate_pairs,
-};
-use xmas_elf::program::SegmentData;
 
 use crate::flags::WaitStatus;
-use crate::futex::futex_wake;
-use crate::link::real_path;
+use crate::futex::{exit_robust_list, futex_wake};
+use crate::loader::ExecImage;
 use crate::process::{Process, PID2PC, TID2TASK};
 
-use crate::signal::{send_signal_to_process, send_signal_to_thread};
+use crate::signal::{send_fault_signal, send_signal_to_process, send_signal_to_thread};
+
+/// 在 procfs 中注册进程模块提供的文件，在文件系统初始化之后调用一次
+pub fn init_procfs() {
+    crate::binfmt::init();
+    crate::loader::init();
+}
 
 /// 初始化内核调度进程
 pub fn init_kernel_process() {
@@ -107,14 +102,20 @@ pub fn exit_current_task(exit_code: i32) -> ! {
             }
         }
     }
+    // 释放当前线程持有的 robust futex
+    exit_robust_list(curr_id);
     if current_task.is_leader() {
         loop {
             let mut all_exited = true;
             for task in process.tasks.lock().deref() {
                 if !task.is_leader() && task.state() != TaskState::Exited {
                     all_exited = false;
-                    send_signal_to_thread(task.id().as_u64() as isize, SignalNo::SIGKILL as isize)
-                        .unwrap();
+                    send_signal_to_thread(
+                        task.id().as_u64() as isize,
+                        SignalNo::SIGKILL as isize,
+                        None,
+                    )
+                    .unwrap();
                 }
             }
             if !all_exited {
@@ -130,8 +131,10 @@ pub fn exit_current_task(exit_code: i32) -> ! {
 
         process.tasks.lock().clear();
         process.fd_manager.fd_table.lock().clear();
+        axfs::lock::release_process_locks(process.pid(), None);
 
         process.signal_modules.lock().clear();
+        process.timers.clear();
 
         let mut pid2pc = PID2PC.lock();
         let kernel_process = pid2pc.get(&KERNEL_PROCESS_ID).unwrap();
@@ -161,6 +164,8 @@ pub fn exit_current_task(exit_code: i32) -> ! {
         drop(tasks);
 
         process.signal_modules.lock().remove(&curr_id);
+        let (utime_ns, stime_ns) = current_task.time_stat_output();
+        process.timers.account_task_time(utime_ns, stime_ns);
         drop(process);
     }
     axtask::exit(exit_code);
@@ -169,100 +174,11 @@ pub fn exit_current_task(exit_code: i32) -> ! {
 /// 返回应用程序入口，用户栈底，用户堆底
 pub fn load_app(
     name: String,
-    mut args: Vec<String>,
-    envs: &Vec<String>,
+    args: Vec<String>,
+    envs: &[String],
     memory_set: &mut MemorySet,
 ) -> AxResult<(VirtAddr, VirtAddr, VirtAddr)> {
-    if name.ends_with(".sh") {
-        args = [vec![String::from("busybox"), String::from("sh")], args].concat();
-        return load_app("busybox".to_string(), args, envs, memory_set);
-    }
-    let elf_data = if let Ok(ans) = axfs::api::read(name.as_str()) {
-        ans
-    } else {
-        // exit(0)
-        info!("App not found: {}", name);
-        return Err(AxError::NotFound);
-    };
-    let elf = xmas_elf::ElfFile::new(&elf_data).expect("Error parsing app ELF file.");
-    if let Some(interp) = elf
-        .program_iter()
-        .find(|ph| ph.get_type() == Ok(xmas_elf::program::Type::Interp))
-    {
-        let interp = match interp.get_data(&elf) {
-            Ok(SegmentData::Undefined(data)) => data,
-            _ => panic!("Invalid data in Interp Elf Program Header"),
-        };
-
-        let interp_path = from_utf8(interp).expect("Interpreter path isn't valid UTF-8");
-        // remove trailing '\0'
-        let interp_path = interp_path.trim_matches(char::from(0)).to_string();
-        let real_interp_path = real_path(&interp_path);
-        args = [vec![real_interp_path.clone()], args].concat();
-        return load_app(real_interp_path, args, envs, memory_set);
-    }
-    info!("load app args: {:?} name: {}", args, name);
-    let elf_base_addr = Some(0x400_0000);
-    warn!("The elf base addr may be different in different arch!");
-    // let (entry, segments, relocate_pairs) = parse_elf(&elf, elf_base_addr);
-    let entry = get_elf_entry(&elf, elf_base_addr);
-    let segments = get_elf_segments(&elf, elf_base_addr);
-    let relocate_pairs = get_relocate_pairs(&elf, elf_base_addr);
-    for segment in segments {
-        memory_set.new_region(
-            segment.vaddr,
-            segment.size,
-            false,
-            segment.flags,
-            segment.data.as_deref(),
-            None,
-        );
-    }
-
-    for relocate_pair in relocate_pairs {
-        let src: usize = relocate_pair.src.into();
-        let dst: usize = relocate_pair.dst.into();
-        let count = relocate_pair.count;
-        unsafe { copy_nonoverlapping(src.to_ne_bytes().as_ptr(), dst as *mut u8, count) }
-    }
-
-    // Now map the stack and the heap
-    let heap_start = VirtAddr::from(USER_HEAP_BASE);
-    let heap_data = [0_u8].repeat(MAX_USER_HEAP_SIZE);
-    memory_set.new_region(
-        heap_start,
-        MAX_USER_HEAP_SIZE,
-        false,
-        MappingFlags::READ | MappingFlags::WRITE | MappingFlags::USER,
-        Some(&heap_data),
-        None,
-    );
-    info!(
-        "[new region] user heap: [{:?}, {:?})",
-        heap_start,
-        heap_start + MAX_USER_HEAP_SIZE
-    );
-
-    let auxv = get_auxv_vector(&elf, elf_base_addr);
-
-    let stack_top = VirtAddr::from(USER_STACK_TOP);
-    let stack_size = MAX_USER_STACK_SIZE;
-
-    let (stack_data, stack_bottom) = get_app_stack_region(args, envs, auxv, stack_top, stack_size);
-    memory_set.new_region(
-        stack_top,
-        stack_size,
-        false,
-        MappingFlags::USER | MappingFlags::READ | MappingFlags::WRITE,
-        Some(&stack_data),
-        None,
-    );
-    info!(
-        "[new region] user stack: [{:?}, {:?})",
-        stack_top,
-        stack_top + stack_size
-    );
-    Ok((entry, stack_bottom.into(), heap_start))
+    ExecImage::open(name, args)?.load(envs, memory_set, 0)
 }
 
 /// 当从内核态到用户态时，统计对应进程的时间信息
@@ -303,7 +219,7 @@ pub fn handle_page_fault(addr: VirtAddr, flags: MappingFlags) {
     {
         axhal::arch::flush_tlb(None);
     } else {
-        let _ = send_signal_to_thread(current().id().as_u64() as isize, SignalNo::SIGSEGV as isize);
+        send_fault_signal(SignalNo::SIGSEGV);
     }
 }
 
@@ -311,60 +227,61 @@ pub fn handle_page_fault(addr: VirtAddr, flags: MappingFlags) {
 /// 若找到了则返回对应的pid
 /// 否则返回一个状态
 ///
+/// `report_stopped` 与 `report_continued` 分别对应 wait4 的 WUNTRACED 与 WCONTINUED，
+/// 此时子进程停止或继续执行也会被报告，但不会从children中删除
+///
 /// # Safety
 ///
 /// 保证传入的 ptr 是有效的
-pub unsafe fn wait_pid(pid: i32, exit_code_ptr: *mut i32) -> Result<u64, WaitStatus> {
+pub unsafe fn wait_pid(
+    pid: i32,
+    exit_code_ptr: *mut i32,
+    report_stopped: bool,
+    report_continued: bool,
+) -> Result<u64, WaitStatus> {
     // 获取当前进程
     let curr_process = current_process();
-    let mut exit_task_id: usize = 0;
-    let mut answer_id: u64 = 0;
+    let mut children = curr_process.children.lock();
     let mut answer_status = WaitStatus::NotExist;
-    for (index, child) in curr_process.children.lock().iter().enumerate() {
-        if pid <= 0 {
-            if pid == 0 {
-                axlog::warn!("Don't support for process group.");
-            }
-            // 任意一个进程结束都可以的
-            answer_status = WaitStatus::Running;
-            if let Some(exit_code) = child.get_code_if_exit() {
-                answer_status = WaitStatus::Exited;
-                info!("wait pid _{}_ with code _{}_", child.pid(), exit_code);
-                exit_task_id = index;
-                if !exit_code_ptr.is_null() {
-                    unsafe {
-                        // 因为没有切换页表，所以可以直接填写
-                        *exit_code_ptr = exit_code << 8;
-                    }
-                }
-                answer_id = child.pid();
-                break;
-            }
-        } else if child.pid() == pid as u64 {
-            // 找到了对应的进程
-            if let Some(exit_code) = child.get_code_if_exit() {
-                answer_status = WaitStatus::Exited;
-                info!("wait pid _{}_ with code _{:?}_", child.pid(), exit_code);
-                exit_task_id = index;
-                if !exit_code_ptr.is_null() {
-                    unsafe {
-                        *exit_code_ptr = exit_code << 8;
-                        // 用于WEXITSTATUS设置编码
-                    }
-                }
-                answer_id = child.pid();
-            } else {
-                answer_status = WaitStatus::Running;
+    let mut answer = None;
+    for (index, child) in children.iter().enumerate() {
+        if pid > 0 && child.pid() != pid as u64 {
+            continue;
+        }
+        if pid == 0 {
+            axlog::warn!("Don't support for process group.");
+        }
+        // 任意一个进程结束都可以的
+        answer_status = WaitStatus::Running;
+        let (status, exited) = if let Some(exit_code) = child.get_code_if_exit() {
+            info!("wait pid _{}_ with code _{}_", child.pid(), exit_code);
+            // 用于WEXITSTATUS设置编码
+            (exit_code << 8, true)
+        } else if let Some(status) = child
+            .job_control
+            .lock()
+            .take_wait_status(report_stopped, report_continued)
+        {
+            info!("wait pid _{}_ with status _{:#x}_", child.pid(), status);
+            (status, false)
+        } else {
+            continue;
+        };
+        if !exit_code_ptr.is_null() {
+            unsafe {
+                // 因为没有切换页表，所以可以直接填写
+                *exit_code_ptr = status;
             }
-            break;
         }
+        answer = Some((index, child.pid(), exited));
+        break;
     }
+    let (index, answer_id, exited) = answer.ok_or(answer_status)?;
     // 若进程成功结束，需要将其从父进程的children中删除
-    if answer_status == WaitStatus::Exited {
-        curr_process.children.lock().remove(exit_task_id);
-        return Ok(answer_id);
+    if exited {
+        children.remove(index);
     }
-    Err(answer_status)
+    Ok(answer_id)
 }
 
 /// 以进程作为中转调用task的yield
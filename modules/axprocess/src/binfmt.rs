@@ -0,0 +1,262 @@
+//! 可执行文件格式的识别
+//!
+//! `execve` 依次尝试以下几种格式，直到得到一个 ELF 文件：
+//!
+//! - ELF 文件，直接加载；
+//! - 以 `#!` 开头的脚本，改为执行其中给出的解释器；
+//! - 通过 `/proc/sys/fs/binfmt_misc/register` 注册的规则，按魔数或扩展名识别，
+//!   与 Linux 的 binfmt_misc 相同。默认有一条规则，用 `busybox sh` 执行没有 `#!`
+//!   的 `.sh` 文件。
+//!
+//! 后两种格式把文件路径作为参数交给解释器。解释器本身也可以是脚本，但嵌套不能超过
+//! [`MAX_RECURSION`] 层，否则返回 `InvalidData`，即 `ENOEXEC`。
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use axerrno::{AxError, AxResult};
+use axsync::Mutex;
+use core::str::from_utf8;
+use lazy_static::lazy_static;
+
+const ELF_MAGIC: &[u8] = b"\x7fELF";
+/// `#!` 行的最大长度，与 Linux 相同
+const SHEBANG_MAX: usize = 256;
+/// 解释器嵌套的最大层数，与 Linux 相同
+pub const MAX_RECURSION: usize = 4;
+
+/// 识别文件的方式
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BinfmtMatch {
+    /// 文件在 `offset` 处的内容与 `mask` 按位与之后，等于 `magic`
+    Magic {
+        /// 魔数在文件中的偏移
+        offset: usize,
+        /// 魔数
+        magic: Vec<u8>,
+        /// 掩码，与魔数等长；为空时比较全部的位
+        mask: Option<Vec<u8>>,
+    },
+    /// 文件名的扩展名，不含 `.`
+    Extension(String),
+}
+
+impl BinfmtMatch {
+    fn matches(&self, path: &str, data: &[u8]) -> bool {
+        match self {
+            Self::Magic {
+                offset,
+                magic,
+                mask,
+            } => {
+                let Some(content) = data.get(*offset..*offset + magic.len()) else {
+                    return false;
+                };
+                content.iter().enumerate().all(|(i, &byte)| {
+                    let mask = mask.as_ref().map_or(0xff, |mask| mask[i]);
+                    byte & mask == magic[i]
+                })
+            }
+            Self::Extension(ext) => {
+                let name = path.rsplit('/').next().unwrap_or(path);
+                name.rsplit_once('.').is_some_and(|(_, e)| e == ext)
+            }
+        }
+    }
+}
+
+/// 一条 binfmt_misc 风格的规则
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BinfmtRule {
+    /// 规则的名字，用于删除规则
+    pub name: String,
+    /// 识别文件的方式
+    pub matcher: BinfmtMatch,
+    /// 解释器的路径，以及放在文件路径之前的参数
+    pub interpreter: Vec<String>,
+}
+
+lazy_static! {
+    static ref RULES: Mutex<Vec<BinfmtRule>> = Mutex::new(vec![BinfmtRule {
+        name: "shell".to_string(),
+        matcher: BinfmtMatch::Extension("sh".to_string()),
+        interpreter: vec!["busybox".to_string(), "sh".to_string()],
+    }]);
+}
+
+/// 注册一条规则。同名的规则已存在时返回 `AlreadyExists`
+fn register_binfmt(rule: BinfmtRule) -> AxResult {
+    let mut rules = RULES.lock();
+    if rule.interpreter.is_empty()
+        || matches!(&rule.matcher, BinfmtMatch::Magic { magic, mask, .. }
+            if magic.is_empty() || mask.as_ref().is_some_and(|mask| mask.len() != magic.len()))
+    {
+        return Err(AxError::InvalidInput);
+    }
+    if rules.iter().any(|r| r.name == rule.name) {
+        return Err(AxError::AlreadyExists);
+    }
+    rules.push(rule);
+    Ok(())
+}
+
+/// 在 procfs 中创建 binfmt_misc 的文件，需要在文件系统初始化之后调用
+///
+/// - `register`：写入 `:name:type:offset:magic:mask:interpreter:flags` 注册规则；
+/// - `status`：读出已注册的规则，写入 `-1` 删除所有规则。
+pub(crate) fn init() {
+    if let Err(err) = axfs::procfs::register_text(
+        "sys/fs/binfmt_misc/register",
+        String::new,
+        Some(register_binfmt_misc),
+    ) {
+        axlog::warn!(
+            "failed to create /proc/sys/fs/binfmt_misc/register: {:?}",
+            err
+        );
+    }
+    if let Err(err) =
+        axfs::procfs::register_text("sys/fs/binfmt_misc/status", show_status, Some(store_status))
+    {
+        axlog::warn!(
+            "failed to create /proc/sys/fs/binfmt_misc/status: {:?}",
+            err
+        );
+    }
+}
+
+/// `status` 的内容：`enabled` 以及每条规则的名字
+fn show_status() -> String {
+    let mut status = String::from("enabled\n");
+    for rule in RULES.lock().iter() {
+        status += &format!("{}\n", rule.name);
+    }
+    status
+}
+
+/// 写入 `status`，只支持 `-1`
+fn store_status(value: &str) -> AxResult {
+    match value {
+        "-1" => {
+            RULES.lock().clear();
+            Ok(())
+        }
+        _ => Err(AxError::InvalidInput),
+    }
+}
+
+/// 按 binfmt_misc 的格式 `:name:type:offset:magic:mask:interpreter:flags`
+/// 注册一条规则。
+///
+/// `type` 为 `M`（魔数）或 `E`（扩展名），魔数与掩码中可以用 `\xHH` 表示任意字节，
+/// 不支持任何 `flags`。
+fn register_binfmt_misc(spec: &str) -> AxResult {
+    let spec = spec.trim_end_matches('\n');
+    let delim = spec.chars().next().ok_or(AxError::InvalidInput)?;
+    let fields: Vec<&str> = spec[delim.len_utf8()..].split(delim).collect();
+    let [name, kind, offset, magic, mask, interpreter, ..] = fields[..] else {
+        return Err(AxError::InvalidInput);
+    };
+    if name.is_empty() || name.contains('/') || interpreter.is_empty() {
+        return Err(AxError::InvalidInput);
+    }
+    let matcher = match kind {
+        "M" => BinfmtMatch::Magic {
+            offset: match offset {
+                "" => 0,
+                offset => offset.parse().map_err(|_| AxError::InvalidInput)?,
+            },
+            magic: unescape(magic)?,
+            mask: match mask {
+                "" => None,
+                mask => Some(unescape(mask)?),
+            },
+        },
+        "E" => BinfmtMatch::Extension(magic.to_string()),
+        _ => return Err(AxError::InvalidInput),
+    };
+    register_binfmt(BinfmtRule {
+        name: name.to_string(),
+        matcher,
+        interpreter: vec![interpreter.to_string()],
+    })
+}
+
+/// 解码 `\xHH` 转义
+fn unescape(s: &str) -> AxResult<Vec<u8>> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' && bytes.get(i + 1) == Some(&b'x') {
+            let hex = s.get(i + 2..i + 4).ok_or(AxError::InvalidInput)?;
+            out.push(u8::from_str_radix(hex, 16).map_err(|_| AxError::InvalidInput)?);
+            i += 4;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    Ok(out)
+}
+
+/// 解析 `#!interpreter [arg]`，返回解释器及其参数。
+///
+/// 与 Linux 相同，解释器之后的内容整体作为一个参数。
+fn parse_shebang(data: &[u8]) -> AxResult<Vec<String>> {
+    let head = &data[2..data.len().min(SHEBANG_MAX)];
+    let line = match head.iter().position(|&b| b == b'\n') {
+        Some(end) => &head[..end],
+        // 解释器可能被截断
+        None if data.len() > SHEBANG_MAX => return Err(AxError::InvalidData),
+        None => head,
+    };
+    let line = from_utf8(line).map_err(|_| AxError::InvalidData)?.trim();
+    let (interp, arg) = line
+        .split_once([' ', '\t'])
+        .map_or((line, ""), |(interp, arg)| (interp, arg.trim()));
+    if interp.is_empty() {
+        return Err(AxError::InvalidData);
+    }
+    let mut interpreter = vec![interp.to_string()];
+    if !arg.is_empty() {
+        interpreter.push(arg.to_string());
+    }
+    Ok(interpreter)
+}
+
+/// 找到最终要加载的 ELF 文件，返回它的路径、内容，以及改写后的参数
+pub(crate) fn resolve(
+    mut path: String,
+    mut args: Vec<String>,
+) -> AxResult<(String, Vec<u8>, Vec<String>)> {
+    for _ in 0..=MAX_RECURSION {
+        let data = axfs::api::read(path.as_str())?;
+        if data.starts_with(ELF_MAGIC) {
+            return Ok((path, data, args));
+        }
+        let interpreter = if data.starts_with(b"#!") {
+            parse_shebang(&data)?
+        } else {
+            RULES
+                .lock()
+                .iter()
+                .find(|rule| rule.matcher.matches(&path, &data))
+                .ok_or(AxError::InvalidData)?
+                .interpreter
+                .clone()
+        };
+        // 依次为解释器及其参数、文件路径、除 argv[0] 以外的原参数
+        let next = interpreter[0].clone();
+        args = interpreter
+            .into_iter()
+            .chain([path])
+            .chain(args.into_iter().skip(1))
+            .collect();
+        path = next;
+    }
+    Err(AxError::InvalidData)
+}
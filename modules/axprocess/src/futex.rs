@@ -4,6 +4,7 @@ use axerrno::LinuxError;
 use axfutex::{flags::FutexFlags, futex_hash, FutexKey, FutexQ, FUTEXQUEUES};
 use axhal::mem::VirtAddr;
 use axlog::info;
+use core::sync::atomic::{AtomicU32, Ordering};
 use core::time::Duration;
 //use axtask::WaitQueue;
 
@@ -30,6 +31,82 @@ impl FutexRobustList {
     }
 }
 
+/// futex 的持有者退出时设置的标志
+const FUTEX_OWNER_DIED: u32 = 0x4000_0000;
+/// futex 上有等待者的标志
+const FUTEX_WAITERS: u32 = 0x8000_0000;
+/// futex 值中持有者的线程号
+const FUTEX_TID_MASK: u32 = 0x3fff_ffff;
+/// robust list 中最多处理的项数，防止用户构造出环
+const ROBUST_LIST_LIMIT: usize = 2048;
+
+/// 读取用户地址空间中的一个值，地址不合法时返回 `None`
+fn read_user<T: Copy>(addr: usize) -> Option<T> {
+    if addr == 0 || addr % core::mem::align_of::<T>() != 0 {
+        return None;
+    }
+    let process = current_process();
+    process.manual_alloc_for_lazy(addr.into()).ok()?;
+    process
+        .manual_alloc_for_lazy((addr + core::mem::size_of::<T>() - 1).into())
+        .ok()?;
+    Some(unsafe { (addr as *const T).read_volatile() })
+}
+
+/// 若 futex 由线程 `tid` 持有，则将其标记为 FUTEX_OWNER_DIED，并唤醒一个等待者
+fn handle_futex_death(uaddr: usize, tid: u64) {
+    if read_user::<u32>(uaddr).is_none() {
+        return;
+    }
+    let futex = unsafe { &*(uaddr as *const AtomicU32) };
+    let mut val = futex.load(Ordering::Acquire);
+    loop {
+        if val & FUTEX_TID_MASK != tid as u32 {
+            return;
+        }
+        let new = (val & FUTEX_WAITERS) | FUTEX_OWNER_DIED;
+        match futex.compare_exchange(val, new, Ordering::AcqRel, Ordering::Acquire) {
+            Ok(_) => break,
+            Err(old) => val = old,
+        }
+    }
+    if val & FUTEX_WAITERS != 0 {
+        let _ = futex_wake(uaddr.into(), FutexFlags::empty(), 1);
+    }
+}
+
+/// 处理线程 `tid` 的 robust list，在线程退出或执行 execve 时调用
+///
+/// 用户空间中的链表头为 `{ next, futex_offset, list_op_pending }`，
+/// 链表项的最低位用于标记 PI futex，需要去掉。
+pub(crate) fn exit_robust_list(tid: u64) {
+    let head = match current_process().robust_list.lock().get(&tid) {
+        Some(list) if list.head != 0 => list.head,
+        _ => return,
+    };
+    let Some([next, offset, pending]) = read_user::<[usize; 3]>(head) else {
+        return;
+    };
+    let pending = pending & !1;
+    let mut entry = next & !1;
+    for _ in 0..ROBUST_LIST_LIMIT {
+        if entry == head {
+            break;
+        }
+        let Some(next) = read_user::<usize>(entry) else {
+            return;
+        };
+        // 正在加锁或解锁的 futex 最后处理
+        if entry != pending {
+            handle_futex_death(entry.wrapping_add(offset), tid);
+        }
+        entry = next & !1;
+    }
+    if pending != 0 {
+        handle_futex_death(pending.wrapping_add(offset), tid);
+    }
+}
+
 fn futex_get_value_locked(vaddr: VirtAddr) -> AxSyscallResult {
     let process = current_process();
     if process.manual_alloc_for_lazy(vaddr).is_ok() {
@@ -6,11 +6,14 @@ pub use api::*;
 mod process;
 pub use process::{Process, PID2PC, TID2TASK};
 
+pub mod binfmt;
 pub mod flags;
 pub mod futex;
 pub mod link;
 mod stdio;
 
 mod fd_manager;
+mod loader;
 
 pub mod signal;
+pub mod timer;
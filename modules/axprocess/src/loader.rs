@@ -0,0 +1,636 @@
+//! ELF 程序的加载
+//!
+//! 可执行文件（`ET_EXEC` 或位置无关的 `ET_DYN`）与其动态链接器（`PT_INTERP`）
+//! 分别映射到各自的基址，动态链接器由用户栈上的辅助向量（auxv）得知可执行文件的
+//! 位置，并完成两者的重定位。
+//!
+//! 格式错误的 ELF 文件在映射之前就会被拒绝，返回 `InvalidData`，对应 `ENOEXEC`。
+
+use alloc::{format, string::String, vec, vec::Vec};
+use core::ptr::copy_nonoverlapping;
+use core::str::from_utf8;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use axconfig::{MAX_USER_HEAP_SIZE, MAX_USER_STACK_SIZE, USER_HEAP_BASE, USER_STACK_TOP};
+use axerrno::{AxError, AxResult};
+use axhal::mem::VirtAddr;
+use axhal::paging::MappingFlags;
+use axlog::{info, warn};
+use axmem::MemorySet;
+use xmas_elf::header::{Class, Data, Type as ElfType};
+use xmas_elf::program::{ProgramHeader, SegmentData, Type};
+use xmas_elf::ElfFile;
+
+use crate::binfmt;
+
+const PAGE_SIZE: usize = 0x1000;
+/// 程序头表项的大小
+const PHDR_SIZE: usize = 56;
+/// 位置无关的可执行文件的加载基址
+const ELF_ET_DYN_BASE: usize = 0x400_0000;
+/// 从此处开始寻找空闲的区域，映射动态链接器与 vDSO
+const INTERP_BASE: usize = 0x2000_0000;
+
+// 地址随机化时各个区域的随机偏移的上限
+/// 位置无关的可执行文件的加载基址
+const ET_DYN_RND: usize = 0x100_0000;
+/// mmap 基址，同时也是动态链接器与 vDSO 的位置
+const MMAP_RND: usize = 0x800_0000;
+/// 用户堆，堆向低地址偏移
+const BRK_RND: usize = 0x200_0000;
+/// 栈顶，栈顶之上的部分不再映射
+const STACK_RND: usize = MAX_USER_STACK_SIZE / 4;
+
+/// personality(2) 中关闭地址随机化的标志
+const ADDR_NO_RANDOMIZE: u32 = 0x0040000;
+
+// 辅助向量的类型
+const AT_NULL: usize = 0;
+const AT_PHDR: usize = 3;
+const AT_PHENT: usize = 4;
+const AT_PHNUM: usize = 5;
+const AT_PAGESZ: usize = 6;
+const AT_BASE: usize = 7;
+const AT_FLAGS: usize = 8;
+const AT_ENTRY: usize = 9;
+const AT_UID: usize = 11;
+const AT_EUID: usize = 12;
+const AT_GID: usize = 13;
+const AT_EGID: usize = 14;
+const AT_HWCAP: usize = 16;
+const AT_CLKTCK: usize = 17;
+const AT_SECURE: usize = 23;
+const AT_RANDOM: usize = 25;
+const AT_HWCAP2: usize = 26;
+const AT_EXECFN: usize = 31;
+const AT_SYSINFO_EHDR: usize = 33;
+
+cfg_if::cfg_if! {
+    if #[cfg(target_arch = "x86_64")] {
+        const EM_HOST: u16 = 62;
+    } else if #[cfg(target_arch = "riscv64")] {
+        const EM_HOST: u16 = 243;
+    } else if #[cfg(target_arch = "aarch64")] {
+        const EM_HOST: u16 = 183;
+    }
+}
+
+/// 硬件能力，即 `AT_HWCAP`
+fn hwcap() -> usize {
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "x86_64")] {
+            // 与 Linux 相同，为 CPUID.1:EDX
+            unsafe { core::arch::x86_64::__cpuid(1).edx as usize }
+        } else if #[cfg(target_arch = "riscv64")] {
+            // 每个单字母扩展占一位，RV64IMAFDC
+            b"imafdc".iter().fold(0, |cap, &ext| cap | 1 << (ext - b'a'))
+        } else {
+            // HWCAP_FP | HWCAP_ASIMD
+            0b11
+        }
+    }
+}
+
+/// 解析 ELF 文件，并检查其能否在本机上加载。
+///
+/// 之后对程序头与段数据的访问都不会越界。
+fn parse_elf(data: &[u8]) -> AxResult<ElfFile<'_>> {
+    let elf = ElfFile::new(data).map_err(|_| AxError::InvalidData)?;
+    let header = &elf.header;
+    if header.pt1.class() != Class::SixtyFour
+        || header.pt1.data() != Data::LittleEndian
+        || u16::from_le_bytes([data[18], data[19]]) != EM_HOST
+    {
+        return Err(AxError::InvalidData);
+    }
+    match header.pt2.type_().as_type() {
+        ElfType::Executable | ElfType::SharedObject => {}
+        _ => return Err(AxError::InvalidData),
+    }
+
+    let ph_count = header.pt2.ph_count() as usize;
+    let ph_end = (header.pt2.ph_offset() as usize).checked_add(ph_count * PHDR_SIZE);
+    if header.pt2.ph_entry_size() as usize != PHDR_SIZE
+        || ph_count == 0
+        || ph_end.map_or(true, |end| end > data.len())
+    {
+        return Err(AxError::InvalidData);
+    }
+
+    let mut has_load = false;
+    for ph in elf.program_iter() {
+        let file_end = ph.offset().checked_add(ph.file_size());
+        if file_end.map_or(true, |end| end > data.len() as u64) {
+            return Err(AxError::InvalidData);
+        }
+        if ph.get_type() == Ok(Type::Load) {
+            if ph.file_size() > ph.mem_size()
+                || ph.virtual_addr().checked_add(ph.mem_size()).is_none()
+            {
+                return Err(AxError::InvalidData);
+            }
+            has_load = true;
+        }
+    }
+    if !has_load {
+        return Err(AxError::InvalidData);
+    }
+    Ok(elf)
+}
+
+/// 所有 `PT_LOAD` 段覆盖的页的范围
+fn load_range(elf: &ElfFile) -> (usize, usize) {
+    elf.program_iter()
+        .filter(|ph| ph.get_type() == Ok(Type::Load))
+        .fold((usize::MAX, 0), |(start, end), ph| {
+            let vaddr = ph.virtual_addr() as usize;
+            (
+                start.min(align_down(vaddr)),
+                end.max(align_up(vaddr + ph.mem_size() as usize)),
+            )
+        })
+}
+
+fn align_down(addr: usize) -> usize {
+    addr & !(PAGE_SIZE - 1)
+}
+
+fn align_up(addr: usize) -> usize {
+    (addr + PAGE_SIZE - 1) & !(PAGE_SIZE - 1)
+}
+
+/// `PT_INTERP` 段给出的动态链接器路径
+fn interp_path(elf: &ElfFile) -> AxResult<Option<String>> {
+    let Some(ph) = elf
+        .program_iter()
+        .find(|ph| ph.get_type() == Ok(Type::Interp))
+    else {
+        return Ok(None);
+    };
+    let Ok(SegmentData::Undefined(data)) = ph.get_data(elf) else {
+        return Err(AxError::InvalidData);
+    };
+    let path = from_utf8(data).map_err(|_| AxError::InvalidData)?;
+    // 去掉末尾的 '\0'
+    Ok(Some(path.trim_end_matches('\0').into()))
+}
+
+/// 将 `PT_LOAD` 段映射到偏移 `bias` 处。
+///
+/// 占用同一页的段合并为一个区域，权限取它们的并集。
+fn map_segments(elf: &ElfFile, bias: usize, memory_set: &mut MemorySet) {
+    let mut segments: Vec<_> = elf
+        .program_iter()
+        .filter(|ph| ph.get_type() == Ok(Type::Load))
+        .collect();
+    segments.sort_by_key(|ph| ph.virtual_addr());
+
+    let mut i = 0;
+    while i < segments.len() {
+        let seg_end =
+            |ph: &ProgramHeader| align_up(bias + (ph.virtual_addr() + ph.mem_size()) as usize);
+        let start = align_down(bias + segments[i].virtual_addr() as usize);
+        let mut end = seg_end(&segments[i]);
+        let mut j = i + 1;
+        while j < segments.len() && align_down(bias + segments[j].virtual_addr() as usize) < end {
+            end = end.max(seg_end(&segments[j]));
+            j += 1;
+        }
+
+        let mut data = vec![0u8; end - start];
+        let mut flags = MappingFlags::USER;
+        for ph in &segments[i..j] {
+            let offset = bias + ph.virtual_addr() as usize - start;
+            let file = &elf.input[ph.offset() as usize..(ph.offset() + ph.file_size()) as usize];
+            data[offset..offset + file.len()].copy_from_slice(file);
+            let ph_flags = ph.flags();
+            if ph_flags.is_read() {
+                flags |= MappingFlags::READ;
+            }
+            if ph_flags.is_write() {
+                flags |= MappingFlags::WRITE;
+            }
+            if ph_flags.is_execute() {
+                flags |= MappingFlags::EXECUTE;
+            }
+        }
+        memory_set.new_region(start.into(), end - start, false, flags, Some(&data), None);
+        i = j;
+    }
+}
+
+/// 程序头表在内存中的地址，即 `AT_PHDR`
+fn phdr_addr(elf: &ElfFile, bias: usize) -> usize {
+    if let Some(ph) = elf
+        .program_iter()
+        .find(|ph| ph.get_type() == Ok(Type::Phdr))
+    {
+        return bias + ph.virtual_addr() as usize;
+    }
+    // 没有 PT_PHDR 时，在包含程序头表的 PT_LOAD 段中寻找
+    let ph_offset = elf.header.pt2.ph_offset();
+    elf.program_iter()
+        .find(|ph| {
+            ph.get_type() == Ok(Type::Load)
+                && ph.offset() <= ph_offset
+                && ph_offset < ph.offset() + ph.file_size()
+        })
+        .map_or(0, |ph| {
+            bias + (ph.virtual_addr() + ph_offset - ph.offset()) as usize
+        })
+}
+
+/// 构造一个不导出任何符号的 vDSO。
+///
+/// C 库能正常解析它，但找不到 `clock_gettime` 等函数，从而回退到系统调用。
+fn vdso_image() -> Vec<u8> {
+    const HASH: usize = 0xb0;
+    const SYMTAB: usize = 0xc0;
+    const STRTAB: usize = 0xd8;
+    const STRTAB_DATA: &[u8] = b"\0linux-vdso.so.1\0";
+    const DYNAMIC: usize = 0xf0;
+    const DYNAMIC_ENTRIES: [(u64, u64); 7] = [
+        (4, HASH as u64),               // DT_HASH
+        (5, STRTAB as u64),             // DT_STRTAB
+        (6, SYMTAB as u64),             // DT_SYMTAB
+        (10, STRTAB_DATA.len() as u64), // DT_STRSZ
+        (11, 24),                       // DT_SYMENT
+        (14, 1),                        // DT_SONAME
+        (0, 0),                         // DT_NULL
+    ];
+    const SIZE: usize = DYNAMIC + DYNAMIC_ENTRIES.len() * 16;
+
+    let mut image = vec![0u8; PAGE_SIZE];
+    let mut put = |offset: usize, bytes: &[u8]| {
+        image[offset..offset + bytes.len()].copy_from_slice(bytes);
+    };
+    // ELF 头
+    put(0, b"\x7fELF\x02\x01\x01");
+    put(16, &3u16.to_le_bytes()); // ET_DYN
+    put(18, &EM_HOST.to_le_bytes());
+    put(20, &1u32.to_le_bytes());
+    put(32, &64u64.to_le_bytes()); // e_phoff
+    put(52, &64u16.to_le_bytes()); // e_ehsize
+    put(54, &(PHDR_SIZE as u16).to_le_bytes());
+    put(56, &2u16.to_le_bytes()); // e_phnum
+    put(58, &64u16.to_le_bytes()); // e_shentsize
+
+    // 程序头：PT_LOAD 与 PT_DYNAMIC
+    for (i, (ty, flags, offset, size, align)) in [
+        (1u32, 5u32, 0, SIZE, PAGE_SIZE),
+        (2, 4, DYNAMIC, SIZE - DYNAMIC, 8),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let ph = 64 + i * PHDR_SIZE;
+        put(ph, &ty.to_le_bytes());
+        put(ph + 4, &flags.to_le_bytes());
+        for field in 0..3 {
+            // p_offset, p_vaddr, p_paddr
+            put(ph + 8 + field * 8, &(offset as u64).to_le_bytes());
+        }
+        put(ph + 32, &(size as u64).to_le_bytes());
+        put(ph + 40, &(size as u64).to_le_bytes());
+        put(ph + 48, &(align as u64).to_le_bytes());
+    }
+    // 只有一个桶和一个空符号的散列表
+    put(HASH, &1u32.to_le_bytes());
+    put(HASH + 4, &1u32.to_le_bytes());
+    put(STRTAB, STRTAB_DATA);
+    for (i, (tag, value)) in DYNAMIC_ENTRIES.iter().enumerate() {
+        put(DYNAMIC + i * 16, &tag.to_le_bytes());
+        put(DYNAMIC + i * 16 + 8, &value.to_le_bytes());
+    }
+    image
+}
+
+/// 地址随机化的程度，即 `/proc/sys/kernel/randomize_va_space` 的值
+///
+/// 0 为关闭，1 为随机化栈、mmap 基址、vDSO 与位置无关的可执行文件，2 为同时随机化用户堆
+static RANDOMIZE_VA_SPACE: AtomicU8 = AtomicU8::new(2);
+
+fn randomize_va_space() -> u8 {
+    RANDOMIZE_VA_SPACE.load(Ordering::Relaxed)
+}
+
+fn show_randomize_va_space() -> String {
+    format!("{}\n", randomize_va_space())
+}
+
+/// 只接受 0、1、2，其余的写入返回 `EINVAL`
+fn store_randomize_va_space(data: &str) -> AxResult {
+    match data.parse() {
+        Ok(value @ 0..=2) => {
+            RANDOMIZE_VA_SPACE.store(value, Ordering::Relaxed);
+            Ok(())
+        }
+        _ => Err(AxError::InvalidInput),
+    }
+}
+
+/// 在 procfs 中注册 `/proc/sys/kernel/randomize_va_space`
+pub(crate) fn init() {
+    if let Err(err) = axfs::procfs::register_text(
+        "sys/kernel/randomize_va_space",
+        show_randomize_va_space,
+        Some(store_randomize_va_space),
+    ) {
+        warn!(
+            "failed to create /proc/sys/kernel/randomize_va_space: {:?}",
+            err
+        );
+    }
+}
+
+/// 小于 `max` 的随机偏移，按 `align` 对齐
+fn random_offset(max: usize, align: usize) -> usize {
+    let slots = max / align;
+    if slots == 0 {
+        return 0;
+    }
+    let mut bytes = [0u8; core::mem::size_of::<usize>()];
+    axrand::fill_bytes(&mut bytes);
+    usize::from_ne_bytes(bytes) % slots * align
+}
+
+/// 自高地址向低地址填写的初始用户栈
+struct StackWriter {
+    data: Vec<u8>,
+    base: usize,
+    pos: usize,
+}
+
+impl StackWriter {
+    fn push_bytes(&mut self, bytes: &[u8]) -> AxResult<usize> {
+        // 参数与环境变量过长
+        self.pos = self
+            .pos
+            .checked_sub(bytes.len())
+            .ok_or(AxError::InvalidInput)?;
+        self.data[self.pos..self.pos + bytes.len()].copy_from_slice(bytes);
+        Ok(self.base + self.pos)
+    }
+
+    fn push_str(&mut self, s: &str) -> AxResult<usize> {
+        self.push_bytes(b"\0")?;
+        self.push_bytes(s.as_bytes())
+    }
+
+    fn push_usize(&mut self, value: usize) -> AxResult<usize> {
+        self.push_bytes(&value.to_ne_bytes())
+    }
+}
+
+/// 构造初始用户栈，返回已用到的各页的起始地址与内容，以及栈指针。
+///
+/// 自 `top` 向下依次为：随机字节、`AT_EXECFN` 与各个字符串，以及 16 字节对齐的
+/// 辅助向量、`envp`、`argv` 与 `argc`。
+fn init_stack(
+    args: &[String],
+    envs: &[String],
+    execfn: &str,
+    mut auxv: Vec<(usize, usize)>,
+    top: usize,
+) -> AxResult<(usize, Vec<u8>, usize)> {
+    let mut stack = StackWriter {
+        data: vec![0u8; align_up(top) - USER_STACK_TOP],
+        base: USER_STACK_TOP,
+        pos: top - USER_STACK_TOP,
+    };
+    let mut random = [0u8; 16];
+    axrand::fill_bytes(&mut random);
+    let random = stack.push_bytes(&random)?;
+    let execfn = stack.push_str(execfn)?;
+    let envp = envs
+        .iter()
+        .map(|env| stack.push_str(env))
+        .collect::<AxResult<Vec<_>>>()?;
+    let argv = args
+        .iter()
+        .map(|arg| stack.push_str(arg))
+        .collect::<AxResult<Vec<_>>>()?;
+    auxv.push((AT_RANDOM, random));
+    auxv.push((AT_EXECFN, execfn));
+    auxv.push((AT_NULL, 0));
+
+    stack.pos &= !0xf;
+    let words = 1 + (argv.len() + 1) + (envp.len() + 1) + auxv.len() * 2;
+    if words % 2 == 1 {
+        stack.push_usize(0)?;
+    }
+    for &(key, value) in auxv.iter().rev() {
+        stack.push_usize(value)?;
+        stack.push_usize(key)?;
+    }
+    stack.push_usize(0)?;
+    for &env in envp.iter().rev() {
+        stack.push_usize(env)?;
+    }
+    stack.push_usize(0)?;
+    for &arg in argv.iter().rev() {
+        stack.push_usize(arg)?;
+    }
+    let sp = stack.push_usize(argv.len())?;
+    let used = align_down(stack.pos);
+    Ok((stack.base + used, stack.data.split_off(used), sp))
+}
+
+/// 读入并检查过的可执行文件，以及它的动态链接器
+pub(crate) struct ExecImage {
+    /// 传给 `execve` 的路径，对脚本而言不是 `elf` 的路径
+    path: String,
+    args: Vec<String>,
+    elf: Vec<u8>,
+    interp: Option<Vec<u8>>,
+}
+
+impl ExecImage {
+    /// 读入并检查可执行文件与其动态链接器，不修改地址空间
+    pub(crate) fn open(path: String, args: Vec<String>) -> AxResult<Self> {
+        let (elf_path, elf, args) = binfmt::resolve(path.clone(), args).map_err(|e| {
+            info!("failed to open app {}: {:?}", path, e);
+            e
+        })?;
+        if elf_path != path {
+            info!("{} is run by {}", path, elf_path);
+        }
+        let interp = {
+            let file = parse_elf(&elf)?;
+            let (start, end) = load_range(&file);
+            let fits = match file.header.pt2.type_().as_type() {
+                ElfType::Executable => {
+                    start >= axconfig::USER_MEMORY_START && end <= USER_HEAP_BASE
+                }
+                _ => end - start <= USER_HEAP_BASE - ELF_ET_DYN_BASE,
+            };
+            if !fits {
+                return Err(AxError::InvalidData);
+            }
+            interp_path(&file)?
+        };
+        let interp = match interp {
+            Some(interp_name) => {
+                let interp = axfs::api::read(interp_name.as_str())?;
+                let file = parse_elf(&interp)?;
+                // 动态链接器自身不能再有动态链接器
+                if file.header.pt2.type_().as_type() != ElfType::SharedObject
+                    || interp_path(&file)?.is_some()
+                {
+                    return Err(AxError::InvalidData);
+                }
+                info!("interpreter of {}: {}", elf_path, interp_name);
+                Some(interp)
+            }
+            None => None,
+        };
+        Ok(Self {
+            path,
+            args,
+            elf,
+            interp,
+        })
+    }
+
+    /// 将程序映射到地址空间，并构造用户栈与堆。
+    ///
+    /// `personality` 中没有 [`ADDR_NO_RANDOMIZE`] 时，按 `randomize_va_space` 随机化各区域的位置。
+    ///
+    /// 返回程序入口（有动态链接器时为其入口），用户栈指针与用户堆底。
+    pub(crate) fn load(
+        self,
+        envs: &[String],
+        memory_set: &mut MemorySet,
+        personality: u32,
+    ) -> AxResult<(VirtAddr, VirtAddr, VirtAddr)> {
+        info!("load app args: {:?} name: {}", self.args, self.path);
+        let randomize = if personality & ADDR_NO_RANDOMIZE != 0 {
+            0
+        } else {
+            randomize_va_space()
+        };
+        let elf = parse_elf(&self.elf)?;
+        let (start, end) = load_range(&elf);
+        let bias = match elf.header.pt2.type_().as_type() {
+            ElfType::Executable => 0,
+            _ if randomize >= 1 => {
+                let room = USER_HEAP_BASE - ELF_ET_DYN_BASE - (end - start);
+                ELF_ET_DYN_BASE + random_offset(ET_DYN_RND.min(room), PAGE_SIZE) - start
+            }
+            _ => ELF_ET_DYN_BASE - start,
+        };
+        map_segments(&elf, bias, memory_set);
+        if self.interp.is_none() && bias != 0 {
+            // 没有动态链接器的位置无关程序，由内核完成重定位
+            for pair in elf_parser::get_relocate_pairs(&elf, Some(bias)) {
+                let src: usize = pair.src.into();
+                let dst: usize = pair.dst.into();
+                unsafe {
+                    copy_nonoverlapping(src.to_ne_bytes().as_ptr(), dst as *mut u8, pair.count)
+                }
+            }
+        }
+
+        // 用户堆，随机化时仍在程序之上
+        let heap_start = if randomize >= 2 {
+            let room = USER_HEAP_BASE - (bias + end);
+            VirtAddr::from(USER_HEAP_BASE - random_offset(BRK_RND.min(room), PAGE_SIZE))
+        } else {
+            VirtAddr::from(USER_HEAP_BASE)
+        };
+        memory_set.new_region(
+            heap_start,
+            MAX_USER_HEAP_SIZE,
+            false,
+            MappingFlags::READ | MappingFlags::WRITE | MappingFlags::USER,
+            None,
+            None,
+        );
+        info!(
+            "[new region] user heap: [{:?}, {:?})",
+            heap_start,
+            heap_start + MAX_USER_HEAP_SIZE
+        );
+
+        // 动态链接器与 vDSO 映射在 mmap 基址之上的空闲区域
+        let mmap_base = if randomize >= 1 {
+            let mmap_base = INTERP_BASE + random_offset(MMAP_RND, PAGE_SIZE);
+            memory_set.set_mmap_base(mmap_base);
+            mmap_base
+        } else {
+            memory_set.set_mmap_base(axconfig::USER_MEMORY_START);
+            INTERP_BASE
+        };
+        let mut entry = bias + elf.header.pt2.entry_point() as usize;
+        let mut interp_base = 0;
+        if let Some(interp) = &self.interp {
+            let interp = parse_elf(interp)?;
+            let (start, end) = load_range(&interp);
+            let base = memory_set
+                .find_free_area(mmap_base.into(), end - start)
+                .ok_or(AxError::NoMemory)?
+                .as_usize();
+            let interp_bias = base - start;
+            map_segments(&interp, interp_bias, memory_set);
+            interp_base = base;
+            entry = interp_bias + interp.header.pt2.entry_point() as usize;
+            info!("[new region] interpreter at {:#x}", base);
+        }
+        let vdso = memory_set
+            .find_free_area(mmap_base.into(), PAGE_SIZE)
+            .ok_or(AxError::NoMemory)?;
+        memory_set.new_region(
+            vdso,
+            PAGE_SIZE,
+            false,
+            MappingFlags::READ | MappingFlags::EXECUTE | MappingFlags::USER,
+            Some(&vdso_image()),
+            None,
+        );
+
+        let auxv = vec![
+            (AT_SYSINFO_EHDR, vdso.as_usize()),
+            (AT_HWCAP, hwcap()),
+            (AT_PAGESZ, PAGE_SIZE),
+            (AT_CLKTCK, 100),
+            (AT_PHDR, phdr_addr(&elf, bias)),
+            (AT_PHENT, PHDR_SIZE),
+            (AT_PHNUM, elf.header.pt2.ph_count() as usize),
+            (AT_BASE, interp_base),
+            (AT_FLAGS, 0),
+            (AT_ENTRY, bias + elf.header.pt2.entry_point() as usize),
+            (AT_UID, 0),
+            (AT_EUID, 0),
+            (AT_GID, 0),
+            (AT_EGID, 0),
+            (AT_SECURE, 0),
+            (AT_HWCAP2, 0),
+        ];
+        // 栈顶按页随机偏移，栈指针在页内再随机偏移
+        let stack_end = USER_STACK_TOP + MAX_USER_STACK_SIZE;
+        let stack_top = if randomize >= 1 {
+            stack_end - random_offset(STACK_RND, PAGE_SIZE) - random_offset(PAGE_SIZE, 16)
+        } else {
+            stack_end
+        };
+        let (stack_start, stack_data, sp) =
+            init_stack(&self.args, envs, &self.path, auxv, stack_top)?;
+        // 只映射已用到的页，其余的页在缺页时向下扩展，直到 RLIMIT_STACK
+        let stack_start = VirtAddr::from(stack_start);
+        memory_set.new_region(
+            stack_start,
+            stack_data.len(),
+            false,
+            MappingFlags::USER | MappingFlags::READ | MappingFlags::WRITE,
+            Some(&stack_data),
+            None,
+        );
+        memory_set.set_grows_down(stack_start);
+        info!(
+            "[new region] user stack: [{:?}, {:?})",
+            stack_start,
+            stack_start + stack_data.len()
+        );
+        Ok((entry.into(), sp.into(), heap_start))
+    }
+}
@@ -8,6 +8,7 @@ use alloc::vec::Vec;
 use alloc::{collections::BTreeMap, string::String};
 use axerrno::{AxError, AxResult};
 use axfs::api::{FileIO, OpenFlags};
+use axfutex::flags::FutexFlags;
 use axhal::arch::{
     read_trapframe_from_kstack, write_page_table_root0, write_trapframe_to_kstack, TrapFrame,
 };
@@ -19,15 +20,17 @@ use axlog::{debug, error};
 use axmem::MemorySet;
 use axsignal::signal_no::SignalNo;
 use axsync::Mutex;
-use axtask::{current, new_task, AxTaskRef, Processor, TaskId};
-use core::sync::atomic::{AtomicBool, AtomicI32, AtomicU64, Ordering};
+use axtask::{current, new_task, AxTaskRef, Processor, TaskId, TaskState, WaitQueue};
+use core::sync::atomic::{AtomicBool, AtomicI32, AtomicU32, AtomicU64, Ordering};
 
 use crate::fd_manager::{FdManager, FdTable};
 use crate::flags::CloneFlags;
-use crate::futex::FutexRobustList;
+use crate::futex::{exit_robust_list, futex_wake, FutexRobustList};
 
-use crate::signal::SignalModule;
+use crate::loader::ExecImage;
+use crate::signal::{send_signal_to_thread, JobControl, SignalModule};
 use crate::stdio::{Stderr, Stdin, Stdout};
+use crate::timer::ProcessTimers;
 use crate::{load_app, yield_now_task};
 
 /// Map from task id to arc pointer of task
@@ -36,6 +39,8 @@ pub static TID2TASK: Mutex<BTreeMap<u64, AxTaskRef>> = Mutex::new(BTreeMap::new(
 /// Map from process id to arc pointer of process
 pub static PID2PC: Mutex<BTreeMap<u64, Arc<Process>>> = Mutex::new(BTreeMap::new());
 const FD_LIMIT_ORIGIN: usize = 1025;
+/// 默认的可以排队的未决信号数的上限
+const SIGPENDING_LIMIT_ORIGIN: u64 = 1024;
 
 extern "C" {
     fn start_signal_trampoline();
@@ -52,6 +57,9 @@ pub struct Process {
     /// 栈大小
     pub stack_size: AtomicU64,
 
+    /// 可以排队的未决信号数的上限，即 RLIMIT_SIGPENDING
+    pub sigpending_limit: AtomicU64,
+
     /// 子进程
     pub children: Mutex<Vec<Arc<Process>>>,
 
@@ -80,6 +88,12 @@ pub struct Process {
     /// 第一维代表TaskID，第二维代表对应的信号处理模块
     pub signal_modules: Mutex<BTreeMap<u64, SignalModule>>,
 
+    /// 因信号而停止或继续执行的状态
+    pub job_control: Mutex<JobControl>,
+
+    /// 等待信号的线程，有信号加入该进程任一线程的未决信号集时被唤醒
+    pub signal_wait: WaitQueue,
+
     /// robust list存储模块
     /// 用来存储线程对共享变量的使用地址
     /// 具体使用交给了用户空间
@@ -90,6 +104,15 @@ pub struct Process {
 
     /// 该进程可执行文件所在的路径
     pub file_path: Mutex<String>,
+
+    /// 是否正在执行 execve，此时除执行 execve 的线程以外的线程都应当退出
+    pub exec_in_progress: AtomicBool,
+
+    /// 执行域，即 personality(2) 设置的值，在 fork 与 execve 时保留
+    pub personality: AtomicU32,
+
+    /// 间隔定时器与 POSIX 定时器，不被子进程继承
+    pub timers: ProcessTimers,
 }
 
 impl Process {
@@ -98,17 +121,28 @@ impl Process {
         self.pid
     }
 
-    /// TODO: 改变了新创建的任务栈大小，但未实现当前任务的栈扩展
-    /// set stack size
+    /// set the kernel stack size of newly created tasks
+    ///
+    /// 用户栈的大小限制见 [`MemorySet::stack_limit`]
     pub fn set_stack_limit(&self, limit: u64) {
         self.stack_size.store(limit, Ordering::Release)
     }
 
-    /// get stack size
+    /// get the kernel stack size of newly created tasks
     pub fn get_stack_limit(&self) -> u64 {
         self.stack_size.load(Ordering::Acquire)
     }
 
+    /// set the limit of queued signals
+    pub fn set_sigpending_limit(&self, limit: u64) {
+        self.sigpending_limit.store(limit, Ordering::Release)
+    }
+
+    /// get the limit of queued signals
+    pub fn get_sigpending_limit(&self) -> u64 {
+        self.sigpending_limit.load(Ordering::Acquire)
+    }
+
     /// get the parent process id
     pub fn get_parent(&self) -> u64 {
         self.parent.load(Ordering::Acquire)
@@ -180,6 +214,21 @@ impl Process {
         (*self.file_path.lock()).clone()
     }
 
+    /// get whether the process is executing execve
+    pub fn get_exec_in_progress(&self) -> bool {
+        self.exec_in_progress.load(Ordering::Acquire)
+    }
+
+    /// get the personality of the process
+    pub fn get_personality(&self) -> u32 {
+        self.personality.load(Ordering::Acquire)
+    }
+
+    /// set the personality of the process
+    pub fn set_personality(&self, personality: u32) {
+        self.personality.store(personality, Ordering::Release)
+    }
+
     /// 若进程运行完成，则获取其返回码
     /// 若正在运行（可能上锁或没有上锁），则返回None
     pub fn get_code_if_exit(&self) -> Option<i32> {
@@ -206,6 +255,7 @@ impl Process {
             pid,
             stack_size: AtomicU64::new(stack_size),
             parent: AtomicU64::new(parent),
+            sigpending_limit: AtomicU64::new(SIGPENDING_LIMIT_ORIGIN),
             children: Mutex::new(Vec::new()),
             tasks: Mutex::new(Vec::new()),
             is_zombie: AtomicBool::new(false),
@@ -216,9 +266,14 @@ impl Process {
             fd_manager: FdManager::new(fd_table, cwd, mask, FD_LIMIT_ORIGIN),
 
             signal_modules: Mutex::new(BTreeMap::new()),
+            job_control: Mutex::new(JobControl::default()),
+            signal_wait: WaitQueue::new(),
             robust_list: Mutex::new(BTreeMap::new()),
             blocked_by_vfork: Mutex::new(false),
             file_path: Mutex::new(String::new()),
+            exec_in_progress: AtomicBool::new(false),
+            personality: AtomicU32::new(0),
+            timers: ProcessTimers::new(pid),
         }
     }
     /// 根据给定参数创建一个新的进程，作为应用程序初始进程
@@ -251,12 +306,10 @@ impl Process {
         }
 
         let (entry, user_stack_bottom, heap_bottom) =
-            if let Ok(ans) = load_app(path.clone(), args, envs, &mut memory_set) {
-                ans
-            } else {
-                error!("Failed to load app {}", path);
-                return Err(AxError::NotFound);
-            };
+            load_app(path.clone(), args, envs, &mut memory_set).map_err(|e| {
+                error!("Failed to load app {}: {:?}", path, e);
+                e
+            })?;
 
         let new_fd_table: FdTable = Arc::new(Mutex::new(vec![
             // 标准输入
@@ -335,7 +388,35 @@ impl Process {
     /// 将当前进程替换为指定的用户程序
     /// args为传入的参数
     /// 任务的统计时间会被重置
+    ///
+    /// 程序不存在或格式错误时返回错误，原进程不受影响
     pub fn exec(&self, name: String, args: Vec<String>, envs: &Vec<String>) -> AxResult<()> {
+        let args = if args.is_empty() {
+            vec![name.clone()]
+        } else {
+            args
+        };
+        let image = ExecImage::open(name.clone(), args)?;
+
+        // 结束其他所有线程，当前线程成为主线程
+        self.kill_other_threads();
+        let current_task = current();
+        // 与其他进程共享地址空间（如 vfork）时，需要像线程退出一样通知 clear_child_tid
+        let clear_child_tid = current_task.get_clear_child_tid();
+        if clear_child_tid != 0 {
+            let shared = Arc::strong_count(&self.memory_set.lock()) > 1;
+            if shared && self.manual_alloc_for_lazy(clear_child_tid.into()).is_ok() {
+                unsafe {
+                    *(clear_child_tid as *mut i32) = 0;
+                }
+                let _ = futex_wake(clear_child_tid.into(), FutexFlags::empty(), 1);
+            }
+            current_task.set_clear_child_tid(0);
+        }
+        exit_robust_list(current_task.id().as_u64());
+        // 间隔定时器保留，POSIX 定时器删除
+        self.timers.clear_posix_timers();
+
         // 首先要处理原先进程的资源
         // 处理分配的页帧
         // 之后加入额外的东西之后再处理其他的包括信号等因素
@@ -364,43 +445,29 @@ impl Process {
 
         // 关闭 `CLOEXEC` 的文件
         self.fd_manager.close_on_exec();
-        let current_task = current();
-        // 再考虑手动结束其他所有的task
-        let mut tasks = self.tasks.lock();
-        for _ in 0..tasks.len() {
-            let task = tasks.pop().unwrap();
-            if task.id() == current_task.id() {
-                // FIXME: This will reset tls forcefully
-                #[cfg(target_arch = "x86_64")]
-                unsafe {
-                    task.set_tls_force(0);
-                    axhal::arch::write_thread_pointer(0);
-                }
-                tasks.push(task);
-            } else {
-                TID2TASK.lock().remove(&task.id().as_u64());
-                panic!("currently not support exec when has another task ");
-            }
+        // FIXME: This will reset tls forcefully
+        #[cfg(target_arch = "x86_64")]
+        unsafe {
+            current_task.set_tls_force(0);
+            axhal::arch::write_thread_pointer(0);
         }
-        // 当前任务被设置为主线程
-        current_task.set_leader(true);
-        // 重置统计时间
+        // 重置统计时间，但进程的 CPU 时间不变
+        let (utime_ns, stime_ns) = current_task.time_stat_output();
+        self.timers.account_task_time(utime_ns, stime_ns);
         current_task.reset_time_stat(current_time_nanos() as usize);
         current_task.set_name(name.split('/').last().unwrap());
-        assert!(tasks.len() == 1);
-        drop(tasks);
-        let args = if args.is_empty() {
-            vec![name.clone()]
-        } else {
-            args
-        };
-        let (entry, user_stack_bottom, heap_bottom) = if let Ok(ans) =
-            load_app(name.clone(), args, envs, &mut self.memory_set.lock().lock())
-        {
-            ans
-        } else {
-            error!("Failed to load app {}", name);
-            return Err(AxError::NotFound);
+        let loaded = image.load(
+            envs,
+            &mut self.memory_set.lock().lock(),
+            self.get_personality(),
+        );
+        let (entry, user_stack_bottom, heap_bottom) = match loaded {
+            Ok(ans) => ans,
+            Err(e) => {
+                // 原先的地址空间已被清空，无法返回
+                error!("Failed to load app {}: {:?}", name, e);
+                crate::exit_current_task(-1);
+            }
         };
         // 切换了地址空间， 需要切换token
         let page_table_token = if self.pid == KERNEL_PROCESS_ID {
@@ -426,12 +493,13 @@ impl Process {
         {
             use axhal::mem::virt_to_phys;
             use axhal::paging::MappingFlags;
-            // 重置信号处理模块
-            // 此时只会留下一个线程
-            self.signal_modules.lock().clear();
-            self.signal_modules
-                .lock()
-                .insert(current_task.id().as_u64(), SignalModule::init_signal(None));
+            // 重置信号处理模块，此时只会留下一个线程
+            let mut signal_modules = self.signal_modules.lock();
+            let mut signal_module = signal_modules.remove(&current_task.id().as_u64()).unwrap();
+            signal_module.reset_for_exec();
+            signal_modules.clear();
+            signal_modules.insert(current_task.id().as_u64(), signal_module);
+            drop(signal_modules);
 
             // 生成信号跳板
             let signal_trampoline_vaddr: VirtAddr = (axconfig::SIGNAL_TRAMPOLINE).into();
@@ -469,6 +537,71 @@ impl Process {
         Ok(())
     }
 
+    /// 结束当前线程以外的所有线程，并等待它们退出，当前线程成为主线程
+    ///
+    /// 当前线程不是主线程时，接管原主线程的 TID 以及其上发给进程的未决信号
+    fn kill_other_threads(&self) {
+        let current_task = current();
+        self.exec_in_progress.store(true, Ordering::Release);
+        if !current_task.is_leader() {
+            // 与发送信号相同，按 tasks、TID2TASK、signal_modules 的顺序加锁，
+            // 交接期间 send_signal_to_process 总能找到主线程
+            let tasks = self.tasks.lock();
+            if let Some(leader) = tasks.iter().find(|t| t.is_leader()).cloned() {
+                let mut tid2task = TID2TASK.lock();
+                let mut signal_modules = self.signal_modules.lock();
+                let leader_id = leader.id().as_u64();
+                let curr_id = current_task.id().as_u64();
+                // 两者交换 TID，原主线程以当前线程原来的 TID 退出
+                axtask::swap_task_ids(&leader, current_task.as_task_ref());
+                tid2task.insert(leader_id, current_task.as_task_ref().clone());
+                tid2task.insert(curr_id, leader.clone());
+                let mut leader_module = signal_modules.remove(&leader_id);
+                let mut module = signal_modules.remove(&curr_id);
+                if let (Some(leader_module), Some(module)) = (&mut leader_module, &mut module) {
+                    module.inherit_from_leader(leader_module);
+                }
+                if let Some(module) = module {
+                    signal_modules.insert(leader_id, module);
+                }
+                if let Some(leader_module) = leader_module {
+                    signal_modules.insert(curr_id, leader_module);
+                }
+                // 原主线程作为普通线程退出，不会结束整个进程
+                current_task.set_leader(true);
+                leader.set_leader(false);
+            } else {
+                current_task.set_leader(true);
+            }
+            drop(tasks);
+        }
+        let curr_id = current_task.id().as_u64();
+        // 其他线程在返回用户态前发现 exec_in_progress 后退出，并将自己从进程中移除
+        loop {
+            let others: Vec<AxTaskRef> = self
+                .tasks
+                .lock()
+                .iter()
+                .filter(|t| t.id().as_u64() != curr_id)
+                .cloned()
+                .collect();
+            if others.is_empty() {
+                break;
+            }
+            for task in others {
+                if task.state() != TaskState::Exited {
+                    let _ = send_signal_to_thread(
+                        task.id().as_u64() as isize,
+                        SignalNo::SIGKILL as isize,
+                        None,
+                    );
+                }
+            }
+            yield_now_task();
+        }
+        self.exec_in_progress.store(false, Ordering::Release);
+    }
+
     /// 实现简易的clone系统调用
     /// 返回值为新产生的任务的id
     pub fn clone_task(
@@ -684,6 +817,8 @@ impl Process {
             ));
             // 复制当前工作文件夹
             new_process.set_cwd(self.get_cwd());
+            new_process.set_sigpending_limit(self.get_sigpending_limit());
+            new_process.set_personality(self.get_personality());
             // 记录该进程，防止被回收
             PID2PC.lock().insert(process_id, Arc::clone(&new_process));
             new_process.tasks.lock().push(Arc::clone(&new_task));
@@ -738,6 +873,11 @@ impl Process {
         // 没有给定用户栈的时候，只能是共享了地址空间，且原先调用clone的有用户栈，此时已经在之前的trap clone时复制了
         if let Some(stack) = stack {
             trap_frame.set_user_sp(stack);
+            // 以 MAP_STACK 或 MAP_GROWSDOWN 映射、且栈顶位于其顶端的栈，与主线程的栈一样在缺页时向下扩展
+            new_memory_set
+                .lock()
+                .lock()
+                .set_stack_grows_down(VirtAddr::from(stack));
             // info!(
             //     "New user stack: sepc:{:X}, stack:{:X}",
             //     trap_frame.sepc, trap_frame.regs.sp
@@ -1,28 +1,44 @@
 //! 负责处理进程中与信号相关的内容
 extern crate alloc;
-use alloc::sync::Arc;
+use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
 use axerrno::{AxError, AxResult};
 use axhal::{
     arch::{read_trapframe_from_kstack, write_trapframe_to_kstack, TrapFrame},
     cpu::this_cpu_id,
+    time::current_time_nanos,
     KERNEL_PROCESS_ID,
 };
 use axlog::{info, warn};
 use axsignal::{
     action::{SigActionFlags, SignalDefault, SIG_DFL, SIG_IGN},
     info::SigInfo,
-    signal_no::SignalNo,
+    is_rt_signal,
+    signal_no::{SignalNo, MAX_SIG_NUM},
     ucontext::{SignalStack, SignalUserContext},
     SignalHandler, SignalSet,
 };
 use axsync::Mutex;
+use core::time::Duration;
+
+/// 进入信号处理函数时保存的上下文，在信号处理函数返回时恢复
+///
+/// 信号处理函数执行期间可以递送新的信号，因此这些上下文构成一个栈
+#[derive(Clone, Copy)]
+pub struct SignalFrame {
+    /// 进入信号处理函数前的trap上下文
+    pub trap_frame: TrapFrame,
+    /// 进入信号处理函数前的信号掩码
+    pub mask: usize,
+    /// 存放在用户栈上的ucontext的地址，仅在使用SA_SIGINFO时存在
+    pub ucontext: Option<usize>,
+}
 
 /// 信号处理模块，进程间不共享
 pub struct SignalModule {
-    /// 是否存在siginfo
-    pub sig_info: bool,
-    /// 保存的trap上下文
-    pub last_trap_frame_for_signal: Option<TrapFrame>,
+    /// 尚未返回的信号处理函数的上下文，栈顶为最内层的信号处理函数
+    pub signal_frames: Vec<SignalFrame>,
+    /// sigsuspend 等临时替换信号掩码时保存的原掩码，在递送信号后恢复
+    pub saved_mask: Option<usize>,
     /// 信号处理函数集
     pub signal_handler: Arc<Mutex<SignalHandler>>,
     /// 未决信号集
@@ -39,11 +55,9 @@ impl SignalModule {
         let signal_handler =
             signal_handler.unwrap_or_else(|| Arc::new(Mutex::new(SignalHandler::new())));
         let signal_set = SignalSet::new();
-        let last_trap_frame_for_signal = None;
-        let sig_info = false;
         Self {
-            sig_info,
-            last_trap_frame_for_signal,
+            signal_frames: Vec::new(),
+            saved_mask: None,
             signal_handler,
             signal_set,
             exit_signal: None,
@@ -75,18 +89,107 @@ impl SignalModule {
     pub fn get_exit_signal(&self) -> Option<SignalNo> {
         self.exit_signal
     }
+
+    /// execve 时重置信号模块
+    ///
+    /// 被捕获的信号恢复为默认处理，被忽略的信号仍被忽略，且处理函数集不再与其他线程共享。
+    /// 未决信号与信号掩码保持不变。
+    pub(crate) fn reset_for_exec(&mut self) {
+        let mut handler = self.signal_handler.lock().clone();
+        for sig_num in 1..=MAX_SIG_NUM {
+            if handler.get_action(sig_num).sa_handler != SIG_IGN {
+                handler.reset_action(sig_num);
+            }
+        }
+        self.signal_handler = Arc::new(Mutex::new(handler));
+        self.signal_frames.clear();
+        self.saved_mask = None;
+        self.alternate_stack = SignalStack::default();
+    }
+
+    /// 接管即将退出的主线程的未决信号与 exit signal
+    ///
+    /// 发给进程的信号都在主线程上排队，非主线程执行 execve 时不能丢弃它们
+    pub(crate) fn inherit_from_leader(&mut self, leader: &mut SignalModule) {
+        let pending = core::mem::take(&mut leader.signal_set);
+        for sig_num in 1..=MAX_SIG_NUM {
+            if pending.pending & (1 << (sig_num - 1)) == 0 {
+                continue;
+            }
+            match pending.info.get(&sig_num) {
+                Some(queue) => {
+                    for info in queue {
+                        self.signal_set.try_add_signal(sig_num, Some(*info));
+                    }
+                }
+                None => self.signal_set.try_add_signal(sig_num, None),
+            }
+        }
+        self.exit_signal = leader.exit_signal.take();
+    }
+
+    /// 当前是否正在备用信号栈上执行信号处理函数
+    fn on_alternate_stack(&self, sp: usize) -> bool {
+        let stack = &self.alternate_stack;
+        stack.flags != axsignal::ucontext::SS_DISABLE
+            && sp > stack.sp
+            && sp <= stack.sp + stack.size
+    }
+}
+
+/// 子进程因信号停止或继续执行时通知父进程的 si_code
+const CLD_STOPPED: i32 = 5;
+/// 同上，子进程继续执行
+const CLD_CONTINUED: i32 = 6;
+
+/// 进程的作业控制状态，即因信号而停止或继续执行
+#[derive(Default)]
+pub struct JobControl {
+    /// 进程是否处于停止状态
+    pub stopped: bool,
+    /// 使进程停止的信号，尚未被 wait4 的 WUNTRACED 报告
+    pub stop_report: Option<SignalNo>,
+    /// 进程已继续执行，尚未被 wait4 的 WCONTINUED 报告
+    pub continue_report: bool,
+}
+
+impl JobControl {
+    /// 取出尚未报告的停止或继续事件，返回 wait4 对应的状态值
+    pub fn take_wait_status(
+        &mut self,
+        report_stopped: bool,
+        report_continued: bool,
+    ) -> Option<i32> {
+        if report_stopped {
+            if let Some(signal) = self.stop_report.take() {
+                return Some(((signal as i32) << 8) | 0x7f);
+            }
+        }
+        if report_continued && self.continue_report {
+            self.continue_report = false;
+            return Some(0xffff);
+        }
+        None
+    }
+}
+
+fn is_stop_signal(signal: SignalNo) -> bool {
+    matches!(
+        signal,
+        SignalNo::SIGSTOP | SignalNo::SIGTSTP | SignalNo::SIGTTIN | SignalNo::SIGTTOU
+    )
 }
 
 const USER_SIGNAL_PROTECT: usize = 512;
 
 use crate::{
     current_process, current_task, exit_current_task,
-    process::{PID2PC, TID2TASK},
+    process::{Process, PID2PC, TID2TASK},
 };
 
-/// 将保存的trap上下文填入内核栈中
+/// 将保存的trap上下文填入内核栈中，并恢复进入信号处理函数前的信号掩码
 ///
-/// 若使用了SIG_INFO，此时会对原有trap上下文作一定修改。
+/// 若使用了SIG_INFO，则以用户栈上的ucontext中的pc与信号掩码为准，因为信号处理函数可能修改了它们。
 ///
 /// 若确实存在可以被恢复的trap上下文，则返回true
 #[no_mangle]
@@ -96,25 +199,16 @@ pub fn load_trap_for_signal() -> bool {
 
     let mut signal_modules = current_process.signal_modules.lock();
     let signal_module = signal_modules.get_mut(&current_task.id().as_u64()).unwrap();
-    if let Some(old_trap_frame) = signal_module.last_trap_frame_for_signal.take() {
-        unsafe {
-            // let now_trap_frame: *mut TrapFrame = current_task.get_first_trap_frame();
-            let mut now_trap_frame =
-                read_trapframe_from_kstack(current_task.get_kernel_stack_top().unwrap());
-            // 考虑当时调用信号处理函数时，sp对应的地址上的内容即是SignalUserContext
-            // 此时认为一定通过sig_return调用这个函数
-            // 所以此时sp的位置应该是SignalUserContext的位置
-            let sp = now_trap_frame.get_sp();
-            now_trap_frame = old_trap_frame;
-            if signal_module.sig_info {
-                let pc = (*(sp as *const SignalUserContext)).get_pc();
-                now_trap_frame.set_pc(pc);
-            }
-            write_trapframe_to_kstack(
-                current_task.get_kernel_stack_top().unwrap(),
-                &now_trap_frame,
-            );
+    if let Some(frame) = signal_module.signal_frames.pop() {
+        let mut trap_frame = frame.trap_frame;
+        let mut mask = frame.mask;
+        if let Some(ucontext) = frame.ucontext {
+            let ucontext = unsafe { &*(ucontext as *const SignalUserContext) };
+            trap_frame.set_pc(ucontext.get_pc());
+            mask = ucontext.get_mask();
         }
+        signal_module.signal_set.mask = mask;
+        write_trapframe_to_kstack(current_task.get_kernel_stack_top().unwrap(), &trap_frame);
         true
     } else {
         false
@@ -127,6 +221,9 @@ fn terminate_process(signal: SignalNo, info: Option<SigInfo>) {
     warn!("Terminate process: {}", current_task.get_process_id());
     if current_task.is_leader() {
         exit_current_task(signal as i32);
+    } else if current_process().get_exec_in_progress() {
+        // 被执行 execve 的线程结束，只退出当前线程
+        exit_current_task(0);
     } else {
         // 此时应当关闭当前进程
         // 选择向主线程发送信号内部来关闭
@@ -140,6 +237,68 @@ fn terminate_process(signal: SignalNo, info: Option<SigInfo>) {
     }
 }
 
+/// 以 SIGCHLD 通知父进程子进程停止或继续执行
+///
+/// 父进程的 SIGCHLD 处理设置了 SA_NOCLDSTOP 时不通知
+fn notify_parent(process: &Process, code: i32, signal: SignalNo) {
+    let parent = process.get_parent();
+    if parent == KERNEL_PROCESS_ID {
+        return;
+    }
+    let Some(parent_process) = PID2PC.lock().get(&parent).cloned() else {
+        return;
+    };
+    let no_cld_stop = parent_process
+        .signal_modules
+        .lock()
+        .values()
+        .next()
+        .is_some_and(|module| {
+            module
+                .signal_handler
+                .lock()
+                .get_action(SignalNo::SIGCHLD as usize)
+                .sa_flags
+                .contains(SigActionFlags::SA_NOCLDSTOP)
+        });
+    if no_cld_stop {
+        return;
+    }
+    let info = SigInfo {
+        si_signo: SignalNo::SIGCHLD as i32,
+        si_code: code,
+        pid: process.pid() as i32,
+        // si_status 与 si_value 位于同一位置
+        si_value: signal as usize,
+        ..Default::default()
+    };
+    let _ = send_signal_to_process(parent as isize, SignalNo::SIGCHLD as isize, Some(info));
+}
+
+/// 处理 Stop 类型的信号：停止整个进程，并等待其被 SIGCONT 或 SIGKILL 唤醒
+fn stop_process(process: &Process, signal: SignalNo) {
+    let mut job_control = process.job_control.lock();
+    let newly_stopped = !job_control.stopped;
+    if newly_stopped {
+        job_control.stopped = true;
+        job_control.stop_report = Some(signal);
+        job_control.continue_report = false;
+    }
+    drop(job_control);
+    if newly_stopped {
+        info!("process {} stopped by {:?}", process.pid(), signal);
+        notify_parent(process, CLD_STOPPED, signal);
+    }
+    wait_while_stopped(process);
+}
+
+/// 进程处于停止状态时，其所有线程都在此睡眠，直到 SIGCONT 或 SIGKILL 使其继续执行
+fn wait_while_stopped(process: &Process) {
+    process
+        .signal_wait
+        .wait_until(|| !process.job_control.lock().stopped);
+}
+
 /// 处理当前进程的信号
 ///
 /// 若返回值为真，代表需要进入处理信号，因此需要执行trap的返回
@@ -147,16 +306,20 @@ pub fn handle_signals() {
     let process = current_process();
     let current_task = current_task();
     if let Some(signal_no) = current_task.check_pending_signal() {
-        send_signal_to_thread(current_task.id().as_u64() as isize, signal_no as isize)
-            .unwrap_or_else(|err| {
-                warn!("send signal failed: {:?}", err);
-            });
+        send_signal_to_thread(
+            current_task.id().as_u64() as isize,
+            signal_no as isize,
+            None,
+        )
+        .unwrap_or_else(|err| {
+            warn!("send signal failed: {:?}", err);
+        });
     }
-    if process.get_zombie() {
+    if process.get_zombie() || process.get_exec_in_progress() {
         if current_task.is_leader() {
             return;
         }
-        // 进程退出了，在测试环境下非主线程应该立即退出
+        // 进程退出了或正在执行 execve，非主线程应该立即退出
         exit_current_task(0);
     }
 
@@ -164,13 +327,22 @@ pub fn handle_signals() {
         // 内核进程不处理信号
         return;
     }
+    process.timers.check_cpu_timers(&process);
+    // 进程被停止时，其余线程也在返回用户态之前停下
+    wait_while_stopped(&process);
+
     let mut signal_modules = process.signal_modules.lock();
 
     let signal_module = signal_modules.get_mut(&current_task.id().as_u64()).unwrap();
+    // 信号处理完成后需要恢复的掩码
+    let saved_mask = signal_module.saved_mask.take();
     let signal_set = &mut signal_module.signal_set;
-    let sig_num = if let Some(sig_num) = signal_set.get_one_signal() {
-        sig_num
+    let (sig_num, sig_info) = if let Some(signal) = signal_set.take_signal() {
+        signal
     } else {
+        if let Some(mask) = saved_mask {
+            signal_set.mask = mask;
+        }
         return;
     };
     info!(
@@ -180,46 +352,30 @@ pub fn handle_signals() {
         sig_num
     );
     let signal = SignalNo::from(sig_num);
-    let mask = signal_set.mask;
-    // 存在未被处理的信号
-    if signal_module.last_trap_frame_for_signal.is_some() {
-        // 之前的trap frame还未被处理
-        // 说明之前的信号处理函数还未返回，即出现了信号嵌套。
-        if signal == SignalNo::SIGSEGV || signal == SignalNo::SIGBUS {
-            // 在处理信号的过程中又触发 SIGSEGV 或 SIGBUS，此时会导致死循环，所以直接结束当前进程
-            drop(signal_modules);
-            exit_current_task(-1);
-        }
-        return;
-    }
-    // 之前的trap frame已经被处理
-    // 说明之前的信号处理函数已经返回，即没有信号嵌套。
-    // 此时可以将当前的trap frame保存起来
-    signal_module.last_trap_frame_for_signal = Some(read_trapframe_from_kstack(
-        current_task.get_kernel_stack_top().unwrap(),
-    ));
-    // current_task.set_siginfo(false);
-    signal_module.sig_info = false;
+    // 信号处理函数返回时恢复的掩码
+    let mask = saved_mask.unwrap_or(signal_set.mask);
     // 调取处理函数
-    let signal_handler = signal_module.signal_handler.lock();
-    let action = signal_handler.get_action(sig_num);
-    if action.sa_handler == SIG_DFL {
+    let mut signal_handler = signal_module.signal_handler.lock();
+    let action = *signal_handler.get_action(sig_num);
+    if action.sa_handler == SIG_DFL || action.sa_handler == SIG_IGN {
         drop(signal_handler);
+        signal_module.signal_set.mask = mask;
         drop(signal_modules);
+        if action.sa_handler == SIG_IGN {
+            // 忽略处理
+            return;
+        }
         // 未显式指定处理函数，使用默认处理函数
         match SignalDefault::get_action(signal) {
-            SignalDefault::Ignore => {
-                // 忽略，此时相当于已经完成了处理，所以要把trap上下文清空
-                load_trap_for_signal();
-            }
+            SignalDefault::Ignore => {}
             SignalDefault::Terminate => {
                 terminate_process(signal, None);
             }
             SignalDefault::Stop => {
-                unimplemented!();
+                stop_process(&process, signal);
             }
             SignalDefault::Cont => {
-                unimplemented!();
+                // 在发送 SIGCONT 时已经恢复了进程的执行
             }
             SignalDefault::Core => {
                 terminate_process(signal, None);
@@ -227,10 +383,10 @@ pub fn handle_signals() {
         }
         return;
     }
-    if action.sa_handler == SIG_IGN {
-        // 忽略处理
-        return;
+    if action.sa_flags.contains(SigActionFlags::SA_RESETHAND) {
+        signal_handler.reset_action(sig_num);
     }
+    drop(signal_handler);
     // 此时需要调用信号处理函数，注意调用的方式是：
     // 通过修改trap上下文的pc指针，使得trap返回之后，直接到达信号处理函数
     // 因此需要处理一系列的trap上下文，使得正确传参与返回。
@@ -238,18 +394,21 @@ pub fn handle_signals() {
     // 1. 传参
     // 2. 返回值ra地址的设定，与是否设置了SA_RESTORER有关
 
-    // 读取当前的trap上下文
-    let mut trap_frame = read_trapframe_from_kstack(current_task.get_kernel_stack_top().unwrap());
+    // 读取当前的trap上下文，若处于另一个信号处理函数中，则其为该信号处理函数的上下文
+    let old_trap_frame = read_trapframe_from_kstack(current_task.get_kernel_stack_top().unwrap());
+    let mut trap_frame = old_trap_frame;
 
-    // // 新的trap上下文的sp指针位置，由于SIGINFO会存放内容，所以需要开个保护区域
+    // 新的trap上下文的sp指针位置，由于SIGINFO会存放内容，所以需要开个保护区域
+    // 嵌套的信号处理函数已经在备用信号栈上时，继续使用当前的栈
     let mut sp = if action.sa_flags.contains(SigActionFlags::SA_ONSTACK)
         && signal_module.alternate_stack.flags != axsignal::ucontext::SS_DISABLE
+        && !signal_module.on_alternate_stack(trap_frame.get_sp())
     {
         axlog::debug!("Use alternate stack");
         // Use alternate stack
         (signal_module.alternate_stack.sp + signal_module.alternate_stack.size - 1) & !0xf
     } else {
-        trap_frame.get_sp() - USER_SIGNAL_PROTECT
+        (trap_frame.get_sp() - USER_SIGNAL_PROTECT) & !0xf
     };
 
     info!("use stack: {:#x}", sp);
@@ -271,10 +430,9 @@ pub fn handle_signals() {
     trap_frame.set_pc(action.sa_handler);
     // 传参
     trap_frame.set_arg0(sig_num);
+    let mut ucontext = None;
     // 若带有SIG_INFO参数，则函数原型为fn(sig: SignalNo, info: &SigInfo, ucontext: &mut UContext)
     if action.sa_flags.contains(SigActionFlags::SA_SIGINFO) {
-        // current_task.set_siginfo(true);
-        signal_module.sig_info = true;
         let sp_base = (((sp - core::mem::size_of::<SigInfo>()) & !0xf)
             - core::mem::size_of::<SignalUserContext>())
             & !0xf;
@@ -286,28 +444,19 @@ pub fn handle_signals() {
 
         // 注意16字节对齐
         sp = (sp - core::mem::size_of::<SigInfo>()) & !0xf;
-        let info = if let Some(info) = signal_set.info.get(&(sig_num - 1)) {
-            info!("test SigInfo: {:?}", info.0.si_val_int);
-            info.0
-        } else {
-            SigInfo {
-                si_signo: sig_num as i32,
-                ..Default::default()
-            }
-        };
         unsafe {
-            *(sp as *mut SigInfo) = info;
+            *(sp as *mut SigInfo) = sig_info;
         }
         trap_frame.set_arg1(sp);
 
         // 接下来存储ucontext
         sp = (sp - core::mem::size_of::<SignalUserContext>()) & !0xf;
 
-        let ucontext = SignalUserContext::init(old_pc, mask);
         unsafe {
-            *(sp as *mut SignalUserContext) = ucontext;
+            *(sp as *mut SignalUserContext) = SignalUserContext::init(old_pc, mask);
         }
         trap_frame.set_arg2(sp);
+        ucontext = Some(sp);
     }
 
     #[cfg(target_arch = "x86_64")]
@@ -318,9 +467,18 @@ pub fn handle_signals() {
     }
 
     trap_frame.set_user_sp(sp);
+    signal_module.signal_frames.push(SignalFrame {
+        trap_frame: old_trap_frame,
+        mask,
+        ucontext,
+    });
+    // 信号处理函数执行期间额外屏蔽 sa_mask，未设置SA_NODEFER时还屏蔽当前信号
+    signal_module.signal_set.mask = mask | action.sa_mask;
+    if !action.sa_flags.contains(SigActionFlags::SA_NODEFER) {
+        signal_module.signal_set.mask |= 1 << (sig_num - 1);
+    }
     // 将修改后的trap上下文写回内核栈
     write_trapframe_to_kstack(current_task.get_kernel_stack_top().unwrap(), &trap_frame);
-    drop(signal_handler);
     drop(signal_modules);
 }
 
@@ -340,40 +498,122 @@ pub fn signal_return() -> isize {
     }
 }
 
+/// 发送信号时立即生效的作业控制
+///
+/// SIGCONT 使停止的进程继续执行并丢弃未决的停止信号，停止信号则丢弃未决的 SIGCONT，
+/// SIGKILL 也会唤醒停止的进程以便其退出。
+///
+/// 若进程因此继续执行，则返回true，此时需要通知父进程
+fn prepare_signal(process: &Process, signal: SignalNo) -> bool {
+    let discarded: &[SignalNo] = if signal == SignalNo::SIGCONT {
+        &[
+            SignalNo::SIGSTOP,
+            SignalNo::SIGTSTP,
+            SignalNo::SIGTTIN,
+            SignalNo::SIGTTOU,
+        ]
+    } else if is_stop_signal(signal) {
+        &[SignalNo::SIGCONT]
+    } else {
+        &[]
+    };
+    if !discarded.is_empty() {
+        for module in process.signal_modules.lock().values_mut() {
+            for sig in discarded {
+                module.signal_set.remove_signal(*sig as usize);
+            }
+        }
+    }
+    if signal != SignalNo::SIGCONT && signal != SignalNo::SIGKILL {
+        return false;
+    }
+    let mut job_control = process.job_control.lock();
+    if !job_control.stopped {
+        return false;
+    }
+    job_control.stopped = false;
+    job_control.stop_report = None;
+    if signal == SignalNo::SIGKILL {
+        drop(job_control);
+        process.signal_wait.notify_all();
+        return false;
+    }
+    job_control.continue_report = true;
+    drop(job_control);
+    process.signal_wait.notify_all();
+    info!("process {} continued", process.pid());
+    true
+}
+
+/// 将信号加入线程 `tid` 的未决信号集
+///
+/// 由 `sigqueue` 等发送的带附加信息的信号（`si_code` 为负）受 RLIMIT_SIGPENDING 的限制：
+/// 排队的信号数达到上限时，实时信号返回 `WouldBlock`，标准信号则不保存附加信息。
+fn add_signal(
+    process: &Process,
+    signal_modules: &mut BTreeMap<u64, SignalModule>,
+    tid: u64,
+    signum: usize,
+    info: Option<SigInfo>,
+) -> AxResult<()> {
+    let queued: usize = signal_modules
+        .values()
+        .map(|module| module.signal_set.queued_count())
+        .sum();
+    let signal_set = &mut signal_modules
+        .get_mut(&tid)
+        .ok_or(AxError::NotFound)?
+        .signal_set;
+    let limited = info.is_some_and(|info| info.si_code < 0);
+    if limited && queued as u64 >= process.get_sigpending_limit() {
+        if is_rt_signal(signum) {
+            return Err(AxError::WouldBlock);
+        }
+        signal_set.add_signal_unqueued(signum);
+    } else {
+        signal_set.try_add_signal(signum, info);
+    }
+    process.signal_wait.notify_all();
+    Ok(())
+}
+
 /// 发送信号到指定的进程
 ///
 /// 默认发送到该进程下的主线程
 pub fn send_signal_to_process(pid: isize, signum: isize, info: Option<SigInfo>) -> AxResult<()> {
-    let mut pid2pc = PID2PC.lock();
-    if !pid2pc.contains_key(&(pid as u64)) {
-        return Err(axerrno::AxError::NotFound);
-    }
-    let process = pid2pc.get_mut(&(pid as u64)).unwrap();
-    let mut now_id: Option<u64> = None;
-    for task in process.tasks.lock().iter_mut() {
-        if task.is_leader() {
-            now_id = Some(task.id().as_u64());
-            break;
-        }
+    let process = PID2PC
+        .lock()
+        .get(&(pid as u64))
+        .cloned()
+        .ok_or(AxError::NotFound)?;
+    // 加入信号前一直持有 tasks 的锁，execve 交接主线程时也持有该锁
+    let tasks = process.tasks.lock();
+    let Some(main_task) = tasks.iter().find(|task| task.is_leader()).cloned() else {
+        return Ok(());
+    };
+    let continued = prepare_signal(&process, SignalNo::from(signum as usize));
+    let mut signal_modules = process.signal_modules.lock();
+    add_signal(
+        &process,
+        &mut signal_modules,
+        main_task.id().as_u64(),
+        signum as usize,
+        info,
+    )?;
+    drop(signal_modules);
+    drop(tasks);
+    // 如果这个时候对应的线程是处于休眠状态的，则唤醒之，进入信号处理阶段
+    if main_task.is_blocked() {
+        axtask::wakeup_task(main_task);
     }
-    if now_id.is_some() {
-        let mut signal_modules = process.signal_modules.lock();
-        let signal_module = signal_modules.get_mut(&now_id.unwrap()).unwrap();
-        signal_module
-            .signal_set
-            .try_add_signal(signum as usize, info);
-        let tid2task = TID2TASK.lock();
-        let main_task = Arc::clone(tid2task.get(&now_id.unwrap()).unwrap());
-        // 如果这个时候对应的线程是处于休眠状态的，则唤醒之，进入信号处理阶段
-        if main_task.is_blocked() {
-            axtask::wakeup_task(main_task);
-        }
+    if continued {
+        notify_parent(&process, CLD_CONTINUED, SignalNo::SIGCONT);
     }
     Ok(())
 }
 
 /// 发送信号到指定的线程
-pub fn send_signal_to_thread(tid: isize, signum: isize) -> AxResult<()> {
+pub fn send_signal_to_thread(tid: isize, signum: isize, info: Option<SigInfo>) -> AxResult<()> {
     let tid2task = TID2TASK.lock();
     let task = if let Some(task) = tid2task.get(&(tid as u64)) {
         Arc::clone(task)
@@ -389,22 +629,102 @@ pub fn send_signal_to_thread(tid: isize, signum: isize) -> AxResult<()> {
         return Err(AxError::NotFound);
     };
     drop(pid2pc);
+    let continued = prepare_signal(&process, SignalNo::from(signum as usize));
     let mut signal_modules = process.signal_modules.lock();
-    if !signal_modules.contains_key(&(tid as u64)) {
-        return Err(axerrno::AxError::NotFound);
-    }
-    let signal_module = signal_modules.get_mut(&(tid as u64)).unwrap();
-    signal_module
-        .signal_set
-        .try_add_signal(signum as usize, None);
+    add_signal(
+        &process,
+        &mut signal_modules,
+        tid as u64,
+        signum as usize,
+        info,
+    )?;
+    drop(signal_modules);
     // 如果这个时候对应的线程是处于休眠状态的，则唤醒之，进入信号处理阶段
     if task.is_blocked() {
         axtask::wakeup_task(task);
     }
+    if continued {
+        notify_parent(&process, CLD_CONTINUED, SignalNo::SIGCONT);
+    }
     Ok(())
 }
 
+/// 向当前线程发送由异常产生的信号，如 SIGSEGV
+///
+/// 若该信号被屏蔽或忽略，则无法处理异常，此时解除屏蔽并恢复默认处理，使进程被终止
+pub fn send_fault_signal(signal: SignalNo) {
+    let process = current_process();
+    let tid = current_task().id().as_u64();
+    let sig_num = signal as usize;
+    if let Some(module) = process.signal_modules.lock().get_mut(&tid) {
+        let mut signal_handler = module.signal_handler.lock();
+        let blocked = module.signal_set.mask & (1 << (sig_num - 1)) != 0;
+        if blocked || signal_handler.get_action(sig_num).sa_handler == SIG_IGN {
+            module.signal_set.mask &= !(1 << (sig_num - 1));
+            signal_handler.reset_action(sig_num);
+        }
+    }
+    let _ = send_signal_to_thread(tid as isize, sig_num as isize, None);
+}
+
 /// Whether the current process has signals pending
 pub fn current_have_signals() -> bool {
     current_process().have_signals().is_some()
 }
+
+/// 当前线程可以接收的未决信号所在的线程：当前线程自身，以及排队发给进程的信号的主线程
+fn signal_receivers(process: &Process) -> [u64; 2] {
+    let tid = current_task().id().as_u64();
+    let leader = process
+        .tasks
+        .lock()
+        .iter()
+        .find(|task| task.is_leader())
+        .map_or(tid, |task| task.id().as_u64());
+    [tid, leader]
+}
+
+/// 查询当前线程或当前进程是否有属于 `set` 的未决信号，不论其是否被屏蔽
+pub fn current_pending_in(set: usize) -> Option<usize> {
+    let process = current_process();
+    let receivers = signal_receivers(&process);
+    let signal_modules = process.signal_modules.lock();
+    receivers.iter().find_map(|tid| {
+        signal_modules
+            .get(tid)
+            .and_then(|module| module.signal_set.find_signal_in(set))
+    })
+}
+
+/// 阻塞当前线程，直到 `condition` 成立，或到达以纳秒计的截止时间 `deadline`
+///
+/// 当前进程的任一线程收到信号时都会重新检查 `condition`，返回时 `condition` 是否成立
+pub fn wait_for_signal(condition: impl Fn() -> bool, deadline: Option<u64>) -> bool {
+    let process = current_process();
+    match deadline {
+        None => process.signal_wait.wait_until(&condition),
+        Some(deadline) => {
+            let now = current_time_nanos();
+            if now < deadline {
+                process
+                    .signal_wait
+                    .wait_timeout_until(Duration::from_nanos(deadline - now), &condition);
+            }
+        }
+    }
+    condition()
+}
+
+/// 从当前线程或当前进程的未决信号中取出一个属于 `set` 的信号，不论其是否被屏蔽
+///
+/// 先取发给当前线程的信号，再取发给进程的信号，用于 sigtimedwait 与 signalfd
+pub fn take_current_signal_in(set: usize) -> Option<(usize, SigInfo)> {
+    let process = current_process();
+    let receivers = signal_receivers(&process);
+    let mut signal_modules = process.signal_modules.lock();
+    receivers.iter().find_map(|tid| {
+        signal_modules
+            .get_mut(tid)
+            .and_then(|module| module.signal_set.take_signal_in(set))
+    })
+}
@@ -0,0 +1,403 @@
+//! 进程的间隔定时器（`setitimer`）与 POSIX 定时器（`timer_create`）
+//!
+//! 以真实时间计时的定时器由 axtask 的定时器列表驱动：到期时，时钟中断把定时器交给
+//! `timer` 内核任务，由它在任务上下文中发送信号并重新设置定时器。
+//!
+//! 以进程 CPU 时间计时的定时器没有办法预知到期的时刻，因此在线程返回用户态、处理信号之前
+//! 检查，精度为一个时钟中断。
+extern crate alloc;
+use alloc::{
+    collections::{BTreeMap, VecDeque},
+    string::ToString,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use axerrno::{AxError, AxResult};
+use axhal::time::current_time_nanos;
+use axsignal::info::{SigInfo, SI_TIMER};
+use axsync::Mutex;
+use axtask::WaitQueue;
+use core::{
+    sync::atomic::{AtomicBool, AtomicI32, AtomicU64, Ordering},
+    time::Duration,
+};
+use spinlock::SpinNoIrq;
+
+use crate::process::Process;
+use crate::signal::{send_signal_to_process, send_signal_to_thread};
+
+/// 以真实时间计时，到期时发送 SIGALRM
+pub const ITIMER_REAL: usize = 0;
+/// 以进程在用户态运行的时间计时，到期时发送 SIGVTALRM
+pub const ITIMER_VIRTUAL: usize = 1;
+/// 以进程运行的时间计时，到期时发送 SIGPROF
+pub const ITIMER_PROF: usize = 2;
+
+/// 一个进程最多可以创建的 POSIX 定时器数
+const MAX_POSIX_TIMERS: usize = 1024;
+
+/// 定时器所用的时钟
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimerClock {
+    /// 真实时间，对应 `CLOCK_REALTIME`、`CLOCK_MONOTONIC` 等
+    Real,
+    /// 进程在用户态运行的时间
+    Virtual,
+    /// 进程运行的时间，包括用户态与内核态，对应 `CLOCK_PROCESS_CPUTIME_ID`
+    Prof,
+}
+
+/// 定时器到期时的通知方式，即 `struct sigevent`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimerNotify {
+    /// 不通知，即 `SIGEV_NONE`
+    None,
+    /// 向进程发送信号，即 `SIGEV_SIGNAL`
+    Process {
+        /// 信号
+        signo: usize,
+        /// 随信号发送的值
+        value: usize,
+    },
+    /// 向进程中的指定线程发送信号，即 `SIGEV_THREAD_ID`
+    Thread {
+        /// 线程号
+        tid: u64,
+        /// 信号
+        signo: usize,
+        /// 随信号发送的值
+        value: usize,
+    },
+}
+
+#[derive(Default)]
+struct TimerState {
+    /// 下一次到期的时刻，按定时器的时钟计，单位为纳秒；为 0 时定时器未启用
+    deadline: u64,
+    /// 到期后重新设置的间隔，为 0 时只到期一次
+    interval: u64,
+    /// 最近一次发送信号时，错过的到期次数
+    overrun: u64,
+    /// 真实时间定时器在 axtask 中注册的回调
+    callback: Option<usize>,
+    /// 每次设置定时器时加一，用于忽略已经过时的回调
+    generation: u64,
+}
+
+/// 一个间隔定时器或 POSIX 定时器
+pub struct IntervalTimer {
+    /// 所属的进程
+    pid: u64,
+    /// `timer_create` 返回的定时器 ID，对间隔定时器没有意义
+    id: i32,
+    clock: TimerClock,
+    notify: TimerNotify,
+    state: SpinNoIrq<TimerState>,
+}
+
+impl IntervalTimer {
+    fn new(pid: u64, id: i32, clock: TimerClock, notify: TimerNotify) -> Arc<Self> {
+        Arc::new(Self {
+            pid,
+            id,
+            clock,
+            notify,
+            state: SpinNoIrq::new(TimerState::default()),
+        })
+    }
+
+    /// 定时器所用的时钟
+    pub fn clock(&self) -> TimerClock {
+        self.clock
+    }
+
+    /// 返回定时器的间隔与距离下一次到期的时间，单位为纳秒
+    ///
+    /// `now` 为定时器的时钟当前的读数，见 [`ProcessTimers::clock_now`]
+    pub fn get(&self, now: u64) -> (u64, u64) {
+        let state = self.state.lock();
+        let remaining = match state.deadline {
+            0 => 0,
+            // 已经到期但还未处理时，至少返回 1 纳秒，表示定时器仍在运行
+            deadline => deadline.saturating_sub(now).max(1),
+        };
+        (state.interval, remaining)
+    }
+
+    /// 设置定时器，`value` 为 0 时停止定时器；返回原来的间隔与剩余时间
+    ///
+    /// `value` 为距离 `now` 的相对时间，`now` 为定时器的时钟当前的读数
+    pub fn set(self: &Arc<Self>, value: u64, interval: u64, now: u64) -> (u64, u64) {
+        let old = self.get(now);
+        let mut state = self.state.lock();
+        if let Some(callback) = state.callback.take() {
+            axtask::cancel_timer_callback(callback);
+        }
+        state.generation += 1;
+        state.overrun = 0;
+        state.interval = interval;
+        state.deadline = match value {
+            0 => 0,
+            value => now.saturating_add(value),
+        };
+        if state.deadline != 0 && self.clock == TimerClock::Real {
+            self.arm(&mut state);
+        }
+        old
+    }
+
+    /// 最近一次发送信号时，错过的到期次数
+    pub fn overrun(&self) -> u64 {
+        self.state.lock().overrun
+    }
+
+    /// 在 axtask 的定时器列表中注册真实时间定时器的下一次到期
+    fn arm(self: &Arc<Self>, state: &mut TimerState) {
+        let timer = Arc::downgrade(self);
+        let generation = state.generation;
+        state.callback = Some(axtask::set_timer_callback(
+            Duration::from_nanos(state.deadline),
+            move |_| {
+                // 在时钟中断中不能发送信号，交给 timer 任务处理
+                FIRED.lock().push_back((timer, generation));
+                TIMER_WAIT.notify_one();
+            },
+        ));
+    }
+
+    /// 若定时器在 `now` 时已经到期，则更新下一次到期的时刻，并返回错过的到期次数
+    fn expire(self: &Arc<Self>, now: u64) -> Option<u64> {
+        let mut state = self.state.lock();
+        if state.deadline == 0 || now < state.deadline {
+            return None;
+        }
+        state.callback = None;
+        let missed = if state.interval == 0 {
+            state.deadline = 0;
+            0
+        } else {
+            let missed = (now - state.deadline) / state.interval;
+            state.deadline += (missed + 1) * state.interval;
+            missed
+        };
+        state.overrun = missed;
+        if state.deadline != 0 && self.clock == TimerClock::Real {
+            self.arm(&mut state);
+        }
+        Some(missed)
+    }
+
+    /// 处理 timer 任务收到的到期事件
+    fn fire(self: &Arc<Self>, generation: u64) {
+        if self.state.lock().generation != generation {
+            // 定时器在到期之后又被重新设置了
+            return;
+        }
+        if let Some(missed) = self.expire(current_time_nanos()) {
+            self.notify(missed);
+        }
+    }
+
+    /// 按通知方式发送信号
+    fn notify(&self, overrun: u64) {
+        let info = |signo: usize, value: usize| SigInfo {
+            si_signo: signo as i32,
+            si_code: SI_TIMER,
+            pid: self.id,
+            uid: overrun.min(i32::MAX as u64) as u32,
+            si_value: value,
+            ..Default::default()
+        };
+        let result = match self.notify {
+            TimerNotify::None => Ok(()),
+            TimerNotify::Process { signo, value } => {
+                send_signal_to_process(self.pid as isize, signo as isize, Some(info(signo, value)))
+            }
+            TimerNotify::Thread { tid, signo, value } => {
+                send_signal_to_thread(tid as isize, signo as isize, Some(info(signo, value)))
+            }
+        };
+        if let Err(err) = result {
+            axlog::debug!("timer of process {} failed to notify: {:?}", self.pid, err);
+        }
+    }
+}
+
+/// 已经到期、等待 timer 任务处理的真实时间定时器
+static FIRED: SpinNoIrq<VecDeque<(Weak<IntervalTimer>, u64)>> = SpinNoIrq::new(VecDeque::new());
+static TIMER_WAIT: WaitQueue = WaitQueue::new();
+static TIMER_TASK_STARTED: AtomicBool = AtomicBool::new(false);
+
+/// timer 任务：在任务上下文中处理到期的真实时间定时器
+fn timer_task() {
+    loop {
+        TIMER_WAIT.wait_until(|| !FIRED.lock().is_empty());
+        loop {
+            let fired = FIRED.lock().pop_front();
+            let Some((timer, generation)) = fired else {
+                break;
+            };
+            if let Some(timer) = timer.upgrade() {
+                timer.fire(generation);
+            }
+        }
+    }
+}
+
+fn start_timer_task() {
+    if !TIMER_TASK_STARTED.swap(true, Ordering::AcqRel) {
+        axtask::spawn_raw(timer_task, "timer".to_string(), axconfig::TASK_STACK_SIZE);
+    }
+}
+
+/// 进程的各个定时器
+pub struct ProcessTimers {
+    pid: u64,
+    /// `setitimer` 的三个间隔定时器，依次为 `ITIMER_REAL`、`ITIMER_VIRTUAL`、`ITIMER_PROF`
+    itimers: [Arc<IntervalTimer>; 3],
+    /// `timer_create` 创建的定时器
+    posix_timers: Mutex<BTreeMap<i32, Arc<IntervalTimer>>>,
+    next_id: AtomicI32,
+    /// 已经退出的线程在用户态与内核态运行的时间，单位为纳秒
+    exited_utime: AtomicU64,
+    exited_stime: AtomicU64,
+}
+
+impl ProcessTimers {
+    /// 创建进程 `pid` 的定时器，它们都未启用
+    pub fn new(pid: u64) -> Self {
+        use axsignal::signal_no::SignalNo;
+        let itimer = |clock, signo: SignalNo| {
+            let notify = TimerNotify::Process {
+                signo: signo as usize,
+                value: 0,
+            };
+            IntervalTimer::new(pid, 0, clock, notify)
+        };
+        Self {
+            pid,
+            itimers: [
+                itimer(TimerClock::Real, SignalNo::SIGALRM),
+                itimer(TimerClock::Virtual, SignalNo::SIGVTALRM),
+                itimer(TimerClock::Prof, SignalNo::SIGPROF),
+            ],
+            posix_timers: Mutex::new(BTreeMap::new()),
+            next_id: AtomicI32::new(0),
+            exited_utime: AtomicU64::new(0),
+            exited_stime: AtomicU64::new(0),
+        }
+    }
+
+    /// `setitimer` 的间隔定时器，`which` 不合法时返回 `None`
+    pub fn itimer(&self, which: usize) -> Option<&Arc<IntervalTimer>> {
+        if which == ITIMER_REAL {
+            start_timer_task();
+        }
+        self.itimers.get(which)
+    }
+
+    /// 创建一个 POSIX 定时器，返回其 ID；`notify` 由定时器 ID 得到通知方式
+    pub fn create<F>(&self, clock: TimerClock, notify: F) -> AxResult<i32>
+    where
+        F: FnOnce(i32) -> TimerNotify,
+    {
+        let mut timers = self.posix_timers.lock();
+        if timers.len() >= MAX_POSIX_TIMERS {
+            return Err(AxError::WouldBlock);
+        }
+        let id = loop {
+            let id = self.next_id.fetch_add(1, Ordering::Relaxed) & i32::MAX;
+            if !timers.contains_key(&id) {
+                break id;
+            }
+        };
+        if clock == TimerClock::Real {
+            start_timer_task();
+        }
+        timers.insert(id, IntervalTimer::new(self.pid, id, clock, notify(id)));
+        Ok(id)
+    }
+
+    /// 根据 ID 找到 POSIX 定时器
+    pub fn get(&self, id: i32) -> Option<Arc<IntervalTimer>> {
+        self.posix_timers.lock().get(&id).cloned()
+    }
+
+    /// 停止并删除一个 POSIX 定时器
+    pub fn delete(&self, id: i32) -> AxResult {
+        let timer = self
+            .posix_timers
+            .lock()
+            .remove(&id)
+            .ok_or(AxError::InvalidInput)?;
+        timer.set(0, 0, 0);
+        Ok(())
+    }
+
+    /// 删除所有 POSIX 定时器，用于 `execve`；间隔定时器在 `execve` 之后保留
+    pub fn clear_posix_timers(&self) {
+        let timers = core::mem::take(&mut *self.posix_timers.lock());
+        for timer in timers.values() {
+            timer.set(0, 0, 0);
+        }
+    }
+
+    /// 停止所有定时器，用于进程退出
+    pub fn clear(&self) {
+        for timer in &self.itimers {
+            timer.set(0, 0, 0);
+        }
+        self.clear_posix_timers();
+    }
+
+    /// 记录一个即将退出，或即将重置运行时间的线程的运行时间，使进程的 CPU 时间不会减少
+    pub fn account_task_time(&self, utime_ns: usize, stime_ns: usize) {
+        self.exited_utime
+            .fetch_add(utime_ns as u64, Ordering::AcqRel);
+        self.exited_stime
+            .fetch_add(stime_ns as u64, Ordering::AcqRel);
+    }
+
+    /// 进程的 CPU 时钟当前的读数，单位为纳秒
+    pub fn clock_now(&self, process: &Process, clock: TimerClock) -> u64 {
+        if clock == TimerClock::Real {
+            return current_time_nanos();
+        }
+        let (mut utime, mut stime) = (
+            self.exited_utime.load(Ordering::Acquire),
+            self.exited_stime.load(Ordering::Acquire),
+        );
+        for task in process.tasks.lock().iter() {
+            let (task_utime, task_stime) = task.time_stat_output();
+            utime += task_utime as u64;
+            stime += task_stime as u64;
+        }
+        match clock {
+            TimerClock::Virtual => utime,
+            _ => utime + stime,
+        }
+    }
+
+    /// 检查以 CPU 时间计时的定时器是否到期，在返回用户态之前调用
+    pub(crate) fn check_cpu_timers(&self, process: &Process) {
+        let armed = |timer: &Arc<IntervalTimer>| {
+            timer.clock != TimerClock::Real && timer.state.lock().deadline != 0
+        };
+        let mut timers: Vec<_> = self.itimers.iter().filter(|t| armed(t)).cloned().collect();
+        timers.extend(
+            self.posix_timers
+                .lock()
+                .values()
+                .filter(|t| armed(t))
+                .cloned(),
+        );
+        if timers.is_empty() {
+            return;
+        }
+        for timer in timers {
+            if let Some(missed) = timer.expire(self.clock_now(process, timer.clock)) {
+                timer.notify(missed);
+            }
+        }
+    }
+}
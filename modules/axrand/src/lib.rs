@@ -0,0 +1,365 @@
+//! [ArceOS](https://github.com/rcore-os/arceos) kernel entropy pool and random
+//! number generator.
+//!
+//! Entropy is collected into an input pool from:
+//!
+//! - the timing jitter of interrupts ([`add_interrupt_randomness`]), credited
+//!   as one bit per two interrupts whose timing is not predictable from the
+//!   previous ones, so that the timer interrupts alone seed the pool within a
+//!   few seconds;
+//! - hardware random number generators, e.g., VirtIO entropy devices
+//!   ([`add_hwgenerator_randomness`]);
+//! - other data that is unpredictable but not credited as entropy, e.g., the
+//!   bytes written to `/dev/random` ([`add_device_randomness`]).
+//!
+//! Once 256 bits of entropy are collected, the pool seeds a ChaCha20-based
+//! CRNG, which produces all random bytes returned by [`fill_bytes`]. The CRNG
+//! erases its key after every request, and is reseeded from the pool at most
+//! once a minute afterwards.
+//!
+//! # Cargo Features
+//!
+//! - `multitask`: sleep in [`wait_for_ready`] until the CRNG is seeded instead
+//!   of spinning.
+//! - `virtio-rng`: seed the pool from VirtIO entropy devices, see
+//!   [`add_hwrng_devices`].
+
+#![no_std]
+
+#[macro_use]
+extern crate log;
+
+#[cfg(feature = "virtio-rng")]
+extern crate alloc;
+
+mod chacha;
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use axhal::time::{current_ticks, ticks_to_nanos, NANOS_PER_SEC};
+use spinlock::SpinNoIrq;
+
+/// Number of 32-bit words in the input pool.
+const POOL_WORDS: usize = 16;
+/// Bits of entropy needed to seed the CRNG.
+const SEED_BITS: usize = 256;
+/// Minimum interval between two reseeds of the CRNG after it is seeded.
+const RESEED_INTERVAL_NANOS: u64 = 60 * NANOS_PER_SEC;
+/// Number of unpredictable interrupt timings credited as one bit of entropy.
+///
+/// The timings are read from a cycle-level counter, so each one has more than
+/// half a bit of jitter, while 256 bits take about five seconds with a 100 Hz
+/// timer.
+const SAMPLES_PER_BIT: usize = 2;
+
+/// The pool where entropy is mixed in before seeding the CRNG.
+struct InputPool {
+    words: [u32; POOL_WORDS],
+    pos: usize,
+    /// Bits of entropy credited since the last extraction.
+    entropy_bits: usize,
+}
+
+impl InputPool {
+    const fn new() -> Self {
+        Self {
+            words: [0; POOL_WORDS],
+            pos: 0,
+            entropy_bits: 0,
+        }
+    }
+
+    fn mix_word(&mut self, word: u32) {
+        let i = self.pos;
+        let prev = self.words[(i + POOL_WORDS - 1) % POOL_WORDS];
+        self.words[i] = (self.words[i] ^ word).wrapping_add(prev.rotate_left(7));
+        self.pos = (i + 1) % POOL_WORDS;
+        if self.pos == 0 {
+            // diffuse every word into the whole pool
+            self.words = chacha::permute(&self.words);
+        }
+    }
+
+    fn mix_bytes(&mut self, data: &[u8]) {
+        for chunk in data.chunks(4) {
+            let mut word = [0; 4];
+            word[..chunk.len()].copy_from_slice(chunk);
+            self.mix_word(u32::from_le_bytes(word));
+        }
+    }
+
+    fn credit(&mut self, bits: usize) {
+        self.entropy_bits = (self.entropy_bits + bits).min(POOL_WORDS * 32);
+    }
+
+    /// Returns a 256-bit seed, and resets the entropy count. The pool keeps
+    /// only a one-way function of its contents, so the seed can not be
+    /// recomputed from the pool later.
+    fn extract(&mut self) -> [u32; 8] {
+        let out = chacha::permute(&self.words);
+        let mut seed = [0; 8];
+        seed.copy_from_slice(&out[..8]);
+        self.words[..8].copy_from_slice(&out[8..]);
+        self.entropy_bits = 0;
+        seed
+    }
+
+    /// Folds the pool into 256 bits without extracting it.
+    fn fold(&self) -> [u32; 8] {
+        let mut folded = [0; 8];
+        for (i, word) in folded.iter_mut().enumerate() {
+            *word = self.words[i] ^ self.words[i + 8];
+        }
+        folded
+    }
+}
+
+/// The ChaCha20-based CRNG.
+struct Crng {
+    key: [u32; 8],
+    /// Time of the last reseed, in nanoseconds.
+    last_reseed: u64,
+}
+
+impl Crng {
+    const fn new() -> Self {
+        Self {
+            key: [0; 8],
+            last_reseed: 0,
+        }
+    }
+
+    fn mix_key(&mut self, seed: &[u32; 8]) {
+        let mut state = [0; 16];
+        state[..8].copy_from_slice(&self.key);
+        state[8..].copy_from_slice(seed);
+        self.key.copy_from_slice(&chacha::permute(&state)[..8]);
+    }
+
+    /// Returns the key for a request, and replaces the current key with a new
+    /// one, so that the output of the request can not be recomputed later.
+    fn next_key(&mut self) -> [u32; 8] {
+        let block = chacha::block(&self.key, 0, 0);
+        let mut key = [0; 8];
+        self.key.copy_from_slice(&block[..8]);
+        key.copy_from_slice(&block[8..]);
+        key
+    }
+}
+
+struct EntropyState {
+    pool: InputPool,
+    crng: Crng,
+    last_ticks: u64,
+    last_delta: u64,
+    /// Unpredictable interrupt timings not credited yet.
+    samples: usize,
+}
+
+impl EntropyState {
+    const fn new() -> Self {
+        Self {
+            pool: InputPool::new(),
+            crng: Crng::new(),
+            last_ticks: 0,
+            last_delta: 0,
+            samples: 0,
+        }
+    }
+
+    /// Mixes the timing of an interrupt. A sample counts if the time differs
+    /// from what the last two samples predict, and one bit of entropy is
+    /// credited every [`SAMPLES_PER_BIT`] samples that count.
+    fn add_timing(&mut self, ticks: u64, extra: u32) -> bool {
+        let delta = ticks.wrapping_sub(self.last_ticks);
+        let delta2 = delta.wrapping_sub(self.last_delta);
+        self.last_ticks = ticks;
+        self.last_delta = delta;
+        self.pool.mix_word(ticks as u32 ^ extra.rotate_left(16));
+        self.pool.mix_word((ticks >> 32) as u32);
+        if delta != 0 && delta2 != 0 {
+            self.samples += 1;
+            if self.samples == SAMPLES_PER_BIT {
+                self.samples = 0;
+                self.pool.credit(1);
+            }
+        }
+        self.try_reseed(ticks)
+    }
+
+    /// Reseeds the CRNG if the pool has enough entropy, and it is the first
+    /// seed or the last reseed is old enough.
+    ///
+    /// Returns true if the CRNG is seeded for the first time, after which the
+    /// caller should call [`wake_waiters`] without holding the state lock.
+    #[must_use]
+    fn try_reseed(&mut self, ticks: u64) -> bool {
+        if self.pool.entropy_bits < SEED_BITS {
+            return false;
+        }
+        let now = ticks_to_nanos(ticks);
+        let ready = is_ready();
+        if ready && now.wrapping_sub(self.crng.last_reseed) < RESEED_INTERVAL_NANOS {
+            return false;
+        }
+        let seed = self.pool.extract();
+        self.crng.mix_key(&seed);
+        self.crng.last_reseed = now;
+        if !ready {
+            READY.store(true, Ordering::Release);
+            info!("random: crng init done");
+        }
+        !ready
+    }
+}
+
+static STATE: SpinNoIrq<EntropyState> = SpinNoIrq::new(EntropyState::new());
+static READY: AtomicBool = AtomicBool::new(false);
+/// The tasks waiting for the CRNG to be seeded.
+#[cfg(feature = "multitask")]
+static WAITERS: axtask::WaitQueue = axtask::WaitQueue::new();
+
+/// Wakes up the tasks in [`wait_for_ready`] once the CRNG is seeded.
+fn wake_waiters() {
+    #[cfg(feature = "multitask")]
+    WAITERS.notify_all();
+}
+
+/// Initializes the entropy pool with the boot time.
+pub fn init() {
+    info!("Initialize entropy pool...");
+    add_device_randomness(&current_ticks().to_le_bytes());
+}
+
+/// Mixes `data` into the pool without crediting any entropy.
+pub fn add_device_randomness(data: &[u8]) {
+    let mut state = STATE.lock();
+    state.pool.mix_bytes(data);
+    state.pool.mix_word(current_ticks() as u32);
+}
+
+/// Mixes `data` from a trusted hardware random number generator into the
+/// pool, and credits all of its bits as entropy.
+pub fn add_hwgenerator_randomness(data: &[u8]) {
+    let mut state = STATE.lock();
+    state.pool.mix_bytes(data);
+    state.pool.credit(data.len() * 8);
+    let seeded = state.try_reseed(current_ticks());
+    drop(state);
+    if seeded {
+        wake_waiters();
+    }
+}
+
+/// Mixes the timing of an interrupt into the pool. It is called on every
+/// interrupt.
+pub fn add_interrupt_randomness(irq: usize) {
+    let seeded = STATE.lock().add_timing(current_ticks(), irq as u32);
+    if seeded {
+        wake_waiters();
+    }
+}
+
+/// Returns whether the CRNG is seeded with enough entropy.
+pub fn is_ready() -> bool {
+    READY.load(Ordering::Acquire)
+}
+
+/// Blocks until the CRNG is seeded or `interrupted` returns true, e.g., when
+/// the waiting task has pending signals.
+///
+/// Returns whether the CRNG is seeded. The CRNG is seeded by interrupts or
+/// hardware random number generators only.
+pub fn wait_for_ready(interrupted: impl Fn() -> bool) -> bool {
+    if is_ready() {
+        return true;
+    }
+    #[cfg(feature = "virtio-rng")]
+    reseed_from_hwrng();
+    #[cfg(feature = "multitask")]
+    WAITERS.wait_until(|| is_ready() || interrupted());
+    #[cfg(not(feature = "multitask"))]
+    while !is_ready() && !interrupted() {
+        core::hint::spin_loop();
+    }
+    is_ready()
+}
+
+/// Fills `buf` with random bytes from the CRNG.
+///
+/// It does not block. If the CRNG is not seeded yet, the bytes are derived
+/// from what the pool has, and may be predictable. Use [`wait_for_ready`]
+/// first if that is not acceptable.
+pub fn fill_bytes(buf: &mut [u8]) {
+    #[cfg(feature = "virtio-rng")]
+    reseed_from_hwrng();
+    let key = {
+        let mut state = STATE.lock();
+        if !is_ready() {
+            let folded = state.pool.fold();
+            state.crng.mix_key(&folded);
+        }
+        state.crng.next_key()
+    };
+    chacha::keystream(&key, 0, buf);
+}
+
+#[cfg(feature = "virtio-rng")]
+mod hwrng {
+    use alloc::vec::Vec;
+    use core::sync::atomic::{AtomicU64, Ordering};
+
+    use axdriver::AxRngDevice;
+    use axhal::time::current_time_nanos;
+    use spinlock::SpinNoIrq;
+
+    use super::{add_hwgenerator_randomness, RESEED_INTERVAL_NANOS, SEED_BITS};
+
+    static DEVICES: SpinNoIrq<Vec<AxRngDevice>> = SpinNoIrq::new(Vec::new());
+    /// Time of the last read from the devices, in nanoseconds.
+    static LAST_READ: AtomicU64 = AtomicU64::new(0);
+
+    /// Reads a seed from every device into the pool.
+    fn read_devices(devs: &mut [AxRngDevice]) {
+        let mut seed = [0; SEED_BITS / 8];
+        for dev in devs {
+            match dev.request(&mut seed) {
+                Ok(len) => add_hwgenerator_randomness(&seed[..len]),
+                Err(e) => warn!("random: failed to read entropy device: {:?}", e),
+            }
+        }
+        seed.fill(0);
+        LAST_READ.store(current_time_nanos(), Ordering::Relaxed);
+    }
+
+    /// Seeds the pool from the VirtIO entropy devices, and keeps them to
+    /// reseed the pool periodically.
+    pub fn add_hwrng_devices(devs: Vec<AxRngDevice>) {
+        let mut devices = DEVICES.lock();
+        devices.extend(devs);
+        if !devices.is_empty() {
+            info!("random: use {} entropy device(s)", devices.len());
+            read_devices(&mut devices);
+        }
+    }
+
+    /// Reads the devices again if the last read is older than the reseed
+    /// interval.
+    pub(super) fn reseed_from_hwrng() {
+        let now = current_time_nanos();
+        if now.wrapping_sub(LAST_READ.load(Ordering::Relaxed)) < RESEED_INTERVAL_NANOS {
+            return;
+        }
+        // skip if another CPU is reading them
+        if let Some(mut devices) = DEVICES.try_lock() {
+            if !devices.is_empty() {
+                read_devices(&mut devices);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "virtio-rng")]
+pub use self::hwrng::add_hwrng_devices;
+#[cfg(feature = "virtio-rng")]
+use self::hwrng::reseed_from_hwrng;
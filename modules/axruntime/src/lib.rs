@@ -85,7 +85,7 @@ static INITED_CPUS: AtomicUsize = AtomicUsize::new(0);
 
 /// Whether all CPUs has been initialized.
 pub fn is_init_ok() -> bool {
-    INITED_CPUS.load(Ordering::Acquire) == axconfig::SMP
+    INITED_CPUS.load(Ordering::Acquire) == axhal::cpu::cpu_num()
 }
 
 /// The main entry point of the ArceOS runtime.
@@ -142,6 +142,8 @@ pub extern "C" fn rust_main(cpu_id: usize, dtb: usize) {
 
     info!("Initialize platform devices...");
     axhal::platform_init();
+    init_log_filter();
+    axrand::init();
 
     cfg_if::cfg_if! {
         if #[cfg(feature = "monolithic")] {
@@ -152,7 +154,12 @@ pub extern "C" fn rust_main(cpu_id: usize, dtb: usize) {
             axtask::init_scheduler();
         }
     }
-    #[cfg(any(feature = "fs", feature = "net", feature = "display"))]
+    #[cfg(any(
+        feature = "fs",
+        feature = "net",
+        feature = "display",
+        feature = "virtio-rng"
+    ))]
     {
         #[allow(unused_variables)]
         let all_devices = axdriver::init_drivers();
@@ -160,11 +167,26 @@ pub extern "C" fn rust_main(cpu_id: usize, dtb: usize) {
         #[cfg(feature = "fs")]
         axfs::init_filesystems(all_devices.block);
 
+        #[cfg(feature = "virtio-console")]
+        axfs::chrdev::init_consoles(all_devices.console);
+
+        #[cfg(feature = "virtio-input")]
+        axfs::chrdev::init_inputs(all_devices.input);
+
+        #[cfg(feature = "virtio-rng")]
+        axrand::add_hwrng_devices(all_devices.rng);
+
+        #[cfg(feature = "virtio-9p")]
+        axfs::init_9p(all_devices.p9);
+
         #[cfg(feature = "net")]
         axnet::init_network(all_devices.net);
 
         #[cfg(feature = "display")]
         axdisplay::init_display(all_devices.display);
+
+        #[cfg(feature = "fbdev")]
+        axfs::chrdev::init_framebuffer();
     }
 
     #[cfg(feature = "irq")]
@@ -185,6 +207,11 @@ pub extern "C" fn rust_main(cpu_id: usize, dtb: usize) {
 
 /// exit the main task
 pub fn exit_main() {
+    // the block layer caches the written data
+    #[cfg(feature = "fs")]
+    if let Err(e) = axfs::api::sync() {
+        warn!("failed to write back the block devices: {:?}", e);
+    }
     #[cfg(feature = "multitask")]
     axtask::exit(0);
     #[cfg(not(feature = "multitask"))]
@@ -194,6 +221,24 @@ pub fn exit_main() {
     }
 }
 
+/// Sets the log filter from the `AX_LOG_FILTER` environment variable at build
+/// time, and then from the `axlog=` option of the kernel command line.
+fn init_log_filter() {
+    let bootargs = axhal::dtb::bootargs().and_then(|bootargs| {
+        bootargs
+            .split_whitespace()
+            .find_map(|arg| arg.strip_prefix("axlog="))
+    });
+    for spec in [option_env!("AX_LOG_FILTER"), bootargs]
+        .into_iter()
+        .flatten()
+    {
+        if let Err(e) = axlog::set_log_filter(spec) {
+            warn!("invalid log filter {:?}: {}", spec, e);
+        }
+    }
+}
+
 #[cfg(feature = "alloc")]
 #[allow(dead_code)]
 fn init_allocator() {
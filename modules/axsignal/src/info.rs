@@ -2,6 +2,15 @@
 //!
 //! 错误信息：详细定义见 `https://man7.org/linux/man-pages/man2/rt_sigaction.2.html`
 
+/// Sent by `kill(2)`
+pub const SI_USER: i32 = 0;
+/// Sent by `sigqueue(3)`
+pub const SI_QUEUE: i32 = -1;
+/// Sent by the expiration of a POSIX timer
+pub const SI_TIMER: i32 = -2;
+/// Sent by `tkill(2)` or `tgkill(2)`
+pub const SI_TKILL: i32 = -6;
+
 /// The information of the signal
 ///
 /// When the `SigAction` specifies that it needs information, it will return it to the user
@@ -18,14 +27,12 @@ pub struct SigInfo {
     /// Padding
     #[allow(unused)]
     pub pad: u32,
-    /// The process ID of the sender
+    /// The process ID of the sender, or the timer ID for `SI_TIMER`
     pub pid: i32,
-    /// The real user ID of the sender
+    /// The real user ID of the sender, or the overrun count for `SI_TIMER`
     pub uid: u32,
-    /// The value sent with the signal
-    pub si_val_int: i32,
-    /// The value pointer of the signal
-    pub si_val_ptr: usize,
+    /// The value sent with the signal, i.e. `union sigval`
+    pub si_value: usize,
 }
 
 impl Default for SigInfo {
@@ -33,12 +40,11 @@ impl Default for SigInfo {
         Self {
             si_signo: 0,
             si_errno: 0,
-            si_code: -6, // SI_TKILL
+            si_code: SI_TKILL,
             pad: 0,
             pid: 0,
             uid: 0,
-            si_val_int: 0,
-            si_val_ptr: 0,
+            si_value: 0,
         }
     }
 }
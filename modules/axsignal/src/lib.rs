@@ -6,12 +6,11 @@
 //! 一次trap，从而检查是否有需要处理的信号。
 #![cfg_attr(not(test), no_std)]
 extern crate alloc;
-use alloc::collections::BTreeMap;
+use alloc::collections::{BTreeMap, VecDeque};
 
 use action::SigAction;
 use info::SigInfo;
 use signal_no::{SignalNo, MAX_SIG_NUM};
-use ucontext::SignalUserContext;
 
 pub mod action;
 pub mod info;
@@ -61,6 +60,18 @@ impl SignalHandler {
     pub unsafe fn set_action(&mut self, sig_num: usize, action: *const SigAction) {
         self.handlers[sig_num - 1] = unsafe { *action };
     }
+
+    /// 将信号处理函数恢复为默认处理，用于 SA_RESETHAND
+    pub fn reset_action(&mut self, sig_num: usize) {
+        self.handlers[sig_num - 1] = SigAction::default();
+    }
+}
+
+/// 判断是否为实时信号
+///
+/// 实时信号会排队，每次发送都会被递送一次；标准信号在未决时再次发送会被合并
+pub fn is_rt_signal(sig_num: usize) -> bool {
+    sig_num >= SignalNo::SIGRTMIN as usize
 }
 
 /// 接受信号的结构，每一个进程都有一个
@@ -70,8 +81,10 @@ pub struct SignalSet {
     pub mask: usize,
     /// 未决信号集
     pub pending: usize,
-    /// 附加信息
-    pub info: BTreeMap<usize, (SigInfo, SignalUserContext)>,
+    /// 附加信息，键为信号编号
+    ///
+    /// 标准信号至多有一个附加信息，实时信号的附加信息按发送的顺序排队
+    pub info: BTreeMap<usize, VecDeque<SigInfo>>,
 }
 
 impl Default for SignalSet {
@@ -94,6 +107,7 @@ impl SignalSet {
     pub fn clear(&mut self) {
         self.mask = 0;
         self.pending = 0;
+        self.info.clear();
     }
 
     /// 查询是否有未决信号，若有则返回对应编号
@@ -123,28 +137,99 @@ impl SignalSet {
     ///
     /// 若有则返回信号编号最低的一个，，并且修改原有信号集
     pub fn get_one_signal(&mut self) -> Option<usize> {
-        match self.find_signal() {
-            Some(pos) => {
-                // 修改原有信号集
-                self.pending &= !(1 << (pos - 1));
-                Some(pos)
-            }
-            None => None,
+        self.take_signal().map(|(sig_num, _)| sig_num)
+    }
+
+    /// 取出一个可以递送的未决信号及其附加信息
+    ///
+    /// 对于排队的实时信号，每次只取出队首的一个，队列为空时才会清除未决位
+    pub fn take_signal(&mut self) -> Option<(usize, SigInfo)> {
+        let sig_num = self.find_signal()?;
+        Some((sig_num, self.dequeue(sig_num)))
+    }
+
+    /// 查询 `set` 中编号最低的未决信号，不论其是否被屏蔽
+    ///
+    /// 用于 sigtimedwait 与 signalfd，它们等待的信号通常已被屏蔽
+    pub fn find_signal_in(&self, set: usize) -> Option<usize> {
+        match self.pending & set {
+            0 => None,
+            pending => Some(pending.trailing_zeros() as usize + 1),
+        }
+    }
+
+    /// 取出 `set` 中编号最低的未决信号及其附加信息，不论其是否被屏蔽
+    pub fn take_signal_in(&mut self, set: usize) -> Option<(usize, SigInfo)> {
+        let sig_num = self.find_signal_in(set)?;
+        Some((sig_num, self.dequeue(sig_num)))
+    }
+
+    /// 取出未决信号 `sig_num` 的一个附加信息，并在没有剩余的排队信息时清除未决位
+    fn dequeue(&mut self, sig_num: usize) -> SigInfo {
+        let queue = self.info.get_mut(&sig_num);
+        let info = queue.as_ref().and_then(|queue| queue.front().copied());
+        if let Some(queue) = queue {
+            queue.pop_front();
         }
+        if self.info.get(&sig_num).map_or(true, VecDeque::is_empty) {
+            self.info.remove(&sig_num);
+            // 修改原有信号集
+            self.pending &= !(1 << (sig_num - 1));
+        }
+        info.unwrap_or(SigInfo {
+            si_signo: sig_num as i32,
+            ..Default::default()
+        })
     }
 
     /// 尝试添加一个bit作为信号
     ///
-    /// 若当前信号已经加入到未决信号集中，则不作处理
+    /// 若当前标准信号已经加入到未决信号集中，则不作处理；实时信号则会排队
     ///
     /// 若信号在掩码中，则仍然加入，但是可能不会触发
     pub fn try_add_signal(&mut self, sig_num: usize, info: Option<SigInfo>) {
         let now_mask = 1 << (sig_num - 1);
-        self.pending |= now_mask;
-        // self.info[sig_num - 1] = info.map(|info| (info, SignalUserContext::default()));
-        if let Some(info) = info {
-            self.info
-                .insert(sig_num, (info, SignalUserContext::default()));
+        if !is_rt_signal(sig_num) {
+            if self.pending & now_mask == 0 {
+                self.pending |= now_mask;
+                if let Some(info) = info {
+                    self.info.insert(sig_num, VecDeque::from([info]));
+                }
+            }
+            return;
         }
+        // 未排队的实时信号也占据一个递送的次数
+        if self.pending & now_mask != 0 && !self.info.contains_key(&sig_num) {
+            self.info.entry(sig_num).or_default().push_back(SigInfo {
+                si_signo: sig_num as i32,
+                ..Default::default()
+            });
+        }
+        self.pending |= now_mask;
+        self.info
+            .entry(sig_num)
+            .or_default()
+            .push_back(info.unwrap_or(SigInfo {
+                si_signo: sig_num as i32,
+                ..Default::default()
+            }));
+    }
+
+    /// 只设置信号的未决位而不保存附加信息
+    ///
+    /// 用于排队的信号数达到上限时，若该信号已经未决则相当于被丢弃
+    pub fn add_signal_unqueued(&mut self, sig_num: usize) {
+        self.pending |= 1 << (sig_num - 1);
+    }
+
+    /// 丢弃一个未决信号，包括其所有排队的附加信息
+    pub fn remove_signal(&mut self, sig_num: usize) {
+        self.pending &= !(1 << (sig_num - 1));
+        self.info.remove(&sig_num);
+    }
+
+    /// 排队等待递送的附加信息的数目，用于实现 RLIMIT_SIGPENDING
+    pub fn queued_count(&self) -> usize {
+        self.info.values().map(VecDeque::len).sum()
     }
 }
@@ -88,13 +88,15 @@ impl Default for SignalUserContext {
 
 impl SignalUserContext {
     /// init the user context by the pc and the mask
-    pub fn init(pc: usize, _mask: usize) -> Self {
+    pub fn init(pc: usize, mask: usize) -> Self {
+        let mut sigmask = [0; 17];
+        sigmask[0] = mask as u64;
         Self {
             flags: 0,
             link: 0,
             stack: SignalStack::default(),
             mcontext: MContext::init_by_pc(pc),
-            sigmask: [0; 17],
+            sigmask,
         }
     }
 
@@ -102,4 +104,9 @@ impl SignalUserContext {
     pub fn get_pc(&self) -> usize {
         self.mcontext.pc
     }
+
+    /// get the signal mask to be restored from the user context
+    pub fn get_mask(&self) -> usize {
+        self.sigmask[0] as usize
+    }
 }
@@ -100,4 +100,9 @@ impl SignalUserContext {
     pub fn get_pc(&self) -> usize {
         self.mcontext.get_pc()
     }
+
+    /// get the signal mask to be restored from the user context
+    pub fn get_mask(&self) -> usize {
+        self.sigmask as usize
+    }
 }
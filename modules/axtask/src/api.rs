@@ -14,7 +14,7 @@ pub use crate::wait_queue::WaitQueue;
 
 pub use crate::processor::{current_processor, Processor};
 
-pub use crate::schedule::schedule;
+pub use crate::schedule::{schedule, swap_task_ids};
 
 #[cfg(feature = "irq")]
 pub use crate::schedule::schedule_timeout;
@@ -165,6 +165,26 @@ pub fn sleep_until(deadline: axhal::time::TimeValue) {
     #[cfg(not(feature = "irq"))]
     axhal::time::busy_wait_until(deadline);
 }
+/// Runs `callback` in the timer interrupt handler once `deadline` is reached.
+///
+/// The callback must not block. Returns an ID that can be passed to
+/// [`cancel_timer_callback`].
+#[cfg(feature = "irq")]
+#[doc(cfg(feature = "irq"))]
+pub fn set_timer_callback<F>(deadline: axhal::time::TimeValue, callback: F) -> usize
+where
+    F: FnOnce(axhal::time::TimeValue) + Send + 'static,
+{
+    crate::timers::set_timer_callback(deadline, callback)
+}
+
+/// Cancels a callback registered by [`set_timer_callback`] that has not run yet.
+#[cfg(feature = "irq")]
+#[doc(cfg(feature = "irq"))]
+pub fn cancel_timer_callback(id: usize) {
+    crate::timers::cancel_timer_callback(id)
+}
+
 /// wake up task
 pub fn wakeup_task(task: AxTaskRef) {
     crate::schedule::wakeup_task(task)
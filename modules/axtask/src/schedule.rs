@@ -21,6 +21,26 @@ pub(crate) fn get_wait_for_exit_queue(task: &AxTaskRef) -> Option<Arc<WaitQueue>
     WAIT_FOR_TASK_EXITS.lock().get(&task.id().as_u64()).cloned()
 }
 
+/// Swaps the IDs of two tasks, e.g., when a thread calling `execve` takes
+/// over the ID of the thread group leader.
+///
+/// The tasks waiting for either of them to exit keep waiting for the same
+/// task.
+pub fn swap_task_ids(a: &AxTaskRef, b: &AxTaskRef) {
+    let mut queues = WAIT_FOR_TASK_EXITS.lock();
+    let (id_a, id_b) = (a.id().as_u64(), b.id().as_u64());
+    let queue_a = queues.remove(&id_a);
+    let queue_b = queues.remove(&id_b);
+    if let Some(queue) = queue_a {
+        queues.insert(id_b, queue);
+    }
+    if let Some(queue) = queue_b {
+        queues.insert(id_a, queue);
+    }
+    a.set_id(id_b);
+    b.set_id(id_a);
+}
+
 /// When the task exits, notify all tasks that are waiting for this task to exit, and
 /// then remove the wait queue of the exited task.
 pub(crate) fn notify_wait_for_exit(task: &AxTaskRef) {
@@ -15,7 +15,7 @@ use crate::{
 
 pub use taskctx::{TaskId, TaskInner};
 
-use core::sync::atomic::{AtomicBool, Ordering};
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use spinlock::{SpinNoIrq, SpinNoIrqOnly, SpinNoIrqOnlyGuard};
 
 extern "C" {
@@ -42,6 +42,9 @@ pub enum TaskState {
 
 pub struct ScheduleTask {
     inner: TaskInner,
+    /// The ID of the task. It is the same as the one of [`TaskInner`] unless
+    /// swapped by [`swap_task_ids`](crate::swap_task_ids).
+    id: AtomicU64,
     /// Store task irq state
     irq_state: AtomicBool,
     /// Task state
@@ -53,6 +56,7 @@ pub struct ScheduleTask {
 impl ScheduleTask {
     fn new(inner: TaskInner, irq_init_state: bool) -> Self {
         Self {
+            id: AtomicU64::new(inner.id().as_u64()),
             state: SpinNoIrqOnly::new(TaskState::Runable),
             processor: SpinNoIrq::new(None),
             irq_state: AtomicBool::new(irq_init_state),
@@ -60,6 +64,17 @@ impl ScheduleTask {
         }
     }
 
+    /// Returns the ID of the task.
+    #[inline]
+    pub fn id(&self) -> TaskId {
+        TaskId::from(self.id.load(Ordering::Acquire))
+    }
+
+    #[inline]
+    pub(crate) fn set_id(&self, id: u64) {
+        self.id.store(id, Ordering::Release);
+    }
+
     #[inline]
     /// lock the task state and ctx_ptr access
     pub fn state_lock_manual(&self) -> ManuallyDrop<SpinNoIrqOnlyGuard<TaskState>> {
@@ -1,5 +1,6 @@
-use alloc::sync::Arc;
+use alloc::{boxed::Box, sync::Arc};
 use axhal::time::current_time;
+use core::sync::atomic::{AtomicUsize, Ordering};
 use lazy_init::LazyInit;
 use spinlock::SpinNoIrq;
 use timer_list::{TimeValue, TimerEvent, TimerList};
@@ -7,25 +8,54 @@ use timer_list::{TimeValue, TimerEvent, TimerList};
 use crate::{AxTaskRef, TaskState};
 
 // TODO: per-CPU
-static TIMER_LIST: LazyInit<SpinNoIrq<TimerList<TaskWakeupEvent>>> = LazyInit::new();
+static TIMER_LIST: LazyInit<SpinNoIrq<TimerList<AxTimerEvent>>> = LazyInit::new();
 
-struct TaskWakeupEvent(AxTaskRef);
+static NEXT_CALLBACK_ID: AtomicUsize = AtomicUsize::new(1);
 
-impl TimerEvent for TaskWakeupEvent {
-    fn callback(self, _now: TimeValue) {
-        crate::schedule::wakeup_task(self.0);
+enum AxTimerEvent {
+    /// Wakes up a sleeping task.
+    TaskWakeup(AxTaskRef),
+    /// Runs a callback registered by [`set_timer_callback`].
+    Callback(usize, Box<dyn FnOnce(TimeValue) + Send>),
+}
+
+impl TimerEvent for AxTimerEvent {
+    fn callback(self, now: TimeValue) {
+        match self {
+            Self::TaskWakeup(task) => crate::schedule::wakeup_task(task),
+            Self::Callback(_, callback) => callback(now),
+        }
     }
 }
 
 pub fn set_alarm_wakeup(deadline: TimeValue, task: AxTaskRef) {
     let mut timer_list = TIMER_LIST.lock();
     task.set_state(TaskState::Blocking);
-    timer_list.set(deadline, TaskWakeupEvent(task));
+    timer_list.set(deadline, AxTimerEvent::TaskWakeup(task));
     drop(timer_list)
 }
 
 pub fn cancel_alarm(task: &AxTaskRef) {
-    TIMER_LIST.lock().cancel(|t| Arc::ptr_eq(&t.0, task));
+    TIMER_LIST
+        .lock()
+        .cancel(|event| matches!(event, AxTimerEvent::TaskWakeup(t) if Arc::ptr_eq(t, task)));
+}
+
+pub fn set_timer_callback<F>(deadline: TimeValue, callback: F) -> usize
+where
+    F: FnOnce(TimeValue) + Send + 'static,
+{
+    let id = NEXT_CALLBACK_ID.fetch_add(1, Ordering::Relaxed);
+    TIMER_LIST
+        .lock()
+        .set(deadline, AxTimerEvent::Callback(id, Box::new(callback)));
+    id
+}
+
+pub fn cancel_timer_callback(id: usize) {
+    TIMER_LIST
+        .lock()
+        .cancel(|event| matches!(event, AxTimerEvent::Callback(t, _) if *t == id));
 }
 
 pub fn check_events() {
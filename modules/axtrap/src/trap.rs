@@ -2,6 +2,7 @@ pub fn handle_irq(_irq_num: usize, _from_user: bool) {
     #[cfg(feature = "irq")]
     {
         let guard = kernel_guard::NoPreempt::new();
+        axrand::add_interrupt_randomness(_irq_num);
         // trap进来，统计时间信息
         // 只有当trap是来自用户态才进行统计
         #[cfg(feature = "monolithic")]